@@ -206,6 +206,10 @@ list_of_functions(
                 "title": "Error handling",
                 "modules": ["core::error"],
             },
+            {
+                "title": "Input",
+                "modules": ["core::io"],
+            },
             {
                 "title": "Floating point",
                 "modules": ["core::numbers"],
@@ -226,6 +230,10 @@ list_of_functions(
                 "title": "Temperature conversion",
                 "modules": ["physics::temperature_conversion"],
             },
+            {
+                "title": "Decibel conversion",
+                "modules": ["units::decibel"],
+            },
         ],
     },
 )
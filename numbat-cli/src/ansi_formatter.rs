@@ -1,6 +1,6 @@
-use numbat::markup::{FormatType, FormattedString, Formatter, Markup};
+use numbat::markup::{AsciiOperators, FormatType, FormattedString, Formatter, Markup};
 
-use colored::Colorize;
+use colored::{control::SHOULD_COLORIZE, Colorize};
 
 pub struct ANSIFormatter;
 
@@ -22,6 +22,15 @@ impl Formatter for ANSIFormatter {
             FormatType::TypeIdentifier => text.blue().italic(),
             FormatType::Operator => text.bold(),
             FormatType::Decorator => text.green(),
+            FormatType::Error => text.red().bold(),
+            FormatType::Warning => text.yellow().bold(),
+            FormatType::Link => {
+                if !SHOULD_COLORIZE.should_colorize() {
+                    return text.clone();
+                }
+                // OSC 8 hyperlink, see https://gist.github.com/egmontkob/eb114294efbcd5adb1944c9f3cb5feb3
+                return format!("\x1b]8;;{text}\x1b\\{}\x1b]8;;\x1b\\", text.underline());
+            }
         })
         .to_string()
     }
@@ -30,3 +39,60 @@ impl Formatter for ANSIFormatter {
 pub fn ansi_format(m: &Markup, indent: bool) -> String {
     ANSIFormatter {}.format(m, indent)
 }
+
+/// Like [`ansi_format`], but if `ascii_operators` is set, operators like `×` or `➞`
+/// and units like `m²` are printed as their ASCII equivalents (`*`, `->`, `m^2`),
+/// for terminals that can not display them.
+pub fn ansi_format_with_ascii_operators(m: &Markup, indent: bool, ascii_operators: bool) -> String {
+    if ascii_operators {
+        AsciiOperators(ANSIFormatter {}).format(m, indent)
+    } else {
+        ansi_format(m, indent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use colored::control::ShouldColorize;
+    use numbat::markup as m;
+
+    /// Forces colored output while it is alive. The previous setting is restored
+    /// afterwards, even if the test fails.
+    struct ForceColors(Option<bool>);
+
+    impl ForceColors {
+        fn new() -> Self {
+            // There is no way to ask whether an override is set. If the current setting
+            // matches the environment, removing the override restores the same behavior.
+            let current = SHOULD_COLORIZE.should_colorize();
+            let previous =
+                (current != ShouldColorize::from_env().should_colorize()).then_some(current);
+            colored::control::set_override(true);
+            Self(previous)
+        }
+    }
+
+    impl Drop for ForceColors {
+        fn drop(&mut self) {
+            match self.0 {
+                Some(previous) => colored::control::set_override(previous),
+                None => colored::control::unset_override(),
+            }
+        }
+    }
+
+    #[test]
+    fn diagnostic_severities() {
+        let _colors = ForceColors::new();
+
+        assert_eq!(
+            ansi_format(&m::error("error"), false),
+            "\x1b[1;31merror\x1b[0m"
+        );
+        assert_eq!(
+            ansi_format(&m::warning("warning"), false),
+            "\x1b[1;33mwarning\x1b[0m"
+        );
+    }
+}
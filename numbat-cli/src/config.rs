@@ -55,6 +55,7 @@ pub struct Config {
     pub prompt: String,
     pub pretty_print: PrettyPrintMode,
     pub color: ColorMode,
+    pub ascii_operators: bool,
 
     #[serde(skip)]
     pub enter_repl: bool,
@@ -74,6 +75,7 @@ impl Default for Config {
             intro_banner: IntroBanner::default(),
             pretty_print: PrettyPrintMode::Auto,
             color: ColorMode::default(),
+            ascii_operators: false,
             load_prelude: true,
             load_user_init: true,
             exchange_rates: Default::default(),
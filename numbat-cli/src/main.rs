@@ -3,7 +3,7 @@ mod completer;
 mod config;
 mod highlighter;
 
-use ansi_formatter::ansi_format;
+use ansi_formatter::ansi_format_with_ascii_operators;
 use colored::control::SHOULD_COLORIZE;
 use completer::NumbatCompleter;
 use config::{ColorMode, Config, ExchangeRateFetchingPolicy, IntroBanner, PrettyPrintMode};
@@ -27,7 +27,7 @@ use rustyline::{
 };
 use rustyline::{EventHandler, Highlighter, KeyCode, KeyEvent, Modifiers};
 
-use std::io::IsTerminal;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::{fs, thread};
@@ -80,6 +80,10 @@ struct Args {
     #[arg(long, value_name = "WHEN")]
     color: Option<ColorMode>,
 
+    /// Print ASCII operators like '*' and '->' instead of '×' and '➞'.
+    #[arg(long, hide_short_help = true)]
+    ascii_operators: bool,
+
     /// What kind of intro banner to show (if any).
     #[arg(long, value_name = "MODE")]
     intro_banner: Option<IntroBanner>,
@@ -145,6 +149,7 @@ impl Cli {
         config.intro_banner = args.intro_banner.unwrap_or(config.intro_banner);
         config.pretty_print = args.pretty_print.unwrap_or(config.pretty_print);
         config.color = args.color.unwrap_or(config.color);
+        config.ascii_operators |= args.ascii_operators;
 
         config.enter_repl =
             (args.file.is_none() && args.expression.is_none()) || args.inspect_interactively;
@@ -335,6 +340,11 @@ impl Cli {
         result
     }
 
+    /// Format markup for the terminal, with ASCII operators if configured
+    fn format(&self, m: &m::Markup, indent: bool) -> String {
+        ansi_format_with_ascii_operators(m, indent, self.config.ascii_operators)
+    }
+
     fn repl_loop(
         &mut self,
         rl: &mut Editor<NumbatHelper, DefaultHistory>,
@@ -351,7 +361,7 @@ impl Cli {
                             "list" | "ls" => {
                                 println!(
                                     "{}",
-                                    ansi_format(
+                                    self.format(
                                         &self.context.lock().unwrap().print_environment(),
                                         false
                                     )
@@ -360,7 +370,7 @@ impl Cli {
                             "list functions" | "ls functions" => {
                                 println!(
                                     "{}",
-                                    ansi_format(
+                                    self.format(
                                         &self.context.lock().unwrap().print_functions(),
                                         false
                                     )
@@ -369,7 +379,7 @@ impl Cli {
                             "list dimensions" | "ls dimensions" => {
                                 println!(
                                     "{}",
-                                    ansi_format(
+                                    self.format(
                                         &self.context.lock().unwrap().print_dimensions(),
                                         false
                                     )
@@ -378,7 +388,7 @@ impl Cli {
                             "list variables" | "ls variables" => {
                                 println!(
                                     "{}",
-                                    ansi_format(
+                                    self.format(
                                         &self.context.lock().unwrap().print_variables(),
                                         false
                                     )
@@ -387,7 +397,7 @@ impl Cli {
                             "list units" | "ls units" => {
                                 println!(
                                     "{}",
-                                    ansi_format(&self.context.lock().unwrap().print_units(), false)
+                                    self.format(&self.context.lock().unwrap().print_units(), false)
                                 );
                             }
                             "clear" => {
@@ -398,7 +408,7 @@ impl Cli {
                             }
                             "help" | "?" => {
                                 let help = help_markup();
-                                print!("{}", ansi_format(&help, true));
+                                print!("{}", self.format(&help, true));
                                 // currently, the ansi formatter adds indents
                                 // _after_ each newline and so we need to manually
                                 // add an extra blank line to absorb this indent
@@ -406,12 +416,9 @@ impl Cli {
                             }
                             _ => {
                                 if let Some(keyword) = line.strip_prefix("info ") {
-                                    let help = self
-                                        .context
-                                        .lock()
-                                        .unwrap()
-                                        .print_info_for_keyword(keyword.trim());
-                                    println!("{}", ansi_format(&help, true));
+                                    let help =
+                                        self.context.lock().unwrap().help_for(keyword.trim());
+                                    println!("{}", self.format(&help, true));
                                     continue;
                                 }
                                 let result = self.parse_and_evaluate(
@@ -457,12 +464,38 @@ impl Cli {
         execution_mode: ExecutionMode,
         pretty_print_mode: PrettyPrintMode,
     ) -> ControlFlow {
+        let interactive = execution_mode == ExecutionMode::Interactive;
+
         let to_be_printed: Arc<Mutex<Vec<m::Markup>>> = Arc::new(Mutex::new(vec![]));
         let to_be_printed_c = to_be_printed.clone();
+        let to_be_printed_i = to_be_printed.clone();
+        let ascii_operators = self.config.ascii_operators;
         let mut settings = InterpreterSettings {
             print_fn: Box::new(move |s: &m::Markup| {
                 to_be_printed_c.lock().unwrap().push(s.clone());
             }),
+            print_value_fn: None,
+            input_fn: Some(Box::new(move |prompt: &str| {
+                // Show everything that has been printed so far before the prompt
+                for s in to_be_printed_i.lock().unwrap().drain(..) {
+                    println!(
+                        "{}",
+                        ansi_format_with_ascii_operators(&s, interactive, ascii_operators)
+                    );
+                }
+                print!("{prompt}");
+                std::io::stdout().flush().ok()?;
+
+                let mut line = String::new();
+                match std::io::stdin().read_line(&mut line) {
+                    Ok(0) | Err(_) => {
+                        println!();
+                        None
+                    }
+                    Ok(_) => Some(line),
+                }
+            })),
+            ..InterpreterSettings::default()
         };
 
         let (result, registry) = {
@@ -474,8 +507,6 @@ impl Cli {
             )
         };
 
-        let interactive = execution_mode == ExecutionMode::Interactive;
-
         let pretty_print = match pretty_print_mode {
             PrettyPrintMode::Always => true,
             PrettyPrintMode::Never => false,
@@ -484,13 +515,18 @@ impl Cli {
 
         match result {
             Ok((statements, interpreter_result)) => {
+                let warnings = self.context.lock().unwrap().warnings().to_vec();
+                for warning in warnings {
+                    self.print_diagnostic(warning);
+                }
+
                 if interactive || pretty_print {
                     println!();
                 }
 
                 if pretty_print {
                     for statement in &statements {
-                        let repr = ansi_format(&statement.pretty_print(), true);
+                        let repr = self.format(&statement.pretty_print(), true);
                         println!("{}", repr);
                         println!();
                     }
@@ -498,7 +534,7 @@ impl Cli {
 
                 let to_be_printed = to_be_printed.lock().unwrap();
                 for s in to_be_printed.iter() {
-                    println!("{}", ansi_format(s, interactive));
+                    println!("{}", self.format(s, interactive));
                 }
                 if interactive && !to_be_printed.is_empty() {
                     println!();
@@ -510,7 +546,7 @@ impl Cli {
                     interactive || pretty_print,
                     interactive || pretty_print,
                 );
-                print!("{}", ansi_format(&result_markup, false));
+                print!("{}", self.format(&result_markup, false));
 
                 if (interactive || pretty_print) && interpreter_result.is_value() {
                     println!();
@@ -524,7 +560,8 @@ impl Cli {
             }
             Err(NumbatError::NameResolutionError(
                 e @ (NameResolutionError::IdentifierClash { .. }
-                | NameResolutionError::ReservedIdentifier(_)),
+                | NameResolutionError::ReservedIdentifier(..)
+                | NameResolutionError::DecoratorsOnDeclaredUnit(..)),
             )) => {
                 self.print_diagnostic(e);
                 execution_mode.exit_status_in_case_of_error()
@@ -138,6 +138,34 @@ fn pretty_printing() {
         ));
 }
 
+#[test]
+fn ascii_operators() {
+    for (ascii_operators, expected_expression, expected_result) in [
+        (false, "2 × x² ➞ centimetre²", "cm²·s    [Length² × Time]"),
+        (
+            true,
+            "2 * x^2 -> centimetre^2",
+            "cm^2*s    [Length^2 * Time]",
+        ),
+    ] {
+        let mut cmd = numbat();
+        if ascii_operators {
+            cmd.arg("--ascii-operators");
+        }
+        cmd.arg("--pretty-print=always")
+            .arg("--expression")
+            .arg("let x = 3 m")
+            .arg("--expression")
+            .arg("2 x^2 -> cm^2")
+            .arg("--expression")
+            .arg("2 x^2 s -> cm^2 s")
+            .assert()
+            .success()
+            .stdout(predicates::str::contains(expected_expression))
+            .stdout(predicates::str::contains(expected_result));
+    }
+}
+
 #[test]
 fn help_text() {
     numbat()
@@ -0,0 +1,75 @@
+use alloc::{format, string::String};
+
+use num_rational::Ratio;
+use num_traits::{Float, Signed};
+
+pub type Rational = Ratio<i128>;
+pub type Exponent = Rational;
+
+pub trait Power {
+    fn power(self, e: Exponent) -> Self;
+
+    fn invert(self) -> Self
+    where
+        Self: Sized,
+    {
+        self.power(Exponent::from_integer(-1))
+    }
+}
+
+/// Ratios of quantities are computed in floating point arithmetic, which results in
+/// values like `12.000000000000002` for `1 ft / 1 inch`. Instead of converting such a
+/// value exactly, we use the first convergent of its continued fraction expansion
+/// that is within a small relative distance. This is used by both the type checker
+/// and the VM, such that they agree on the unit of `a^(1 ft / 1 inch)`.
+pub fn approximate_ratio(value: f64) -> Option<Exponent> {
+    const TOLERANCE: f64 = 1e-12;
+
+    let (mut numer_prev, mut numer) = (0i128, 1i128);
+    let (mut denom_prev, mut denom) = (1i128, 0i128);
+    let mut x = value;
+    for _ in 0..64 {
+        let term = Float::floor(x);
+        if !term.is_finite() || Float::abs(term) > i64::MAX as f64 {
+            return None;
+        }
+        let term = term as i128;
+
+        (numer_prev, numer) = (numer, term.checked_mul(numer)?.checked_add(numer_prev)?);
+        (denom_prev, denom) = (denom, term.checked_mul(denom)?.checked_add(denom_prev)?);
+
+        if Float::abs(numer as f64 / denom as f64 - value) <= TOLERANCE * Float::abs(value) {
+            return Some(Rational::new(numer, denom));
+        }
+        x = 1.0 / (x - term as f64);
+    }
+    None
+}
+
+pub fn pretty_exponent(e: &Exponent) -> String {
+    if e == &Ratio::from_integer(5) {
+        "⁵".into()
+    } else if e == &Ratio::from_integer(4) {
+        "⁴".into()
+    } else if e == &Ratio::from_integer(3) {
+        "³".into()
+    } else if e == &Ratio::from_integer(2) {
+        "²".into()
+    } else if e == &Ratio::from_integer(1) {
+        "".into()
+    } else if e == &Ratio::from_integer(-1) {
+        "⁻¹".into()
+    } else if e == &Ratio::from_integer(-2) {
+        "⁻²".into()
+    } else if e == &Ratio::from_integer(-3) {
+        "⁻³".into()
+    } else if e == &Ratio::from_integer(-4) {
+        "⁻⁴".into()
+    } else if e == &Ratio::from_integer(-5) {
+        "⁻⁵".into()
+    } else if e.is_positive() && e.is_integer() {
+        format!("^{}", e)
+    } else {
+        format!("^({})", e)
+    }
+}
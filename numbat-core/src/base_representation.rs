@@ -0,0 +1,67 @@
+use alloc::string::String;
+use core::fmt::Display;
+
+use num_traits::Zero;
+
+use crate::{
+    arithmetic::{pretty_exponent, Exponent, Power, Rational},
+    pretty_print::PrettyPrint,
+    product::{Canonicalize, Product},
+};
+
+pub type BaseEntry = String;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BaseRepresentationFactor(pub BaseEntry, pub Exponent);
+
+impl Display for BaseRepresentationFactor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{}", self.0, pretty_exponent(&self.1))
+    }
+}
+
+impl Canonicalize for BaseRepresentationFactor {
+    type MergeKey = BaseEntry;
+
+    fn merge_key(&self) -> Self::MergeKey {
+        self.0.clone() // TODO(minor): can cloning be prevented here?
+    }
+
+    fn merge(self, other: Self) -> Self {
+        BaseRepresentationFactor(self.0, self.1 + other.1)
+    }
+
+    fn is_trivial(&self) -> bool {
+        self.1 == Rational::zero()
+    }
+}
+
+impl Power for BaseRepresentationFactor {
+    fn power(self, e: Exponent) -> Self {
+        let BaseRepresentationFactor(entry, exp) = self;
+        BaseRepresentationFactor(entry, exp * e)
+    }
+}
+
+// TODO(minor): this could be represented with a base index in the first tuple component instead of a cloned string
+pub type BaseRepresentation = Product<BaseRepresentationFactor, true>;
+
+impl Display for BaseRepresentation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.iter().count() == 0 {
+            f.write_str("Scalar")
+        } else {
+            f.write_str(&self.as_string(|f| f.1, '×', '/', true))
+        }
+    }
+}
+
+impl PrettyPrint for BaseRepresentation {
+    fn pretty_print(&self) -> crate::markup::Markup {
+        if self.iter().count() == 0 {
+            crate::markup::type_identifier("Scalar")
+        } else {
+            self.pretty_print_with(|f| f.1, '×', '/', true, None)
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Options for formatting quantities, see [`crate::quantity::Quantity::pretty_print_with_options`].
+
+use crate::number::RoundingMode;
+use crate::unit::Unit;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrefixStyle {
+    /// Short unit names and prefixes, e.g. `km`
+    #[default]
+    Short,
+    /// Long unit names and prefixes, e.g. `kilometer`
+    Long,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DivisionStyle {
+    /// Factors with negative exponents are written as a division, e.g. `m/s`
+    #[default]
+    Division,
+    /// Factors with negative exponents are written as reciprocals, e.g. `m·s⁻¹`
+    Reciprocal,
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Maximum number of significant digits for non-integer numbers
+    pub precision: u8,
+    /// How the last significant digit is rounded
+    pub rounding_mode: RoundingMode,
+    pub prefix_style: PrefixStyle,
+    pub division_style: DivisionStyle,
+    /// If set, the quantity is converted to this unit before formatting
+    pub target_unit: Option<Unit>,
+    /// Character between the integer and the fractional part, e.g. `,` for `1,5 m`
+    pub decimal_separator: char,
+    /// Character between groups of three digits in large integers, e.g. `.` for `1.000.000`
+    pub thousands_separator: char,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            precision: 6,
+            rounding_mode: RoundingMode::default(),
+            prefix_style: PrefixStyle::default(),
+            division_style: DivisionStyle::default(),
+            target_unit: None,
+            decimal_separator: '.',
+            thousands_separator: '_',
+        }
+    }
+}
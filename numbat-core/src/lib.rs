@@ -0,0 +1,23 @@
+//! Numbers, units and physical quantities of the Numbat language.
+//!
+//! This is the part of Numbat that can be used on targets without the standard
+//! library: the crate is `#![no_std]` (it only needs `alloc`) unless the `std`
+//! feature is enabled. The parser, type checker and the VM live in the `numbat`
+//! crate, which re-exports these modules.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod arithmetic;
+pub mod base_representation;
+pub mod format;
+pub mod markup;
+pub mod number;
+pub mod prefix;
+pub mod pretty_print;
+pub mod product;
+pub mod quantity;
+pub mod unit;
@@ -1,4 +1,9 @@
-use std::fmt::Display;
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Display;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FormatType {
@@ -14,6 +19,12 @@ pub enum FormatType {
     TypeIdentifier,
     Operator,
     Decorator,
+    /// A URL. Formatters that support it render this as a clickable link.
+    Link,
+    /// Error messages and other output related to errors.
+    Error,
+    /// Warnings, which are rendered differently from errors.
+    Warning,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -35,12 +46,12 @@ impl Markup {
 }
 
 impl Display for Markup {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", PlainTextFormatter {}.format(self, false))
     }
 }
 
-impl std::ops::Add for Markup {
+impl core::ops::Add for Markup {
     type Output = Markup;
 
     fn add(self, rhs: Self) -> Self::Output {
@@ -50,13 +61,13 @@ impl std::ops::Add for Markup {
     }
 }
 
-impl std::ops::AddAssign for Markup {
+impl core::ops::AddAssign for Markup {
     fn add_assign(&mut self, rhs: Self) {
         self.0.extend(rhs.0)
     }
 }
 
-impl std::iter::Sum for Markup {
+impl core::iter::Sum for Markup {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         iter.fold(empty(), |acc, n| acc + n)
     }
@@ -170,6 +181,30 @@ pub fn decorator(text: impl AsRef<str>) -> Markup {
     ))
 }
 
+pub fn link(url: impl AsRef<str>) -> Markup {
+    Markup::from(FormattedString(
+        OutputType::Normal,
+        FormatType::Link,
+        url.as_ref().to_string(),
+    ))
+}
+
+pub fn error(text: impl AsRef<str>) -> Markup {
+    Markup::from(FormattedString(
+        OutputType::Normal,
+        FormatType::Error,
+        text.as_ref().to_string(),
+    ))
+}
+
+pub fn warning(text: impl AsRef<str>) -> Markup {
+    Markup::from(FormattedString(
+        OutputType::Normal,
+        FormatType::Warning,
+        text.as_ref().to_string(),
+    ))
+}
+
 pub fn nl() -> Markup {
     Markup::from(FormattedString(
         OutputType::Normal,
@@ -209,3 +244,118 @@ impl Formatter for PlainTextFormatter {
         text.clone()
     }
 }
+
+/// ASCII equivalent of a Unicode operator, if there is one.
+fn ascii_operator(operator: &str) -> Option<&'static str> {
+    match operator {
+        "×" | "·" => Some("*"),
+        "➞" => Some("->"),
+        "≤" => Some("<="),
+        "≥" => Some(">="),
+        "≠" => Some("!="),
+        "²" => Some("^2"),
+        "³" => Some("^3"),
+        _ => None,
+    }
+}
+
+/// ASCII equivalent of a superscript digit or minus sign.
+fn ascii_superscript(c: char) -> Option<char> {
+    match c {
+        '⁻' => Some('-'),
+        '⁰' => Some('0'),
+        '¹' => Some('1'),
+        '²' => Some('2'),
+        '³' => Some('3'),
+        '⁴' => Some('4'),
+        '⁵' => Some('5'),
+        '⁶' => Some('6'),
+        '⁷' => Some('7'),
+        '⁸' => Some('8'),
+        '⁹' => Some('9'),
+        _ => None,
+    }
+}
+
+/// Replaces exponents and `·` in a unit like `km²·s⁻¹` (or a type like `Length²`)
+/// by `km^2*s^-1`.
+fn ascii_exponents(text: &str) -> String {
+    let mut output = String::new();
+    let mut in_exponent = false;
+    for c in text.chars() {
+        match ascii_superscript(c) {
+            Some(ascii) => {
+                if !in_exponent {
+                    output.push('^');
+                }
+                output.push(ascii);
+                in_exponent = true;
+            }
+            None => {
+                output.push(if c == '·' { '*' } else { c });
+                in_exponent = false;
+            }
+        }
+    }
+    output
+}
+
+/// A formatter that replaces Unicode operators like `×`, `➞`, `≤` or `²` by their
+/// ASCII equivalents (`*`, `->`, `<=`, `^2`), e.g. for terminals that can not
+/// display them. Units like `km²·s` are written as `km^2*s`. The output can still
+/// be parsed by Numbat. All other parts are formatted by the wrapped formatter.
+pub struct AsciiOperators<F>(pub F);
+
+impl<F: Formatter> Formatter for AsciiOperators<F> {
+    fn format_part(&self, part: &FormattedString) -> String {
+        let FormattedString(output_type, format_type, text) = part;
+
+        let ascii = match format_type {
+            FormatType::Operator => ascii_operator(text).map(|ascii| ascii.to_string()),
+            FormatType::Unit | FormatType::TypeIdentifier => Some(ascii_exponents(text)),
+            _ => None,
+        };
+
+        match ascii {
+            Some(ascii) => {
+                self.0
+                    .format_part(&FormattedString(output_type.clone(), *format_type, ascii))
+            }
+            None => self.0.format_part(part),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_exponents_in_units() {
+        for (name, ascii) in [
+            ("m", "m"),
+            ("m²", "m^2"),
+            ("m/s²", "m/s^2"),
+            ("km²·s", "km^2*s"),
+            ("kg·m²·s⁻³", "kg*m^2*s^-3"),
+            ("°", "°"),
+        ] {
+            assert_eq!(
+                AsciiOperators(PlainTextFormatter {}).format(&unit(name), false),
+                ascii
+            );
+        }
+
+        assert_eq!(
+            AsciiOperators(PlainTextFormatter {}).format(
+                &(type_identifier("Length²")
+                    + space()
+                    + operator("×")
+                    + space()
+                    + type_identifier("Time")),
+                false
+            ),
+            "Length^2 * Time"
+        );
+    }
+}
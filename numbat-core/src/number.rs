@@ -0,0 +1,326 @@
+use alloc::{format, string::String};
+
+use num_traits::{Float, Pow};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)] // TODO: we probably want to remove 'Copy' once we move to a more sophisticated numerical type
+pub struct Number(pub f64);
+
+impl Eq for Number {}
+
+/// How numbers are rounded, both by functions like `round` and when numbers are
+/// printed with a limited number of significant digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Round to the nearest neighbor. If the value is half-way between two neighbors,
+    /// round to the even one: `2.5 → 2`, `3.5 → 4` (banker's rounding)
+    #[default]
+    HalfEven,
+    /// Round to the nearest neighbor. If the value is half-way between two neighbors,
+    /// round away from zero: `2.5 → 3`, `-2.5 → -3`
+    HalfUp,
+    /// Round towards zero, i.e. drop all further digits: `2.9 → 2`, `-2.9 → -2`
+    Truncate,
+}
+
+impl RoundingMode {
+    /// Round the given value to an integer
+    pub fn round(self, x: f64) -> f64 {
+        match self {
+            RoundingMode::HalfEven => {
+                let rounded = Float::round(x);
+                if Float::abs(rounded - x) == 0.5 {
+                    2.0 * Float::round(x / 2.0)
+                } else {
+                    rounded
+                }
+            }
+            RoundingMode::HalfUp => Float::round(x),
+            RoundingMode::Truncate => Float::trunc(x),
+        }
+    }
+
+    /// Round the given value to `digits` significant digits
+    pub fn round_to_significant_digits(self, x: f64, digits: i32) -> f64 {
+        if x == 0.0 || !x.is_finite() {
+            return x;
+        }
+
+        // Scale by a power of ten such that the digits to keep end up in front of the decimal
+        // point. Dividing by (instead of multiplying with) the inverse of a large factor avoids
+        // rounding errors in the factor itself.
+        let exponent = digits - 1 - Float::floor(Float::log10(Float::abs(x))) as i32;
+        if exponent >= 0 {
+            let factor = Float::powi(10f64, exponent);
+            self.round(x * factor) / factor
+        } else {
+            let factor = Float::powi(10f64, -exponent);
+            self.round(x / factor) * factor
+        }
+    }
+}
+
+impl Number {
+    pub fn from_f64(n: f64) -> Self {
+        Number(n)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        let Number(n) = self;
+        n
+    }
+
+    pub fn pow(self, other: &Number) -> Self {
+        Number::from_f64(self.to_f64().pow(other.to_f64()))
+    }
+
+    #[cfg(feature = "std")]
+    fn is_integer(self) -> bool {
+        Float::trunc(self.0) == self.0
+    }
+
+    pub fn pretty_print(self) -> String {
+        self.pretty_print_with_precision(6)
+    }
+
+    /// Like [`Number::pretty_print`], but with the given (maximum) number of
+    /// significant digits for non-integer numbers.
+    pub fn pretty_print_with_precision(self, significant_digits: u8) -> String {
+        self.pretty_print_with_rounding(significant_digits, RoundingMode::default())
+    }
+
+    /// Like [`Number::pretty_print_with_precision`], but with the given rounding mode
+    /// for the last significant digit.
+    ///
+    /// Without the `std` feature, numbers are printed with Rust's own float formatting,
+    /// which ignores the number of significant digits and the rounding mode.
+    #[cfg(not(feature = "std"))]
+    pub fn pretty_print_with_rounding(
+        self,
+        _significant_digits: u8,
+        _rounding_mode: RoundingMode,
+    ) -> String {
+        format!("{}", self.0)
+    }
+
+    /// Like [`Number::pretty_print_with_precision`], but with the given rounding mode
+    /// for the last significant digit.
+    #[cfg(feature = "std")]
+    pub fn pretty_print_with_rounding(
+        self,
+        significant_digits: u8,
+        rounding_mode: RoundingMode,
+    ) -> String {
+        use alloc::string::ToString;
+        use num_traits::ToPrimitive;
+
+        let number = self.0;
+
+        // 64-bit floats can accurately represent integers up to 2^52 [1],
+        // which is approximately 4.5 × 10^15.
+        //
+        // [1] https://stackoverflow.com/a/43656339
+        //
+        if self.is_integer() && Float::abs(self.0) < 1e15 {
+            use num_format::{CustomFormat, Grouping, ToFormattedString};
+
+            let format = CustomFormat::builder()
+                .grouping(if Float::abs(self.0) >= 100_000.0 {
+                    Grouping::Standard
+                } else {
+                    Grouping::Posix
+                })
+                .minus_sign("-")
+                .separator("_")
+                .build()
+                .unwrap();
+
+            number
+                .to_i64()
+                .expect("small enough integers are representable as i64")
+                .to_formatted_string(&format)
+        } else {
+            use pretty_dtoa::{dtoa, FmtFloatConfig};
+
+            let config = FmtFloatConfig::default()
+                .max_significant_digits(significant_digits)
+                .add_point_zero(false)
+                .lower_e_break(-6)
+                .upper_e_break(6);
+
+            // `pretty_dtoa` only rounds half-way cases up, so ties that should be
+            // rounded to the even neighbor are truncated instead.
+            let config = match rounding_mode {
+                RoundingMode::HalfEven if is_tie_with_even_digit(number, significant_digits) => {
+                    config.truncate()
+                }
+                RoundingMode::HalfEven | RoundingMode::HalfUp => config.round(),
+                RoundingMode::Truncate => config.truncate(),
+            };
+
+            let formatted_number = dtoa(number, config);
+
+            if formatted_number.contains('.') && !formatted_number.contains('e') {
+                let formatted_number = formatted_number.trim_end_matches('0');
+                if formatted_number.ends_with('.') {
+                    // The number is not an integer, but looks like one after rounding to
+                    // the given number of significant digits. Keep the `.0` to indicate
+                    // that the value is not exact. Exact integers are handled above.
+                    format!("{}0", formatted_number)
+                } else {
+                    formatted_number.to_string()
+                }
+            } else if formatted_number.contains('e') && !formatted_number.contains("e-") {
+                formatted_number.replace('e', "e+")
+            } else {
+                formatted_number
+            }
+        }
+    }
+}
+
+/// Whether the shortest decimal representation of `number` ends with a `5` directly after
+/// the given number of significant digits, and the last significant digit is even.
+#[cfg(feature = "std")]
+fn is_tie_with_even_digit(number: f64, significant_digits: u8) -> bool {
+    let n = significant_digits as usize;
+    if n == 0 {
+        return false;
+    }
+
+    let representation = format!("{:e}", Float::abs(number));
+    let digits: alloc::vec::Vec<u8> = representation
+        .split('e')
+        .next()
+        .unwrap_or_default()
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .collect();
+
+    digits.len() == n + 1 && digits[n] == b'5' && (digits[n - 1] - b'0') % 2 == 0
+}
+
+impl core::ops::Add for Number {
+    type Output = Number;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Number(self.0 + rhs.0)
+    }
+}
+
+impl core::ops::Sub for Number {
+    type Output = Number;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Number(self.0 - rhs.0)
+    }
+}
+
+impl core::ops::Mul for Number {
+    type Output = Number;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Number(self.0 * rhs.0)
+    }
+}
+
+impl core::ops::Div for Number {
+    type Output = Number;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Number(self.0 / rhs.0)
+    }
+}
+
+impl core::ops::Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Self::Output {
+        Number(-self.0)
+    }
+}
+
+impl core::iter::Product for Number {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Number::from_f64(1.0), |acc, n| acc * n)
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_pretty_print() {
+    assert_eq!(Number::from_f64(1.).pretty_print(), "1");
+    assert_eq!(Number::from_f64(100.).pretty_print(), "100");
+    assert_eq!(Number::from_f64(1.234).pretty_print(), "1.234");
+    assert_eq!(Number::from_f64(12345.6).pretty_print(), "12345.6");
+    assert_eq!(Number::from_f64(1.234e50).pretty_print(), "1.234e+50");
+    assert_eq!(Number::from_f64(-1.234e50).pretty_print(), "-1.234e+50");
+    assert_eq!(Number::from_f64(1.234e-50).pretty_print(), "1.234e-50");
+    assert_eq!(Number::from_f64(-1.234e-50).pretty_print(), "-1.234e-50");
+
+    assert_eq!(Number::from_f64(1234.).pretty_print(), "1234");
+    assert_eq!(Number::from_f64(12345.).pretty_print(), "12345");
+    assert_eq!(Number::from_f64(123456.).pretty_print(), "123_456");
+    assert_eq!(
+        Number::from_f64(1234567890.).pretty_print(),
+        "1_234_567_890"
+    );
+    assert_eq!(
+        Number::from_f64(1234567890000000.).pretty_print(),
+        "1.23457e+15"
+    );
+
+    assert_eq!(Number::from_f64(1.23456789).pretty_print(), "1.23457");
+    assert_eq!(
+        Number::from_f64(1234567890000.1).pretty_print(),
+        "1.23457e+12"
+    );
+
+    assert_eq!(Number::from_f64(100.00001).pretty_print(), "100.0");
+
+    assert_eq!(Number::from_f64(0.00001).pretty_print(), "0.00001");
+    assert_eq!(Number::from_f64(0.000001).pretty_print(), "0.000001");
+    assert_eq!(Number::from_f64(0.0000001).pretty_print(), "1.0e-7");
+}
+
+#[test]
+fn test_rounding_modes() {
+    let round = |mode: RoundingMode, x: f64| mode.round(x);
+
+    for (x, half_even, half_up, truncate) in [
+        (2.5, 2.0, 3.0, 2.0),
+        (3.5, 4.0, 4.0, 3.0),
+        (-2.5, -2.0, -3.0, -2.0),
+        (-3.5, -4.0, -4.0, -3.0),
+        (0.5, 0.0, 1.0, 0.0),
+        (2.4, 2.0, 2.0, 2.0),
+        (2.6, 3.0, 3.0, 2.0),
+        (-2.6, -3.0, -3.0, -2.0),
+    ] {
+        assert_eq!(
+            round(RoundingMode::HalfEven, x),
+            half_even,
+            "half-even: {x}"
+        );
+        assert_eq!(round(RoundingMode::HalfUp, x), half_up, "half-up: {x}");
+        assert_eq!(round(RoundingMode::Truncate, x), truncate, "truncate: {x}");
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_pretty_print_with_rounding() {
+    let pretty =
+        |x: f64, mode: RoundingMode| Number::from_f64(x).pretty_print_with_rounding(2, mode);
+
+    assert_eq!(pretty(0.125, RoundingMode::HalfEven), "0.12");
+    assert_eq!(pretty(0.135, RoundingMode::HalfEven), "0.14");
+    assert_eq!(pretty(-0.125, RoundingMode::HalfEven), "-0.12");
+    assert_eq!(pretty(0.1251, RoundingMode::HalfEven), "0.13");
+    assert_eq!(pretty(0.125, RoundingMode::HalfUp), "0.13");
+    assert_eq!(pretty(0.135, RoundingMode::HalfUp), "0.14");
+    assert_eq!(pretty(0.129, RoundingMode::Truncate), "0.12");
+    assert_eq!(pretty(2.5e10 + 0.5, RoundingMode::HalfEven), "2.5e+10");
+
+    assert_eq!(Number::from_f64(1.234565).pretty_print(), "1.23456");
+    assert_eq!(Number::from_f64(1.234575).pretty_print(), "1.23458");
+}
@@ -1,5 +1,71 @@
+use alloc::{format, string::String};
+
 use crate::number::Number;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptsPrefix {
+    pub short: bool,
+    pub long: bool,
+}
+
+impl AcceptsPrefix {
+    pub fn only_long() -> Self {
+        Self {
+            long: true,
+            short: false,
+        }
+    }
+
+    pub fn only_short() -> Self {
+        Self {
+            long: false,
+            short: true,
+        }
+    }
+
+    pub fn both() -> Self {
+        Self {
+            long: true,
+            short: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            long: false,
+            short: false,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn powi(base: f64, exp: i32) -> f64 {
+    base.powi(exp)
+}
+
+/// Same algorithm as the runtime implementation of `f64::powi`, such that prefix
+/// factors are bit-identical with and without `std` (`libm` only has `pow`).
+#[cfg(not(feature = "std"))]
+fn powi(mut base: f64, exp: i32) -> f64 {
+    let mut n = exp.unsigned_abs();
+    let mut result = 1.0;
+    loop {
+        if n & 1 == 1 {
+            result *= base;
+        }
+        n /= 2;
+        if n == 0 {
+            break;
+        }
+        base *= base;
+    }
+    if exp < 0 {
+        1.0 / result
+    } else {
+        result
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Prefix {
     /// Represents a metric/decimal prefix symbolizing 10^n
@@ -11,8 +77,8 @@ pub enum Prefix {
 impl Prefix {
     pub fn factor(&self) -> Number {
         match self {
-            Prefix::Metric(exp) => Number::from_f64(10.0f64.powi(*exp)),
-            Prefix::Binary(exp) => Number::from_f64(2.0f64.powi(*exp)),
+            Prefix::Metric(exp) => Number::from_f64(powi(10.0, *exp)),
+            Prefix::Binary(exp) => Number::from_f64(powi(2.0, *exp)),
         }
     }
 
@@ -20,67 +86,67 @@ impl Prefix {
         Prefix::Metric(0)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn micro() -> Self {
         Prefix::Metric(-6)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn milli() -> Self {
         Prefix::Metric(-3)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn centi() -> Self {
         Prefix::Metric(-2)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn deci() -> Self {
         Prefix::Metric(-1)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn deca() -> Self {
         Prefix::Metric(1)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn hecto() -> Self {
         Prefix::Metric(2)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn kilo() -> Self {
         Prefix::Metric(3)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn mega() -> Self {
         Prefix::Metric(6)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn giga() -> Self {
         Prefix::Metric(9)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn tera() -> Self {
         Prefix::Metric(12)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn kibi() -> Self {
         Prefix::Binary(10)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn mebi() -> Self {
         Prefix::Binary(20)
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn gibi() -> Self {
         Prefix::Binary(30)
     }
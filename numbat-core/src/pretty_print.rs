@@ -1,3 +1,5 @@
+use alloc::string::String;
+
 use crate::markup::Markup;
 
 pub trait PrettyPrint {
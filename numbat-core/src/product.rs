@@ -1,4 +1,9 @@
-use std::{
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
     fmt::Display,
     ops::{Div, Mul},
 };
@@ -255,7 +260,7 @@ impl<Factor, const CANONICALIZE: bool> IntoIterator for Product<Factor, CANONICA
     }
 }
 
-impl<Factor: Clone + Ord + Canonicalize, const CANONICALIZE: bool> std::iter::Product<Factor>
+impl<Factor: Clone + Ord + Canonicalize, const CANONICALIZE: bool> core::iter::Product<Factor>
     for Product<Factor, CANONICALIZE>
 {
     fn product<I>(iter: I) -> Self
@@ -266,7 +271,7 @@ impl<Factor: Clone + Ord + Canonicalize, const CANONICALIZE: bool> std::iter::Pr
     }
 }
 
-impl<Factor: Clone + Ord + Canonicalize, const CANONICALIZE: bool> std::iter::Product
+impl<Factor: Clone + Ord + Canonicalize, const CANONICALIZE: bool> core::iter::Product
     for Product<Factor, CANONICALIZE>
 {
     fn product<I>(iter: I) -> Self
@@ -278,7 +283,7 @@ impl<Factor: Clone + Ord + Canonicalize, const CANONICALIZE: bool> std::iter::Pr
 }
 
 pub struct ProductIter<'a, Factor> {
-    inner: std::slice::Iter<'a, Factor>,
+    inner: core::slice::Iter<'a, Factor>,
 }
 
 impl<'a, Factor> Iterator for ProductIter<'a, Factor> {
@@ -290,7 +295,7 @@ impl<'a, Factor> Iterator for ProductIter<'a, Factor> {
 }
 
 pub struct ProductIntoIter<Factor> {
-    inner: std::vec::IntoIter<Factor>,
+    inner: alloc::vec::IntoIter<Factor>,
 }
 
 impl<Factor> Iterator for ProductIntoIter<Factor> {
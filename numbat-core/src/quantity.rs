@@ -0,0 +1,1082 @@
+use crate::arithmetic::{approximate_ratio, Exponent, Power};
+use crate::format::FormatOptions;
+use crate::number::Number;
+use crate::prefix::{AcceptsPrefix, Prefix};
+use crate::pretty_print::PrettyPrint;
+use crate::unit::{is_multiple_of, CanonicalName, Unit, UnitFactor};
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use itertools::Itertools;
+use num_rational::Ratio;
+use num_traits::{Euclid, Float, Zero};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuantityError {
+    IncompatibleUnits(Unit, Unit), // TODO: this can currently be triggered if there are multiple base units for the same dimension (no way to convert between them)
+
+    NonRationalExponent,
+
+    InvalidBinaryRepresentation,
+}
+
+// Written by hand instead of deriving `thiserror::Error`, which needs `std`.
+impl fmt::Display for QuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantityError::IncompatibleUnits(from, to) => write!(
+                f,
+                "Conversion error: unit '{from}' can not be converted to '{to}'"
+            ),
+            QuantityError::NonRationalExponent => write!(f, "Non-rational exponent"),
+            QuantityError::InvalidBinaryRepresentation => {
+                write!(f, "Invalid binary representation of a quantity")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QuantityError {}
+
+pub type Result<T> = core::result::Result<T, QuantityError>;
+
+/// Largest exponent (in magnitude) that is applied to the unit of a dimensionless
+/// quantity. For larger exponents, the quantity is converted to a scalar first, as
+/// the exponents of the unit factors could otherwise overflow.
+const MAX_DIMENSIONLESS_UNIT_EXPONENT: f64 = 1e6;
+
+/// Version of the binary format written by [`Quantity::to_bytes`]
+const BINARY_FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Quantity {
+    value: Number,
+    unit: Unit,
+}
+
+impl Quantity {
+    pub fn new(value: Number, unit: Unit) -> Self {
+        Quantity { value, unit }
+    }
+
+    pub fn new_f64(value: f64, unit: Unit) -> Self {
+        Quantity {
+            value: Number::from_f64(value),
+            unit,
+        }
+    }
+
+    pub fn from_scalar(value: f64) -> Quantity {
+        Quantity::new_f64(value, Unit::scalar())
+    }
+
+    pub fn from_unit(unit: Unit) -> Quantity {
+        Quantity::new_f64(1.0, unit)
+    }
+
+    pub fn unit(&self) -> &Unit {
+        &self.unit
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value.to_f64() == 0.0
+    }
+
+    pub fn abs(&self) -> Quantity {
+        Quantity::new_f64(Float::abs(self.value.to_f64()), self.unit.clone())
+    }
+
+    /// The sign of the quantity as a dimensionless number: -1, 0 or 1 (or NaN for NaN inputs).
+    pub fn signum(&self) -> Quantity {
+        let value = self.value.to_f64();
+        Quantity::from_scalar(if value == 0.0 { 0.0 } else { value.signum() })
+    }
+
+    pub fn to_base_unit_representation(&self) -> Quantity {
+        let (unit, factor) = self.unit.to_base_unit_representation();
+        Quantity::new(self.value * factor, unit)
+    }
+
+    /// Like [`Quantity::to_base_unit_representation`], but in coherent SI units. These
+    /// only differ for masses, which use kilogram instead of the base unit gram.
+    pub fn to_si_base_units(&self) -> Quantity {
+        let base = self.to_base_unit_representation();
+        let si_unit = Unit::from_factors(base.unit.iter().map(|factor| UnitFactor {
+            prefix: if factor.unit_id.name == "gram" {
+                Prefix::Metric(3)
+            } else {
+                factor.prefix
+            },
+            ..factor.clone()
+        }));
+        base.convert_to(&si_unit)
+            .expect("conversion between units with the same base units")
+    }
+
+    /// Relative tolerance used by [`Quantity::display_eq`]. A few ulps are enough to
+    /// absorb rounding errors from unit conversions like `100 cm -> m`.
+    pub const DISPLAY_EQ_RELATIVE_TOLERANCE: f64 = 4.0 * f64::EPSILON;
+
+    /// Whether two quantities describe the same physical value, up to the given relative
+    /// tolerance. Unlike `==`, which compares the exact values after a conversion, both
+    /// quantities are first normalized to base units, and the values `a` and `b` are
+    /// considered equal if `|a - b| <= relative_tolerance × max(|a|, |b|)`. Quantities
+    /// of different dimensions are never equal, NaN is never equal to anything.
+    pub fn approx_eq(&self, other: &Self, relative_tolerance: f64) -> bool {
+        let lhs = self.to_base_unit_representation();
+        let rhs = other.to_base_unit_representation();
+        if lhs.unit != rhs.unit {
+            return false;
+        }
+
+        let a = lhs.value.to_f64();
+        let b = rhs.value.to_f64();
+        a == b || Float::abs(a - b) <= relative_tolerance * Float::abs(a).max(Float::abs(b))
+    }
+
+    /// Whether two quantities are equal for display purposes, e.g. `1 m` and `100 cm`.
+    /// This is [`Quantity::approx_eq`] with [`Quantity::DISPLAY_EQ_RELATIVE_TOLERANCE`].
+    pub fn display_eq(&self, other: &Self) -> bool {
+        self.approx_eq(other, Self::DISPLAY_EQ_RELATIVE_TOLERANCE)
+    }
+
+    /// A key for deduplicating quantities in hash-based collections, consisting of the
+    /// base unit and the value in base units, rounded to 12 significant digits. Quantities
+    /// that are [`Quantity::display_eq`] have the same key, unless their values are right
+    /// next to a rounding boundary.
+    pub fn display_key(&self) -> (String, u64) {
+        let base = self.to_base_unit_representation();
+        let rounded: f64 = format!("{:.11e}", base.value.to_f64())
+            .parse()
+            .expect("formatted float can be parsed");
+        // Make sure that 0 and -0 have the same key
+        let rounded = if rounded == 0.0 { 0.0 } else { rounded };
+        (base.unit.to_string(), rounded.to_bits())
+    }
+
+    /// Serialize the quantity into a compact binary form: its value in base units, followed
+    /// by the base unit factors. The result can be read back with [`Quantity::from_bytes`],
+    /// which does not need access to the unit registry.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let base = self.to_base_unit_representation();
+
+        let mut bytes = vec![BINARY_FORMAT_VERSION];
+        bytes.extend(base.value.to_f64().to_le_bytes());
+
+        let factors: Vec<_> = base.unit.iter().collect();
+        write_varint(&mut bytes, factors.len() as u128);
+        for factor in factors {
+            let canonical_name = &factor.unit_id.canonical_name;
+            write_string(&mut bytes, &factor.unit_id.name);
+            write_string(&mut bytes, &canonical_name.name);
+            bytes.push(
+                u8::from(canonical_name.accepts_prefix.short)
+                    | (u8::from(canonical_name.accepts_prefix.long) << 1)
+                    | (u8::from(canonical_name.plural.is_some()) << 2),
+            );
+            if let Some(plural) = &canonical_name.plural {
+                write_string(&mut bytes, plural);
+            }
+
+            // Zigzag encoding, such that small negative numerators stay small
+            let numer = *factor.exponent.numer();
+            write_varint(&mut bytes, ((numer << 1) ^ (numer >> 127)) as u128);
+            write_varint(&mut bytes, *factor.exponent.denom() as u128);
+        }
+
+        bytes
+    }
+
+    /// Deserialize a quantity that was written by [`Quantity::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Quantity> {
+        let mut reader = ByteReader { bytes };
+
+        if reader.take(1)? != [BINARY_FORMAT_VERSION] {
+            return Err(QuantityError::InvalidBinaryRepresentation);
+        }
+
+        let value = f64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+
+        let mut unit = Unit::scalar();
+        for _ in 0..reader.varint()? {
+            let name = reader.string()?;
+            let display_name = reader.string()?;
+            let flags = reader.take(1)?[0];
+            let canonical_name = CanonicalName {
+                name: display_name,
+                accepts_prefix: AcceptsPrefix {
+                    short: flags & 0b001 != 0,
+                    long: flags & 0b010 != 0,
+                },
+                plural: if flags & 0b100 != 0 {
+                    Some(reader.string()?)
+                } else {
+                    None
+                },
+            };
+
+            let numer = reader.varint()?;
+            let numer = ((numer >> 1) as i128) ^ -((numer & 1) as i128);
+            let denom = i128::try_from(reader.varint()?)
+                .ok()
+                .filter(|d| *d != 0)
+                .ok_or(QuantityError::InvalidBinaryRepresentation)?;
+
+            unit = unit * Unit::new_base(&name, canonical_name).power(Ratio::new(numer, denom));
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(QuantityError::InvalidBinaryRepresentation);
+        }
+
+        Ok(Quantity::new_f64(value, unit))
+    }
+
+    pub fn convert_to(&self, target_unit: &Unit) -> Result<Quantity> {
+        if &self.unit == target_unit || self.unsafe_value().to_f64().is_zero() {
+            Ok(Quantity::new(self.value, target_unit.clone()))
+        } else {
+            // Remove common unit factors to reduce unnecessary conversion procedures
+            // For example: when converting from km/hour to mile/hour, there is no need
+            // to also perform the hour->second conversion, which would be needed, as
+            // we go back to base units for now. Removing common factors is just one
+            // heuristic, but it would be better to solve this in a more general way.
+            // For more details on this problem, see [1].
+            //
+            // [1] https://github.com/sharkdp/numbat/issues/118.
+            let mut common_unit_factors = Unit::scalar();
+            let target_unit_canonicalized = target_unit.canonicalized();
+
+            for factor in self.unit.canonicalized().iter() {
+                if let Some(other_factor) = target_unit_canonicalized
+                    .iter()
+                    .find(|&f| factor.prefix == f.prefix && factor.unit_id == f.unit_id)
+                {
+                    if factor.exponent > Ratio::zero() && other_factor.exponent > Ratio::zero() {
+                        common_unit_factors = common_unit_factors
+                            * Unit::from_factor(UnitFactor {
+                                exponent: core::cmp::min(factor.exponent, other_factor.exponent),
+                                ..factor.clone()
+                            });
+                    } else if factor.exponent < Ratio::zero()
+                        && other_factor.exponent < Ratio::zero()
+                    {
+                        common_unit_factors = common_unit_factors
+                            * Unit::from_factor(UnitFactor {
+                                exponent: core::cmp::max(factor.exponent, other_factor.exponent),
+                                ..factor.clone()
+                            });
+                    }
+                }
+            }
+
+            let target_unit_reduced =
+                (target_unit.clone() / common_unit_factors.clone()).canonicalized();
+            let own_unit_reduced =
+                (self.unit.clone() / common_unit_factors.clone()).canonicalized();
+
+            if let Some(value) =
+                own_unit_reduced.convert_via_common_unit(self.value, &target_unit_reduced)
+            {
+                return Ok(Quantity::new(value, target_unit.clone()));
+            }
+
+            let (target_base_unit_representation, factor) =
+                target_unit_reduced.to_base_unit_representation();
+
+            let quantity_base_unit_representation = (self.clone()
+                / Quantity::from_unit(common_unit_factors))
+            .to_base_unit_representation();
+            let own_base_unit_representation = own_unit_reduced.to_base_unit_representation().0;
+
+            if own_base_unit_representation == target_base_unit_representation {
+                Ok(Quantity::new(
+                    *quantity_base_unit_representation.unsafe_value() / factor,
+                    target_unit.clone(),
+                ))
+            } else {
+                // TODO: can this even be triggered? replace by an assertion?
+                Err(QuantityError::IncompatibleUnits(
+                    self.unit.clone(),
+                    target_unit.clone(),
+                ))
+            }
+        }
+    }
+
+    pub fn full_simplify(&self) -> Self {
+        // Heuristic 1
+        if let Ok(scalar_result) = self.convert_to(&Unit::scalar()) {
+            return scalar_result;
+        }
+
+        // Heuristic 2
+        let unit = self.unit.canonicalized();
+        if unit.iter().count() > 1 {
+            for factor in unit.iter() {
+                let mut factor = factor.clone();
+                factor.exponent = Exponent::from_integer(1);
+                let factor_unit = Unit::from_factor(factor);
+
+                if let Some(alpha) = is_multiple_of(&unit, &factor_unit) {
+                    if alpha.is_integer() {
+                        let simplified_unit = factor_unit.power(alpha);
+                        if let Ok(q) = self.convert_to(&simplified_unit) {
+                            return q;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Heuristic 3
+        let removed_exponent = |u: &UnitFactor| {
+            let base_unit = u.unit_id.base_unit_and_factor().0;
+            if let Some(first_factor) = base_unit.into_iter().next() {
+                first_factor.exponent
+            } else {
+                Ratio::from_integer(1)
+            }
+        };
+
+        let mut factor = Number::from_f64(1.0);
+        let mut simplified_unit = Unit::scalar();
+
+        for (_, group) in &self
+            .unit
+            .canonicalized()
+            .iter()
+            .group_by(|f| f.unit_id.sort_key())
+        {
+            let group_as_unit = Unit::from_factors(group.cloned());
+            let group_representative = group_as_unit
+                .iter()
+                .max_by(|&f1, &f2| {
+                    // TODO: describe this heuristic
+                    (f1.unit_id.is_base().cmp(&f2.unit_id.is_base()))
+                        .then(f1.exponent.cmp(&f2.exponent))
+                })
+                .expect("At least one unit factor in the group");
+
+            let target_unit = if group_as_unit.to_base_unit_representation().0.is_scalar() {
+                // If the group-representative is convertible to a scalar,
+                // use 'scalar' as the target unit instead. This allows us
+                // to simplify, for example, '3% · kg' to '0.03 kg'.
+                Unit::scalar()
+            } else {
+                let exponent = group_as_unit
+                    .iter()
+                    .map(|f| {
+                        f.exponent * removed_exponent(f) / removed_exponent(group_representative)
+                    })
+                    .sum();
+                Unit::from_factor(UnitFactor {
+                    exponent,
+                    ..group_representative.clone()
+                })
+            };
+
+            let converted = Quantity::from_unit(group_as_unit)
+                .convert_to(&target_unit)
+                .unwrap();
+
+            simplified_unit = simplified_unit * target_unit;
+            factor = factor * converted.value;
+        }
+
+        simplified_unit.canonicalize();
+
+        Quantity::new(self.value * factor, simplified_unit)
+    }
+
+    pub fn as_scalar(&self) -> Result<Number> {
+        Ok(self.convert_to(&Unit::scalar())?.value)
+    }
+
+    pub fn unsafe_value(&self) -> &Number {
+        &self.value
+    }
+
+    pub fn power(self, exp: Quantity) -> Result<Self> {
+        let exponent_as_scalar = exp.as_scalar()?.to_f64();
+
+        if exponent_as_scalar.is_nan()
+            || Float::abs(exponent_as_scalar) > MAX_DIMENSIONLESS_UNIT_EXPONENT
+        {
+            if let Ok(base) = self.as_scalar() {
+                return Ok(Quantity::from_scalar(Float::powf(
+                    base.to_f64(),
+                    exponent_as_scalar,
+                )));
+            }
+        }
+
+        Ok(Quantity::new_f64(
+            Float::powf(self.value.to_f64(), exponent_as_scalar),
+            self.unit.power(
+                approximate_ratio(exponent_as_scalar).ok_or(QuantityError::NonRationalExponent)?,
+            ),
+        ))
+    }
+
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        if other.is_zero() {
+            None
+        } else {
+            Some(self / other)
+        }
+    }
+
+    /// The (non-negative) remainder of the Euclidean division by `other`, in the
+    /// unit of `self`. Like [`Quantity::checked_div`], this returns `None` if `other`
+    /// is zero.
+    pub fn rem_euclid(&self, other: &Self) -> Result<Option<Self>> {
+        let divisor = other.convert_to(&self.unit)?.value.to_f64();
+        if divisor == 0.0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Quantity::new_f64(
+            Euclid::rem_euclid(&self.value.to_f64(), &divisor),
+            self.unit.clone(),
+        )))
+    }
+}
+
+impl From<&Number> for Quantity {
+    fn from(n: &Number) -> Self {
+        Quantity::from_scalar(n.to_f64())
+    }
+}
+
+impl core::ops::Add for &Quantity {
+    type Output = Result<Quantity>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.is_zero() {
+            Ok(rhs.clone())
+        } else if rhs.is_zero() {
+            Ok(self.clone())
+        } else {
+            Ok(Quantity {
+                value: self.value + rhs.convert_to(&self.unit)?.value,
+                unit: self.unit.clone(),
+            })
+        }
+    }
+}
+
+impl core::ops::Sub for &Quantity {
+    type Output = Result<Quantity>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        if self.is_zero() {
+            Ok(-rhs.clone())
+        } else if rhs.is_zero() {
+            Ok(self.clone())
+        } else {
+            Ok(Quantity {
+                value: self.value - rhs.convert_to(&self.unit)?.value,
+                unit: self.unit.clone(),
+            })
+        }
+    }
+}
+
+impl core::ops::Mul for Quantity {
+    type Output = Quantity;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Quantity {
+            value: self.value * rhs.value,
+            unit: self.unit * rhs.unit,
+        }
+    }
+}
+
+impl core::ops::Div for Quantity {
+    type Output = Quantity;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Quantity {
+            value: self.value / rhs.value,
+            unit: self.unit / rhs.unit,
+        }
+    }
+}
+
+impl core::ops::Neg for Quantity {
+    type Output = Quantity;
+
+    fn neg(self) -> Self::Output {
+        Quantity {
+            value: -self.value,
+            unit: self.unit,
+        }
+    }
+}
+
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        if let Ok(other_converted) = other.convert_to(self.unit()) {
+            self.value == other_converted.value
+        } else {
+            false
+        }
+    }
+}
+
+impl Eq for Quantity {}
+
+impl PartialOrd for Quantity {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        let other_converted = other.convert_to(self.unit()).ok()?;
+        self.value.partial_cmp(&other_converted.value)
+    }
+}
+
+impl Quantity {
+    /// Like [`PrettyPrint::pretty_print`], but with the given options. The quantity is
+    /// not converted to [`FormatOptions::target_unit`].
+    pub fn pretty_print_with_options(&self, options: &FormatOptions) -> crate::markup::Markup {
+        use crate::markup;
+
+        let formatted_number: String = self
+            .unsafe_value()
+            .pretty_print_with_rounding(options.precision, options.rounding_mode)
+            .chars()
+            .map(|c| match c {
+                '.' => options.decimal_separator,
+                '_' => options.thousands_separator,
+                c => c,
+            })
+            .collect();
+
+        let unit_str = self
+            .unit()
+            .to_string_with_options(*self.unsafe_value(), options);
+
+        markup::value(formatted_number)
+            + if unit_str == "°" || unit_str == "′" || unit_str == "″" || unit_str.is_empty() {
+                markup::empty()
+            } else {
+                markup::space()
+            }
+            + markup::unit(unit_str)
+    }
+}
+
+impl PrettyPrint for Quantity {
+    fn pretty_print(&self) -> crate::markup::Markup {
+        self.pretty_print_with_options(&FormatOptions::default())
+    }
+}
+
+impl core::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use crate::markup::{Formatter, PlainTextFormatter};
+
+        let markup = self.pretty_print();
+        let formatter = PlainTextFormatter {};
+        write!(f, "{}", formatter.format(&markup, false).trim())
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut n: u128) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    write_varint(bytes, s.len() as u128);
+    bytes.extend(s.as_bytes());
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.bytes.len() {
+            return Err(QuantityError::InvalidBinaryRepresentation);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn varint(&mut self) -> Result<u128> {
+        let mut n = 0u128;
+        for shift in (0..128).step_by(7) {
+            let byte = self.take(1)?[0];
+            n |= u128::from(byte & 0x7f)
+                .checked_shl(shift)
+                .ok_or(QuantityError::InvalidBinaryRepresentation)?;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(QuantityError::InvalidBinaryRepresentation)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = usize::try_from(self.varint()?)
+            .map_err(|_| QuantityError::InvalidBinaryRepresentation)?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| QuantityError::InvalidBinaryRepresentation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arithmetic::Rational;
+    use crate::prefix::Prefix;
+
+    use super::*;
+
+    #[test]
+    fn abs_and_signum() {
+        let meter = Unit::meter();
+
+        let q = Quantity::new_f64(-3.0, meter.clone());
+        assert_eq!(q.abs(), Quantity::new_f64(3.0, meter.clone()));
+        assert_eq!(q.signum(), Quantity::from_scalar(-1.0));
+
+        let q = Quantity::new_f64(2.5, meter.clone());
+        assert_eq!(q.abs(), q);
+        assert_eq!(q.signum(), Quantity::from_scalar(1.0));
+
+        let q = Quantity::new_f64(0.0, meter.clone());
+        assert_eq!(q.abs(), q);
+        assert_eq!(q.signum(), Quantity::from_scalar(0.0));
+        assert_eq!(
+            Quantity::new_f64(-0.0, meter).signum(),
+            Quantity::from_scalar(0.0)
+        );
+
+        assert!(Quantity::from_scalar(f64::NAN)
+            .signum()
+            .unsafe_value()
+            .to_f64()
+            .is_nan());
+    }
+
+    #[test]
+    fn checked_div() {
+        let meter = Unit::meter();
+        let second = Unit::second();
+
+        assert_eq!(
+            Quantity::new_f64(6.0, meter.clone())
+                .checked_div(Quantity::new_f64(2.0, second.clone())),
+            Some(Quantity::new_f64(3.0, meter.clone() / second.clone()))
+        );
+        assert_eq!(
+            Quantity::new_f64(1.0, meter.clone()).checked_div(Quantity::new_f64(0.0, second)),
+            None
+        );
+        assert_eq!(
+            Quantity::new_f64(1.0, meter.clone()).checked_div(Quantity::new_f64(0.0, meter)),
+            None
+        );
+        assert_eq!(
+            Quantity::from_scalar(1.0).checked_div(Quantity::from_scalar(0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn rem_euclid() {
+        let meter = Unit::meter();
+        let centimeter = Unit::centimeter();
+
+        assert_eq!(
+            Quantity::new_f64(7.0, meter.clone())
+                .rem_euclid(&Quantity::new_f64(3.0, meter.clone()))
+                .unwrap(),
+            Some(Quantity::new_f64(1.0, meter.clone()))
+        );
+        assert_eq!(
+            Quantity::new_f64(-7.0, meter.clone())
+                .rem_euclid(&Quantity::new_f64(3.0, meter.clone()))
+                .unwrap(),
+            Some(Quantity::new_f64(2.0, meter.clone()))
+        );
+        assert_eq!(
+            Quantity::new_f64(250.0, centimeter.clone())
+                .rem_euclid(&Quantity::new_f64(1.0, meter.clone()))
+                .unwrap(),
+            Some(Quantity::new_f64(50.0, centimeter))
+        );
+        assert_eq!(
+            Quantity::new_f64(1.0, meter.clone())
+                .rem_euclid(&Quantity::new_f64(0.0, Unit::centimeter()))
+                .unwrap(),
+            None
+        );
+        assert!(Quantity::new_f64(1.0, meter)
+            .rem_euclid(&Quantity::new_f64(1.0, Unit::second()))
+            .is_err());
+    }
+
+    #[test]
+    fn display_equality() {
+        let one_meter = Quantity::new_f64(1.0, Unit::meter());
+        let hundred_centimeters = Quantity::new_f64(100.0, Unit::centimeter());
+        let slightly_longer = Quantity::new_f64(1.0001, Unit::meter());
+
+        assert!(one_meter.display_eq(&hundred_centimeters));
+        assert!(hundred_centimeters.display_eq(&one_meter));
+        assert!(!one_meter.display_eq(&slightly_longer));
+        assert!(!one_meter.display_eq(&Quantity::new_f64(1.0, Unit::second())));
+
+        assert!(one_meter.approx_eq(&slightly_longer, 1e-3));
+        assert!(!one_meter.approx_eq(&slightly_longer, 1e-5));
+
+        let nan = Quantity::new_f64(f64::NAN, Unit::meter());
+        assert!(!nan.display_eq(&nan));
+        let inf = Quantity::new_f64(f64::INFINITY, Unit::meter());
+        assert!(inf.display_eq(&inf));
+
+        assert_eq!(one_meter.display_key(), hundred_centimeters.display_key());
+        assert_ne!(one_meter.display_key(), slightly_longer.display_key());
+        assert_eq!(
+            Quantity::new_f64(0.0, Unit::meter()).display_key(),
+            Quantity::new_f64(-0.0, Unit::centimeter()).display_key()
+        );
+
+        let distinct: alloc::collections::BTreeSet<_> =
+            [&one_meter, &hundred_centimeters, &slightly_longer]
+                .iter()
+                .map(|q| q.display_key())
+                .collect();
+        assert_eq!(distinct.len(), 2);
+    }
+
+    #[test]
+    fn conversion_trivial() {
+        let meter = Unit::meter();
+        let second = Unit::second();
+
+        let length = Quantity::new_f64(2.0, meter.clone());
+
+        assert!(length.convert_to(&meter).is_ok());
+
+        assert!(length.convert_to(&second).is_err());
+        assert!(length.convert_to(&Unit::scalar()).is_err());
+    }
+
+    #[test]
+    fn conversion_basic() {
+        use approx::assert_relative_eq;
+
+        let meter = Unit::meter();
+        let foot = Unit::new_derived(
+            "foot",
+            CanonicalName::new("ft", AcceptsPrefix::none()),
+            Number::from_f64(0.3048),
+            meter.clone(),
+        );
+
+        let length = Quantity::new_f64(2.0, meter.clone());
+
+        let length_in_foot = length.convert_to(&foot).expect("conversion succeeds");
+        // 0.3048 = 381/1250, so the exact result is 2500/381
+        assert_eq!(length_in_foot.unsafe_value().to_f64(), 2500.0 / 381.0);
+
+        let length_converted_back_to_meter = length_in_foot
+            .convert_to(&meter)
+            .expect("conversion succeeds");
+        assert_relative_eq!(
+            length_converted_back_to_meter.unsafe_value().to_f64(),
+            2.0,
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        use crate::prefix::Prefix;
+
+        use approx::assert_relative_eq;
+
+        let meter = Unit::meter();
+        let centimeter = Unit::meter().with_prefix(Prefix::centi());
+
+        let length = Quantity::new_f64(2.5, meter.clone());
+        {
+            let length_in_centimeter = length.convert_to(&centimeter).expect("conversion succeeds");
+            assert_relative_eq!(
+                length_in_centimeter.unsafe_value().to_f64(),
+                250.0,
+                epsilon = 1e-6
+            );
+
+            let length_converted_back_to_meter = length_in_centimeter
+                .convert_to(&meter)
+                .expect("conversion succeeds");
+            assert_relative_eq!(
+                length_converted_back_to_meter.unsafe_value().to_f64(),
+                2.5,
+                epsilon = 1e-6
+            );
+        }
+        {
+            let volume = length
+                .power(Quantity::from_scalar(3.0))
+                .expect("exponent is scalar");
+
+            let volume_in_centimeter3 = volume
+                .convert_to(&centimeter.powi(3))
+                .expect("conversion succeeds");
+            assert_relative_eq!(
+                volume_in_centimeter3.unsafe_value().to_f64(),
+                15_625_000.0,
+                epsilon = 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn power_with_large_exponents() {
+        let exponent = |e: f64| Quantity::from_scalar(e);
+
+        let q = Quantity::from_scalar(0.5).power(exponent(1e300)).unwrap();
+        assert_eq!(q, Quantity::from_scalar(0.0));
+
+        let q = Quantity::from_scalar(2.0).power(exponent(-1e9)).unwrap();
+        assert_eq!(q, Quantity::from_scalar(0.0));
+
+        // Dimensionless units are converted to scalars first
+        let q = Quantity::new_f64(2.0, Unit::percent())
+            .power(exponent(1e9))
+            .unwrap();
+        assert_eq!(q, Quantity::from_scalar(0.0));
+
+        // Smaller exponents are still applied to the unit
+        let q = Quantity::new_f64(2.0, Unit::percent())
+            .power(exponent(2.0))
+            .unwrap();
+        assert_eq!(q, Quantity::new_f64(4.0, Unit::percent().powi(2)));
+    }
+
+    #[test]
+    fn full_simplify_basic() {
+        let q = Quantity::new_f64(2.0, Unit::meter() / Unit::second());
+        assert_eq!(q.full_simplify(), q);
+    }
+
+    #[test]
+    fn full_simplify_convertible_to_scalar() {
+        {
+            let q = Quantity::new_f64(2.0, Unit::meter() / Unit::millimeter());
+            assert_eq!(q.full_simplify(), Quantity::from_scalar(2000.0));
+        }
+        {
+            let q = Quantity::new_f64(2.0, Unit::kilometer() / Unit::millimeter());
+            assert_eq!(q.full_simplify(), Quantity::from_scalar(2000000.0));
+        }
+        {
+            let q = Quantity::new_f64(2.0, Unit::meter() / Unit::centimeter() * Unit::second());
+            assert_eq!(
+                q.full_simplify(),
+                Quantity::new_f64(2.0 * 100.0, Unit::second())
+            );
+        }
+        {
+            let q = Quantity::new_f64(1.0, Unit::kph() / (Unit::kilometer() / Unit::hour()));
+            assert_eq!(q.full_simplify(), Quantity::from_scalar(1.0));
+        }
+    }
+
+    #[test]
+    fn full_simplify_unit_rearrangements() {
+        {
+            let q = Quantity::new_f64(2.0, Unit::meter() * Unit::second() * Unit::meter());
+            let expected = Quantity::new_f64(2.0, Unit::meter().powi(2) * Unit::second());
+            assert_eq!(q.full_simplify(), expected);
+        }
+        {
+            let q = Quantity::new_f64(2.0, Unit::kilometer() / Unit::millimeter());
+            assert_eq!(q.full_simplify(), Quantity::from_scalar(2000000.0));
+        }
+        {
+            let q = Quantity::new_f64(1.0, Unit::meter() * Unit::gram() / Unit::centimeter());
+            assert_eq!(q.full_simplify(), Quantity::new_f64(100.0, Unit::gram()));
+        }
+    }
+
+    #[test]
+    fn si_base_units() {
+        let q = Quantity::new_f64(36.0, Unit::kph());
+        assert_eq!(
+            q.to_si_base_units(),
+            Quantity::new_f64(10.0, Unit::meter() / Unit::second())
+        );
+
+        let q = Quantity::new_f64(2.0, Unit::gram() * Unit::kilometer());
+        let si = q.to_si_base_units();
+        assert_eq!(si.unit(), &(Unit::kilogram() * Unit::meter()));
+        approx::assert_relative_eq!(si.unsafe_value().to_f64(), 2.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn binary_representation() {
+        let meter = Unit::meter();
+        let second = Unit::second();
+        let per_second_squared = second.clone().power(Rational::from_integer(-2));
+
+        let acceleration = Quantity::new_f64(9.81, meter.clone() * per_second_squared);
+        let bytes = acceleration.to_bytes();
+        let decoded = Quantity::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, acceleration);
+        assert_eq!(decoded.unit(), acceleration.unit());
+        assert_eq!(
+            decoded * Quantity::new_f64(2.0, second.clone()),
+            Quantity::new_f64(19.62, meter.clone() / second.clone())
+        );
+
+        // Quantities are stored in terms of base units
+        let distance = Quantity::new_f64(-2.0, Unit::kilometer());
+        let decoded = Quantity::from_bytes(&distance.to_bytes()).unwrap();
+        assert_eq!(decoded.unit(), &meter);
+        assert_eq!(decoded.unsafe_value().to_f64(), -2000.0);
+
+        let root = Quantity::new_f64(3.0, second.power(Ratio::new(-1, 2)));
+        assert_eq!(Quantity::from_bytes(&root.to_bytes()).unwrap(), root);
+
+        let scalar = Quantity::from_scalar(0.5);
+        assert_eq!(scalar.to_bytes().len(), 10);
+        assert_eq!(Quantity::from_bytes(&scalar.to_bytes()).unwrap(), scalar);
+
+        for invalid in [
+            &[][..],
+            &bytes[..bytes.len() - 1],
+            &[&bytes[..], &[0]].concat(),
+            &[&[2], &bytes[1..]].concat(),
+        ] {
+            assert_eq!(
+                Quantity::from_bytes(invalid),
+                Err(QuantityError::InvalidBinaryRepresentation)
+            );
+        }
+    }
+
+    #[test]
+    fn full_simplify_scalarlike_units() {
+        {
+            let q = Quantity::new_f64(3.0, Unit::percent() * Unit::kilogram());
+            assert_eq!(q.full_simplify(), Quantity::new_f64(0.03, Unit::kilogram()));
+        }
+    }
+
+    #[test]
+    fn full_simplify_complex() {
+        {
+            let q = Quantity::new_f64(5.0, Unit::second() * Unit::millimeter() / Unit::meter());
+            let expected = Quantity::new_f64(0.005, Unit::second());
+            assert_eq!(q.full_simplify(), expected);
+        }
+        {
+            let q = Quantity::new_f64(
+                5.0,
+                Unit::bit().with_prefix(Prefix::mega()) / Unit::second() * Unit::hour(),
+            );
+            let expected = Quantity::new_f64(18000.0, Unit::bit().with_prefix(Prefix::mega()));
+            assert_eq!(q.full_simplify(), expected);
+        }
+        {
+            let q = Quantity::new_f64(5.0, Unit::centimeter() * Unit::meter());
+            let expected = Quantity::new_f64(500.0, Unit::centimeter().powi(2));
+            assert_eq!(q.full_simplify(), expected);
+        }
+        {
+            let q = Quantity::new_f64(5.0, Unit::meter() * Unit::centimeter());
+            let expected = Quantity::new_f64(500.0, Unit::centimeter().powi(2));
+            assert_eq!(q.full_simplify(), expected);
+        }
+        {
+            let q = Quantity::new_f64(1.0, Unit::hertz() / Unit::second());
+            let expected = Quantity::new_f64(1.0, Unit::second().powi(-2));
+            assert_eq!(q.full_simplify(), expected);
+        }
+        {
+            let q = Quantity::new_f64(1.0, Unit::gallon() / Unit::inch());
+            let expected = Quantity::new_f64(231.0, Unit::inch().powi(2));
+            assert_eq!(q.full_simplify(), expected);
+        }
+        {
+            let q = Quantity::new_f64(1.0, Unit::gallon() / Unit::inch().powi(2));
+            let expected = Quantity::new_f64(231.0, Unit::inch());
+            assert_eq!(q.full_simplify(), expected);
+        }
+    }
+
+    #[test]
+    fn si_compliant_pretty_printing() {
+        //  See: https://en.wikipedia.org/wiki/International_System_of_Units
+        //        -> Unit symbols and the value of quantities
+
+        // The value of a quantity is written as a number followed by a space
+        // (representing a multiplication sign) and a unit symbol; e.g., 2.21 kg,
+        // 7.3×10² m², 22 K.
+        assert_eq!(
+            Quantity::new_f64(2.21, Unit::kilogram()).to_string(),
+            "2.21 kg"
+        );
+        assert_eq!(Quantity::new_f64(22.0, Unit::kelvin()).to_string(), "22 K");
+
+        // Exceptions are the symbols for plane angular degrees, minutes, and
+        // seconds (°, ′, and ″), which are placed immediately after the
+        // number with no intervening space.
+        assert_eq!(Quantity::new_f64(90.0, Unit::degree()).to_string(), "90°");
+
+        // A prefix is part of the unit, and its symbol is prepended to the
+        // unit symbol without a separator (e.g., k in km, M in MPa, G in GHz).
+        // Compound prefixes are not allowed.
+        assert_eq!(
+            Quantity::new_f64(1.0, Unit::hertz().with_prefix(Prefix::giga())).to_string(),
+            "1 GHz"
+        );
+
+        // Symbols for derived units formed by multiplication are joined with a
+        // centre dot (·) or a non-breaking space; e.g., N·m or N m.
+        assert_eq!(
+            Quantity::new_f64(1.0, Unit::newton() * Unit::meter()).to_string(),
+            "1 N·m"
+        );
+
+        // Symbols for derived units formed by division are joined with a solidus
+        // (/), or given as a negative exponent. E.g., the "metre per second" can
+        // be written m/s, m s^(−1), m·s^(−1), or m/s. Only one solidus should
+        // be used; e.g., kg/(m·s²) and kg·m^(−1)·s^(−2) are acceptable, but
+        // kg/m/s² is ambiguous and unacceptable.
+        assert_eq!(
+            Quantity::new_f64(1.0, Unit::meter() / Unit::meter()).to_string(),
+            "1 m/m"
+        );
+        assert_eq!(
+            Quantity::new_f64(
+                1.0,
+                Unit::kilogram() / (Unit::meter() * Unit::second().powi(2))
+            )
+            .to_string(),
+            "1 kg/(m·s²)"
+        );
+    }
+}
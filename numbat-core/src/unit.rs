@@ -1,13 +1,19 @@
-use std::{fmt::Display, ops::Div};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt::Display, ops::Div};
 
 use itertools::Itertools;
-use num_traits::{ToPrimitive, Zero};
+use num_traits::{CheckedDiv, CheckedMul, ToPrimitive, Zero};
 
 use crate::{
     arithmetic::{pretty_exponent, Exponent, Power, Rational},
+    format::{DivisionStyle, FormatOptions, PrefixStyle},
     number::Number,
-    prefix::Prefix,
-    prefix_parser::AcceptsPrefix,
+    prefix::{AcceptsPrefix, Prefix},
     product::{Canonicalize, Product},
 };
 
@@ -25,6 +31,8 @@ pub enum UnitKind {
 pub struct CanonicalName {
     pub name: String,
     pub accepts_prefix: AcceptsPrefix,
+    /// Plural form of the name, see the `@plural` decorator
+    pub plural: Option<String>,
 }
 
 impl CanonicalName {
@@ -32,6 +40,7 @@ impl CanonicalName {
         Self {
             name: name.into(),
             accepts_prefix,
+            plural: None,
         }
     }
 }
@@ -46,7 +55,7 @@ pub struct UnitIdentifier {
 #[derive(Clone, Debug)]
 pub struct BaseUnitAndFactor(pub Unit, pub Number);
 
-impl std::iter::Product for BaseUnitAndFactor {
+impl core::iter::Product for BaseUnitAndFactor {
     fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
         let (fst, snd) = iter.tee();
         BaseUnitAndFactor(fst.map(|i| i.0).product(), snd.map(|i| i.1).product())
@@ -58,6 +67,16 @@ impl UnitIdentifier {
         matches!(self.kind, UnitKind::Base)
     }
 
+    /// The full name of the unit, e.g. `meter` for `m`. This is the canonical name
+    /// if that is a long name (see the `@canonical` decorator), or the unit name.
+    pub fn long_name(&self) -> &str {
+        if self.canonical_name.accepts_prefix == AcceptsPrefix::only_long() {
+            &self.canonical_name.name
+        } else {
+            &self.name
+        }
+    }
+
     pub fn unit_and_factor(&self) -> BaseUnitAndFactor {
         match &self.kind {
             UnitKind::Base => BaseUnitAndFactor(
@@ -102,6 +121,40 @@ impl UnitIdentifier {
         }
     }
 
+    /// The chain of units that this unit is defined in terms of, starting with the
+    /// unit itself. Every entry contains the factor to convert from this unit to
+    /// the respective unit. The chain ends at a base unit or at a unit that is
+    /// defined in terms of a compound unit.
+    fn defining_chain(&self) -> Vec<(&str, SplitFactor)> {
+        let mut chain = vec![(self.name.as_str(), SplitFactor::one())];
+
+        let mut current = self;
+        let mut factor = SplitFactor::one();
+        while let UnitKind::Derived(defining_factor, defining_unit) = &current.kind {
+            let [defining_unit_factor] = defining_unit.iter().collect::<Vec<_>>()[..] else {
+                break;
+            };
+            if defining_unit_factor.exponent != Rational::from_integer(1) {
+                break;
+            }
+
+            let Some(next_factor) = factor
+                .checked_mul(SplitFactor::new(*defining_factor))
+                .and_then(|f| {
+                    f.checked_mul(SplitFactor::new(defining_unit_factor.prefix.factor()))
+                })
+            else {
+                break;
+            };
+
+            factor = next_factor;
+            current = &defining_unit_factor.unit_id;
+            chain.push((current.name.as_str(), factor));
+        }
+
+        chain
+    }
+
     pub fn sort_key(&self) -> Vec<(String, Exponent)> {
         use num_integer::Integer;
 
@@ -153,14 +206,58 @@ impl UnitIdentifier {
     }
 }
 
+/// A conversion factor, split into a part that is a simple fraction (like 1/60 or
+/// 9/10) and can be represented exactly, and the remaining floating point factor.
+#[derive(Debug, Clone, Copy)]
+struct SplitFactor {
+    exact: Rational,
+    approximate: ConversionFactor,
+}
+
+impl SplitFactor {
+    fn one() -> Self {
+        SplitFactor {
+            exact: Rational::from_integer(1),
+            approximate: Number::from_f64(1.0),
+        }
+    }
+
+    fn new(factor: ConversionFactor) -> Self {
+        const MAX_DENOMINATOR: i128 = 10_000;
+        const MAX_NUMERATOR: i128 = 1 << 53;
+
+        match Rational::approximate_float(factor.to_f64()).filter(|r| {
+            *r.denom() <= MAX_DENOMINATOR
+                && r.numer().abs() <= MAX_NUMERATOR
+                && r.to_f64() == Some(factor.to_f64())
+        }) {
+            Some(exact) => SplitFactor {
+                exact,
+                approximate: Number::from_f64(1.0),
+            },
+            None => SplitFactor {
+                exact: Rational::from_integer(1),
+                approximate: factor,
+            },
+        }
+    }
+
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        Some(SplitFactor {
+            exact: self.exact.checked_mul(&other.exact)?,
+            approximate: self.approximate * other.approximate,
+        })
+    }
+}
+
 impl PartialOrd for UnitIdentifier {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
 impl Ord for UnitIdentifier {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.sort_key().cmp(&other.sort_key())
     }
 }
@@ -203,7 +300,7 @@ impl Power for UnitFactor {
 }
 
 impl Display for UnitFactor {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let prefix = if self.unit_id.canonical_name.accepts_prefix.short {
             self.prefix.as_string_short()
         } else {
@@ -300,7 +397,70 @@ impl Unit {
         (base_unit_representation, factor)
     }
 
-    #[cfg(test)]
+    /// If both units consist of a single factor with the same exponent and are
+    /// defined in terms of a common unit, converts `value` from `self` to `target`
+    /// via the closest such unit. Compared to going through the base units, this
+    /// avoids rounding errors, e.g. when converting from `turn` to `gradian` (both
+    /// of which are defined in terms of `degree`). Simple fractions like 1/60 or
+    /// 9/10 in the definitions are applied exactly.
+    pub fn convert_via_common_unit(&self, value: Number, target: &Unit) -> Option<Number> {
+        let (own, target) = match (
+            &self.iter().collect::<Vec<_>>()[..],
+            &target.iter().collect::<Vec<_>>()[..],
+        ) {
+            ([own], [target]) if own.exponent == target.exponent && own.exponent.is_integer() => {
+                (*own, *target)
+            }
+            _ => return None,
+        };
+
+        let target_chain = target.unit_id.defining_chain();
+        let (own_factor, target_factor) =
+            own.unit_id
+                .defining_chain()
+                .into_iter()
+                .find_map(|(name, own_factor)| {
+                    target_chain
+                        .iter()
+                        .find(|(target_name, _)| *target_name == name)
+                        .map(|(_, target_factor)| (own_factor, *target_factor))
+                })?;
+
+        let own_factor = SplitFactor::new(own.prefix.factor()).checked_mul(own_factor)?;
+        let target_factor = SplitFactor::new(target.prefix.factor()).checked_mul(target_factor)?;
+
+        let exponent = own.exponent.to_integer();
+        let exact_ratio = num_traits::checked_pow(
+            own_factor.exact.checked_div(&target_factor.exact)?,
+            exponent.unsigned_abs() as usize,
+        )?;
+        let exact_ratio = if exponent < 0 {
+            exact_ratio.recip()
+        } else {
+            exact_ratio
+        };
+
+        // Numerator and denominator need to be exactly representable for the
+        // multiplication and division below to be exact.
+        const MAX_EXACT: i128 = 1 << 53;
+        if exact_ratio.numer().abs() > MAX_EXACT || *exact_ratio.denom() > MAX_EXACT {
+            return None;
+        }
+
+        let value = if own_factor.approximate == target_factor.approximate {
+            value
+        } else {
+            let exponent = Number::from_f64(exponent as f64);
+            value * own_factor.approximate.pow(&exponent) / target_factor.approximate.pow(&exponent)
+        };
+
+        Some(
+            value * Number::from_f64(*exact_ratio.numer() as f64)
+                / Number::from_f64(*exact_ratio.denom() as f64),
+        )
+    }
+
+    #[cfg(any(test, feature = "testing"))]
     pub fn meter() -> Self {
         Self::new_base(
             "meter",
@@ -308,7 +468,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn centimeter() -> Self {
         Self::new_base(
             "meter",
@@ -317,7 +477,7 @@ impl Unit {
         .with_prefix(Prefix::centi())
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn millimeter() -> Self {
         Self::new_base(
             "meter",
@@ -326,7 +486,7 @@ impl Unit {
         .with_prefix(Prefix::milli())
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn kilometer() -> Self {
         Self::new_base(
             "meter",
@@ -335,7 +495,7 @@ impl Unit {
         .with_prefix(Prefix::kilo())
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn second() -> Self {
         Self::new_base(
             "second",
@@ -343,17 +503,17 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn gram() -> Self {
         Self::new_base("gram", CanonicalName::new("g", AcceptsPrefix::only_short()))
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn kilogram() -> Self {
         Self::gram().with_prefix(Prefix::kilo())
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn kelvin() -> Self {
         Self::new_base(
             "kelvin",
@@ -361,7 +521,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn radian() -> Self {
         Self::new_derived(
             "radian",
@@ -371,17 +531,17 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn degree() -> Self {
         Self::new_derived(
             "degree",
             CanonicalName::new("°", AcceptsPrefix::none()),
-            Number::from_f64(std::f64::consts::PI / 180.0),
+            Number::from_f64(core::f64::consts::PI / 180.0),
             Self::radian(),
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn percent() -> Self {
         Self::new_derived(
             "percent",
@@ -391,7 +551,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn hertz() -> Self {
         Self::new_derived(
             "hertz",
@@ -401,7 +561,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn newton() -> Self {
         Self::new_derived(
             "newton",
@@ -411,7 +571,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn minute() -> Self {
         Self::new_derived(
             "minute",
@@ -421,7 +581,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn hour() -> Self {
         Self::new_derived(
             "hour",
@@ -431,7 +591,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn kph() -> Self {
         Self::new_derived(
             "kilometer_per_hour",
@@ -441,7 +601,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn inch() -> Self {
         Self::new_derived(
             "inch",
@@ -451,7 +611,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn gallon() -> Self {
         Self::new_derived(
             "gallon",
@@ -461,7 +621,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn foot() -> Self {
         Self::new_derived(
             "foot",
@@ -471,7 +631,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn yard() -> Self {
         Self::new_derived(
             "yard",
@@ -481,7 +641,7 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn mile() -> Self {
         Self::new_derived(
             "mile",
@@ -491,12 +651,12 @@ impl Unit {
         )
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn bit() -> Self {
         Self::new_base("bit", CanonicalName::new("bit", AcceptsPrefix::only_long()))
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "testing"))]
     pub fn byte() -> Self {
         Self::new_derived(
             "byte",
@@ -508,11 +668,61 @@ impl Unit {
 }
 
 impl Display for Unit {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_str(&self.as_string(|f| f.exponent, '·', '/', false))
     }
 }
 
+impl Unit {
+    /// Format the unit as part of a quantity with the given value. A unit that
+    /// consists of a single factor with a plural name (see the `@plural` decorator)
+    /// is written in plural form, unless the absolute value is one.
+    pub fn to_string_for_value(&self, value: Number) -> String {
+        self.to_string_with_options(value, &FormatOptions::default())
+    }
+
+    /// Like [`Unit::to_string_for_value`], but with the prefix and division style
+    /// of the given options.
+    pub fn to_string_with_options(&self, value: Number, options: &FormatOptions) -> String {
+        let unit = match options.prefix_style {
+            PrefixStyle::Short => self.clone(),
+            PrefixStyle::Long => self.with_long_names(),
+        };
+
+        if let [factor] = &unit.iter().collect::<Vec<_>>()[..] {
+            if let Some(plural) = &factor.unit_id.canonical_name.plural {
+                if factor.exponent == Rational::from_integer(1) && value.to_f64().abs() != 1.0 {
+                    return format!("{}{}", factor.prefix.as_string_long(), plural);
+                }
+            }
+        }
+
+        match options.division_style {
+            DivisionStyle::Division => unit.to_string(),
+            DivisionStyle::Reciprocal => unit.iter().map(|f| f.to_string()).join("·"),
+        }
+    }
+
+    /// Format the unit with the full names of its factors, e.g. `kilometer/hour`
+    /// instead of `km/h` (see [`UnitIdentifier::long_name`]).
+    pub fn to_string_long(&self) -> String {
+        self.with_long_names().to_string()
+    }
+
+    fn with_long_names(&self) -> Unit {
+        Unit::from_factors(self.iter().map(|factor| UnitFactor {
+            unit_id: UnitIdentifier {
+                canonical_name: CanonicalName::new(
+                    factor.unit_id.long_name(),
+                    AcceptsPrefix::only_long(),
+                ),
+                ..factor.unit_id.clone()
+            },
+            ..factor.clone()
+        }))
+    }
+}
+
 /// This function attempts to solves the equation a = C · b^alpha, where
 /// C is a constant and alpha is a rational exponent. If there is a solution,
 /// `Some(alpha)` is returned. If not, `None` is returned.
@@ -767,6 +977,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn to_string_long() {
+        assert_eq!(Unit::meter().to_string_long(), "meter");
+        assert_eq!(Unit::kilometer().to_string_long(), "kilometer");
+        assert_eq!(
+            (Unit::kilometer() / Unit::hour()).to_string_long(),
+            "kilometer/hour"
+        );
+        assert_eq!(
+            (Unit::meter() / (Unit::second() * Unit::second()))
+                .canonicalized()
+                .to_string_long(),
+            "meter/second²"
+        );
+    }
+
     #[test]
     fn is_multiple_of_basic() {
         assert_eq!(
@@ -21,6 +21,18 @@ pub fn jt_format(class: Option<&str>, content: &str) -> String {
     }
 }
 
+pub fn jt_link(url: &str) -> String {
+    if url.is_empty() {
+        return "".into();
+    }
+
+    let content = html_escape::encode_text(url)
+        .replace('[', "&#91;")
+        .replace(']', "&#93;");
+
+    format!("[[!;;;hl-link;{content}]{content}]")
+}
+
 impl Formatter for JqueryTerminalFormatter {
     fn format_part(
         &self,
@@ -39,6 +51,9 @@ impl Formatter for JqueryTerminalFormatter {
             FormatType::TypeIdentifier => Some("type-identifier"),
             FormatType::Operator => Some("operator"),
             FormatType::Decorator => Some("decorator"),
+            FormatType::Error => Some("error"),
+            FormatType::Warning => Some("warning"),
+            FormatType::Link => return jt_link(s),
         };
         jt_format(css_class, s)
     }
@@ -91,6 +91,7 @@ impl Numbat {
             print_fn: Box::new(move |s: &m::Markup| {
                 to_be_printed_c.lock().unwrap().push(s.clone());
             }),
+            ..InterpreterSettings::default()
         };
 
         let nl = &self.format(&numbat::markup::nl(), false);
@@ -139,7 +140,8 @@ impl Numbat {
             Err(NumbatError::ResolverError(e)) => self.print_diagnostic(&e),
             Err(NumbatError::NameResolutionError(
                 e @ (NameResolutionError::IdentifierClash { .. }
-                | NameResolutionError::ReservedIdentifier(_)),
+                | NameResolutionError::ReservedIdentifier(..)
+                | NameResolutionError::DecoratorsOnDeclaredUnit(..)),
             )) => self.print_diagnostic(&e),
             Err(NumbatError::TypeCheckError(e)) => self.print_diagnostic(&e),
             Err(NumbatError::RuntimeError(e)) => self.print_diagnostic(&e),
@@ -171,7 +173,7 @@ impl Numbat {
     }
 
     pub fn print_info(&mut self, keyword: &str) -> JsValue {
-        let output = self.ctx.print_info_for_keyword(keyword);
+        let output = self.ctx.help_for(keyword);
         self.format(&output, true).into()
     }
 
@@ -0,0 +1,31 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use numbat::module_importer::BuiltinModuleImporter;
+use numbat::resolver::CodeSource;
+use numbat::Context;
+
+fn small_function_calls(c: &mut Criterion) {
+    for (name, inlining) in [("inlined", true), ("not inlined", false)] {
+        let importer = BuiltinModuleImporter::default();
+        let mut context = Context::new(importer);
+        context.set_function_inlining(inlining);
+        let _ = context
+            .interpret(
+                "use prelude
+                 fn square(x) = x * x
+                 fn sum_of_squares(n: Scalar) -> Scalar =
+                     if n == 0 then 0 else square(n) + square(-n) + sum_of_squares(n - 1)",
+                CodeSource::Internal,
+            )
+            .unwrap();
+
+        c.bench_function(&format!("Small function calls ({name})"), |b| {
+            b.iter_with_setup(
+                || context.clone(),
+                |mut ctx| ctx.interpret("sum_of_squares(1000)", CodeSource::Text),
+            )
+        });
+    }
+}
+
+criterion_group!(benches, small_function_calls);
+criterion_main!(benches);
@@ -1,8 +1,13 @@
 use crate::markup as m;
 use crate::span::Span;
 use crate::{
-    arithmetic::Exponent, decorator::Decorator, markup::Markup, number::Number, prefix::Prefix,
-    pretty_print::PrettyPrint, resolver::ModulePath,
+    arithmetic::Exponent,
+    decorator::{decorator_markup, Decorator},
+    markup::Markup,
+    number::Number,
+    prefix::Prefix,
+    pretty_print::{escape_numbat_string, PrettyPrint},
+    resolver::ModulePath,
 };
 use itertools::Itertools;
 use num_traits::Signed;
@@ -12,6 +17,8 @@ pub enum UnaryOperator {
     Factorial,
     Negate,
     LogicalNeg,
+    /// Conversion to base units (`-> SI`)
+    ConvertToBaseUnits,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,6 +27,7 @@ pub enum BinaryOperator {
     Sub,
     Mul,
     Div,
+    Mod,
     Power,
     ConvertTo,
     LessThan,
@@ -41,6 +49,7 @@ impl PrettyPrint for BinaryOperator {
             Sub => m::space() + m::operator("-") + m::space(),
             Mul => m::space() + m::operator("×") + m::space(),
             Div => m::space() + m::operator("/") + m::space(),
+            Mod => m::space() + m::keyword("mod") + m::space(),
             Power => m::operator("^"),
             ConvertTo => m::space() + m::operator("➞") + m::space(),
             LessThan => m::space() + m::operator("<") + m::space(),
@@ -65,6 +74,26 @@ pub enum StringPart {
     },
 }
 
+impl PrettyPrint for StringPart {
+    fn pretty_print(&self) -> Markup {
+        match self {
+            StringPart::Fixed(s) => m::string(escape_numbat_string(s)),
+            StringPart::Interpolation {
+                span: _,
+                expr,
+                format_specifiers,
+            } => {
+                let mut markup = m::operator("{") + expr.pretty_print();
+                if let Some(format_specifiers) = format_specifiers {
+                    markup += m::text(format_specifiers);
+                }
+                markup += m::operator("}");
+                markup
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Scalar(Span, Number),
@@ -82,6 +111,14 @@ pub enum Expression {
         rhs: Box<Expression>,
         span_op: Option<Span>, // not available for implicit multiplication and unicode exponents
     },
+    /// Application of a user-defined infix operator (`a <> b`), which is
+    /// desugared to a call of the bound function by the type checker.
+    UserOperator {
+        symbol: String,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span_op: Span,
+    },
     FunctionCall(Span, Span, Box<Expression>, Vec<Expression>),
     Boolean(Span, bool),
     String(Span, Vec<StringPart>),
@@ -119,6 +156,7 @@ impl Expression {
                 }
                 span
             }
+            Expression::UserOperator { lhs, rhs, .. } => lhs.full_span().extend(&rhs.full_span()),
             Expression::FunctionCall(_identifier_span, full_span, _, _) => *full_span,
             Expression::Boolean(span, _) => *span,
             Expression::Condition(span_if, _, _, then_expr) => {
@@ -133,6 +171,257 @@ impl Expression {
     }
 }
 
+fn pretty_scalar(n: &Number) -> Markup {
+    m::value(n.pretty_print())
+}
+
+fn expr_with_parens(expr: &Expression) -> Markup {
+    match expr {
+        Expression::Scalar(..)
+        | Expression::Identifier(..)
+        | Expression::UnitIdentifier(..)
+        | Expression::TypedHole(..)
+        | Expression::FunctionCall(..)
+        | Expression::Boolean(..)
+        | Expression::String(..)
+        | Expression::InstantiateStruct { .. }
+        | Expression::AccessField(..)
+        | Expression::List(..) => expr.pretty_print(),
+        Expression::UnaryOperator { .. }
+        | Expression::BinaryOperator { .. }
+        | Expression::UserOperator { .. }
+        | Expression::Condition(..) => m::operator("(") + expr.pretty_print() + m::operator(")"),
+    }
+}
+
+/// Add parens, if needed -- liberal version, can not be used for exponentiation.
+fn expr_with_parens_liberal(expr: &Expression) -> Markup {
+    match expr {
+        Expression::BinaryOperator {
+            op: BinaryOperator::Mul,
+            lhs,
+            rhs,
+            ..
+        } if matches!(**lhs, Expression::Scalar(..))
+            && matches!(
+                **rhs,
+                Expression::Identifier(..) | Expression::UnitIdentifier(..)
+            ) =>
+        {
+            expr.pretty_print()
+        }
+        _ => expr_with_parens(expr),
+    }
+}
+
+fn is_binop(expr: &Expression, ops: &[BinaryOperator]) -> bool {
+    matches!(expr, Expression::BinaryOperator { op, .. } if ops.contains(op))
+}
+
+fn pretty_print_binop(op: &BinaryOperator, lhs: &Expression, rhs: &Expression) -> Markup {
+    use BinaryOperator::*;
+
+    let add_parens_unless = |expr: &Expression, ops: &[BinaryOperator]| {
+        if is_binop(expr, ops) {
+            expr.pretty_print()
+        } else {
+            expr_with_parens_liberal(expr)
+        }
+    };
+
+    match op {
+        ConvertTo => {
+            // Conversions have the lowest precedence of all binary operators and
+            // are left-associative. Only conditions (and nested conversions on
+            // the right hand side) need parens.
+            let lhs = if matches!(lhs, Expression::Condition(..)) {
+                expr_with_parens(lhs)
+            } else {
+                lhs.pretty_print()
+            };
+            let rhs = if matches!(
+                rhs,
+                Expression::Condition(..)
+                    | Expression::UnaryOperator {
+                        op: UnaryOperator::ConvertToBaseUnits,
+                        ..
+                    }
+            ) || is_binop(rhs, &[ConvertTo])
+            {
+                expr_with_parens(rhs)
+            } else {
+                rhs.pretty_print()
+            };
+            lhs + op.pretty_print() + rhs
+        }
+        Mul => match (lhs, rhs) {
+            (Expression::Scalar(_, s), Expression::Identifier(_, name)) => {
+                // Fuse multiplication of a scalar and identifier
+                pretty_scalar(s) + m::space() + m::identifier(name)
+            }
+            (Expression::Scalar(_, s), Expression::UnitIdentifier(_, prefix, _, full_name)) => {
+                // Fuse multiplication of a scalar and a unit to a quantity
+                pretty_scalar(s)
+                    + m::space()
+                    + m::unit(format!("{}{}", prefix.as_string_long(), full_name))
+            }
+            _ => {
+                add_parens_unless(lhs, &[Power, Mul])
+                    + op.pretty_print()
+                    + add_parens_unless(rhs, &[Power, Mul])
+            }
+        },
+        Div | Mod => {
+            add_parens_unless(lhs, &[Power, Mul])
+                + op.pretty_print()
+                + add_parens_unless(rhs, &[Power])
+        }
+        Add => {
+            add_parens_unless(lhs, &[Power, Mul, Add])
+                + op.pretty_print()
+                + add_parens_unless(rhs, &[Power, Mul, Add])
+        }
+        Sub => {
+            add_parens_unless(lhs, &[Power, Mul])
+                + op.pretty_print()
+                + add_parens_unless(rhs, &[Power, Mul])
+        }
+        Power if matches!(rhs, Expression::Scalar(_, n) if n.to_f64() == 2.0) => {
+            expr_with_parens(lhs) + m::operator("²")
+        }
+        Power if matches!(rhs, Expression::Scalar(_, n) if n.to_f64() == 3.0) => {
+            expr_with_parens(lhs) + m::operator("³")
+        }
+        _ => expr_with_parens(lhs) + op.pretty_print() + expr_with_parens(rhs),
+    }
+}
+
+fn pretty_print_arguments(args: &[Expression]) -> Markup {
+    m::operator("(")
+        + Itertools::intersperse(
+            args.iter().map(|a| a.pretty_print()),
+            m::operator(",") + m::space(),
+        )
+        .sum()
+        + m::operator(")")
+}
+
+impl PrettyPrint for Expression {
+    fn pretty_print(&self) -> Markup {
+        match self {
+            Expression::Scalar(_, n) => pretty_scalar(n),
+            Expression::Identifier(_, name) => m::identifier(name),
+            Expression::UnitIdentifier(_, prefix, _name, full_name) => {
+                m::unit(format!("{}{}", prefix.as_string_long(), full_name))
+            }
+            Expression::TypedHole(_) => m::operator("?"),
+            Expression::UnaryOperator {
+                op: UnaryOperator::Negate,
+                expr,
+                ..
+            } => m::operator("-") + expr_with_parens(expr),
+            Expression::UnaryOperator {
+                op: UnaryOperator::Factorial,
+                expr,
+                ..
+            } => expr_with_parens(expr) + m::operator("!"),
+            Expression::UnaryOperator {
+                op: UnaryOperator::LogicalNeg,
+                expr,
+                ..
+            } => m::operator("!") + expr_with_parens(expr),
+            Expression::UnaryOperator {
+                op: UnaryOperator::ConvertToBaseUnits,
+                expr,
+                ..
+            } => {
+                // Like other conversions, this has the lowest precedence
+                let expr = if matches!(**expr, Expression::Condition(..)) {
+                    expr_with_parens(expr)
+                } else {
+                    expr.pretty_print()
+                };
+                expr + BinaryOperator::ConvertTo.pretty_print() + m::keyword("SI")
+            }
+            Expression::BinaryOperator { op, lhs, rhs, .. } => pretty_print_binop(op, lhs, rhs),
+            Expression::UserOperator {
+                symbol, lhs, rhs, ..
+            } => {
+                // User-defined operators bind less tightly than arithmetic operators
+                let operand = |expr: &Expression| {
+                    use BinaryOperator::*;
+                    if is_binop(expr, &[Add, Sub, Mul, Div, Mod, Power]) {
+                        expr.pretty_print()
+                    } else {
+                        expr_with_parens_liberal(expr)
+                    }
+                };
+                operand(lhs) + m::space() + m::operator(symbol) + m::space() + operand(rhs)
+            }
+            Expression::FunctionCall(_, _, callable, args) => {
+                expr_with_parens(callable) + pretty_print_arguments(args)
+            }
+            Expression::Boolean(_, val) => val.pretty_print(),
+            Expression::String(_, parts) => {
+                m::operator("\"") + parts.iter().map(|p| p.pretty_print()).sum() + m::operator("\"")
+            }
+            Expression::Condition(_, condition, then, else_) => {
+                m::keyword("if")
+                    + m::space()
+                    + expr_with_parens(condition)
+                    + m::space()
+                    + m::keyword("then")
+                    + m::space()
+                    + expr_with_parens(then)
+                    + m::space()
+                    + m::keyword("else")
+                    + m::space()
+                    + expr_with_parens(else_)
+            }
+            Expression::InstantiateStruct { name, fields, .. } => {
+                m::type_identifier(name)
+                    + m::space()
+                    + m::operator("{")
+                    + if fields.is_empty() {
+                        m::empty()
+                    } else {
+                        m::space()
+                            + Itertools::intersperse(
+                                fields.iter().map(|(_, n, e)| {
+                                    m::identifier(n)
+                                        + m::operator(":")
+                                        + m::space()
+                                        + e.pretty_print()
+                                }),
+                                m::operator(",") + m::space(),
+                            )
+                            .sum()
+                            + m::space()
+                    }
+                    + m::operator("}")
+            }
+            Expression::AccessField(_, _, expr, attr) => {
+                // A scalar needs parens as well, since `2.foo` would be tokenized as `2. foo`
+                let expr = if matches!(**expr, Expression::Scalar(..)) {
+                    m::operator("(") + expr.pretty_print() + m::operator(")")
+                } else {
+                    expr_with_parens(expr)
+                };
+                expr + m::operator(".") + m::identifier(attr)
+            }
+            Expression::List(_, elements) => {
+                m::operator("[")
+                    + Itertools::intersperse(
+                        elements.iter().map(|e| e.pretty_print()),
+                        m::operator(",") + m::space(),
+                    )
+                    .sum()
+                    + m::operator("]")
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 macro_rules! scalar {
     ( $num:expr ) => {{
@@ -277,6 +566,32 @@ impl TypeAnnotation {
             TypeAnnotation::List(span, _) => *span,
         }
     }
+
+    /// The same annotation, with all spans moved to a different code source
+    pub(crate) fn with_code_source_id(&self, id: usize) -> Self {
+        match self {
+            TypeAnnotation::TypeExpression(d) => {
+                TypeAnnotation::TypeExpression(d.with_code_source_id(id))
+            }
+            TypeAnnotation::Bool(span) => TypeAnnotation::Bool(span.with_code_source_id(id)),
+            TypeAnnotation::String(span) => TypeAnnotation::String(span.with_code_source_id(id)),
+            TypeAnnotation::DateTime(span) => {
+                TypeAnnotation::DateTime(span.with_code_source_id(id))
+            }
+            TypeAnnotation::Fn(span, parameter_types, return_type) => TypeAnnotation::Fn(
+                span.with_code_source_id(id),
+                parameter_types
+                    .iter()
+                    .map(|t| t.with_code_source_id(id))
+                    .collect(),
+                Box::new(return_type.with_code_source_id(id)),
+            ),
+            TypeAnnotation::List(span, element_type) => TypeAnnotation::List(
+                span.with_code_source_id(id),
+                Box::new(element_type.with_code_source_id(id)),
+            ),
+        }
+    }
 }
 
 impl PrettyPrint for TypeAnnotation {
@@ -343,6 +658,32 @@ impl TypeExpression {
             },
         }
     }
+
+    /// The same expression, with all spans moved to a different code source
+    pub(crate) fn with_code_source_id(&self, id: usize) -> Self {
+        match self {
+            TypeExpression::Unity(span) => TypeExpression::Unity(span.with_code_source_id(id)),
+            TypeExpression::TypeIdentifier(span, name) => {
+                TypeExpression::TypeIdentifier(span.with_code_source_id(id), name.clone())
+            }
+            TypeExpression::Multiply(span_op, lhs, rhs) => TypeExpression::Multiply(
+                span_op.with_code_source_id(id),
+                Box::new(lhs.with_code_source_id(id)),
+                Box::new(rhs.with_code_source_id(id)),
+            ),
+            TypeExpression::Divide(span_op, lhs, rhs) => TypeExpression::Divide(
+                span_op.with_code_source_id(id),
+                Box::new(lhs.with_code_source_id(id)),
+                Box::new(rhs.with_code_source_id(id)),
+            ),
+            TypeExpression::Power(span_op, lhs, span_exponent, exp) => TypeExpression::Power(
+                span_op.map(|span| span.with_code_source_id(id)),
+                Box::new(lhs.with_code_source_id(id)),
+                span_exponent.with_code_source_id(id),
+                *exp,
+            ),
+        }
+    }
 }
 
 fn with_parens(dexpr: &TypeExpression) -> Markup {
@@ -386,22 +727,67 @@ pub enum ProcedureKind {
     Assert,
     AssertEq,
     Type,
+    Debug,
+    Csv,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TypeParameterBound {
     Dim,
+    /// The type parameter must be equal to the given dimension (`where D: Length`)
+    Equal(TypeExpression),
+}
+
+impl TypeParameterBound {
+    /// The same bound, with all spans moved to a different code source
+    pub(crate) fn with_code_source_id(&self, id: usize) -> Self {
+        match self {
+            TypeParameterBound::Dim => TypeParameterBound::Dim,
+            TypeParameterBound::Equal(dexpr) => {
+                TypeParameterBound::Equal(dexpr.with_code_source_id(id))
+            }
+        }
+    }
+}
+
+/// A `let` definition in the body of a function (`fn f(x) = { let y = 2 x; x + y }`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVariable {
+    pub identifier_span: Span,
+    pub identifier: String,
+    pub expr: Expression,
+    pub type_annotation: Option<TypeAnnotation>,
+}
+
+/// A definition at the beginning of a function body block
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocalDefinition {
+    Variable(LocalVariable),
+    /// A function (always a [Statement::DefineFunction] with a body) that can use the
+    /// parameters and the local definitions that precede it
+    Function(Box<Statement>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Expression(Expression),
+    /// The last expression of the input, if it is followed by a semicolon that
+    /// suppresses its result (`let x = 2 m; x^2;`)
+    SuppressedExpression(Expression),
     DefineVariable {
         identifier_span: Span,
         identifier: String,
         expr: Expression,
         type_annotation: Option<TypeAnnotation>,
         decorators: Vec<Decorator>,
+        /// Whether the variable has been defined with `let mut`
+        mutable: bool,
+    },
+    /// Assignment of a new value to a mutable variable (`x = …`)
+    ReassignVariable {
+        identifier_span: Span,
+        identifier: String,
+        expr: Expression,
     },
     DefineFunction {
         function_name_span: Span,
@@ -409,6 +795,8 @@ pub enum Statement {
         type_parameters: Vec<(Span, String, Option<TypeParameterBound>)>,
         /// Parameters, optionally with type annotations.
         parameters: Vec<(Span, String, Option<TypeAnnotation>)>,
+        /// Local variables and functions that are defined at the beginning of the function body
+        local_definitions: Vec<LocalDefinition>,
         /// Function body. If it is absent, the function is implemented via FFI
         body: Option<Expression>,
         /// Optional annotated return type
@@ -417,6 +805,9 @@ pub enum Statement {
     },
     DefineDimension(Span, String, Vec<TypeExpression>),
     DefineBaseUnit(Span, String, Option<TypeExpression>, Vec<Decorator>),
+    /// Forward declaration of a unit (`unit foo: Length = ...`) whose value is
+    /// supplied by a later `unit foo = …` definition
+    DeclareUnit(Span, String, TypeExpression, Vec<Decorator>),
     DefineDerivedUnit {
         identifier_span: Span,
         identifier: String,
@@ -432,6 +823,307 @@ pub enum Statement {
         struct_name: String,
         fields: Vec<(Span, String, TypeAnnotation)>,
     },
+    /// Binding of an infix operator to a function (`operator <> = combine`)
+    DefineOperator {
+        symbol_span: Span,
+        symbol: String,
+        function_name_span: Span,
+        function_name: String,
+    },
+}
+
+pub(crate) fn pretty_print_type_annotation(annotation: &Option<TypeAnnotation>) -> Markup {
+    annotation
+        .as_ref()
+        .map(|a| m::operator(":") + m::space() + a.pretty_print())
+        .unwrap_or_default()
+}
+
+/// Pretty print the definition of a local variable in a function body block
+pub(crate) fn pretty_print_local_variable(
+    identifier: &str,
+    annotation: &Option<TypeAnnotation>,
+    expr: Markup,
+) -> Markup {
+    m::keyword("let")
+        + m::space()
+        + m::identifier(identifier)
+        + pretty_print_type_annotation(annotation)
+        + m::space()
+        + m::operator("=")
+        + m::space()
+        + expr
+}
+
+/// Pretty print a function body, including the (optional) block of local definitions
+pub(crate) fn pretty_print_function_body(
+    local_definitions: impl Iterator<Item = Markup>,
+    body: Markup,
+) -> Markup {
+    let mut local_definitions = local_definitions.peekable();
+    if local_definitions.peek().is_none() {
+        return body;
+    }
+
+    m::operator("{")
+        + m::space()
+        + local_definitions
+            .map(|definition| definition + m::operator(";") + m::space())
+            .sum()
+        + body
+        + m::space()
+        + m::operator("}")
+}
+
+impl PrettyPrint for LocalDefinition {
+    fn pretty_print(&self) -> Markup {
+        match self {
+            LocalDefinition::Variable(l) => pretty_print_local_variable(
+                &l.identifier,
+                &l.type_annotation,
+                l.expr.pretty_print(),
+            ),
+            LocalDefinition::Function(statement) => statement.pretty_print(),
+        }
+    }
+}
+
+impl PrettyPrint for Statement {
+    fn pretty_print(&self) -> Markup {
+        match self {
+            Statement::Expression(expr) => expr.pretty_print(),
+            Statement::SuppressedExpression(expr) => expr.pretty_print() + m::operator(";"),
+            Statement::DefineVariable {
+                identifier,
+                expr,
+                type_annotation,
+                decorators,
+                mutable,
+                ..
+            } => {
+                decorator_markup(decorators)
+                    + m::keyword("let")
+                    + m::space()
+                    + if *mutable {
+                        m::keyword("mut") + m::space()
+                    } else {
+                        m::empty()
+                    }
+                    + m::identifier(identifier)
+                    + pretty_print_type_annotation(type_annotation)
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + expr.pretty_print()
+            }
+            Statement::ReassignVariable {
+                identifier, expr, ..
+            } => {
+                m::identifier(identifier)
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + expr.pretty_print()
+            }
+            Statement::DefineFunction {
+                function_name,
+                type_parameters,
+                parameters,
+                local_definitions,
+                body,
+                return_type_annotation,
+                decorators,
+                ..
+            } => {
+                let markup_type_parameters = if type_parameters.is_empty() {
+                    m::empty()
+                } else {
+                    m::operator("<")
+                        + Itertools::intersperse(
+                            type_parameters.iter().map(|(_, name, bound)| {
+                                m::type_identifier(name)
+                                    + if let Some(TypeParameterBound::Dim) = bound {
+                                        m::operator(":") + m::space() + m::type_identifier("Dim")
+                                    } else {
+                                        m::empty()
+                                    }
+                            }),
+                            m::operator(", "),
+                        )
+                        .sum()
+                        + m::operator(">")
+                };
+
+                let markup_parameters = Itertools::intersperse(
+                    parameters.iter().map(|(_, name, annotation)| {
+                        m::identifier(name) + pretty_print_type_annotation(annotation)
+                    }),
+                    m::operator(", "),
+                )
+                .sum();
+
+                let markup_return_type = return_type_annotation
+                    .as_ref()
+                    .map(|a| m::space() + m::operator("->") + m::space() + a.pretty_print())
+                    .unwrap_or_default();
+
+                let where_clauses: Vec<_> = type_parameters
+                    .iter()
+                    .filter_map(|(_, name, bound)| match bound {
+                        Some(TypeParameterBound::Equal(dexpr)) => Some(
+                            m::type_identifier(name)
+                                + m::operator(":")
+                                + m::space()
+                                + dexpr.pretty_print(),
+                        ),
+                        _ => None,
+                    })
+                    .collect();
+                let markup_where_clauses = if where_clauses.is_empty() {
+                    m::empty()
+                } else {
+                    m::space()
+                        + m::keyword("where")
+                        + m::space()
+                        + Itertools::intersperse(where_clauses.into_iter(), m::operator(", ")).sum()
+                };
+
+                decorator_markup(decorators)
+                    + m::keyword("fn")
+                    + m::space()
+                    + m::identifier(function_name)
+                    + markup_type_parameters
+                    + m::operator("(")
+                    + markup_parameters
+                    + m::operator(")")
+                    + markup_return_type
+                    + markup_where_clauses
+                    + body
+                        .as_ref()
+                        .map(|e| {
+                            m::space()
+                                + m::operator("=")
+                                + m::space()
+                                + pretty_print_function_body(
+                                    local_definitions.iter().map(|d| d.pretty_print()),
+                                    e.pretty_print(),
+                                )
+                        })
+                        .unwrap_or_default()
+            }
+            Statement::DefineDimension(_, identifier, dexprs) if dexprs.is_empty() => {
+                m::keyword("dimension") + m::space() + m::type_identifier(identifier)
+            }
+            Statement::DefineDimension(_, identifier, dexprs) => {
+                m::keyword("dimension")
+                    + m::space()
+                    + m::type_identifier(identifier)
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + Itertools::intersperse(
+                        dexprs.iter().map(|d| d.pretty_print()),
+                        m::space() + m::operator("=") + m::space(),
+                    )
+                    .sum()
+            }
+            Statement::DefineBaseUnit(_, identifier, dexpr, decorators) => {
+                decorator_markup(decorators)
+                    + m::keyword("unit")
+                    + m::space()
+                    + m::unit(identifier)
+                    + dexpr
+                        .as_ref()
+                        .map(|d| m::operator(":") + m::space() + d.pretty_print())
+                        .unwrap_or_default()
+            }
+            Statement::DeclareUnit(_, identifier, dexpr, decorators) => {
+                decorator_markup(decorators)
+                    + m::keyword("unit")
+                    + m::space()
+                    + m::unit(identifier)
+                    + m::operator(":")
+                    + m::space()
+                    + dexpr.pretty_print()
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + m::operator("...")
+            }
+            Statement::DefineDerivedUnit {
+                identifier,
+                expr,
+                type_annotation,
+                decorators,
+                ..
+            } => {
+                decorator_markup(decorators)
+                    + m::keyword("unit")
+                    + m::space()
+                    + m::unit(identifier)
+                    + pretty_print_type_annotation(type_annotation)
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + expr.pretty_print()
+            }
+            Statement::ProcedureCall(_, kind, args) => {
+                let identifier = match kind {
+                    ProcedureKind::Print => "print",
+                    ProcedureKind::Assert => "assert",
+                    ProcedureKind::AssertEq => "assert_eq",
+                    ProcedureKind::Type => "type",
+                    ProcedureKind::Debug => "debug",
+                    ProcedureKind::Csv => "csv",
+                };
+                m::identifier(identifier) + pretty_print_arguments(args)
+            }
+            Statement::ModuleImport(_, module_path) => {
+                m::keyword("use") + m::space() + m::text(module_path.to_string())
+            }
+            Statement::DefineOperator {
+                symbol,
+                function_name,
+                ..
+            } => {
+                m::keyword("operator")
+                    + m::space()
+                    + m::operator(symbol)
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + m::identifier(function_name)
+            }
+            Statement::DefineStruct {
+                struct_name,
+                fields,
+                ..
+            } => {
+                m::keyword("struct")
+                    + m::space()
+                    + m::type_identifier(struct_name)
+                    + m::space()
+                    + m::operator("{")
+                    + if fields.is_empty() {
+                        m::empty()
+                    } else {
+                        m::space()
+                            + Itertools::intersperse(
+                                fields.iter().map(|(_, n, t)| {
+                                    m::identifier(n)
+                                        + m::operator(":")
+                                        + m::space()
+                                        + t.pretty_print()
+                                }),
+                                m::operator(",") + m::space(),
+                            )
+                            .sum()
+                            + m::space()
+                    }
+                    + m::operator("}")
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -439,6 +1131,30 @@ pub trait ReplaceSpans {
     fn replace_spans(&self) -> Self;
 }
 
+#[cfg(test)]
+impl ReplaceSpans for LocalVariable {
+    fn replace_spans(&self) -> Self {
+        LocalVariable {
+            identifier_span: Span::dummy(),
+            identifier: self.identifier.clone(),
+            expr: self.expr.replace_spans(),
+            type_annotation: self.type_annotation.as_ref().map(|t| t.replace_spans()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl ReplaceSpans for LocalDefinition {
+    fn replace_spans(&self) -> Self {
+        match self {
+            LocalDefinition::Variable(l) => LocalDefinition::Variable(l.replace_spans()),
+            LocalDefinition::Function(statement) => {
+                LocalDefinition::Function(Box::new(statement.replace_spans()))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 impl ReplaceSpans for TypeAnnotation {
     fn replace_spans(&self) -> Self {
@@ -457,6 +1173,16 @@ impl ReplaceSpans for TypeAnnotation {
     }
 }
 
+#[cfg(test)]
+impl ReplaceSpans for TypeParameterBound {
+    fn replace_spans(&self) -> Self {
+        match self {
+            TypeParameterBound::Dim => TypeParameterBound::Dim,
+            TypeParameterBound::Equal(dexpr) => TypeParameterBound::Equal(dexpr.replace_spans()),
+        }
+    }
+}
+
 #[cfg(test)]
 impl ReplaceSpans for TypeExpression {
     fn replace_spans(&self) -> Self {
@@ -569,6 +1295,14 @@ impl ReplaceSpans for Expression {
                 elements.iter().map(|e| e.replace_spans()).collect(),
             ),
             Expression::TypedHole(_) => Expression::TypedHole(Span::dummy()),
+            Expression::UserOperator {
+                symbol, lhs, rhs, ..
+            } => Expression::UserOperator {
+                symbol: symbol.clone(),
+                lhs: Box::new(lhs.replace_spans()),
+                rhs: Box::new(rhs.replace_spans()),
+                span_op: Span::dummy(),
+            },
         }
     }
 }
@@ -578,24 +1312,39 @@ impl ReplaceSpans for Statement {
     fn replace_spans(&self) -> Self {
         match self {
             Statement::Expression(expr) => Statement::Expression(expr.replace_spans()),
+            Statement::SuppressedExpression(expr) => {
+                Statement::SuppressedExpression(expr.replace_spans())
+            }
             Statement::DefineVariable {
                 identifier_span: _,
                 identifier,
                 expr,
                 type_annotation,
                 decorators,
+                mutable,
             } => Statement::DefineVariable {
                 identifier_span: Span::dummy(),
                 identifier: identifier.clone(),
                 expr: expr.replace_spans(),
                 type_annotation: type_annotation.as_ref().map(|t| t.replace_spans()),
                 decorators: decorators.clone(),
+                mutable: *mutable,
+            },
+            Statement::ReassignVariable {
+                identifier_span: _,
+                identifier,
+                expr,
+            } => Statement::ReassignVariable {
+                identifier_span: Span::dummy(),
+                identifier: identifier.clone(),
+                expr: expr.replace_spans(),
             },
             Statement::DefineFunction {
                 function_name_span: _,
                 function_name,
                 type_parameters,
                 parameters,
+                local_definitions,
                 body,
                 return_type_annotation,
                 decorators,
@@ -604,7 +1353,13 @@ impl ReplaceSpans for Statement {
                 function_name: function_name.clone(),
                 type_parameters: type_parameters
                     .iter()
-                    .map(|(_, name, bound)| (Span::dummy(), name.clone(), bound.clone()))
+                    .map(|(_, name, bound)| {
+                        (
+                            Span::dummy(),
+                            name.clone(),
+                            bound.as_ref().map(|b| b.replace_spans()),
+                        )
+                    })
                     .collect(),
                 parameters: parameters
                     .iter()
@@ -616,6 +1371,10 @@ impl ReplaceSpans for Statement {
                         )
                     })
                     .collect(),
+                local_definitions: local_definitions
+                    .iter()
+                    .map(|d| d.replace_spans())
+                    .collect(),
                 body: body.clone().map(|b| b.replace_spans()),
                 return_type_annotation: return_type_annotation.as_ref().map(|t| t.replace_spans()),
                 decorators: decorators.clone(),
@@ -631,6 +1390,12 @@ impl ReplaceSpans for Statement {
                 type_.as_ref().map(|t| t.replace_spans()),
                 decorators.clone(),
             ),
+            Statement::DeclareUnit(_, name, type_, decorators) => Statement::DeclareUnit(
+                Span::dummy(),
+                name.clone(),
+                type_.replace_spans(),
+                decorators.clone(),
+            ),
             Statement::DefineDerivedUnit {
                 identifier_span: _,
                 identifier,
@@ -668,6 +1433,16 @@ impl ReplaceSpans for Statement {
                     })
                     .collect(),
             },
+            Statement::DefineOperator {
+                symbol,
+                function_name,
+                ..
+            } => Statement::DefineOperator {
+                symbol_span: Span::dummy(),
+                symbol: symbol.clone(),
+                function_name_span: Span::dummy(),
+                function_name: function_name.clone(),
+            },
         }
     }
 }
@@ -678,3 +1453,385 @@ impl ReplaceSpans for Vec<Statement> {
         self.iter().map(|s| s.replace_spans()).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::{AsciiOperators, Formatter, PlainTextFormatter};
+    use proptest::prelude::*;
+
+    fn parse(code: &str) -> Vec<Statement> {
+        crate::parser::parse(code, 0).unwrap().replace_spans()
+    }
+
+    #[track_caller]
+    fn roundtrip_check(code: &str) {
+        let statements = parse(code);
+        let pretty_printed: Vec<_> = statements
+            .iter()
+            .map(|s| PlainTextFormatter {}.format(&s.pretty_print(), false))
+            .collect();
+        println!("{code} => {pretty_printed:?}");
+        assert_eq!(statements, parse(&pretty_printed.join("\n")));
+    }
+
+    #[test]
+    fn pretty_print_roundtrip_check() {
+        roundtrip_check("2 + 3 * 4");
+        roundtrip_check("-3^4 + 2 / (4 + 2 * 3)");
+        roundtrip_check("1 - 2 - 3 - (4 - 5)");
+        roundtrip_check("1 / 2 / (3 / 4)");
+        roundtrip_check("a mod b * c mod (d mod e) + f");
+        roundtrip_check("2 m/s -> km/h");
+        roundtrip_check("(2 meter)^3");
+        roundtrip_check("sin(x)^2 + (-3)!");
+        roundtrip_check("if a < b then a else b");
+        roundtrip_check("!(a && b) || c");
+        roundtrip_check("Foo { foo: 1 meter, bar: 1 second }.foo");
+        roundtrip_check("[1, 2, 3]");
+        roundtrip_check("\"x = {x:.3}, newline: \\n\"");
+        roundtrip_check("(2).foo");
+        roundtrip_check("a -> (b -> c)");
+        roundtrip_check("(if a then b else c) -> d");
+
+        roundtrip_check("let x: Length = 2 m");
+        roundtrip_check("fn f<D: Dim, T>(x: D, y) -> D where T: Length = x");
+        roundtrip_check("fn foreign(x: Scalar) -> Scalar");
+        roundtrip_check("fn foo(x) = { let y = 2 x; y + x }");
+        roundtrip_check("fn foo(x: Length) -> Length = { let y: Length = 2 x; let z = y; z + x }");
+        roundtrip_check("dimension Velocity = Length / Time");
+        roundtrip_check("@aliases(m: short)\n@metric_prefixes\nunit meter: Length");
+        roundtrip_check("@name(\"Inch\")\nunit inch: Length = 0.0254 m");
+        roundtrip_check("@aliases(f)\nunit foo: Length = ...");
+        roundtrip_check("assert_eq(1 m, 100 cm)");
+        roundtrip_check("use core::scalar");
+        roundtrip_check("struct Foo { foo: Length, bar: Time }");
+    }
+
+    #[test]
+    fn canonical_form() {
+        let ctx = crate::Context::new_without_importer();
+
+        let canonicalize = |code: &str| {
+            let canonical = ctx.canonical_form(code).unwrap();
+            assert_eq!(parse(code), parse(&canonical));
+            assert_eq!(ctx.canonical_form(&canonical).unwrap(), canonical);
+            canonical
+        };
+
+        assert_eq!(canonicalize("2*  3+4"), "2 × 3 + 4");
+        assert_eq!(canonicalize("2*(3+4)"), "2 × (3 + 4)");
+        assert_eq!(canonicalize("let  v=2 m/s"), "let v = 2 m / s");
+        assert_eq!(canonicalize("3km->m"), "3 km ➞ m");
+        assert_eq!(canonicalize("2 pi  r"), "2 pi × r");
+        assert_eq!(
+            canonicalize("fn f(x)=x^2 # square\n\nf( 3 )"),
+            "fn f(x) = x²\nf(3)"
+        );
+
+        assert!(ctx.canonical_form("2 +").is_err());
+    }
+
+    #[test]
+    fn pretty_print_ascii_operators() {
+        for (code, unicode, ascii) in [
+            ("a * b", "a × b", "a * b"),
+            ("a -> b", "a ➞ b", "a -> b"),
+            ("a <= b", "a ≤ b", "a <= b"),
+            ("a >= b", "a ≥ b", "a >= b"),
+            ("a != b", "a ≠ b", "a != b"),
+            ("x^2 + y^3", "x² + y³", "x^2 + y^3"),
+            ("(a * b)^2 -> c", "(a × b)² ➞ c", "(a * b)^2 -> c"),
+            (
+                "2 meter^3 / second",
+                "2 × meter³ / second",
+                "2 * meter^3 / second",
+            ),
+            ("a^b ≠ c", "(a^b) ≠ c", "(a^b) != c"),
+        ] {
+            let statements = parse(code);
+            let markup = statements[0].pretty_print();
+
+            assert_eq!(PlainTextFormatter {}.format(&markup, false), unicode);
+
+            let ascii_code = AsciiOperators(PlainTextFormatter {}).format(&markup, false);
+            assert_eq!(ascii_code, ascii);
+            assert!(ascii_code.is_ascii());
+            assert_eq!(parse(&ascii_code), statements);
+        }
+    }
+
+    fn arb_scalar() -> impl Strategy<Value = Expression> {
+        prop_oneof![
+            (0u32..1000).prop_map(|n| n as f64),
+            (0u32..10000).prop_map(|n| n as f64 / 100.0),
+        ]
+        .prop_map(|n| Expression::Scalar(Span::dummy(), Number::from_f64(n)))
+    }
+
+    fn arb_identifier() -> impl Strategy<Value = String> {
+        prop::sample::select(vec!["x", "y", "foo", "meter", "länge", "µ", "_prefixed"])
+            .prop_map(|name| name.to_string())
+    }
+
+    fn arb_operand() -> impl Strategy<Value = Expression> {
+        prop_oneof![
+            arb_scalar(),
+            arb_identifier().prop_map(|name| Expression::Identifier(Span::dummy(), name)),
+        ]
+    }
+
+    fn arb_leaf() -> impl Strategy<Value = Expression> {
+        prop_oneof![
+            6 => arb_operand(),
+            1 => any::<bool>().prop_map(|b| Expression::Boolean(Span::dummy(), b)),
+            1 => "[a-z {}\\\\\"\n]{0,5}"
+                .prop_map(|s| Expression::String(Span::dummy(), vec![StringPart::Fixed(s)])),
+        ]
+    }
+
+    fn arb_unary_operator() -> impl Strategy<Value = UnaryOperator> {
+        prop::sample::select(vec![
+            UnaryOperator::Factorial,
+            UnaryOperator::Negate,
+            UnaryOperator::LogicalNeg,
+            UnaryOperator::ConvertToBaseUnits,
+        ])
+    }
+
+    fn arb_binary_operator() -> impl Strategy<Value = BinaryOperator> {
+        use BinaryOperator::*;
+
+        prop::sample::select(vec![
+            Add,
+            Sub,
+            Mul,
+            Div,
+            Mod,
+            Power,
+            ConvertTo,
+            LessThan,
+            GreaterThan,
+            LessOrEqual,
+            GreaterOrEqual,
+            Equal,
+            NotEqual,
+            LogicalAnd,
+            LogicalOr,
+        ])
+    }
+
+    /// Random (syntactically valid, but usually ill-typed) expressions, as they
+    /// could be produced by the parser.
+    fn arb_expression() -> impl Strategy<Value = Expression> {
+        arb_leaf().prop_recursive(4, 32, 3, |inner| {
+            prop_oneof![
+                1 => (arb_unary_operator(), inner.clone()).prop_map(|(op, expr)| {
+                    Expression::UnaryOperator {
+                        op,
+                        expr: Box::new(expr),
+                        span_op: Span::dummy(),
+                    }
+                }),
+                4 => (inner.clone(), arb_binary_operator(), inner.clone()).prop_map(
+                    |(lhs, op, rhs)| Expression::BinaryOperator {
+                        op,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                        span_op: Some(Span::dummy()),
+                    }
+                ),
+                1 => (arb_identifier(), prop::collection::vec(inner.clone(), 0..3)).prop_map(
+                    |(name, args)| Expression::FunctionCall(
+                        Span::dummy(),
+                        Span::dummy(),
+                        Box::new(Expression::Identifier(Span::dummy(), name)),
+                        args,
+                    )
+                ),
+                1 => (inner.clone(), inner.clone(), inner.clone()).prop_map(|(c, t, e)| {
+                    Expression::Condition(Span::dummy(), Box::new(c), Box::new(t), Box::new(e))
+                }),
+                1 => prop::collection::vec(inner.clone(), 0..3)
+                    .prop_map(|elements| Expression::List(Span::dummy(), elements)),
+                1 => prop::collection::vec((arb_identifier(), inner.clone()), 0..3).prop_map(
+                    |fields| Expression::InstantiateStruct {
+                        full_span: Span::dummy(),
+                        ident_span: Span::dummy(),
+                        name: "Foo".into(),
+                        fields: fields
+                            .into_iter()
+                            .map(|(name, expr)| (Span::dummy(), name, expr))
+                            .collect(),
+                    }
+                ),
+                1 => (inner.clone(), arb_identifier()).prop_map(|(expr, field)| {
+                    Expression::AccessField(Span::dummy(), Span::dummy(), Box::new(expr), field)
+                }),
+                // The tokenizer does not support string literals inside of
+                // interpolations, so we only interpolate simple operations.
+                1 => (arb_operand(), arb_binary_operator(), arb_operand()).prop_map(
+                    |(lhs, op, rhs)| Expression::String(
+                        Span::dummy(),
+                        vec![
+                            StringPart::Fixed("value: ".into()),
+                            StringPart::Interpolation {
+                                span: Span::dummy(),
+                                expr: Box::new(Expression::BinaryOperator {
+                                    op,
+                                    lhs: Box::new(lhs),
+                                    rhs: Box::new(rhs),
+                                    span_op: Some(Span::dummy()),
+                                }),
+                                format_specifiers: None,
+                            }
+                        ]
+                    )
+                ),
+            ]
+        })
+    }
+
+    /// The pretty printer omits parentheses for associative operators, so
+    /// `a + (b + c)` is printed as `a + b + c`. Rewrite all chains of additions
+    /// and multiplications as left-associative operations to account for this.
+    fn normalize_associativity(expr: &Expression) -> Expression {
+        fn operands(expr: Expression, op: BinaryOperator, result: &mut Vec<Expression>) {
+            match expr {
+                Expression::BinaryOperator {
+                    op: op_inner,
+                    lhs,
+                    rhs,
+                    ..
+                } if op_inner == op => {
+                    operands(*lhs, op, result);
+                    operands(*rhs, op, result);
+                }
+                expr => result.push(expr),
+            }
+        }
+
+        match expr {
+            Expression::BinaryOperator {
+                op: op @ (BinaryOperator::Add | BinaryOperator::Mul),
+                ..
+            } => {
+                let mut result = vec![];
+                operands(expr.clone(), *op, &mut result);
+                result
+                    .iter()
+                    .map(normalize_associativity)
+                    .reduce(|lhs, rhs| Expression::BinaryOperator {
+                        op: *op,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                        span_op: Some(Span::dummy()),
+                    })
+                    .unwrap()
+            }
+            Expression::BinaryOperator { op, lhs, rhs, .. } => Expression::BinaryOperator {
+                op: *op,
+                lhs: Box::new(normalize_associativity(lhs)),
+                rhs: Box::new(normalize_associativity(rhs)),
+                span_op: Some(Span::dummy()),
+            },
+            Expression::UnaryOperator { op, expr, span_op } => Expression::UnaryOperator {
+                op: *op,
+                expr: Box::new(normalize_associativity(expr)),
+                span_op: *span_op,
+            },
+            Expression::FunctionCall(span, full_span, callable, args) => Expression::FunctionCall(
+                *span,
+                *full_span,
+                Box::new(normalize_associativity(callable)),
+                args.iter().map(normalize_associativity).collect(),
+            ),
+            Expression::Condition(span, condition, then, else_) => Expression::Condition(
+                *span,
+                Box::new(normalize_associativity(condition)),
+                Box::new(normalize_associativity(then)),
+                Box::new(normalize_associativity(else_)),
+            ),
+            Expression::List(span, elements) => Expression::List(
+                *span,
+                elements.iter().map(normalize_associativity).collect(),
+            ),
+            Expression::InstantiateStruct {
+                full_span,
+                ident_span,
+                name,
+                fields,
+            } => Expression::InstantiateStruct {
+                full_span: *full_span,
+                ident_span: *ident_span,
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(span, n, e)| (*span, n.clone(), normalize_associativity(e)))
+                    .collect(),
+            },
+            Expression::AccessField(span, ident_span, expr, field) => Expression::AccessField(
+                *span,
+                *ident_span,
+                Box::new(normalize_associativity(expr)),
+                field.clone(),
+            ),
+            Expression::String(span, parts) => Expression::String(
+                *span,
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        StringPart::Interpolation {
+                            span,
+                            expr,
+                            format_specifiers,
+                        } => StringPart::Interpolation {
+                            span: *span,
+                            expr: Box::new(normalize_associativity(expr)),
+                            format_specifiers: format_specifiers.clone(),
+                        },
+                        part => part.clone(),
+                    })
+                    .collect(),
+            ),
+            expr => expr.clone(),
+        }
+    }
+
+    fn parse_expression(code: &str) -> Result<Expression, String> {
+        match crate::parser::parse(code, 0) {
+            Ok(statements) => match statements.replace_spans().as_slice() {
+                [Statement::Expression(expr)] => Ok(expr.clone()),
+                statements => Err(format!("expected a single expression, got {statements:?}")),
+            },
+            Err((_, errors)) => Err(format!("{errors:?}")),
+        }
+    }
+
+    fn pretty(expr: &Expression) -> String {
+        PlainTextFormatter {}.format(&expr.pretty_print(), false)
+    }
+
+    proptest! {
+        #[test]
+        fn pretty_print_roundtrip_property(expr in arb_expression()) {
+            let code = pretty(&expr);
+            let parsed = parse_expression(&code)
+                .map_err(|e| TestCaseError::fail(format!("could not parse '{code}': {e}")))?;
+            prop_assert!(
+                normalize_associativity(&parsed) == normalize_associativity(&expr.replace_spans()),
+                "'{}' was parsed as '{}' ({:?})",
+                code,
+                pretty(&parsed),
+                parsed
+            );
+
+            let code_roundtrip = pretty(&parsed);
+            prop_assert!(
+                parse_expression(&code_roundtrip).as_ref() == Ok(&parsed),
+                "'{}' does not roundtrip",
+                code_roundtrip
+            );
+        }
+    }
+}
@@ -1,36 +1,62 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use itertools::Itertools;
+use numbat_exchange_rates::ExchangeRates;
 
 use crate::ast::ProcedureKind;
+use crate::compiled_function::CompiledFunctionError;
 use crate::decorator::Decorator;
 use crate::dimension::DimensionRegistry;
+use crate::ffi::{ArityRange, ForeignFunctionFilter};
 use crate::interpreter::{
     Interpreter, InterpreterResult, InterpreterSettings, Result, RuntimeError,
 };
 use crate::name_resolution::LAST_RESULT_IDENTIFIERS;
-use crate::prefix::Prefix;
-use crate::prefix_parser::AcceptsPrefix;
+use crate::prefix::{AcceptsPrefix, Prefix};
 use crate::pretty_print::PrettyPrint;
-use crate::typed_ast::{BinaryOperator, Expression, Statement, StringPart, UnaryOperator};
+use crate::quantity::Quantity;
+use crate::traversal::{for_all_expressions_and_interpolations, ForAllExpressions};
+use crate::typechecker::type_scheme::TypeScheme;
+use crate::typed_ast::{
+    BinaryOperator, Expression, LocalDefinition, Statement, StringPart, UnaryOperator,
+};
 use crate::unit::{CanonicalName, Unit};
 use crate::unit_registry::{UnitMetadata, UnitRegistry};
 use crate::value::FunctionReference;
-use crate::vm::{Constant, ExecutionContext, Op, Vm};
+use crate::vm::{
+    Constant, ExecutionContext, LazyEvaluation, LazyGlobalFunction, Op, RelocatableChunk, Vm,
+};
 use crate::{decorator, ffi, Type};
 
+/// Name of the placeholder unit for derived-unit constants, until their definition ran
+const DUMMY_UNIT_NAME: &str = "<dummy>";
+
+/// Builtin functions whose arguments are passed on without simplification. For `raw`,
+/// this is the whole point. `mod` simplifies its result itself, after computing the
+/// remainder in the unit of the first argument. This way, `mod(370°, 360°)` is computed
+/// in degrees instead of in (inexact) radians.
+const UNSIMPLIFIED_ARGUMENT_FUNCTIONS: &[&str] = &["raw", "mod"];
+
+/// Functions whose body consists of at most this many expression nodes are
+/// inlined at their call sites
+const MAX_INLINED_FUNCTION_SIZE: usize = 16;
+
 #[derive(Debug, Clone, Default)]
 pub struct LocalMetadata {
     pub name: Option<String>,
     pub url: Option<String>,
     pub description: Option<String>,
     pub aliases: Vec<String>,
+    pub examples: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Local {
     identifier: String,
     depth: usize,
+    /// Whether the value might be an interval (see [`BytecodeInterpreter::may_be_interval`])
+    may_be_interval: bool,
     pub metadata: LocalMetadata,
 }
 
@@ -41,12 +67,45 @@ pub struct BytecodeInterpreter {
     locals: Vec<Vec<Local>>,
     // Maps names of units to indices of the respective constants in the VM
     unit_name_to_constant_index: HashMap<String, u16>,
-    /// List of functions
-    functions: HashMap<String, bool>,
+    /// Constant indices of forward-declared units whose value has not been defined yet
+    declared_units: HashMap<String, u16>,
+    /// List of functions, with a flag whether they are foreign functions, and their arity
+    functions: HashMap<String, (bool, ArityRange)>,
+    /// Bytecode for the bodies of small functions, which is copied to their call sites
+    inline_templates: HashMap<String, Vec<u8>>,
+    /// Whether or not calls to small functions are inlined
+    inline_functions: bool,
+    /// Exchange rates for this interpreter, instead of the global exchange rate cache
+    exchange_rates: Option<ExchangeRates>,
+    /// Defining expressions of units that depend on exchange rates, together with their
+    /// unit information and constant indices. They are re-evaluated (in order) when the
+    /// exchange rates are changed.
+    exchange_rate_units: Vec<(Expression, u16, u16)>,
 }
 
 impl BytecodeInterpreter {
     fn compile_expression(&mut self, expr: &Expression) -> Result<()> {
+        if let Expression::BinaryOperator(_span, BinaryOperator::ConvertTo, lhs, rhs, _type) = expr
+        {
+            // Conversions of constant quantities to known units are performed at compile
+            // time, unless the result changes with the exchange rates
+            if let Some(quantity) = self.evaluate_const_conversion(lhs, rhs).filter(|_| {
+                !self.depends_on_exchange_rates(lhs) && !self.depends_on_exchange_rates(rhs)
+            }) {
+                let index = self.vm.add_constant(Constant::Quantity(quantity));
+                self.vm.add_op1(Op::LoadConstant, index);
+                return Ok(());
+            }
+        }
+
+        if let Expression::BinaryOperator(_span, operator, lhs, rhs, _type) = expr {
+            // Multiplying or dividing by `1` and adding or subtracting `0` does not
+            // change the other operand, so we skip the operation altogether
+            if let Some(operand) = identity_operand(operator, lhs, rhs) {
+                return self.compile_expression(operand);
+            }
+        }
+
         match expr {
             Expression::Scalar(_span, n, _type) => {
                 let index = self.vm.add_constant(Constant::Scalar(n.to_f64()));
@@ -55,29 +114,21 @@ impl BytecodeInterpreter {
             Expression::Identifier(_span, identifier, _type) => {
                 // Searching in reverse order ensures that we find the innermost identifier of that name first (shadowing)
 
-                let current_depth = self.locals.len() - 1;
-
-                if let Some(position) = self.locals[current_depth]
-                    .iter()
-                    .rposition(|l| &l.identifier == identifier && l.depth == current_depth)
-                {
-                    self.vm.add_op1(Op::GetLocal, position as u16); // TODO: check overflow
+                if let Some(position) = self.local_position(identifier) {
+                    let position =
+                        u16::try_from(position).map_err(|_| RuntimeError::TooManyLocals)?;
+                    self.vm.add_op1(Op::GetLocal, position);
                 } else if let Some(upvalue_position) = self.locals[0]
                     .iter()
                     .rposition(|l| &l.identifier == identifier)
                 {
                     self.vm.add_op1(Op::GetUpvalue, upvalue_position as u16);
+                } else if let Some(lazy_global_idx) = self.vm.get_lazy_global_idx(identifier) {
+                    self.vm.add_op1(Op::GetLazyGlobal, lazy_global_idx);
                 } else if LAST_RESULT_IDENTIFIERS.contains(&identifier.as_str()) {
                     self.vm.add_op(Op::GetLastResult);
-                } else if let Some(is_foreign) = self.functions.get(identifier) {
-                    let index = self
-                        .vm
-                        .add_constant(Constant::FunctionReference(if *is_foreign {
-                            FunctionReference::Foreign(identifier.clone())
-                        } else {
-                            FunctionReference::Normal(identifier.clone())
-                        }));
-                    self.vm.add_op1(Op::LoadConstant, index);
+                } else if self.functions.contains_key(identifier) {
+                    self.load_function_reference(identifier);
                 } else {
                     unreachable!("Unknown identifier '{identifier}'")
                 }
@@ -107,27 +158,33 @@ impl BytecodeInterpreter {
                 self.compile_expression(lhs)?;
                 self.vm.add_op(Op::LogicalNeg);
             }
+            Expression::UnaryOperator(_span, UnaryOperator::ConvertToBaseUnits, lhs, _type) => {
+                self.compile_expression(lhs)?;
+                self.vm.add_op(Op::ConvertToBaseUnits);
+            }
+            Expression::BinaryOperator(_span, BinaryOperator::Mul, lhs, rhs, _type)
+                if self.is_same_local(lhs, rhs) =>
+            {
+                // Squaring a local variable (`x * x`): load it only once
+                self.compile_expression(lhs)?;
+                self.vm.add_op(Op::Dup);
+                self.vm.add_op(Op::Multiply);
+            }
+            Expression::BinaryOperator(_span, BinaryOperator::Power, lhs, rhs, _type)
+                if matches!(rhs.as_ref(), Expression::Scalar(_, n, _) if n.to_f64() == 2.0)
+                    && matches!(lhs.as_ref(), Expression::Identifier(..))
+                    && !self.may_be_interval(lhs) =>
+            {
+                // `x^2` is compiled like `x * x`. This is only done if `x` can not be an
+                // interval, since `Power` fails for intervals while `Multiply` does not.
+                self.compile_expression(lhs)?;
+                self.vm.add_op(Op::Dup);
+                self.vm.add_op(Op::Multiply);
+            }
             Expression::BinaryOperator(_span, operator, lhs, rhs, _type) => {
                 self.compile_expression(lhs)?;
                 self.compile_expression(rhs)?;
-
-                let op = match operator {
-                    BinaryOperator::Add => Op::Add,
-                    BinaryOperator::Sub => Op::Subtract,
-                    BinaryOperator::Mul => Op::Multiply,
-                    BinaryOperator::Div => Op::Divide,
-                    BinaryOperator::Power => Op::Power,
-                    BinaryOperator::ConvertTo => Op::ConvertTo,
-                    BinaryOperator::LessThan => Op::LessThan,
-                    BinaryOperator::GreaterThan => Op::GreaterThan,
-                    BinaryOperator::LessOrEqual => Op::LessOrEqual,
-                    BinaryOperator::GreaterOrEqual => Op::GreatorOrEqual,
-                    BinaryOperator::Equal => Op::Equal,
-                    BinaryOperator::NotEqual => Op::NotEqual,
-                    BinaryOperator::LogicalAnd => Op::LogicalAnd,
-                    BinaryOperator::LogicalOr => Op::LogicalOr,
-                };
-                self.vm.add_op(op);
+                self.vm.add_op(binary_operator_op(operator));
             }
             Expression::BinaryOperatorForDate(_span, operator, lhs, rhs, type_) => {
                 self.compile_expression(lhs)?;
@@ -155,19 +212,18 @@ impl BytecodeInterpreter {
                 self.vm.add_op(op);
             }
             Expression::FunctionCall(_span, _full_span, name, args, _type) => {
-                // Put all arguments on top of the stack
+                // Put all arguments on top of the stack. Some builtin functions receive
+                // their arguments as they are (see UNSIMPLIFIED_ARGUMENT_FUNCTIONS).
+                let simplify_arguments = !self.takes_unsimplified_arguments(name);
                 for arg in args {
-                    self.compile_expression_with_simplify(arg)?;
+                    if !simplify_arguments {
+                        self.compile_expression(arg)?;
+                    } else {
+                        self.compile_expression_with_simplify(arg)?;
+                    }
                 }
 
-                if let Some(idx) = self.vm.get_ffi_callable_idx(name) {
-                    // TODO: check overflow:
-                    self.vm.add_op2(Op::FFICallFunction, idx, args.len() as u16);
-                } else {
-                    let idx = self.vm.get_function_idx(name);
-
-                    self.vm.add_op2(Op::Call, idx, args.len() as u16); // TODO: check overflow
-                }
+                self.compile_call(name, args.len())?;
             }
             Expression::InstantiateStruct(_span, exprs, struct_info) => {
                 // structs must be consistently ordered in the VM, so we reorder
@@ -209,7 +265,8 @@ impl BytecodeInterpreter {
                 // Put the callable on top of the stack
                 self.compile_expression(callable)?;
 
-                self.vm.add_op1(Op::CallCallable, args.len() as u16);
+                self.vm
+                    .add_op1(Op::CallCallable, Self::num_args(args.len())?);
             }
             Expression::Boolean(_, val) => {
                 let index = self.vm.add_constant(Constant::Boolean(*val));
@@ -238,6 +295,14 @@ impl BytecodeInterpreter {
                 self.vm.add_op1(Op::JoinString, string_parts.len() as u16); // TODO: this can overflow
             }
             Expression::Condition(_, condition, then_expr, else_expr) => {
+                // If the condition is known at compile time, we only compile the
+                // branch that is actually taken.
+                match evaluate_const_condition(condition) {
+                    Some(true) => return self.compile_expression(then_expr),
+                    Some(false) => return self.compile_expression(else_expr),
+                    None => {}
+                }
+
                 self.compile_expression(condition)?;
 
                 let if_jump_offset = self.vm.current_offset() + 1; // +1 for the opcode
@@ -248,16 +313,11 @@ impl BytecodeInterpreter {
                 let else_jump_offset = self.vm.current_offset() + 1;
                 self.vm.add_op1(Op::Jump, 0xffff);
 
-                let else_block_offset = self.vm.current_offset();
-                self.vm
-                    .patch_u16_value_at(if_jump_offset, else_block_offset - (if_jump_offset + 2));
+                self.patch_jump(if_jump_offset)?;
 
                 self.compile_expression(else_expr)?;
 
-                let end_offset = self.vm.current_offset();
-
-                self.vm
-                    .patch_u16_value_at(else_jump_offset, end_offset - (else_jump_offset + 2));
+                self.patch_jump(else_jump_offset)?;
             }
             Expression::List(_, elements, _) => {
                 for element in elements {
@@ -274,9 +334,225 @@ impl BytecodeInterpreter {
         Ok(())
     }
 
+    /// The position of a local variable (or parameter) of the current function
+    fn local_position(&self, identifier: &str) -> Option<usize> {
+        let current_depth = self.locals.len() - 1;
+        self.locals[current_depth]
+            .iter()
+            .rposition(|l| l.identifier == identifier && l.depth == current_depth)
+    }
+
+    /// Whether both expressions refer to the same local variable
+    fn is_same_local(&self, lhs: &Expression, rhs: &Expression) -> bool {
+        match (lhs, rhs) {
+            (Expression::Identifier(_, lhs_name, _), Expression::Identifier(_, rhs_name, _)) => {
+                lhs_name == rhs_name && self.local_position(lhs_name).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether an expression might evaluate to an interval. Intervals have the same type
+    /// as plain quantities, so this is decided by looking at how the value is computed.
+    fn may_be_interval(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Scalar(..) | Expression::UnitIdentifier(..) => false,
+            Expression::Identifier(_, identifier, _) => match self.local_position(identifier) {
+                Some(position) => self.locals[self.current_depth()][position].may_be_interval,
+                None => true,
+            },
+            Expression::UnaryOperator(_, _, operand, _) => self.may_be_interval(operand),
+            Expression::BinaryOperator(_, _, lhs, rhs, _) => {
+                self.may_be_interval(lhs) || self.may_be_interval(rhs)
+            }
+            Expression::Condition(_, _, then_expr, else_expr) => {
+                self.may_be_interval(then_expr) || self.may_be_interval(else_expr)
+            }
+            _ => true,
+        }
+    }
+
+    fn takes_unsimplified_arguments(&self, name: &str) -> bool {
+        UNSIMPLIFIED_ARGUMENT_FUNCTIONS.contains(&name)
+            && matches!(self.functions.get(name), Some((true, _)))
+    }
+
+    /// Emit the call of a function whose arguments are already on the stack
+    fn compile_call(&mut self, name: &str, num_args: usize) -> Result<()> {
+        // The typechecker makes sure that the number of arguments is correct. Make sure
+        // that we never emit a malformed call, in case the two ever get out of sync.
+        if let Some((_, arity)) = self.functions.get(name) {
+            if num_args > 0 && num_args < *arity.start() {
+                // A partial application (see `Context::set_partial_application`). The
+                // supplied arguments are already on the stack.
+                self.load_function_reference(name);
+                self.vm.add_op1(Op::PartialApply, Self::num_args(num_args)?);
+                return Ok(());
+            }
+
+            if !arity.contains(&num_args) {
+                return Err(RuntimeError::CallWithWrongArity(
+                    name.into(),
+                    arity.clone(),
+                    num_args,
+                ));
+            }
+        }
+
+        // A function with a body shadows a builtin (foreign) function of the same name
+        let is_user_defined = matches!(self.functions.get(name), Some((false, _)));
+
+        let num_args = Self::num_args(num_args)?;
+        if let Some(idx) = self
+            .vm
+            .get_ffi_callable_idx(name)
+            .filter(|_| !is_user_defined)
+        {
+            self.vm.add_op2(Op::FFICallFunction, idx, num_args);
+        } else if let Some(template) = self
+            .inline_templates
+            .get(name)
+            .filter(|_| self.inline_functions)
+        {
+            // Instead of calling the function, evaluate its body right here. The
+            // arguments stay on the stack until the result has been computed.
+            let template = template.clone();
+            self.vm.add_inline_template(&template);
+            self.vm.add_op1(Op::DropInlineArguments, num_args);
+        } else {
+            let idx = self.vm.get_function_idx(name);
+
+            self.vm.add_op2(Op::Call, idx, num_args);
+        }
+
+        Ok(())
+    }
+
+    /// Compile the body of a function into a template that can be copied to its call
+    /// sites, if the function is small enough. The template is compiled right after the
+    /// function itself, such that all global names are resolved in the same way.
+    fn compile_inline_template(
+        &mut self,
+        parameters: &[String],
+        body: &Expression,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut size = 0;
+        body.for_all_expressions(&mut |_| size += 1);
+        if size > MAX_INLINED_FUNCTION_SIZE || !is_inlinable(body, parameters) {
+            return Ok(None);
+        }
+
+        self.vm.begin_inline_template();
+        let result = self
+            .compile_inlined_expression(body, parameters, 0)
+            .map(|()| self.simplify_result(body));
+        let template = self.vm.end_inline_template();
+
+        result.map(|()| Some(template))
+    }
+
+    /// Compile an expression in the body of an inlined function. The arguments of the
+    /// function are on the stack, with `depth` temporary values on top of them.
+    fn compile_inlined_expression(
+        &mut self,
+        expr: &Expression,
+        parameters: &[String],
+        depth: usize,
+    ) -> Result<()> {
+        if !mentions_parameters(expr, parameters) {
+            return self.compile_expression(expr);
+        }
+
+        if let Expression::BinaryOperator(_span, operator, lhs, rhs, _type) = expr {
+            if let Some(operand) = identity_operand(operator, lhs, rhs) {
+                return self.compile_inlined_expression(operand, parameters, depth);
+            }
+        }
+
+        match expr {
+            Expression::Identifier(_span, identifier, _type) => {
+                let position = parameters.iter().rposition(|p| p == identifier).unwrap();
+                let distance = depth + parameters.len() - 1 - position;
+                self.vm.add_op1(Op::GetInlineArgument, distance as u16);
+            }
+            Expression::UnaryOperator(_span, operator, expr, _type) => {
+                self.compile_inlined_expression(expr, parameters, depth)?;
+                self.vm.add_op(match operator {
+                    UnaryOperator::Negate => Op::Negate,
+                    UnaryOperator::Factorial => Op::Factorial,
+                    UnaryOperator::LogicalNeg => Op::LogicalNeg,
+                    UnaryOperator::ConvertToBaseUnits => Op::ConvertToBaseUnits,
+                });
+            }
+            Expression::BinaryOperator(_span, operator, lhs, rhs, _type) => {
+                self.compile_inlined_expression(lhs, parameters, depth)?;
+                self.compile_inlined_expression(rhs, parameters, depth + 1)?;
+                self.vm.add_op(binary_operator_op(operator));
+            }
+            Expression::FunctionCall(_span, _full_span, name, args, _type) => {
+                let simplify_arguments = !self.takes_unsimplified_arguments(name);
+                for (i, arg) in args.iter().enumerate() {
+                    self.compile_inlined_expression(arg, parameters, depth + i)?;
+                    if simplify_arguments {
+                        self.simplify_result(arg);
+                    }
+                }
+
+                self.compile_call(name, args.len())?;
+            }
+            Expression::Condition(_span, condition, then_expr, else_expr) => {
+                match evaluate_const_condition(condition) {
+                    Some(true) => {
+                        return self.compile_inlined_expression(then_expr, parameters, depth)
+                    }
+                    Some(false) => {
+                        return self.compile_inlined_expression(else_expr, parameters, depth)
+                    }
+                    None => {}
+                }
+
+                self.compile_inlined_expression(condition, parameters, depth)?;
+
+                let if_jump_offset = self.vm.current_offset() + 1; // +1 for the opcode
+                self.vm.add_op1(Op::JumpIfFalse, 0xffff);
+
+                self.compile_inlined_expression(then_expr, parameters, depth)?;
+
+                let else_jump_offset = self.vm.current_offset() + 1;
+                self.vm.add_op1(Op::Jump, 0xffff);
+
+                self.patch_jump(if_jump_offset)?;
+
+                self.compile_inlined_expression(else_expr, parameters, depth)?;
+
+                self.patch_jump(else_jump_offset)?;
+            }
+            _ => unreachable!("Checked by is_inlinable"),
+        }
+
+        Ok(())
+    }
+
+    /// Patch the (placeholder) argument of a jump instruction at `jump_offset`
+    /// such that it jumps to the current end of the chunk.
+    fn patch_jump(&mut self, jump_offset: usize) -> Result<()> {
+        let distance = self.vm.current_offset() - (jump_offset + 2); // +2 for the argument
+        let distance = u16::try_from(distance).map_err(|_| RuntimeError::JumpTooFar(distance))?;
+        self.vm.patch_u16_value_at(jump_offset, distance);
+
+        Ok(())
+    }
+
     fn compile_expression_with_simplify(&mut self, expr: &Expression) -> Result<()> {
         self.compile_expression(expr)?;
+        self.simplify_result(expr);
 
+        Ok(())
+    }
+
+    /// Simplify the value of the (just compiled) expression on top of the stack, if
+    /// it is the result of an arithmetic operation
+    fn simplify_result(&mut self, expr: &Expression) {
         match expr {
             Expression::Scalar(..)
             | Expression::Identifier(..)
@@ -296,8 +572,6 @@ impl BytecodeInterpreter {
             }
             Expression::TypedHole(_, _) => unreachable!("Typed holes cause type inference errors"),
         }
-
-        Ok(())
     }
 
     fn compile_statement(
@@ -330,55 +604,70 @@ impl BytecodeInterpreter {
                     url: crate::decorator::url(decorators),
                     description: crate::decorator::description(decorators),
                     aliases: aliases.clone(),
+                    examples: crate::decorator::examples(decorators),
                 };
 
+                let may_be_interval = self.may_be_interval(expr);
                 for alias_name in aliases {
                     self.compile_expression_with_simplify(expr)?;
 
                     self.locals[current_depth].push(Local {
                         identifier: alias_name.clone(),
                         depth: 0,
+                        may_be_interval,
                         metadata: metadata.clone(),
                     });
                 }
             }
+            Statement::ReassignVariable(identifier, expr) => {
+                let current_depth = self.current_depth();
+
+                let position = self.locals[current_depth]
+                    .iter()
+                    .rposition(|l| &l.identifier == identifier)
+                    .expect("Reassigned variables are checked to exist by the type checker");
+
+                // The variable and its aliases have been defined next to each other (see
+                // above). Overwrite all of them in place, such that functions referring to
+                // the variable see the new value.
+                let aliases = &self.locals[current_depth][position].metadata.aliases;
+                let index_in_aliases = aliases.iter().position(|a| a == identifier).unwrap();
+                let first_position = position - index_in_aliases;
+                let positions = first_position..(first_position + aliases.len());
+
+                let may_be_interval = self.may_be_interval(expr);
+                for local in &mut self.locals[current_depth][positions.clone()] {
+                    local.may_be_interval = may_be_interval;
+                }
+
+                self.compile_expression_with_simplify(expr)?;
+                self.vm.add_op1(Op::SetLocal, first_position as u16); // TODO: check overflow
+
+                for position in positions.skip(1) {
+                    self.vm.add_op1(Op::GetLocal, first_position as u16);
+                    self.vm.add_op1(Op::SetLocal, position as u16);
+                }
+            }
             Statement::DefineFunction(
                 name,
                 _decorators,
                 _type_parameters,
                 parameters,
+                local_definitions,
                 Some(expr),
                 _return_type,
                 _return_type_annotation,
                 _readable_return_type,
             ) => {
-                self.vm.begin_function(name);
-
-                self.locals.push(vec![]);
-
-                let current_depth = self.current_depth();
-                for parameter in parameters {
-                    self.locals[current_depth].push(Local {
-                        identifier: parameter.1.clone(),
-                        depth: current_depth,
-                        metadata: LocalMetadata::default(),
-                    });
-                }
-
-                self.compile_expression_with_simplify(expr)?;
-                self.vm.add_op(Op::Return);
-
-                self.locals.pop();
-
-                self.vm.end_function();
-
-                self.functions.insert(name.clone(), false);
+                let parameters: Vec<_> = parameters.iter().map(|p| p.1.clone()).collect();
+                self.compile_function(name, &[], &parameters, local_definitions, expr)?;
             }
             Statement::DefineFunction(
                 name,
                 _decorators,
                 _type_parameters,
                 parameters,
+                _local_definitions,
                 None,
                 _return_type,
                 _return_type_annotation,
@@ -390,7 +679,9 @@ impl BytecodeInterpreter {
                 self.vm
                     .add_foreign_function(name, parameters.len()..=parameters.len());
 
-                self.functions.insert(name.clone(), true);
+                self.functions
+                    .insert(name.clone(), (true, parameters.len()..=parameters.len()));
+                self.inline_templates.remove(name);
             }
             Statement::DefineDimension(_name, _dexprs) => {
                 // Declaring a dimension is like introducing a new type. The information
@@ -418,6 +709,7 @@ impl BytecodeInterpreter {
                             ),
                             url: decorator::url(decorators),
                             description: decorator::description(decorators),
+                            examples: decorator::examples(decorators),
                             binary_prefixes: decorators.contains(&Decorator::BinaryPrefixes),
                             metric_prefixes: decorators.contains(&Decorator::MetricPrefixes),
                         },
@@ -433,6 +725,18 @@ impl BytecodeInterpreter {
                         .insert(name.into(), constant_idx);
                 }
             }
+            Statement::DeclareUnit(unit_name, decorators, _annotation, _type) => {
+                // The constant is filled in once the value definition of the unit runs.
+                // Until then, loading it results in a runtime error.
+                let constant_idx = self
+                    .vm
+                    .add_constant(Constant::DeclaredUnit(unit_name.clone()));
+                for (name, _) in decorator::name_and_aliases(unit_name, decorators) {
+                    self.unit_name_to_constant_index
+                        .insert(name.into(), constant_idx);
+                }
+                self.declared_units.insert(unit_name.clone(), constant_idx);
+            }
             Statement::DefineDerivedUnit(
                 unit_name,
                 expr,
@@ -445,13 +749,13 @@ impl BytecodeInterpreter {
                     .map(|(name, ap)| (name.clone(), ap))
                     .collect();
 
-                let constant_idx = self.vm.add_constant(Constant::Unit(Unit::new_base(
-                    "<dummy>",
-                    CanonicalName {
-                        name: "<dummy>".to_string(),
-                        accepts_prefix: AcceptsPrefix::both(),
-                    },
-                ))); // TODO: dummy is just a temp. value until the SetUnitConstant op runs
+                let constant_idx = match self.declared_units.remove(unit_name) {
+                    Some(constant_idx) => constant_idx,
+                    None => self.vm.add_constant(Constant::Unit(Unit::new_base(
+                        DUMMY_UNIT_NAME,
+                        CanonicalName::new(DUMMY_UNIT_NAME, AcceptsPrefix::both()),
+                    ))), // TODO: dummy is just a temp. value until the SetUnitConstant op runs
+                };
                 let unit_information_idx = self.vm.add_unit_information(
                     unit_name,
                     Some(
@@ -472,15 +776,28 @@ impl BytecodeInterpreter {
                         canonical_name: decorator::get_canonical_unit_name(unit_name, decorators),
                         url: decorator::url(decorators),
                         description: decorator::description(decorators),
+                        examples: decorator::examples(decorators),
                         binary_prefixes: decorators.contains(&Decorator::BinaryPrefixes),
                         metric_prefixes: decorators.contains(&Decorator::MetricPrefixes),
                     },
                 ); // TODO: there is some asymmetry here because we do not introduce identifiers for base units
 
-                self.compile_expression_with_simplify(expr)?;
+                // Do not simplify the defining expression: the unit in which it is
+                // expressed (e.g. `degree` for `unit gradian = 90° / 100`) is later
+                // used to convert between related units without going through the
+                // base units.
+                self.compile_expression(expr)?;
                 self.vm
                     .add_op2(Op::SetUnitConstant, unit_information_idx, constant_idx);
 
+                if self.depends_on_exchange_rates(expr) {
+                    self.exchange_rate_units.push((
+                        expr.clone(),
+                        unit_information_idx,
+                        constant_idx,
+                    ));
+                }
+
                 // TODO: code duplication with DeclareBaseUnit branch above
                 for (name, _) in decorator::name_and_aliases(unit_name, decorators) {
                     self.unit_name_to_constant_index
@@ -492,9 +809,22 @@ impl BytecodeInterpreter {
                 let arg = &args[0];
 
                 use crate::markup as m;
-                let idx = self.vm.add_string(
-                    m::dimmed("=") + m::whitespace(" ") + arg.get_type_scheme().pretty_print(), // TODO
-                );
+                let type_scheme = arg.get_type_scheme();
+                let mut markup = m::dimmed("=") + m::whitespace(" ") + type_scheme.pretty_print();
+
+                // Report the names of derived dimensions, if there are any.
+                let names = Self::derived_dimension_names(&type_scheme, dimension_registry);
+                if !names.is_empty() {
+                    markup += m::dimmed("    [")
+                        + Itertools::intersperse(
+                            names.iter().map(m::type_identifier),
+                            m::dimmed(" or "),
+                        )
+                        .sum()
+                        + m::dimmed("]");
+                }
+
+                let idx = self.vm.add_string(markup);
                 self.vm.add_op1(Op::PrintString, idx);
             }
             Statement::ProcedureCall(kind, args) => {
@@ -503,6 +833,21 @@ impl BytecodeInterpreter {
                     self.compile_expression_with_simplify(arg)?;
                 }
 
+                // The 'debug' procedure additionally receives the (statically known)
+                // type of its argument, in the same form as it is shown by 'type'.
+                let mut num_args = args.len();
+                if kind == &ProcedureKind::Debug {
+                    let type_scheme = args[0].get_type_scheme();
+                    let mut type_ = type_scheme.pretty_print().to_string();
+                    let names = Self::derived_dimension_names(&type_scheme, dimension_registry);
+                    if !names.is_empty() {
+                        type_ += &format!(" ({})", names.join(" or "));
+                    }
+                    let idx = self.vm.add_constant(Constant::String(type_));
+                    self.vm.add_op1(Op::LoadConstant, idx);
+                    num_args += 1;
+                }
+
                 let name = &ffi::procedures().get(kind).unwrap().name;
 
                 let callable_idx = self.vm.get_ffi_callable_idx(name).unwrap();
@@ -513,7 +858,7 @@ impl BytecodeInterpreter {
                 self.vm.add_op3(
                     Op::FFICallProcedure,
                     callable_idx,
-                    args.len() as u16,
+                    num_args as u16,
                     spans_idx,
                 );
                 // TODO: check overflow
@@ -521,14 +866,42 @@ impl BytecodeInterpreter {
             Statement::DefineStruct(struct_info) => {
                 self.vm.add_struct_info(struct_info);
             }
+            Statement::DefineOperator(_, _) => {
+                // Nothing to do here, operators have been desugared to function
+                // calls by the type checker.
+            }
         }
 
         Ok(())
     }
 
+    /// Names of derived dimensions (e.g. 'Energy' or 'Torque' for 'Length² × Mass / Time²')
+    /// for the given type, if there are any.
+    fn derived_dimension_names(
+        type_scheme: &TypeScheme,
+        dimension_registry: &DimensionRegistry,
+    ) -> Vec<String> {
+        match type_scheme {
+            TypeScheme::Quantified(0, qualified_type) => match &qualified_type.inner {
+                Type::Dimension(dtype) if !dtype.is_scalar() => {
+                    dimension_registry.get_derived_entry_names_for(&dtype.to_base_representation())
+                }
+                _ => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
     fn run(&mut self, settings: &mut InterpreterSettings) -> Result<InterpreterResult> {
         let mut ctx = ExecutionContext {
             print_fn: &mut settings.print_fn,
+            print_value_fn: settings.print_value_fn.as_deref_mut(),
+            input_fn: settings.input_fn.as_deref_mut(),
+            max_steps: settings.max_steps,
+            max_output_bytes: settings.max_output_bytes,
+            output_bytes: 0,
+            rounding_mode: settings.rounding_mode,
+            exchange_rates: self.exchange_rates.as_ref(),
         };
 
         self.vm.disassemble();
@@ -544,10 +917,260 @@ impl BytecodeInterpreter {
         self.vm.set_debug(activate);
     }
 
+    pub(crate) fn set_max_stack_depth(&mut self, max_stack_depth: usize) {
+        self.vm.set_max_stack_depth(max_stack_depth);
+    }
+
+    /// Use the given exchange rates from now on. Units that have already been defined
+    /// in terms of exchange rates are updated accordingly.
+    pub(crate) fn set_exchange_rates(&mut self, exchange_rates: ExchangeRates) -> Result<()> {
+        self.exchange_rates = Some(exchange_rates);
+
+        if self.exchange_rate_units.is_empty() {
+            return Ok(());
+        }
+
+        for (expr, unit_information_idx, constant_idx) in self.exchange_rate_units.clone() {
+            self.compile_expression(&expr)?;
+            self.vm
+                .add_op2(Op::UpdateUnitConstant, unit_information_idx, constant_idx);
+        }
+
+        let mut settings = InterpreterSettings {
+            print_fn: Box::new(|_: &crate::markup::Markup| {}),
+            ..InterpreterSettings::default()
+        };
+        let _ = self.run(&mut settings)?;
+
+        Ok(())
+    }
+
+    /// Whether an expression calls `exchange_rate(…)`, or refers to a unit that
+    /// does so in its definition.
+    fn depends_on_exchange_rates(&self, expr: &Expression) -> bool {
+        let mut depends = false;
+        expr.for_all_expressions(&mut |e| match e {
+            Expression::FunctionCall(_, _, name, _, _) if name == "exchange_rate" => depends = true,
+            Expression::UnitIdentifier(_, _, unit_name, _, _) => {
+                let constant_idx = self.unit_name_to_constant_index.get(unit_name);
+                if self
+                    .exchange_rate_units
+                    .iter()
+                    .any(|(_, _, idx)| Some(idx) == constant_idx)
+                {
+                    depends = true
+                }
+            }
+            _ => {}
+        });
+        depends
+    }
+
+    pub(crate) fn has_exchange_rates(&self) -> bool {
+        self.exchange_rates.is_some()
+    }
+
+    pub(crate) fn set_foreign_function_filter(&mut self, filter: ForeignFunctionFilter) {
+        self.vm.set_foreign_function_filter(filter);
+    }
+
+    pub(crate) fn set_function_inlining(&mut self, activate: bool) {
+        self.inline_functions = activate;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn disassembly(&self) -> String {
+        self.vm.disassembly()
+    }
+
+    fn evaluate_const_conversion(&self, lhs: &Expression, rhs: &Expression) -> Option<Quantity> {
+        let lhs = self.evaluate_const_quantity(lhs)?;
+        let rhs = self.evaluate_const_quantity(rhs)?;
+        lhs.convert_to(rhs.unit()).ok()
+    }
+
+    /// Evaluates quantities like `3 km / h` at compile time, if all units involved
+    /// are already known. Derived units are only known once their definition ran.
+    fn evaluate_const_quantity(&self, expr: &Expression) -> Option<Quantity> {
+        match expr {
+            Expression::Scalar(_, n, _) => Some(Quantity::from_scalar(n.to_f64())),
+            Expression::UnitIdentifier(_, prefix, unit_name, _, _) => {
+                let index = self.unit_name_to_constant_index.get(unit_name)?;
+                match self.vm.constant(*index) {
+                    Constant::Unit(unit)
+                        if !unit.iter().any(|f| f.unit_id.name == DUMMY_UNIT_NAME) =>
+                    {
+                        Some(Quantity::from_unit(unit.clone().with_prefix(*prefix)))
+                    }
+                    _ => None,
+                }
+            }
+            Expression::UnaryOperator(_, UnaryOperator::Negate, expr, _) => {
+                Some(-self.evaluate_const_quantity(expr)?)
+            }
+            Expression::BinaryOperator(_, BinaryOperator::Mul, lhs, rhs, _) => {
+                Some(self.evaluate_const_quantity(lhs)? * self.evaluate_const_quantity(rhs)?)
+            }
+            Expression::BinaryOperator(_, BinaryOperator::Div, lhs, rhs, _) => self
+                .evaluate_const_quantity(lhs)?
+                .checked_div(self.evaluate_const_quantity(rhs)?),
+            _ => None,
+        }
+    }
+
     fn current_depth(&self) -> usize {
         self.locals.len() - 1
     }
 
+    fn num_args(num_args: usize) -> Result<u16> {
+        u16::try_from(num_args).map_err(|_| RuntimeError::TooManyArguments(num_args))
+    }
+
+    /// Push a reference to the function with the given name onto the stack
+    fn load_function_reference(&mut self, name: &str) {
+        let index = self
+            .vm
+            .add_constant(Constant::FunctionReference(if self.functions[name].0 {
+                FunctionReference::Foreign(name.to_owned())
+            } else {
+                FunctionReference::Normal(name.to_owned())
+            }));
+        self.vm.add_op1(Op::LoadConstant, index);
+    }
+
+    /// Compile a function with a body into a chunk of its own. The values of the
+    /// `captured` locals of an enclosing function are passed in front of the parameters.
+    fn compile_function(
+        &mut self,
+        name: &str,
+        captured: &[String],
+        parameters: &[String],
+        local_definitions: &[LocalDefinition],
+        body: &Expression,
+    ) -> Result<()> {
+        // Functions that are defined in the body get chunks of their own. They are
+        // compiled first, since only one chunk can be compiled at a time.
+        let mut visible_locals: Vec<_> = captured.iter().chain(parameters).cloned().collect();
+        let mut local_functions = vec![];
+        for local_definition in local_definitions {
+            match local_definition {
+                LocalDefinition::Variable(identifier, _, _) => {
+                    visible_locals.push(identifier.clone());
+                }
+                LocalDefinition::Function(statement) => {
+                    let Statement::DefineFunction(
+                        local_name,
+                        _,
+                        _,
+                        local_parameters,
+                        local_local_definitions,
+                        Some(local_body),
+                        _,
+                        _,
+                        _,
+                    ) = statement.as_ref()
+                    else {
+                        unreachable!("Local functions always have a body")
+                    };
+
+                    let mut identifiers = vec![];
+                    for_all_expressions_and_interpolations(statement.as_ref(), &mut |e| {
+                        if let Expression::Identifier(_, identifier, _) = e {
+                            identifiers.push(identifier.clone());
+                        }
+                    });
+                    let local_captured: Vec<_> = visible_locals
+                        .iter()
+                        .filter(|l| identifiers.contains(l))
+                        .unique()
+                        .cloned()
+                        .collect();
+
+                    let chunk_name = format!("{name}::{local_name}");
+                    let local_parameters: Vec<_> =
+                        local_parameters.iter().map(|p| p.1.clone()).collect();
+                    self.compile_function(
+                        &chunk_name,
+                        &local_captured,
+                        &local_parameters,
+                        local_local_definitions,
+                        local_body,
+                    )?;
+
+                    visible_locals.push(local_name.clone());
+                    local_functions.push((local_name.clone(), chunk_name, local_captured));
+                }
+            }
+        }
+        let mut local_functions = local_functions.into_iter();
+
+        self.vm.begin_function(name);
+
+        let arity = captured.len() + parameters.len();
+        self.functions
+            .insert(name.to_owned(), (false, arity..=arity));
+
+        self.locals.push(vec![]);
+
+        let current_depth = self.current_depth();
+        for parameter in captured.iter().chain(parameters) {
+            self.locals[current_depth].push(Local {
+                identifier: parameter.clone(),
+                depth: current_depth,
+                may_be_interval: true,
+                metadata: LocalMetadata::default(),
+            });
+        }
+
+        // Local variables are stored in the slots right after the parameters. Local
+        // functions are partial applications of their chunk to the captured values.
+        for local_definition in local_definitions {
+            let (identifier, may_be_interval) = match local_definition {
+                LocalDefinition::Variable(identifier, _, local_expr) => {
+                    self.compile_expression_with_simplify(local_expr)?;
+                    (identifier.clone(), self.may_be_interval(local_expr))
+                }
+                LocalDefinition::Function(_) => {
+                    let (local_name, chunk_name, local_captured) = local_functions.next().unwrap();
+                    for identifier in &local_captured {
+                        let position = self.local_position(identifier).unwrap();
+                        let position =
+                            u16::try_from(position).map_err(|_| RuntimeError::TooManyLocals)?;
+                        self.vm.add_op1(Op::GetLocal, position);
+                    }
+                    self.load_function_reference(&chunk_name);
+                    if !local_captured.is_empty() {
+                        self.vm
+                            .add_op1(Op::PartialApply, Self::num_args(local_captured.len())?);
+                    }
+                    (local_name, false)
+                }
+            };
+            self.locals[current_depth].push(Local {
+                identifier,
+                depth: current_depth,
+                may_be_interval,
+                metadata: LocalMetadata::default(),
+            });
+        }
+
+        self.compile_expression_with_simplify(body)?;
+        self.vm.add_op(Op::Return);
+
+        self.vm.end_function();
+
+        self.inline_templates.remove(name);
+        if captured.is_empty() && local_definitions.is_empty() {
+            if let Some(template) = self.compile_inline_template(parameters, body)? {
+                self.inline_templates.insert(name.to_owned(), template);
+            }
+        }
+
+        self.locals.pop();
+
+        Ok(())
+    }
+
     pub fn get_defining_unit(&self, unit_name: &str) -> Option<&Unit> {
         self.unit_name_to_constant_index
             .get(unit_name)
@@ -561,6 +1184,56 @@ impl BytecodeInterpreter {
     pub fn lookup_global(&self, name: &str) -> Option<&Local> {
         self.locals[0].iter().find(|l| l.identifier == name)
     }
+
+    /// Extract the compiled code of a (non-foreign) function, see [Vm::export_function]
+    pub(crate) fn export_function(
+        &self,
+        name: &str,
+    ) -> std::result::Result<RelocatableChunk, CompiledFunctionError> {
+        if !matches!(self.functions.get(name), Some((false, _))) {
+            return Err(CompiledFunctionError::UnknownFunction(name.into()));
+        }
+
+        self.vm
+            .export_function(name, |idx| self.locals[0][idx as usize].identifier.clone())
+            .ok_or_else(|| CompiledFunctionError::UsesStructs(name.into()))
+    }
+
+    /// Add a global variable whose value is computed by `compute` when it is accessed
+    pub(crate) fn add_lazy_global(
+        &mut self,
+        name: &str,
+        evaluation: LazyEvaluation,
+        compute: Arc<LazyGlobalFunction>,
+    ) {
+        self.vm.add_lazy_global(name, evaluation, compute);
+    }
+
+    /// Add the code of a function that has been compiled in a different context, see
+    /// [Vm::import_function]
+    pub(crate) fn import_function(
+        &mut self,
+        chunk: &RelocatableChunk,
+        arity: usize,
+        code_source_id: usize,
+    ) -> std::result::Result<(), CompiledFunctionError> {
+        let globals = &self.locals[0];
+        self.vm
+            .import_function(chunk, code_source_id, |name| {
+                let position = globals.iter().rposition(|l| l.identifier == name)?;
+                Some(position as u16)
+            })
+            .map_err(|dependency| CompiledFunctionError::MissingDependency {
+                function: chunk.name().into(),
+                dependency,
+            })?;
+
+        self.functions
+            .insert(chunk.name().into(), (false, arity..=arity));
+        self.inline_templates.remove(chunk.name());
+
+        Ok(())
+    }
 }
 
 impl Interpreter for BytecodeInterpreter {
@@ -570,6 +1243,11 @@ impl Interpreter for BytecodeInterpreter {
             locals: vec![vec![]],
             unit_name_to_constant_index: HashMap::new(),
             functions: HashMap::new(),
+            inline_templates: HashMap::new(),
+            declared_units: HashMap::new(),
+            inline_functions: true,
+            exchange_rates: None,
+            exchange_rate_units: vec![],
         }
     }
 
@@ -583,10 +1261,160 @@ impl Interpreter for BytecodeInterpreter {
             self.compile_statement(statement, dimension_registry)?;
         }
 
-        self.run(settings)
+        let result = self.run(settings)?;
+
+        // Only expression statements produce a value. If the program ends with a
+        // definition or a procedure call like `print(…)`, there is no result, even
+        // if one of the earlier statements was an expression.
+        if statements
+            .last()
+            .and_then(Statement::as_expression)
+            .is_some()
+        {
+            Ok(result)
+        } else {
+            Ok(InterpreterResult::Continue)
+        }
     }
 
     fn get_unit_registry(&self) -> &UnitRegistry {
         &self.vm.unit_registry
     }
 }
+
+fn binary_operator_op(operator: &BinaryOperator) -> Op {
+    match operator {
+        BinaryOperator::Add => Op::Add,
+        BinaryOperator::Sub => Op::Subtract,
+        BinaryOperator::Mul => Op::Multiply,
+        BinaryOperator::Div => Op::Divide,
+        BinaryOperator::Mod => Op::Modulo,
+        BinaryOperator::Power => Op::Power,
+        BinaryOperator::ConvertTo => Op::ConvertTo,
+        BinaryOperator::LessThan => Op::LessThan,
+        BinaryOperator::GreaterThan => Op::GreaterThan,
+        BinaryOperator::LessOrEqual => Op::LessOrEqual,
+        BinaryOperator::GreaterOrEqual => Op::GreatorOrEqual,
+        BinaryOperator::Equal => Op::Equal,
+        BinaryOperator::NotEqual => Op::NotEqual,
+        BinaryOperator::LogicalAnd => Op::LogicalAnd,
+        BinaryOperator::LogicalOr => Op::LogicalOr,
+    }
+}
+
+/// If the operation is `x * 1`, `1 * x`, `x / 1`, `x + 0`, `0 + x` or `x - 0`, returns
+/// the operand `x`. Note that `0 * x` is not folded, since the result is a zero with the
+/// dimension of `x`, not `0` itself.
+fn identity_operand<'a>(
+    operator: &BinaryOperator,
+    lhs: &'a Expression,
+    rhs: &'a Expression,
+) -> Option<&'a Expression> {
+    let is_literal = |expr: &Expression, value: f64| matches!(expr, Expression::Scalar(_, n, _) if n.to_f64() == value);
+
+    match operator {
+        BinaryOperator::Mul if is_literal(lhs, 1.0) => Some(rhs),
+        BinaryOperator::Mul | BinaryOperator::Div if is_literal(rhs, 1.0) => Some(lhs),
+        BinaryOperator::Add if is_literal(lhs, 0.0) => Some(rhs),
+        BinaryOperator::Add | BinaryOperator::Sub if is_literal(rhs, 0.0) => Some(lhs),
+        _ => None,
+    }
+}
+
+/// Whether an expression refers to one of the given parameters. String interpolations
+/// are treated as if they did, since they are not visited by [ForAllExpressions].
+fn mentions_parameters(expr: &Expression, parameters: &[String]) -> bool {
+    let mut mentions = false;
+    expr.for_all_expressions(&mut |e| match e {
+        Expression::Identifier(_, identifier, _) if parameters.contains(identifier) => {
+            mentions = true
+        }
+        Expression::String(_, parts)
+            if parts
+                .iter()
+                .any(|p| matches!(p, StringPart::Interpolation { .. })) =>
+        {
+            mentions = true
+        }
+        _ => {}
+    });
+    mentions
+}
+
+/// Whether the parameters of a function are only used in expressions that
+/// [BytecodeInterpreter::compile_inlined_expression] can handle.
+fn is_inlinable(expr: &Expression, parameters: &[String]) -> bool {
+    if !mentions_parameters(expr, parameters) {
+        return true;
+    }
+
+    match expr {
+        Expression::Identifier(..) => true,
+        Expression::UnaryOperator(_, _, expr, _) => is_inlinable(expr, parameters),
+        Expression::BinaryOperator(_, _, lhs, rhs, _) => {
+            is_inlinable(lhs, parameters) && is_inlinable(rhs, parameters)
+        }
+        Expression::FunctionCall(_, _, _, args, _) => {
+            args.iter().all(|arg| is_inlinable(arg, parameters))
+        }
+        Expression::Condition(_, condition, then_expr, else_expr) => {
+            is_inlinable(condition, parameters)
+                && is_inlinable(then_expr, parameters)
+                && is_inlinable(else_expr, parameters)
+        }
+        _ => false,
+    }
+}
+
+/// Evaluates a boolean condition at compile time, if possible. This supports
+/// boolean literals, logical operators, and comparisons of scalar literals.
+fn evaluate_const_condition(expr: &Expression) -> Option<bool> {
+    match expr {
+        Expression::Boolean(_, value) => Some(*value),
+        Expression::UnaryOperator(_, UnaryOperator::LogicalNeg, expr, _) => {
+            Some(!evaluate_const_condition(expr)?)
+        }
+        Expression::BinaryOperator(_, BinaryOperator::LogicalAnd, lhs, rhs, _) => {
+            Some(evaluate_const_condition(lhs)? && evaluate_const_condition(rhs)?)
+        }
+        Expression::BinaryOperator(_, BinaryOperator::LogicalOr, lhs, rhs, _) => {
+            Some(evaluate_const_condition(lhs)? || evaluate_const_condition(rhs)?)
+        }
+        Expression::BinaryOperator(_, op, lhs, rhs, _) => {
+            let lhs = evaluate_const_scalar(lhs)?;
+            let rhs = evaluate_const_scalar(rhs)?;
+            match op {
+                BinaryOperator::LessThan => Some(lhs < rhs),
+                BinaryOperator::GreaterThan => Some(lhs > rhs),
+                BinaryOperator::LessOrEqual => Some(lhs <= rhs),
+                BinaryOperator::GreaterOrEqual => Some(lhs >= rhs),
+                BinaryOperator::Equal => Some(lhs == rhs),
+                BinaryOperator::NotEqual => Some(lhs != rhs),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn evaluate_const_scalar(expr: &Expression) -> Option<f64> {
+    match expr {
+        Expression::Scalar(_, n, _) => Some(n.to_f64()),
+        Expression::UnaryOperator(_, UnaryOperator::Negate, expr, _) => {
+            Some(-evaluate_const_scalar(expr)?)
+        }
+        Expression::BinaryOperator(_, op, lhs, rhs, _) => {
+            let lhs = evaluate_const_scalar(lhs)?;
+            let rhs = evaluate_const_scalar(rhs)?;
+            match op {
+                BinaryOperator::Add => Some(lhs + rhs),
+                BinaryOperator::Sub => Some(lhs - rhs),
+                BinaryOperator::Mul => Some(lhs * rhs),
+                // Leave division by zero to the runtime, which reports a proper error
+                BinaryOperator::Div if rhs != 0.0 => Some(lhs / rhs),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,90 @@
+use thiserror::Error;
+
+use crate::{
+    name_resolution::NameResolutionError,
+    resolver::CodeSource,
+    typechecker::{type_scheme::TypeScheme, FunctionMetadata, FunctionSignature},
+    typed_ast::Type,
+    vm::RelocatableChunk,
+};
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum CompiledFunctionError {
+    #[error("Unknown function '{0}'")]
+    UnknownFunction(String),
+
+    #[error("Function '{0}' makes use of structs, which can not be moved to a different context")]
+    UsesStructs(String),
+
+    #[error(
+        "Function '{function}' refers to '{dependency}', which is not defined in this context"
+    )]
+    MissingDependency {
+        function: String,
+        dependency: String,
+    },
+
+    #[error("Function '{function}' refers to '{dependency}', which has a different type in this context")]
+    IncompatibleDependency {
+        function: String,
+        dependency: String,
+    },
+
+    #[error("{0}")]
+    NameResolutionError(NameResolutionError),
+}
+
+/// A type checked and compiled function that has been extracted from one [`Context`]
+/// with [`Context::export_function`], and which can be added to another [`Context`]
+/// with [`Context::import_function`] without checking and compiling it again.
+///
+/// The function does not refer to any state of the original context. Functions and
+/// global variables that it depends on are looked up by name when it is imported, and
+/// need to have the same types as in the original context.
+///
+/// [`Context`]: crate::Context
+/// [`Context::export_function`]: crate::Context::export_function
+/// [`Context::import_function`]: crate::Context::import_function
+#[derive(Debug, Clone)]
+pub struct CompiledFunction {
+    pub(crate) signature: FunctionSignature,
+    pub(crate) metadata: FunctionMetadata,
+    pub(crate) chunk: RelocatableChunk,
+    /// Types of the functions and global variables that the function refers to
+    pub(crate) dependencies: Vec<(String, TypeScheme)>,
+    /// The code that the function has been defined in, such that its spans can still
+    /// be used for error messages after importing it
+    pub(crate) code_source: CodeSource,
+    pub(crate) code_source_name: String,
+    pub(crate) code: String,
+}
+
+impl CompiledFunction {
+    pub fn name(&self) -> &str {
+        self.chunk.name()
+    }
+}
+
+/// Whether or not a struct type appears anywhere in the given type
+pub(crate) fn contains_struct_type(type_scheme: &TypeScheme) -> bool {
+    fn contains(type_: &Type) -> bool {
+        match type_ {
+            Type::Struct(_) => true,
+            Type::Fn(parameter_types, return_type) => {
+                parameter_types.iter().any(contains) || contains(return_type)
+            }
+            Type::List(element_type) => contains(element_type),
+            Type::TVar(_)
+            | Type::TPar(_)
+            | Type::Dimension(_)
+            | Type::Boolean
+            | Type::String
+            | Type::DateTime => false,
+        }
+    }
+
+    match type_scheme {
+        TypeScheme::Concrete(type_) => contains(type_),
+        TypeScheme::Quantified(_, qualified_type) => contains(&qualified_type.inner),
+    }
+}
@@ -16,10 +16,19 @@ impl ExchangeRatesCache {
         rates.as_ref().and_then(|r| r.get(currency)).cloned()
     }
 
+    /// Parse the exchange rates from the XML format of the European Central Bank and
+    /// replace the cached rates.
     pub fn set_from_xml(xml_content: &str) {
-        EXCHANGE_RATES
-            .set(Mutex::new(parse_exchange_rates(xml_content)))
+        Self::set(parse_exchange_rates(xml_content));
+    }
+
+    /// Replace the cached exchange rates, e.g. with updated rates from a custom source.
+    pub fn set(rates: Option<ExchangeRates>) {
+        let mut cached_rates = EXCHANGE_RATES
+            .get_or_init(|| Mutex::new(None))
+            .lock()
             .unwrap();
+        *cached_rates = rates;
     }
 
     #[cfg(feature = "fetch-exchangerates")]
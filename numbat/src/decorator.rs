@@ -1,4 +1,9 @@
-use crate::{prefix_parser::AcceptsPrefix, unit::CanonicalName};
+use itertools::Itertools;
+
+use crate::markup as m;
+use crate::{
+    markup::Markup, prefix::AcceptsPrefix, pretty_print::escape_numbat_string, unit::CanonicalName,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Decorator {
@@ -8,6 +13,9 @@ pub enum Decorator {
     Url(String),
     Name(String),
     Description(String),
+    Plural(String),
+    Canonical(String),
+    Example(String),
 }
 
 pub fn name_and_aliases<'a>(
@@ -38,6 +46,25 @@ pub fn name_and_aliases<'a>(
 }
 
 pub fn get_canonical_unit_name(unit_name: &str, decorators: &[Decorator]) -> CanonicalName {
+    if let Some(canonical) = canonical(decorators) {
+        let unit_name = unit_name.to_string();
+        let accepts_prefix = name_and_aliases(&unit_name, decorators)
+            .find(|(name, _)| **name == canonical)
+            .map(|(_, accepts_prefix)| accepts_prefix);
+
+        if let Some(accepts_prefix) = accepts_prefix {
+            return CanonicalName {
+                plural: if canonical == unit_name {
+                    plural(decorators)
+                } else {
+                    None
+                },
+                name: canonical,
+                accepts_prefix,
+            };
+        }
+    }
+
     for decorator in decorators {
         if let Decorator::Aliases(aliases) = decorator {
             for (alias, accepts_prefix) in aliases {
@@ -53,6 +80,7 @@ pub fn get_canonical_unit_name(unit_name: &str, decorators: &[Decorator]) -> Can
     CanonicalName {
         name: unit_name.into(),
         accepts_prefix: AcceptsPrefix::only_long(),
+        plural: plural(decorators),
     }
 }
 
@@ -65,6 +93,24 @@ pub fn name(decorators: &[Decorator]) -> Option<String> {
     None
 }
 
+pub fn plural(decorators: &[Decorator]) -> Option<String> {
+    for decorator in decorators {
+        if let Decorator::Plural(plural) = decorator {
+            return Some(plural.clone());
+        }
+    }
+    None
+}
+
+pub fn canonical(decorators: &[Decorator]) -> Option<String> {
+    for decorator in decorators {
+        if let Decorator::Canonical(canonical) = decorator {
+            return Some(canonical.clone());
+        }
+    }
+    None
+}
+
 pub fn url(decorators: &[Decorator]) -> Option<String> {
     for decorator in decorators {
         if let Decorator::Url(url) = decorator {
@@ -89,6 +135,16 @@ pub fn description(decorators: &[Decorator]) -> Option<String> {
     }
 }
 
+pub fn examples(decorators: &[Decorator]) -> Vec<String> {
+    decorators
+        .iter()
+        .filter_map(|decorator| match decorator {
+            Decorator::Example(example) => Some(example.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn contains_aliases_with_prefixes(decorates: &[Decorator]) -> bool {
     for decorator in decorates {
         if let Decorator::Aliases(aliases) = decorator {
@@ -110,3 +166,78 @@ pub fn contains_aliases(decorators: &[Decorator]) -> bool {
 
     false
 }
+
+fn accepts_prefix_markup(accepts_prefix: &Option<AcceptsPrefix>) -> Markup {
+    if let Some(accepts_prefix) = accepts_prefix {
+        m::operator(":")
+            + m::space()
+            + match accepts_prefix {
+                AcceptsPrefix {
+                    short: true,
+                    long: true,
+                } => m::keyword("both"),
+                AcceptsPrefix {
+                    short: true,
+                    long: false,
+                } => m::keyword("short"),
+                AcceptsPrefix {
+                    short: false,
+                    long: true,
+                } => m::keyword("long"),
+                AcceptsPrefix {
+                    short: false,
+                    long: false,
+                } => m::keyword("none"),
+            }
+    } else {
+        m::empty()
+    }
+}
+
+fn string_argument_markup(argument: &str) -> Markup {
+    m::operator("(")
+        + m::string(format!("\"{}\"", escape_numbat_string(argument)))
+        + m::operator(")")
+}
+
+pub(crate) fn decorator_markup(decorators: &[Decorator]) -> Markup {
+    let mut markup_decorators = m::empty();
+    for decorator in decorators {
+        markup_decorators = markup_decorators
+            + match decorator {
+                Decorator::MetricPrefixes => m::decorator("@metric_prefixes"),
+                Decorator::BinaryPrefixes => m::decorator("@binary_prefixes"),
+                Decorator::Aliases(names) => {
+                    m::decorator("@aliases")
+                        + m::operator("(")
+                        + Itertools::intersperse(
+                            names.iter().map(|(name, accepts_prefix)| {
+                                m::unit(name) + accepts_prefix_markup(accepts_prefix)
+                            }),
+                            m::operator(", "),
+                        )
+                        .sum()
+                        + m::operator(")")
+                }
+                Decorator::Url(url) => m::decorator("@url") + string_argument_markup(url),
+                Decorator::Name(name) => m::decorator("@name") + string_argument_markup(name),
+                Decorator::Description(description) => {
+                    m::decorator("@description") + string_argument_markup(description)
+                }
+                Decorator::Plural(plural) => {
+                    m::decorator("@plural") + string_argument_markup(plural)
+                }
+                Decorator::Example(example) => {
+                    m::decorator("@example") + string_argument_markup(example)
+                }
+                Decorator::Canonical(canonical) => {
+                    m::decorator("@canonical")
+                        + m::operator("(")
+                        + m::unit(canonical)
+                        + m::operator(")")
+                }
+            }
+            + m::nl();
+    }
+    markup_decorators
+}
@@ -0,0 +1,115 @@
+use crate::ast::TypeParameterBound;
+use crate::dimension::DimensionRegistry;
+use crate::pretty_print::PrettyPrint;
+use crate::registry::BaseRepresentation;
+use crate::typed_ast::Statement;
+
+/// A function definition, with all types in readable form (e.g. `Length` instead of `Meter`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSummary {
+    pub name: String,
+    /// Type parameter names along with their bound (`Dim`), if any
+    pub type_parameters: Vec<(String, Option<String>)>,
+    /// Parameter names along with their types
+    pub parameters: Vec<(String, String)>,
+    pub return_type: String,
+}
+
+/// A unit definition, along with the dimension of the unit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitSummary {
+    pub name: String,
+    pub dimension: String,
+}
+
+/// A dimension definition, along with its representation in terms of base dimensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DimensionSummary {
+    pub name: String,
+    pub base_representation: BaseRepresentation,
+}
+
+/// The signatures of all functions, units and dimensions that are defined in a
+/// piece of code, in the order of their definition (see [`crate::Context::type_check_file`])
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefinitionSummary {
+    pub functions: Vec<FunctionSummary>,
+    pub units: Vec<UnitSummary>,
+    pub dimensions: Vec<DimensionSummary>,
+}
+
+impl DefinitionSummary {
+    pub(crate) fn from_statements(statements: &[Statement], registry: &DimensionRegistry) -> Self {
+        let mut summary = DefinitionSummary::default();
+
+        for statement in statements {
+            match statement {
+                Statement::DefineFunction(
+                    name,
+                    _,
+                    type_parameters,
+                    parameters,
+                    _,
+                    _,
+                    _,
+                    _,
+                    readable_return_type,
+                ) => summary.functions.push(FunctionSummary {
+                    name: name.clone(),
+                    type_parameters: type_parameters
+                        .iter()
+                        .map(|(name, bound)| {
+                            let bound = bound.as_ref().map(|bound| match bound {
+                                TypeParameterBound::Dim => "Dim".to_string(),
+                                TypeParameterBound::Equal(dexpr) => {
+                                    dexpr.pretty_print().to_string()
+                                }
+                            });
+                            (name.clone(), bound)
+                        })
+                        .collect(),
+                    parameters: parameters
+                        .iter()
+                        .map(|(_, name, _, readable_type)| {
+                            (name.clone(), readable_type.to_string())
+                        })
+                        .collect(),
+                    return_type: readable_return_type.to_string(),
+                }),
+                Statement::DefineBaseUnit(name, _, annotation, type_) => {
+                    let dimension = match annotation {
+                        Some(annotation) => annotation.pretty_print(),
+                        None => type_.to_readable_type(registry),
+                    };
+                    summary.units.push(UnitSummary {
+                        name: name.clone(),
+                        dimension: dimension.to_string(),
+                    });
+                }
+                Statement::DeclareUnit(name, _, annotation, _) => {
+                    summary.units.push(UnitSummary {
+                        name: name.clone(),
+                        dimension: annotation.pretty_print().to_string(),
+                    });
+                }
+                Statement::DefineDerivedUnit(name, _, _, _, _, readable_type) => {
+                    summary.units.push(UnitSummary {
+                        name: name.clone(),
+                        dimension: readable_type.to_string(),
+                    });
+                }
+                Statement::DefineDimension(name, _) => {
+                    summary.dimensions.push(DimensionSummary {
+                        name: name.clone(),
+                        base_representation: registry
+                            .get_base_representation_for_name(name)
+                            .expect("dimension has been added to the registry"),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        summary
+    }
+}
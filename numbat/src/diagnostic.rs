@@ -4,9 +4,10 @@ use crate::{
     interpreter::RuntimeError,
     parser::ParseError,
     pretty_print::PrettyPrint,
+    registry::BaseRepresentation,
     resolver::ResolverError,
     typechecker::{IncompatibleDimensionsError, TypeCheckError},
-    NameResolutionError,
+    NameResolutionError, Warning,
 };
 
 pub type Diagnostic = codespan_reporting::diagnostic::Diagnostic<usize>;
@@ -63,11 +64,17 @@ impl ErrorDiagnostic for NameResolutionError {
                         .diagnostic_label(LabelStyle::Primary)
                         .with_message("identifier is already in use"),
                 ])],
-            NameResolutionError::ReservedIdentifier(span) => vec![Diagnostic::error()
+            NameResolutionError::ReservedIdentifier(name, span) => vec![Diagnostic::error()
                 .with_message("reserved identifier may not be used")
                 .with_labels(vec![span
                     .diagnostic_label(LabelStyle::Primary)
-                    .with_message("reserved identifier")])],
+                    .with_message(format!("'{name}' is a reserved identifier"))])],
+            NameResolutionError::DecoratorsOnDeclaredUnit(_, span) => vec![Diagnostic::error()
+                .with_message("while resolving names")
+                .with_labels(vec![span
+                    .diagnostic_label(LabelStyle::Primary)
+                    .with_message("value definition of a forward-declared unit")])
+                .with_notes(vec![self.to_string()])],
         }
     }
 }
@@ -100,16 +107,17 @@ impl ErrorDiagnostic for TypeCheckError {
                 expected_dimensions,
                 ..
             }) => {
-                let expected_type = if expected_dimensions.is_empty() {
-                    format!("{expected_type}")
-                } else {
-                    expected_dimensions.join(" or ")
-                };
-                let actual_type = if actual_dimensions.is_empty() {
-                    format!("{actual_type}")
-                } else {
-                    actual_dimensions.join(" or ")
+                // Dimensionless types are always shown as 'Scalar', instead of listing
+                // all dimensionless aliases like 'Angle' or 'SolidAngle'.
+                let describe = |type_: &BaseRepresentation, names: &[String]| {
+                    if names.is_empty() || type_ == &BaseRepresentation::unity() {
+                        format!("{type_}")
+                    } else {
+                        names.join(" or ")
+                    }
                 };
+                let expected_type = describe(expected_type, expected_dimensions);
+                let actual_type = describe(actual_type, actual_dimensions);
 
                 let labels = vec![
                     span_expected
@@ -329,16 +337,36 @@ impl ErrorDiagnostic for TypeCheckError {
                     format!("Use 'unit {unit_name}' for ad-hoc units."),
                     format!("Use 'unit {unit_name}: Scalar = …' for derived units."),
                 ]),
+            TypeCheckError::AssignmentToImmutableIdentifier(span, definition_span, _) => {
+                let mut labels = vec![span
+                    .diagnostic_label(LabelStyle::Primary)
+                    .with_message("assignment to immutable identifier")];
+                if let Some(definition_span) = definition_span {
+                    labels.push(
+                        definition_span
+                            .diagnostic_label(LabelStyle::Secondary)
+                            .with_message("defined here"),
+                    );
+                }
+                d.with_labels(labels)
+            }
             TypeCheckError::ForeignFunctionNeedsTypeAnnotations(span, _)
+            | TypeCheckError::AssignmentToUnknownVariable(span, _)
             | TypeCheckError::UnknownForeignFunction(span, _)
+            | TypeCheckError::ForeignFunctionNotAvailable(span, _)
+            | TypeCheckError::ForeignFunctionSignatureMismatch { span, .. }
             | TypeCheckError::NonRationalExponent(span)
             | TypeCheckError::OverflowInConstExpr(span)
+            | TypeCheckError::ExponentTooLarge(span, _)
             | TypeCheckError::ExpectedDimensionType(span, _)
             | TypeCheckError::ExpectedBool(span)
             | TypeCheckError::NoFunctionReferenceToGenericFunction(span)
             | TypeCheckError::OnlyFunctionsAndReferencesCanBeCalled(span)
             | TypeCheckError::DerivedUnitDefinitionMustNotBeGeneric(span)
-            | TypeCheckError::MultipleTypedHoles(span) => d.with_labels(vec![span
+            | TypeCheckError::MultipleTypedHoles(span)
+            | TypeCheckError::UnknownOperator(span, _)
+            | TypeCheckError::OperatorFunctionNotBinary(span, _, _)
+            | TypeCheckError::RecursiveLocalFunction(span, _) => d.with_labels(vec![span
                 .diagnostic_label(LabelStyle::Primary)
                 .with_message(inner_error)]),
             TypeCheckError::MissingDimension(span, dim) => d
@@ -470,7 +498,16 @@ impl ErrorDiagnostic for TypeCheckError {
                 .with_notes(vec![
                     "Consider adding `: Dim` after the type parameter".to_owned()
                 ]),
-            TypeCheckError::ExponentiationNeedsTypeAnnotation(span) => d.with_labels(vec![span
+            TypeCheckError::UnsatisfiedConstraint(span, ..) => d
+                .with_labels(vec![span
+                    .diagnostic_label(LabelStyle::Primary)
+                    .with_message(inner_error)])
+                .with_notes(vec![
+                    "The constraint stems from a `where` clause in the function definition"
+                        .to_owned(),
+                ]),
+            TypeCheckError::ExponentiationNeedsTypeAnnotation(span)
+            | TypeCheckError::DecimalExponentInStrictMode(span) => d.with_labels(vec![span
                 .diagnostic_label(LabelStyle::Primary)
                 .with_message(inner_error)]),
             TypeCheckError::TypedHoleInStatement(span, type_, statement, matches) => {
@@ -518,7 +555,8 @@ impl ErrorDiagnostic for RuntimeError {
                     ])
                     .with_notes(vec![inner])]
             }
-            RuntimeError::AssertEq3Failed(span_lhs, lhs, span_rhs, rhs, _) => {
+            RuntimeError::AssertEq3Failed(span_lhs, lhs, span_rhs, rhs, _)
+            | RuntimeError::AssertEqRelativeFailed(span_lhs, lhs, span_rhs, rhs, _) => {
                 vec![Diagnostic::error()
                     .with_message("Assertion failed")
                     .with_labels(vec![
@@ -537,3 +575,26 @@ impl ErrorDiagnostic for RuntimeError {
         }
     }
 }
+
+impl ErrorDiagnostic for Warning {
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Warning::RedefinitionChangesType {
+                span,
+                original_span,
+                original_type,
+                new_type,
+                ..
+            } => vec![Diagnostic::warning()
+                .with_message("type of identifier changes in redefinition")
+                .with_labels(vec![
+                    original_span
+                        .diagnostic_label(LabelStyle::Secondary)
+                        .with_message(format!("Previously defined with type {original_type}")),
+                    span.diagnostic_label(LabelStyle::Primary)
+                        .with_message(format!("Redefined with type {new_type}")),
+                ])
+                .with_notes(vec![self.to_string()])],
+        }
+    }
+}
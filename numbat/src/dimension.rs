@@ -4,6 +4,8 @@ use crate::registry::{BaseRepresentation, Registry, Result};
 use crate::span::Span;
 use crate::BaseRepresentationFactor;
 
+use itertools::Itertools;
+
 #[derive(Default, Clone)]
 pub struct DimensionRegistry {
     registry: Registry<()>,
@@ -92,6 +94,25 @@ impl DimensionRegistry {
     pub fn contains(&self, dimension_name: &str) -> bool {
         self.registry.contains(dimension_name)
     }
+
+    /// All known dimensions as `(name, base representation, is_base)`. Base dimensions
+    /// come first (in the order of their definition), followed by the derived ones
+    /// (sorted by name).
+    pub fn iter_dimensions(&self) -> impl Iterator<Item = (String, BaseRepresentation, bool)> + '_ {
+        let base_dimensions = self.registry.iter_base_entries().map(|name| (name, true));
+        let derived_dimensions = self
+            .registry
+            .iter_derived_entries()
+            .sorted_unstable()
+            .map(|name| (name, false));
+
+        base_dimensions
+            .chain(derived_dimensions)
+            .map(|(name, is_base)| {
+                let base_representation = self.get_base_representation_for_name(&name).unwrap();
+                (name, base_representation, is_base)
+            })
+    }
 }
 
 #[test]
@@ -118,6 +139,22 @@ fn basic() {
         .add_derived_dimension("Energy", &parse_dexpr("Momentum^2 / Mass"))
         .unwrap();
 
+    assert_eq!(
+        registry
+            .iter_dimensions()
+            .map(|(name, _, is_base)| (name, is_base))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Length".into(), true),
+            ("Time".into(), true),
+            ("Mass".into(), true),
+            ("Acceleration".into(), false),
+            ("Energy".into(), false),
+            ("Momentum".into(), false),
+            ("Velocity".into(), false),
+        ]
+    );
+
     assert_eq!(
         registry.get_base_representation(&parse_dexpr("Length")),
         Ok(BaseRepresentation::from_factor(BaseRepresentationFactor(
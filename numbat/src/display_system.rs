@@ -0,0 +1,58 @@
+/// A system of units in which results are displayed, e.g. the imperial system.
+///
+/// A display system assigns a preferred unit to a number of dimensions. If a display
+/// system is active, the result of an expression is converted as follows:
+///
+/// - If the system has an entry for the dimension of the result, its unit is used.
+///   Different dimensions may share the same base representation (e.g. `Energy` and
+///   `Torque`). In this case, the entry that was added first takes precedence.
+/// - Otherwise, if the system has an entry for each base dimension that appears in
+///   the result, the result is expressed in terms of those units (e.g. `foot²` for an
+///   area in the imperial system).
+/// - Otherwise, the result is left as it is.
+///
+/// Results of explicit conversions (`… -> unit`) and scalars are never converted.
+/// Entries that refer to unknown dimensions or units are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisplaySystem {
+    units: Vec<(String, String)>,
+}
+
+impl DisplaySystem {
+    /// An empty display system, to which units can be added with [`DisplaySystem::with_unit`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `unit` (e.g. `ft` or `km`) to display quantities of the given `dimension`.
+    pub fn with_unit(mut self, dimension: &str, unit: &str) -> Self {
+        self.units.push((dimension.to_string(), unit.to_string()));
+        self
+    }
+
+    /// Metric units, based on the units from the prelude.
+    pub fn metric() -> Self {
+        Self::new()
+            .with_unit("Length", "m")
+            .with_unit("Mass", "kg")
+            .with_unit("Time", "s")
+            .with_unit("Volume", "L")
+    }
+
+    /// Imperial units, based on the units from the prelude.
+    pub fn imperial() -> Self {
+        Self::new()
+            .with_unit("Length", "ft")
+            .with_unit("Mass", "lb")
+            .with_unit("Time", "s")
+            .with_unit("Volume", "gal")
+            .with_unit("Velocity", "mph")
+            .with_unit("Force", "lbf")
+            .with_unit("Pressure", "psi")
+    }
+
+    /// The `(dimension, unit)` entries of this system, in order of precedence.
+    pub fn units(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.units.iter().map(|(d, u)| (d.as_str(), u.as_str()))
+    }
+}
@@ -4,11 +4,15 @@ use super::Result;
 use crate::currency::ExchangeRatesCache;
 use crate::quantity::Quantity;
 use crate::value::Value;
+use crate::vm::ExecutionContext;
 
-pub fn exchange_rate(mut args: Args) -> Result<Value> {
-    let rate = string_arg!(args);
+pub fn exchange_rate(ctx: &mut ExecutionContext, mut args: Args) -> Result<Value> {
+    let currency = string_arg!(args);
 
-    let exchange_rates = ExchangeRatesCache::new();
+    let rate = match ctx.exchange_rates {
+        Some(exchange_rates) => exchange_rates.get(&currency).cloned(),
+        None => ExchangeRatesCache::new().get_rate(&currency),
+    };
 
-    return_scalar!(exchange_rates.get_rate(&rate).unwrap_or(f64::NAN))
+    return_scalar!(rate.unwrap_or(f64::NAN))
 }
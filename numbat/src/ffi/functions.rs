@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::sync::OnceLock;
 
 use super::{macros::*, Args};
-use crate::{quantity::Quantity, value::Value, RuntimeError};
+use crate::{interval::Interval, quantity::Quantity, unit::Unit, value::Value, RuntimeError};
 
 use super::{Callable, ForeignFunction, Result};
 
@@ -11,6 +11,7 @@ static FFI_FUNCTIONS: OnceLock<HashMap<String, ForeignFunction>> = OnceLock::new
 pub(crate) fn functions() -> &'static HashMap<String, ForeignFunction> {
     use super::currency::*;
     use super::datetime::*;
+    use super::io::*;
     use super::lists::*;
     use super::lookup::*;
     use super::math::*;
@@ -35,18 +36,50 @@ pub(crate) fn functions() -> &'static HashMap<String, ForeignFunction> {
             };
         }
 
+        macro_rules! insert_function_with_context {
+            ($callable:expr, $arity:expr) => {
+                m.insert(
+                    stringify!($callable).to_string(),
+                    ForeignFunction {
+                        name: stringify!($callable).to_string(),
+                        arity: $arity,
+                        callable: Callable::FunctionWithContext($callable),
+                    },
+                );
+            };
+        }
+
+        macro_rules! insert_function_with_units {
+            ($callable:expr, $arity:expr) => {
+                m.insert(
+                    stringify!($callable).to_string(),
+                    ForeignFunction {
+                        name: stringify!($callable).to_string(),
+                        arity: $arity,
+                        callable: Callable::FunctionWithUnits($callable),
+                    },
+                );
+            };
+        }
+
         // Core
         insert_function!(error, 1..=1);
         insert_function!(unit_of, 1..=1);
+        insert_function_with_units!(simplify, 1..=1);
+        insert_function!(raw, 1..=1);
+        insert_function!(interval, 2..=2);
 
         // Math
         insert_function!("mod", mod_, 2..=2);
 
         insert_function!(abs, 1..=1);
-        insert_function!(round, 1..=1);
+        insert_function!(signum, 1..=1);
+        insert_function_with_context!(round, 1..=1);
         insert_function!(floor, 1..=1);
         insert_function!(ceil, 1..=1);
         insert_function!(trunc, 1..=1);
+        insert_function_with_context!(round_to, 2..=2);
+        insert_function_with_context!(signif, 2..=2);
 
         insert_function!(sin, 1..=1);
         insert_function!(cos, 1..=1);
@@ -55,6 +88,7 @@ pub(crate) fn functions() -> &'static HashMap<String, ForeignFunction> {
         insert_function!(acos, 1..=1);
         insert_function!(atan, 1..=1);
         insert_function!(atan2, 2..=2);
+        insert_function!(hypot, 2..=2);
         insert_function!(sinh, 1..=1);
         insert_function!(cosh, 1..=1);
         insert_function!(tanh, 1..=1);
@@ -65,6 +99,7 @@ pub(crate) fn functions() -> &'static HashMap<String, ForeignFunction> {
         insert_function!(ln, 1..=1);
         insert_function!(log10, 1..=1);
         insert_function!(log2, 1..=1);
+        insert_function!(log_base, 2..=2);
         insert_function!(gamma, 1..=1);
 
         insert_function!(is_nan, 1..=1);
@@ -96,11 +131,15 @@ pub(crate) fn functions() -> &'static HashMap<String, ForeignFunction> {
         insert_function!(from_unixtime, 1..=1);
 
         // Currency
-        insert_function!(exchange_rate, 1..=1);
+        insert_function_with_context!(exchange_rate, 1..=1);
 
         // Database lookup
         insert_function!(_get_chemical_element_data_raw, 1..=1);
 
+        // Input
+        insert_function_with_context!(input, 1..=1);
+        insert_function_with_context!(read_number, 1..=1);
+
         m
     })
 }
@@ -115,3 +154,38 @@ fn unit_of(mut args: Args) -> Result<Value> {
     let input_unit = quantity_arg!(args).unit().clone();
     return_quantity!(1.0, input_unit)
 }
+
+fn simplify(units: &[&Unit], mut args: Args) -> Result<Value> {
+    let simplified = quantity_arg!(args).full_simplify();
+
+    // Use a named unit for products like `N·m`, if there is exactly one unit that is
+    // equal to it (`J`). Units that only have the same dimension (`erg`, `kWh`) or
+    // ambiguous names (`Gy` and `Sv` for `m²/s²`) are not considered.
+    if simplified.unit().iter().count() > 1 {
+        let mut equal_units = units.iter().filter(|unit| {
+            Quantity::from_unit(simplified.unit().clone())
+                .convert_to(unit)
+                .map(|q| (q.unsafe_value().to_f64() - 1.0).abs() < 1e-12)
+                .unwrap_or(false)
+        });
+
+        if let (Some(unit), None) = (equal_units.next(), equal_units.next()) {
+            return Ok(Value::Quantity(Quantity::new(
+                *simplified.unsafe_value(),
+                (*unit).clone(),
+            )));
+        }
+    }
+
+    Ok(Value::Quantity(simplified))
+}
+
+fn raw(mut args: Args) -> Result<Value> {
+    Ok(arg!(args))
+}
+
+fn interval(mut args: Args) -> Result<Value> {
+    let lower = quantity_arg!(args);
+    let upper = quantity_arg!(args);
+    Ok(Value::Interval(Interval::new(lower, upper)?))
+}
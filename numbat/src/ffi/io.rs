@@ -0,0 +1,49 @@
+use super::macros::*;
+use super::Args;
+use super::Result;
+use crate::quantity::Quantity;
+use crate::value::Value;
+use crate::vm::ExecutionContext;
+use crate::RuntimeError;
+
+fn read_line(ctx: &mut ExecutionContext, prompt: &str) -> Result<String> {
+    let input_fn = ctx
+        .input_fn
+        .as_mut()
+        .ok_or(RuntimeError::NoInputAvailable)?;
+
+    let line = input_fn(prompt).ok_or(RuntimeError::NoInputAvailable)?;
+
+    Ok(line.trim_end_matches(['\n', '\r']).to_string())
+}
+
+pub fn input(ctx: &mut ExecutionContext, mut args: Args) -> Result<Value> {
+    let prompt = string_arg!(args);
+
+    return_string!(read_line(ctx, &prompt)?)
+}
+
+pub fn read_number(ctx: &mut ExecutionContext, mut args: Args) -> Result<Value> {
+    let prompt = string_arg!(args);
+    let line = read_line(ctx, &prompt)?;
+
+    let input = line.trim().replace('_', "");
+
+    match input.parse::<f64>() {
+        Ok(number) => return_scalar!(number),
+        Err(_) if starts_with_number(&input) => {
+            Err(RuntimeError::UnitInNumberInput(line.trim().to_string()))
+        }
+        Err(_) => Err(RuntimeError::InvalidNumberInput(line.trim().to_string())),
+    }
+}
+
+/// Whether the input starts with a number that is followed by something else,
+/// most likely a unit (e.g. `3 m` or `2.5km`).
+fn starts_with_number(input: &str) -> bool {
+    input.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '-' || c == '+')
+        && input
+            .char_indices()
+            .skip(1)
+            .any(|(i, _)| input[..i].trim_end().parse::<f64>().is_ok())
+}
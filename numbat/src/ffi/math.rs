@@ -4,15 +4,21 @@ use super::Result;
 
 use crate::quantity::Quantity;
 use crate::value::Value;
+use crate::vm::ExecutionContext;
+use crate::RuntimeError;
 
 pub fn mod_(mut args: Args) -> Result<Value> {
     let x = quantity_arg!(args);
     let y = quantity_arg!(args);
 
-    let x_value = x.unsafe_value().to_f64();
-    let y_value = y.convert_to(x.unit()).unwrap().unsafe_value().to_f64();
-
-    return_quantity!(x_value.rem_euclid(y_value), x.unit().clone())
+    // The arguments are not simplified (see `UNSIMPLIFIED_ARGUMENT_FUNCTIONS` in the
+    // bytecode interpreter), such that the remainder is computed in the unit of `x`.
+    // Only the result is simplified.
+    let remainder = x
+        .rem_euclid(&y)
+        .map_err(RuntimeError::QuantityError)?
+        .ok_or(RuntimeError::DivisionByZero)?;
+    Ok(Value::Quantity(remainder.full_simplify()))
 }
 
 // A simple math function with signature 'Dim D. Fn[(D) -> D]', which only operates on the value of the quantity
@@ -37,12 +43,59 @@ macro_rules! simple_scalar_math_function {
     };
 }
 
-simple_polymorphic_math_function!(abs, abs);
-simple_polymorphic_math_function!(round, round);
+pub fn abs(mut args: Args) -> Result<Value> {
+    let arg = quantity_arg!(args);
+    Ok(Value::Quantity(arg.abs()))
+}
+
+pub fn signum(mut args: Args) -> Result<Value> {
+    let arg = quantity_arg!(args);
+    Ok(Value::Quantity(arg.signum()))
+}
+
+pub fn round(ctx: &mut ExecutionContext, mut args: Args) -> Result<Value> {
+    let arg = quantity_arg!(args);
+
+    let value = arg.unsafe_value().to_f64();
+    return_quantity!(ctx.rounding_mode.round(value), arg.unit().clone())
+}
+
 simple_polymorphic_math_function!(floor, floor);
 simple_polymorphic_math_function!(ceil, ceil);
 simple_polymorphic_math_function!(trunc, trunc);
 
+pub fn round_to(ctx: &mut ExecutionContext, mut args: Args) -> Result<Value> {
+    let x = quantity_arg!(args);
+    let step = quantity_arg!(args);
+
+    let x_value = x.unsafe_value().to_f64();
+    let step_value = step.convert_to(x.unit()).unwrap().unsafe_value().to_f64();
+
+    if step_value == 0.0 {
+        return Err(RuntimeError::ZeroRoundingStep);
+    }
+
+    return_quantity!(
+        ctx.rounding_mode.round(x_value / step_value) * step_value,
+        x.unit().clone()
+    )
+}
+
+pub fn signif(ctx: &mut ExecutionContext, mut args: Args) -> Result<Value> {
+    let x = quantity_arg!(args);
+    let n = scalar_arg!(args).to_f64();
+
+    if n < 1.0 || n.fract() != 0.0 {
+        return Err(RuntimeError::InvalidNumberOfSignificantFigures);
+    }
+
+    let result = ctx
+        .rounding_mode
+        .round_to_significant_digits(x.unsafe_value().to_f64(), n as i32);
+
+    return_quantity!(result, x.unit().clone())
+}
+
 simple_scalar_math_function!(sin, sin);
 simple_scalar_math_function!(cos, cos);
 simple_scalar_math_function!(tan, tan);
@@ -60,6 +113,17 @@ pub fn atan2(mut args: Args) -> Result<Value> {
     return_scalar!(y_value.atan2(x_value))
 }
 
+pub fn hypot(mut args: Args) -> Result<Value> {
+    let a = quantity_arg!(args);
+    let b = quantity_arg!(args);
+
+    let a_value = a.unsafe_value().to_f64();
+    let b_value = b.convert_to(a.unit()).unwrap().unsafe_value().to_f64();
+
+    // f64::hypot avoids the overflow (and underflow) of the intermediate squares
+    return_quantity!(a_value.hypot(b_value), a.unit().clone())
+}
+
 simple_scalar_math_function!(sinh, sinh);
 simple_scalar_math_function!(cosh, cosh);
 simple_scalar_math_function!(tanh, tanh);
@@ -71,6 +135,20 @@ simple_scalar_math_function!(ln, ln);
 simple_scalar_math_function!(log10, log10);
 simple_scalar_math_function!(log2, log2);
 
+pub fn log_base(mut args: Args) -> Result<Value> {
+    let base = scalar_arg!(args).to_f64();
+    let x = scalar_arg!(args).to_f64();
+
+    if base <= 0.0 || base == 1.0 {
+        return Err(RuntimeError::InvalidLogarithmBase);
+    }
+    if x <= 0.0 {
+        return Err(RuntimeError::LogarithmOfNonPositiveNumber);
+    }
+
+    return_scalar!(x.log(base))
+}
+
 pub fn gamma(mut args: Args) -> Result<Value> {
     let input = scalar_arg!(args).to_f64();
 
@@ -1,6 +1,7 @@
 mod currency;
 mod datetime;
 mod functions;
+mod io;
 mod lists;
 mod lookup;
 mod macros;
@@ -8,10 +9,13 @@ mod math;
 mod procedures;
 mod strings;
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+
+use itertools::Itertools;
 
 use crate::interpreter::RuntimeError;
 use crate::span::Span;
+use crate::unit::Unit;
 use crate::value::Value;
 use crate::vm::ExecutionContext;
 
@@ -19,6 +23,20 @@ type ControlFlow = std::ops::ControlFlow<RuntimeError>;
 
 pub(crate) type ArityRange = std::ops::RangeInclusive<usize>;
 
+/// A readable description of the number of arguments in an arity range, e.g.
+/// "2 arguments", "at least 1 argument", or "2..3 arguments".
+pub(crate) fn describe_arity(arity: &ArityRange) -> String {
+    let noun = |n: usize| if n == 1 { "argument" } else { "arguments" };
+
+    if arity.start() == arity.end() {
+        format!("{} {}", arity.start(), noun(*arity.start()))
+    } else if *arity.end() == usize::MAX {
+        format!("at least {} {}", arity.start(), noun(*arity.start()))
+    } else {
+        format!("{}..{} arguments", arity.start(), arity.end())
+    }
+}
+
 type Result<T> = std::result::Result<T, RuntimeError>;
 
 pub(crate) type Args = VecDeque<Value>;
@@ -27,6 +45,10 @@ type BoxedFunction = Box<dyn Fn(Args) -> Result<Value> + Send + Sync>;
 
 pub(crate) enum Callable {
     Function(BoxedFunction),
+    /// A function that needs access to the execution context, e.g. to read input.
+    FunctionWithContext(fn(&mut ExecutionContext, Args) -> Result<Value>),
+    /// A function that needs to know about all units that have been defined so far.
+    FunctionWithUnits(fn(&[&Unit], Args) -> Result<Value>),
     Procedure(fn(&mut ExecutionContext, Args, Vec<Span>) -> ControlFlow),
 }
 
@@ -36,5 +58,36 @@ pub(crate) struct ForeignFunction {
     pub(crate) callable: Callable,
 }
 
+/// Restricts which foreign functions and procedures (like `input` or `print`) are
+/// available, e.g. when running untrusted code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ForeignFunctionFilter {
+    /// All foreign functions and procedures are available
+    #[default]
+    AllowAll,
+    /// Only the given foreign functions and procedures are available
+    AllowOnly(HashSet<String>),
+    /// All foreign functions and procedures except for the given ones are available
+    Deny(HashSet<String>),
+}
+
+impl ForeignFunctionFilter {
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            ForeignFunctionFilter::AllowAll => true,
+            ForeignFunctionFilter::AllowOnly(names) => names.contains(name),
+            ForeignFunctionFilter::Deny(names) => !names.contains(name),
+        }
+    }
+}
+
 pub(crate) use functions::functions;
 pub(crate) use procedures::procedures;
+
+/// Names and arities of all foreign functions, sorted by name.
+pub(crate) fn function_arities() -> impl Iterator<Item = (&'static str, ArityRange)> {
+    functions()
+        .values()
+        .map(|ff| (ff.name.as_str(), ff.arity.clone()))
+        .sorted_by_key(|(name, _)| *name)
+}
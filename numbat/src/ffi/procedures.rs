@@ -4,7 +4,8 @@ use std::sync::OnceLock;
 
 use super::macros::*;
 use crate::{
-    ast::ProcedureKind, ffi::ControlFlow, pretty_print::PrettyPrint, span::Span, value::Value,
+    ast::ProcedureKind, ffi::ControlFlow, markup as m, number::Number, number::RoundingMode,
+    pretty_print::PrettyPrint, quantity::Quantity, span::Span, unit::Unit, value::Value,
     vm::ExecutionContext, RuntimeError,
 };
 
@@ -40,6 +41,22 @@ pub(crate) fn procedures() -> &'static HashMap<ProcedureKind, ForeignFunction> {
                 callable: Callable::Procedure(assert_eq),
             },
         );
+        m.insert(
+            ProcedureKind::Debug,
+            ForeignFunction {
+                name: "debug".into(),
+                arity: 2..=2,
+                callable: Callable::Procedure(debug),
+            },
+        );
+        m.insert(
+            ProcedureKind::Csv,
+            ForeignFunction {
+                name: "csv".into(),
+                arity: 1..=3,
+                callable: Callable::Procedure(csv),
+            },
+        );
         // Note: The 'type' procedure is missing here because it has special handling code in the compiler
 
         m
@@ -49,12 +66,92 @@ pub(crate) fn procedures() -> &'static HashMap<ProcedureKind, ForeignFunction> {
 fn print(ctx: &mut ExecutionContext, mut args: Args, _: Vec<Span>) -> ControlFlow {
     assert!(args.len() <= 1);
 
-    if args.is_empty() {
-        (ctx.print_fn)(&crate::markup::text(""))
+    let output = if args.is_empty() {
+        crate::markup::text("")
     } else {
-        match arg!(args) {
-            Value::String(string) => (ctx.print_fn)(&crate::markup::text(string)), // print string without quotes
-            arg => (ctx.print_fn)(&arg.pretty_print()),
+        let arg = arg!(args);
+        if let Some(print_value_fn) = &mut ctx.print_value_fn {
+            print_value_fn(&arg);
+        }
+        match arg {
+            Value::String(string) => crate::markup::text(string), // print string without quotes
+            arg => arg.pretty_print_with_rounding(ctx.rounding_mode),
+        }
+    };
+
+    match ctx.print(&output) {
+        Ok(()) => ControlFlow::Continue(()),
+        Err(e) => ControlFlow::Break(e),
+    }
+}
+
+/// Note: besides the value itself, this receives the type of the value as a second
+/// argument, which is added by the compiler.
+fn debug(ctx: &mut ExecutionContext, mut args: Args, _: Vec<Span>) -> ControlFlow {
+    assert!(args.len() == 2);
+
+    let value = arg!(args);
+    let type_ = string_arg!(args);
+
+    let base_representation = match &value {
+        Value::Quantity(q) => Some(q.to_base_unit_representation().pretty_print()),
+        Value::Interval(i) => {
+            let base_unit = i.lower().to_base_unit_representation().unit().clone();
+            match i.convert_to(&base_unit) {
+                Ok(i) => Some(i.pretty_print()),
+                Err(e) => return ControlFlow::Break(e),
+            }
+        }
+        _ => None,
+    };
+
+    let mut output = value.pretty_print() + m::dimmed("    [type: ") + m::text(type_.trim());
+    if let Some(base_representation) = base_representation {
+        output += m::dimmed(", base units: ") + base_representation;
+    }
+    output += m::dimmed("]");
+
+    match ctx.print(&output) {
+        Ok(()) => ControlFlow::Continue(()),
+        Err(e) => ControlFlow::Break(e),
+    }
+}
+
+/// Prints a list of quantities as CSV rows, with an optional separator (`,` by default)
+/// and an optional flag whether to include a column with the unit (`true` by default).
+fn csv(ctx: &mut ExecutionContext, mut args: Args, _: Vec<Span>) -> ControlFlow {
+    assert!((1..=3).contains(&args.len()));
+
+    let values = list_arg!(args);
+    let separator = args.pop_front().map_or(",".into(), Value::unsafe_as_string);
+    let include_unit = args.pop_front().map_or(true, |v| v.unsafe_as_bool());
+
+    let quantities: Vec<Quantity> = values.into_iter().map(Value::unsafe_as_quantity).collect();
+
+    // All values in the column are given in the unit of the first one
+    let unit = quantities
+        .first()
+        .map(|q| q.unit().clone())
+        .unwrap_or_else(Unit::scalar);
+
+    for quantity in &quantities {
+        let value = match quantity.convert_to(&unit) {
+            Ok(q) => q.unsafe_value().to_f64(),
+            Err(e) => return ControlFlow::Break(RuntimeError::QuantityError(e)),
+        };
+        // Round to 15 significant digits, such that the conversion does not
+        // show up as floating point noise (`3.5999999999999996`)
+        let number = RoundingMode::default()
+            .round_to_significant_digits(value, 15)
+            .to_string();
+        let row = if include_unit {
+            format!("{number}{separator}{}", unit.to_string_long())
+        } else {
+            number
+        };
+
+        if let Err(e) = ctx.print(&m::text(row)) {
+            return ControlFlow::Break(e);
         }
     }
 
@@ -88,7 +185,7 @@ fn assert_eq(_: &mut ExecutionContext, mut args: Args, arg_spans: Vec<Span>) ->
             rhs.clone(),
         ));
 
-        if lhs.is_quantity() {
+        if lhs.is_quantity() && rhs.is_quantity() {
             let lhs = lhs.unsafe_as_quantity();
             let rhs = rhs.unsafe_as_quantity();
 
@@ -107,11 +204,41 @@ fn assert_eq(_: &mut ExecutionContext, mut args: Args, arg_spans: Vec<Span>) ->
             error
         }
     } else {
+        if args.iter().any(|arg| matches!(arg, Value::Interval(_))) {
+            return ControlFlow::Break(RuntimeError::UnsupportedIntervalOperation);
+        }
+
         let lhs = quantity_arg!(args);
         let rhs = quantity_arg!(args);
         let result = &lhs - &rhs;
         let eps = quantity_arg!(args);
 
+        // If the compared quantities have a dimension but the tolerance is dimensionless
+        // (e.g. `assert_eq(measured, expected, 1%)`), the tolerance is relative to the
+        // (absolute) value of the expected quantity. Otherwise, it is an absolute tolerance.
+        if lhs.as_scalar().is_err() {
+            if let Ok(relative_eps) = eps.as_scalar() {
+                return match result.and_then(|diff| diff.convert_to(rhs.unit())) {
+                    Err(e) => ControlFlow::Break(RuntimeError::QuantityError(e)),
+                    Ok(diff_converted) => {
+                        let diff = diff_converted.unsafe_value().to_f64().abs();
+                        let max_diff = relative_eps.to_f64() * rhs.unsafe_value().to_f64().abs();
+                        if diff <= max_diff {
+                            ControlFlow::Continue(())
+                        } else {
+                            ControlFlow::Break(RuntimeError::AssertEqRelativeFailed(
+                                span_lhs,
+                                lhs.clone(),
+                                span_rhs,
+                                rhs.clone(),
+                                Number::from_f64(relative_eps.to_f64() * 100.0).pretty_print(),
+                            ))
+                        }
+                    }
+                };
+            }
+        }
+
         match result {
             Ok(diff) => match diff.convert_to(eps.unit()) {
                 Err(e) => ControlFlow::Break(RuntimeError::QuantityError(e)),
@@ -8,18 +8,23 @@ use crate::InterpreterSettings;
 
 use std::sync::{Arc, Mutex};
 
-fn evaluate_example(context: &mut Context, input: &str) -> m::Markup {
+/// Evaluate an example in the given context and render its output and result. Errors
+/// are rendered instead of being returned, as examples can be user-defined.
+pub(crate) fn evaluate_example(context: &mut Context, input: &str) -> m::Markup {
     let statement_output: Arc<Mutex<Vec<m::Markup>>> = Arc::new(Mutex::new(vec![]));
     let statement_output_c = statement_output.clone();
     let mut settings = InterpreterSettings {
         print_fn: Box::new(move |s: &m::Markup| {
             statement_output_c.lock().unwrap().push(s.clone());
         }),
+        ..InterpreterSettings::default()
     };
 
-    let (statements, interpreter_result) = context
-        .interpret_with_settings(&mut settings, input, CodeSource::Internal)
-        .expect("No error in 'help' examples");
+    let (statements, interpreter_result) =
+        match context.interpret_with_settings(&mut settings, input, CodeSource::Internal) {
+            Ok(result) => result,
+            Err(e) => return m::whitespace("    ") + m::error(e.to_string()),
+        };
 
     let markup =
         statement_output
@@ -19,6 +19,18 @@ pub fn html_format(class: Option<&str>, content: &str) -> String {
     }
 }
 
+pub fn html_link(url: &str) -> String {
+    if url.is_empty() {
+        return "".into();
+    }
+
+    format!(
+        "<a class=\"numbat-link\" href=\"{href}\">{content}</a>",
+        href = html_escape::encode_double_quoted_attribute(url),
+        content = html_escape::encode_text(url)
+    )
+}
+
 impl Formatter for HtmlFormatter {
     fn format_part(
         &self,
@@ -37,6 +49,9 @@ impl Formatter for HtmlFormatter {
             FormatType::TypeIdentifier => Some("type-identifier"),
             FormatType::Operator => Some("operator"),
             FormatType::Decorator => Some("decorator"),
+            FormatType::Error => Some("error"),
+            FormatType::Warning => Some("warning"),
+            FormatType::Link => return html_link(s),
         };
         html_format(css_class, s)
     }
@@ -1,13 +1,14 @@
 use crate::{
     dimension::DimensionRegistry,
     markup::Markup,
-    pretty_print::PrettyPrint,
+    number::RoundingMode,
     quantity::{Quantity, QuantityError},
     span::Span,
     typed_ast::Statement,
     unit_registry::{UnitRegistry, UnitRegistryError},
 };
 
+use crate::ffi::{describe_arity, ArityRange};
 use crate::markup as m;
 
 use thiserror::Error;
@@ -32,6 +33,8 @@ pub enum RuntimeError {
     AssertEq2Failed(Span, Value, Span, Value),
     #[error("Assertion failed because the following two quantities differ by more than {4}:\n  {1}\n  {3}")]
     AssertEq3Failed(Span, Quantity, Span, Quantity, Quantity),
+    #[error("Assertion failed because the following two quantities differ by more than {4}% (relative to the second one):\n  {1}\n  {3}")]
+    AssertEqRelativeFailed(Span, Quantity, Span, Quantity, String),
     #[error("Could not load exchange rates from European Central Bank.")]
     CouldNotLoadExchangeRates,
     #[error("User error: {0}")]
@@ -57,6 +60,53 @@ pub enum RuntimeError {
 
     #[error("Empty list")]
     EmptyList,
+
+    #[error("Internal error: function '{0}' was compiled with a call using {}, but needs {}", describe_arity(&(*.2..=*.2)), describe_arity(.1))]
+    CallWithWrongArity(String, ArityRange, usize),
+    #[error("Conditional expression is too large: jump distance of {0} bytes exceeds the maximum of 65535 bytes")]
+    JumpTooFar(usize),
+
+    #[error("Rounding step can not be zero")]
+    ZeroRoundingStep,
+    #[error("Expected number of significant figures to be a positive integer")]
+    InvalidNumberOfSignificantFigures,
+    #[error("Expected logarithm base to be positive and different from one")]
+    InvalidLogarithmBase,
+    #[error("Expected logarithm argument to be positive")]
+    LogarithmOfNonPositiveNumber,
+
+    #[error("Lower bound of interval is larger than its upper bound")]
+    InvalidIntervalBounds,
+    #[error("Intervals can only be used in additions, subtractions, multiplications, divisions and unit conversions")]
+    UnsupportedIntervalOperation,
+
+    #[error("Unit '{0}' can not be defined by a value that is zero or infinite")]
+    InvalidUnitDefinition(String),
+    #[error("Unit '{0}' was declared, but its value has not been defined yet")]
+    UnitValueNotDefined(String),
+
+    #[error("No input available")]
+    NoInputAvailable,
+    #[error("Could not parse '{0}' as a number")]
+    InvalidNumberInput(String),
+    #[error("Could not parse '{0}' as a number: units can not be entered here. Multiply the result by a unit instead, e.g. 'read_number(…) × meter'")]
+    UnitInNumberInput(String),
+
+    #[error("Program was aborted after executing more than {0} instructions")]
+    StepLimitExceeded(u64),
+    #[error("Program was aborted after more than {0} nested function calls")]
+    StackOverflow(usize),
+    #[error("Program was aborted after printing more than {0} bytes")]
+    OutputLimitExceeded(usize),
+
+    #[error("Foreign function or procedure '{0}' is not available in this context")]
+    ForeignFunctionNotAvailable(String),
+
+    #[error("Function is too large: it uses more than 65536 local values")]
+    TooManyLocals,
+
+    #[error("Function call with {0} arguments exceeds the maximum of 65535 arguments")]
+    TooManyArguments(usize),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -73,6 +123,26 @@ impl InterpreterResult {
         registry: &DimensionRegistry,
         with_type_info: bool,
         with_equal_sign: bool,
+    ) -> Markup {
+        self.to_markup_with_rounding(
+            evaluated_statement,
+            registry,
+            with_type_info,
+            with_equal_sign,
+            RoundingMode::default(),
+        )
+    }
+
+    /// Like [`to_markup`](Self::to_markup), but the value is displayed with the given
+    /// rounding mode, which should be the [`InterpreterSettings::rounding_mode`] that
+    /// was used to compute it.
+    pub fn to_markup_with_rounding(
+        &self,
+        evaluated_statement: Option<&Statement>,
+        registry: &DimensionRegistry,
+        with_type_info: bool,
+        with_equal_sign: bool,
+        rounding_mode: RoundingMode,
     ) -> Markup {
         match self {
             Self::Value(value) => {
@@ -99,7 +169,7 @@ impl InterpreterResult {
                     m::empty()
                 };
 
-                leader + value.pretty_print() + type_markup + m::nl()
+                leader + value.pretty_print_with_rounding(rounding_mode) + type_markup + m::nl()
             }
             Self::Continue => m::empty(),
         }
@@ -133,8 +203,32 @@ pub type Result<T> = std::result::Result<T, RuntimeError>;
 
 pub type PrintFunction = dyn FnMut(&Markup) + Send;
 
+pub type PrintValueFunction = dyn FnMut(&Value) + Send;
+
+/// Receives the prompt passed to `input(…)` and returns the line that was read,
+/// or `None` if no more input is available.
+pub type InputFunction = dyn FnMut(&str) -> Option<String> + Send;
+
 pub struct InterpreterSettings {
     pub print_fn: Box<PrintFunction>,
+    /// Optionally receives the values passed to `print(…)`, in addition to the
+    /// formatted output passed to `print_fn`. This is mostly useful for tests.
+    pub print_value_fn: Option<Box<PrintValueFunction>>,
+    /// The source for `input(…)` and `read_number(…)`. If this is not set, calls
+    /// to these functions fail with [`RuntimeError::NoInputAvailable`].
+    pub input_fn: Option<Box<InputFunction>>,
+    /// The maximum number of VM instructions that may be executed for a single
+    /// call to the interpreter. If this limit is exceeded, the program is aborted
+    /// with [`RuntimeError::StepLimitExceeded`]. There is no limit by default.
+    pub max_steps: Option<u64>,
+    /// The maximum number of bytes that may be passed to `print_fn` for a single
+    /// call to the interpreter. If this limit is exceeded, the program is aborted
+    /// with [`RuntimeError::OutputLimitExceeded`]. There is no limit by default.
+    pub max_output_bytes: Option<usize>,
+    /// The rounding mode used by `round`, `round_to` and `signif`, and for the
+    /// last displayed digit of printed values. Half-way cases are rounded to the
+    /// even neighbor by default.
+    pub rounding_mode: RoundingMode,
 }
 
 impl Default for InterpreterSettings {
@@ -143,6 +237,11 @@ impl Default for InterpreterSettings {
             print_fn: Box::new(move |s: &Markup| {
                 print!("{}", s);
             }),
+            print_value_fn: None,
+            input_fn: None,
+            max_steps: None,
+            max_output_bytes: None,
+            rounding_mode: RoundingMode::default(),
         }
     }
 }
@@ -161,7 +260,8 @@ pub trait Interpreter {
 
 #[cfg(test)]
 mod tests {
-    use crate::prefix_parser::AcceptsPrefix;
+    use crate::prefix::AcceptsPrefix;
+    use crate::typed_ast::{Expression, Statement};
     use crate::unit::{CanonicalName, Unit};
     use crate::{bytecode_interpreter::BytecodeInterpreter, prefix_transformer::Transformer};
 
@@ -191,10 +291,30 @@ mod tests {
         unit hertz: Frequency = 1 / second
 
         fn sin(x: Scalar) -> Scalar
-        fn atan2<D>(y: D, x: D) -> Scalar";
+        fn atan2<D>(y: D, x: D) -> Scalar
+
+        fn input(prompt: String) -> String
+        fn read_number(prompt: String) -> Scalar";
 
     #[track_caller]
-    fn get_interpreter_result(input: &str) -> Result<InterpreterResult> {
+    fn interpret(input: &str) -> (BytecodeInterpreter, Result<InterpreterResult>) {
+        interpret_with_settings(input, &mut InterpreterSettings::default())
+    }
+
+    #[track_caller]
+    fn interpret_with_settings(
+        input: &str,
+        settings: &mut InterpreterSettings,
+    ) -> (BytecodeInterpreter, Result<InterpreterResult>) {
+        interpret_with_interpreter(BytecodeInterpreter::new(), input, settings)
+    }
+
+    #[track_caller]
+    fn interpret_with_interpreter(
+        mut interpreter: BytecodeInterpreter,
+        input: &str,
+        settings: &mut InterpreterSettings,
+    ) -> (BytecodeInterpreter, Result<InterpreterResult>) {
         let full_code = format!("{prelude}\n{input}", prelude = TEST_PRELUDE, input = input);
         let statements = crate::parser::parse(&full_code, 0)
             .expect("No parse errors for inputs in this test suite");
@@ -205,11 +325,17 @@ mod tests {
         let statements_typechecked = typechecker
             .check(statements_transformed)
             .expect("No type check errors for inputs in this test suite");
-        BytecodeInterpreter::new().interpret_statements(
-            &mut InterpreterSettings::default(),
+        let result = interpreter.interpret_statements(
+            settings,
             &statements_typechecked,
             typechecker.registry(),
-        )
+        );
+        (interpreter, result)
+    }
+
+    #[track_caller]
+    fn get_interpreter_result(input: &str) -> Result<InterpreterResult> {
+        interpret(input).1
     }
 
     #[track_caller]
@@ -318,6 +444,31 @@ mod tests {
         assert_evaluates_to_scalar("2\n3", 3.0);
     }
 
+    #[test]
+    fn procedure_calls_and_definitions_yield_no_result() {
+        assert_eq!(
+            get_interpreter_result("2 + 3\nprint(1)").unwrap(),
+            InterpreterResult::Continue
+        );
+        assert_eq!(
+            get_interpreter_result("1\nlet x = 2").unwrap(),
+            InterpreterResult::Continue
+        );
+        assert_evaluates_to_scalar("print(1)\n2", 2.0);
+
+        assert_eq!(get_printed_output("2 + 3\nprint(1)").trim(), "1");
+    }
+
+    #[test]
+    fn execution_continues_after_procedure_calls() {
+        assert_eq!(get_printed_output("print(1)\nprint(2)"), "12");
+        assert_eq!(
+            get_printed_output("print(1)\nlet x = 2\nprint(x)\nassert(x == 2)\nprint(3)"),
+            "123"
+        );
+        assert_evaluates_to_scalar("print(1)\nprint(2)\n3", 3.0);
+    }
+
     #[test]
     fn variable_definitions() {
         assert_evaluates_to_scalar("let x = 2\nlet y = 3\nx + y", 2.0 + 3.0);
@@ -334,8 +485,437 @@ mod tests {
         assert_evaluates_to_scalar("atan2(2 meter, 1 meter)", 2.0f64.atan2(1.0f64));
     }
 
+    #[test]
+    fn calls_with_wrong_arity_are_not_compiled() {
+        let code = format!("{TEST_PRELUDE}\nfn f(x: Scalar) -> Scalar = x\nf(1)");
+        let statements = crate::parser::parse(&code, 0).unwrap();
+        let statements = Transformer::new().transform(statements).unwrap();
+        let mut typechecker = crate::typechecker::TypeChecker::default();
+        let mut statements = typechecker.check(statements).unwrap();
+
+        // Construct a call that the typechecker would have rejected
+        let Some(Statement::Expression(Expression::FunctionCall(_, _, _, args, _))) =
+            statements.last_mut()
+        else {
+            unreachable!();
+        };
+        args.push(args[0].clone());
+
+        let result = BytecodeInterpreter::new().interpret_statements(
+            &mut InterpreterSettings::default(),
+            &statements,
+            typechecker.registry(),
+        );
+        assert_eq!(
+            result,
+            Err(RuntimeError::CallWithWrongArity("f".into(), 1..=1, 2))
+        );
+    }
+
+    #[track_caller]
+    fn get_printed_output(input: &str) -> String {
+        let output = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+        let output_clone = output.clone();
+        let mut settings = InterpreterSettings {
+            print_fn: Box::new(move |s: &Markup| {
+                output_clone.lock().unwrap().push_str(&s.to_string());
+            }),
+            ..InterpreterSettings::default()
+        };
+        let _ = interpret_with_settings(input, &mut settings).1.unwrap();
+        let output = output.lock().unwrap().clone();
+        output
+    }
+
+    #[test]
+    fn printed_values_can_be_captured() {
+        let values = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let values_clone = values.clone();
+        let mut settings = InterpreterSettings {
+            print_fn: Box::new(|_: &Markup| {}),
+            print_value_fn: Some(Box::new(move |v: &Value| {
+                values_clone.lock().unwrap().push(v.clone());
+            })),
+            ..InterpreterSettings::default()
+        };
+        let _ = interpret_with_settings("print(3 m)\nprint(\"test\")\nprint()", &mut settings)
+            .1
+            .unwrap();
+
+        let values = values.lock().unwrap();
+        assert_eq!(values.len(), 2);
+
+        let quantity = values[0].clone().unsafe_as_quantity();
+        assert_eq!(quantity.unsafe_value().to_f64(), 3.0);
+        assert_eq!(quantity.unit(), &Unit::meter());
+
+        assert_eq!(values[1], Value::String("test".into()));
+    }
+
+    #[track_caller]
+    fn interpret_with_input(
+        input: &str,
+        lines: &[&str],
+    ) -> (Vec<String>, Result<InterpreterResult>) {
+        let prompts = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let prompts_clone = prompts.clone();
+        let mut lines: std::collections::VecDeque<String> =
+            lines.iter().map(|l| format!("{l}\n")).collect();
+        let mut settings = InterpreterSettings {
+            print_fn: Box::new(|_: &Markup| {}),
+            print_value_fn: None,
+            input_fn: Some(Box::new(move |prompt: &str| {
+                prompts_clone.lock().unwrap().push(prompt.to_string());
+                lines.pop_front()
+            })),
+            ..InterpreterSettings::default()
+        };
+        let result = interpret_with_settings(input, &mut settings).1;
+        let prompts = prompts.lock().unwrap().clone();
+        (prompts, result)
+    }
+
+    #[test]
+    fn step_limit() {
+        let with_step_limit = |max_steps| InterpreterSettings {
+            print_fn: Box::new(|_: &Markup| {}),
+            print_value_fn: None,
+            input_fn: None,
+            max_steps: Some(max_steps),
+            ..InterpreterSettings::default()
+        };
+        let countdown =
+            "fn countdown(n: Scalar) -> Scalar = if n == 0 then 0 else countdown(n - 1)";
+
+        let result = interpret_with_settings(
+            &format!("{countdown}\ncountdown(100_000)"),
+            &mut with_step_limit(10_000),
+        )
+        .1;
+        assert_eq!(result, Err(RuntimeError::StepLimitExceeded(10_000)));
+
+        let result = interpret_with_settings(
+            &format!("{countdown}\ncountdown(10)"),
+            &mut with_step_limit(10_000),
+        )
+        .1;
+        assert_eq!(
+            result,
+            Ok(InterpreterResult::Value(Value::Quantity(
+                Quantity::from_scalar(0.0)
+            )))
+        );
+    }
+
+    #[test]
+    fn output_limit() {
+        let output = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let output_clone = output.clone();
+        let mut settings = InterpreterSettings {
+            print_fn: Box::new(move |s: &Markup| {
+                output_clone.lock().unwrap().push(s.to_string());
+            }),
+            print_value_fn: None,
+            input_fn: None,
+            max_steps: None,
+            max_output_bytes: Some(20),
+            ..InterpreterSettings::default()
+        };
+
+        let result = interpret_with_settings(
+            "let x = 2 meter\nprint(\"x = {x}\")\nprint(\"x = {x}\")",
+            &mut settings,
+        )
+        .1;
+        assert_eq!(result, Ok(InterpreterResult::Continue));
+        assert_eq!(*output.lock().unwrap(), vec!["x = 2 m", "x = 2 m"]);
+
+        output.lock().unwrap().clear();
+        let result = interpret_with_settings(
+            "let x = 2 meter\nprint(\"x = {x}\")\nprint(\"x = {x}\")\nprint(\"x = {x}\")",
+            &mut settings,
+        )
+        .1;
+        assert_eq!(result, Err(RuntimeError::OutputLimitExceeded(20)));
+        assert_eq!(*output.lock().unwrap(), vec!["x = 2 m", "x = 2 m"]);
+    }
+
+    #[test]
+    fn results_are_tagged_with_their_value_kind() {
+        assert_eq!(
+            get_interpreter_result("2 meter").unwrap(),
+            InterpreterResult::Value(Value::Quantity(Quantity::new_f64(2.0, Unit::meter())))
+        );
+        assert_eq!(
+            get_interpreter_result("1 < 2").unwrap(),
+            InterpreterResult::Value(Value::Boolean(true))
+        );
+        assert_eq!(
+            get_interpreter_result("[1, 2]").unwrap(),
+            InterpreterResult::Value(Value::List(
+                [1.0, 2.0]
+                    .into_iter()
+                    .map(|x| Value::Quantity(Quantity::from_scalar(x)))
+                    .collect()
+            ))
+        );
+        assert_eq!(
+            get_interpreter_result("\"test\"").unwrap(),
+            InterpreterResult::Value(Value::String("test".into()))
+        );
+        assert_eq!(
+            get_interpreter_result("let x = 1").unwrap(),
+            InterpreterResult::Continue
+        );
+    }
+
+    #[test]
+    fn input_is_read_from_input_source() {
+        let (prompts, result) = interpret_with_input(
+            "let name = input(\"Name: \")
+             let distance = read_number(\"Distance in m: \") meter
+             \"{name}: {distance}\"",
+            &["Alice", " 1_500.5 "],
+        );
+        assert_eq!(prompts, vec!["Name: ", "Distance in m: "]);
+        assert_eq!(
+            result.unwrap(),
+            InterpreterResult::Value(Value::String("Alice: 1500.5 m".into()))
+        );
+
+        let (_, result) = interpret_with_input("read_number(\"\") meter", &["2e3"]);
+        assert_eq!(
+            result.unwrap(),
+            InterpreterResult::Value(Value::Quantity(Quantity::new_f64(2000.0, Unit::meter())))
+        );
+
+        let (_, result) = interpret_with_input("read_number(\"\")", &["two"]);
+        assert_eq!(
+            result.unwrap_err(),
+            RuntimeError::InvalidNumberInput("two".into())
+        );
+
+        for line in ["3 m", "2.5km", "-1e3 m/s", "4 · m"] {
+            let (_, result) = interpret_with_input("read_number(\"\")", &[line]);
+            assert_eq!(
+                result.unwrap_err(),
+                RuntimeError::UnitInNumberInput(line.into())
+            );
+        }
+
+        // No more input lines
+        let (_, result) = interpret_with_input("input(\"a\")\ninput(\"b\")", &["x"]);
+        assert_eq!(result.unwrap_err(), RuntimeError::NoInputAvailable);
+
+        // No input source
+        assert_runtime_error("input(\"Name: \")", RuntimeError::NoInputAvailable);
+    }
+
+    #[test]
+    fn type_procedure_reports_derived_dimensions() {
+        assert_eq!(
+            get_printed_output("type(2 m/s)").trim(),
+            "= Length / Time    [Velocity]"
+        );
+        assert_eq!(get_printed_output("type(2 m)").trim(), "= Length");
+        assert_eq!(get_printed_output("type(2)").trim(), "= Scalar");
+
+        assert_eq!(
+            get_printed_output(
+                "unit kilogram: Mass
+                 dimension Force = Mass * Length / Time^2
+                 dimension Torque = Force * Length
+                 type(2 kilogram * 3 m * 4 m / (5 s)^2)"
+            )
+            .trim(),
+            "= Length² × Mass / Time²    [Torque]"
+        );
+
+        assert_eq!(
+            get_printed_output(
+                "unit kilogram: Mass
+                 dimension Energy = Mass * Velocity^2
+                 dimension Torque = Mass * Length^2 / Time^2
+                 type(1 kilogram * 1 m^2 / s^2)"
+            )
+            .trim(),
+            "= Length² × Mass / Time²    [Energy or Torque]"
+        );
+    }
+
+    #[test]
+    fn constant_conditions_are_folded() {
+        let (interpreter, result) = interpret("if 1 < 2 then 42 else 43");
+        assert_eq!(
+            result.unwrap(),
+            InterpreterResult::Value(Value::Quantity(Quantity::from_scalar(42.0)))
+        );
+        let disassembly = interpreter.disassembly();
+        assert!(disassembly.contains("(value: 42)"));
+        assert!(!disassembly.contains("(value: 43)"));
+        assert!(!disassembly.contains("JumpIfFalse"));
+
+        let (interpreter, _) = interpret("if !(1 < 2 && -3 == 2 - 5) then 42 else 43");
+        let disassembly = interpreter.disassembly();
+        assert!(!disassembly.contains("(value: 42)"));
+        assert!(disassembly.contains("(value: 43)"));
+        assert!(!disassembly.contains("JumpIfFalse"));
+
+        // Non-constant conditions are not folded
+        let (interpreter, _) = interpret("let x = 1\nif x < 2 then 42 else 43");
+        let disassembly = interpreter.disassembly();
+        assert!(disassembly.contains("(value: 42)"));
+        assert!(disassembly.contains("(value: 43)"));
+        assert!(disassembly.contains("JumpIfFalse"));
+    }
+
+    #[test]
+    fn constant_conversions_are_folded() {
+        let (interpreter, result) = interpret("2 km / 4 s -> m/s");
+        assert_eq!(
+            result.unwrap(),
+            InterpreterResult::Value(Value::Quantity(Quantity::new_f64(
+                500.0,
+                Unit::meter() / Unit::second()
+            )))
+        );
+        let disassembly = interpreter.disassembly();
+        assert!(disassembly.contains("500 m/s"));
+        assert!(!disassembly.contains("ConvertTo"));
+
+        // Non-constant quantities are converted at runtime
+        let (interpreter, _) = interpret("let x = 2 km\nx -> m");
+        assert!(interpreter.disassembly().contains("ConvertTo"));
+
+        // Units defined in the same program are not known at compile time
+        let (interpreter, result) = interpret("4 Hz -> Hz");
+        assert_eq!(
+            result.unwrap(),
+            InterpreterResult::Value(Value::Quantity(Quantity::new_f64(4.0, Unit::hertz())))
+        );
+        assert!(interpreter.disassembly().contains("ConvertTo"));
+    }
+
+    #[test]
+    fn identity_operations_are_elided() {
+        let count_ops = |input: &str, op: &str| {
+            let (interpreter, result) = interpret(input);
+            assert!(result.is_ok());
+            interpreter
+                .disassembly()
+                .lines()
+                .filter(|line| line.split_whitespace().nth(1) == Some(op))
+                .count()
+        };
+        let baseline = |op| count_ops("let x = 2 m\nx", op);
+
+        for (input, op) in [
+            ("let x = 2 m\nx * 1", "Multiply"),
+            ("let x = 2 m\n1 * x", "Multiply"),
+            ("let x = 2 m\nx / 1", "Divide"),
+            ("let x = 2 m\nx + 0", "Add"),
+            ("let x = 2 m\n0 + x", "Add"),
+            ("let x = 2 m\nx - 0", "Subtract"),
+            ("let x = 2 m\n(x + 0) * 1.0 / 1 - 0", "Multiply"),
+        ] {
+            assert_eq!(count_ops(input, op), baseline(op), "input: {input}");
+        }
+
+        // Multiplication with zero and non-identity operations are kept
+        for (input, op) in [
+            ("let x = 2 m\nx * 0", "Multiply"),
+            ("let x = 2 m\nx * 2", "Multiply"),
+            ("let x = 2 m\n1 / x", "Divide"),
+            ("let x = 2 m\n0 - x", "Subtract"),
+            ("let x = 2 m\nx + 1 m", "Add"),
+        ] {
+            assert_eq!(count_ops(input, op), baseline(op) + 1, "input: {input}");
+        }
+
+        assert_evaluates_to(
+            "let x = 2 m\n(x + 0) * 1 / 1 - 0",
+            Quantity::new_f64(2.0, Unit::meter()),
+        );
+        assert_evaluates_to(
+            "let x = 2 m\n0 + 1 * x",
+            Quantity::new_f64(2.0, Unit::meter()),
+        );
+        assert_evaluates_to("let x = 2 m\nx * 0", Quantity::new_f64(0.0, Unit::meter()));
+        assert_evaluates_to_scalar("fn f(x) = x * 1 + 0\nf(3)", 3.0);
+    }
+
+    #[test]
+    fn squares_of_plain_quantities_are_multiplications() {
+        let has_power = |input: &str| {
+            let (interpreter, result) = interpret(input);
+            assert!(result.is_ok());
+            interpreter.disassembly().contains("Power")
+        };
+
+        assert!(!has_power("let x = 2 m\nx^2"));
+        assert!(!has_power("fn f(x) = { let y = 3 m; y^2 }\nf(1)"));
+        assert!(!has_power("let mut x = 2 m\nx = -x * 3\nx^2"));
+        assert_evaluates_to(
+            "let x = 2 m\nx^2",
+            Quantity::new_f64(4.0, Unit::meter().powi(2)),
+        );
+
+        // Parameters or the results of function calls might be intervals
+        assert!(has_power("fn f(x) = x^2\nf(2)"));
+        assert!(has_power("fn f(x) = { let y = x + 1; y^2 }\nf(2)"));
+        assert!(has_power("let x = 2 m\nx^3"));
+    }
+
     #[test]
     fn division_by_zero_raises_runtime_error() {
         assert_runtime_error("1/0", RuntimeError::DivisionByZero);
+        assert_runtime_error("1 m / (0 s)", RuntimeError::DivisionByZero);
+        assert_runtime_error(
+            "fn ratio<A: Dim, B: Dim>(x: A, y: B) -> A/B = x/y
+             ratio(1 m, 0 s)",
+            RuntimeError::DivisionByZero,
+        );
+        assert_runtime_error(
+            "fn ratio<A: Dim, B: Dim>(x: A, y: B) -> A/B = x/y
+             ratio(1 m, 0 m)",
+            RuntimeError::DivisionByZero,
+        );
+    }
+
+    #[test]
+    fn small_functions_are_inlined() {
+        let programs = [
+            "fn sq(x) = x * x\nsq(3 m)",
+            "fn f(x, y) = x - y\nf(10, 3)",
+            "fn f(x, y) = x - y\n1 + f(f(5, 1), f(1, 2 + 3))",
+            "let a = 1\nfn f(x) = x + a\nlet a = 5\nf(1) + a",
+            "fn f(x) = x\nfn g(x) = f(x) + 1\nfn f(x) = 2 x\ng(1) + f(1)",
+            "fn cube(x) = x^3\nfn f(x, y) = if x > y then cube(x) else cube(y)\nf(2, 3) - f(4, 1)",
+            "fn f(x: Length) -> Length = x -> m\nf(1 km)",
+            "fn f(x, y) = x / y\nf(1 meter, 1 millimeter)",
+            "fn raw<D: Dim>(x: D) -> D\nfn f(x) = raw(x)\nf(1 meter / millimeter)",
+            "fn f(x) = -x!\nf(4)",
+            "fn f(a, b, c) = a * (b - c)\nf(2, 3, 4)",
+            "fn f(x) = \"{x}!\"\nf(2 m)",
+            "fn f(x: Scalar) -> Scalar = if x < 0 then f(-x) else x\nf(-3)",
+            "fn f(x) = sin(x) + atan2(x, 1)\nf(0.5)",
+            "fn f(x) = x + 1\n1 + 2 * f(f(1) * 3)",
+        ];
+
+        for program in programs {
+            let mut interpreter = BytecodeInterpreter::new();
+            interpreter.set_function_inlining(false);
+            let (_, expected) =
+                interpret_with_interpreter(interpreter, program, &mut Default::default());
+            let (_, actual) = interpret(program);
+            assert_eq!(actual.unwrap(), expected.unwrap(), "program: {program}");
+        }
+
+        let (interpreter, _) = interpret("fn sq(x) = x * x\nsq(3)");
+        assert!(interpreter.disassembly().contains("DropInlineArguments"));
+
+        // Functions which are not small are called as usual
+        let (interpreter, _) = interpret("fn f(x) = x + x + x + x + x + x + x + x + x + x\nf(3)");
+        assert!(!interpreter.disassembly().contains("DropInlineArguments"));
     }
 }
@@ -0,0 +1,254 @@
+use crate::{
+    interpreter::RuntimeError, markup as m, number::RoundingMode, pretty_print::PrettyPrint,
+    quantity::Quantity, unit::Unit,
+};
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// A closed interval `[lower .. upper]` of quantities, used to propagate tolerances
+/// through a calculation. Both bounds are always stored in the same unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interval {
+    lower: Quantity,
+    upper: Quantity,
+}
+
+impl Interval {
+    pub fn new(lower: Quantity, upper: Quantity) -> Result<Self> {
+        let interval = Self::from_bounds(lower, upper)?;
+        if interval.lower > interval.upper {
+            return Err(RuntimeError::InvalidIntervalBounds);
+        }
+        Ok(interval)
+    }
+
+    /// The interval `[q .. q]` that only contains a single quantity.
+    pub fn degenerate(q: Quantity) -> Self {
+        Interval {
+            lower: q.clone(),
+            upper: q,
+        }
+    }
+
+    fn from_bounds(lower: Quantity, upper: Quantity) -> Result<Self> {
+        let upper = upper
+            .convert_to(lower.unit())
+            .map_err(RuntimeError::QuantityError)?;
+        Ok(Interval { lower, upper })
+    }
+
+    /// The smallest interval containing all of the given candidates.
+    fn hull(candidates: [Quantity; 4]) -> Result<Self> {
+        let [first, rest @ ..] = candidates;
+        let mut lower = first.clone();
+        let mut upper = first;
+        for candidate in rest {
+            if candidate < lower {
+                lower = candidate;
+            } else if candidate > upper {
+                upper = candidate;
+            }
+        }
+        Self::from_bounds(lower, upper)
+    }
+
+    pub fn lower(&self) -> &Quantity {
+        &self.lower
+    }
+
+    pub fn upper(&self) -> &Quantity {
+        &self.upper
+    }
+
+    pub fn add(&self, rhs: &Self) -> Result<Self> {
+        Self::from_bounds(
+            (&self.lower + &rhs.lower).map_err(RuntimeError::QuantityError)?,
+            (&self.upper + &rhs.upper).map_err(RuntimeError::QuantityError)?,
+        )
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Result<Self> {
+        Self::from_bounds(
+            (&self.lower - &rhs.upper).map_err(RuntimeError::QuantityError)?,
+            (&self.upper - &rhs.lower).map_err(RuntimeError::QuantityError)?,
+        )
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Result<Self> {
+        Self::hull([
+            self.lower.clone() * rhs.lower.clone(),
+            self.lower.clone() * rhs.upper.clone(),
+            self.upper.clone() * rhs.lower.clone(),
+            self.upper.clone() * rhs.upper.clone(),
+        ])
+    }
+
+    pub fn checked_div(&self, rhs: &Self) -> Result<Self> {
+        // The quotient is unbounded if the divisor contains zero
+        if rhs.lower.unsafe_value().to_f64() <= 0.0 && rhs.upper.unsafe_value().to_f64() >= 0.0 {
+            return Err(RuntimeError::DivisionByZero);
+        }
+
+        Self::hull([
+            self.lower.clone() / rhs.lower.clone(),
+            self.lower.clone() / rhs.upper.clone(),
+            self.upper.clone() / rhs.lower.clone(),
+            self.upper.clone() / rhs.upper.clone(),
+        ])
+    }
+
+    pub fn convert_to(&self, target_unit: &Unit) -> Result<Self> {
+        Ok(Interval {
+            lower: self
+                .lower
+                .convert_to(target_unit)
+                .map_err(RuntimeError::QuantityError)?,
+            upper: self
+                .upper
+                .convert_to(target_unit)
+                .map_err(RuntimeError::QuantityError)?,
+        })
+    }
+
+    pub fn full_simplify(&self) -> Self {
+        let lower = self.lower.full_simplify();
+        match self.upper.convert_to(lower.unit()) {
+            Ok(upper) => Interval { lower, upper },
+            Err(_) => self.clone(),
+        }
+    }
+}
+
+impl std::ops::Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Self::Output {
+        Interval {
+            lower: -self.upper,
+            upper: -self.lower,
+        }
+    }
+}
+
+impl Interval {
+    /// Like [`PrettyPrint::pretty_print`], but both bounds are rounded to the
+    /// displayed number of digits with the given rounding mode.
+    pub fn pretty_print_with_rounding(&self, rounding_mode: RoundingMode) -> m::Markup {
+        let bound = |q: &Quantity| {
+            q.unsafe_value()
+                .pretty_print_with_rounding(6, rounding_mode)
+        };
+        let unit_str = self
+            .upper
+            .unit()
+            .to_string_for_value(*self.upper.unsafe_value());
+
+        m::operator("[")
+            + m::value(bound(&self.lower))
+            + m::space()
+            + m::operator("..")
+            + m::space()
+            + m::value(bound(&self.upper))
+            + m::operator("]")
+            + if unit_str == "°" || unit_str == "′" || unit_str == "″" || unit_str.is_empty() {
+                m::empty()
+            } else {
+                m::space()
+            }
+            + m::unit(unit_str)
+    }
+}
+
+impl PrettyPrint for Interval {
+    fn pretty_print(&self) -> m::Markup {
+        self.pretty_print_with_rounding(RoundingMode::default())
+    }
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::markup::{Formatter, PlainTextFormatter};
+
+        let markup = self.pretty_print();
+        let formatter = PlainTextFormatter {};
+        write!(f, "{}", formatter.format(&markup, false).trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(lower: f64, upper: f64, unit: Unit) -> Interval {
+        Interval::new(
+            Quantity::new_f64(lower, unit.clone()),
+            Quantity::new_f64(upper, unit),
+        )
+        .unwrap()
+    }
+
+    fn bounds(interval: &Interval) -> (f64, f64) {
+        (
+            interval.lower().unsafe_value().to_f64(),
+            interval.upper().unsafe_value().to_f64(),
+        )
+    }
+
+    fn assert_bounds(interval: &Interval, lower: f64, upper: f64) {
+        let (actual_lower, actual_upper) = bounds(interval);
+        approx::assert_relative_eq!(actual_lower, lower, max_relative = 1e-12);
+        approx::assert_relative_eq!(actual_upper, upper, max_relative = 1e-12);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = interval(1.0, 2.0, Unit::meter());
+        let b = interval(-3.0, 4.0, Unit::meter());
+
+        assert_eq!(bounds(&a.add(&b).unwrap()), (-2.0, 6.0));
+        assert_eq!(bounds(&a.sub(&b).unwrap()), (-3.0, 5.0));
+        assert_eq!(bounds(&a.mul(&b).unwrap()), (-6.0, 8.0));
+        assert_eq!(bounds(&b.mul(&b).unwrap()), (-12.0, 16.0));
+        assert_eq!(bounds(&(-b.clone())), (-4.0, 3.0));
+
+        let c = interval(-4.0, -2.0, Unit::meter());
+        assert_eq!(bounds(&a.checked_div(&c).unwrap()), (-1.0, -0.25));
+        assert!(matches!(
+            a.checked_div(&b),
+            Err(RuntimeError::DivisionByZero)
+        ));
+    }
+
+    #[test]
+    fn units() {
+        let a = interval(1.0, 2.0, Unit::meter());
+        let b = interval(10.0, 20.0, Unit::centimeter());
+
+        let sum = a.add(&b).unwrap();
+        assert_eq!(sum.upper().unit(), &Unit::meter());
+        assert_bounds(&sum, 1.1, 2.2);
+
+        let converted = sum.convert_to(&Unit::centimeter()).unwrap();
+        assert_bounds(&converted, 110.0, 220.0);
+
+        assert!(matches!(
+            Interval::new(
+                Quantity::new_f64(2.0, Unit::meter()),
+                Quantity::new_f64(1.0, Unit::meter())
+            ),
+            Err(RuntimeError::InvalidIntervalBounds)
+        ));
+    }
+
+    #[test]
+    fn pretty_printing() {
+        assert_eq!(
+            interval(9.79, 9.83, Unit::meter()).to_string(),
+            "[9.79 .. 9.83] m"
+        );
+        assert_eq!(
+            Interval::degenerate(Quantity::from_scalar(2.0)).to_string(),
+            "[2 .. 2]"
+        );
+    }
+}
@@ -5,6 +5,7 @@ pub const KEYWORDS: &[&str] = &[
     "per ",
     "to ",
     "let ",
+    "mut ",
     "fn ",
     "dimension ",
     "unit ",
@@ -22,11 +23,14 @@ pub const KEYWORDS: &[&str] = &[
     "false",
     "NaN",
     "inf",
+    "SI",
     // procedures
     "print(",
     "assert(",
     "assert_eq(",
     "type(",
+    "debug(",
+    "csv(",
     // Type names
     "Bool",
     "String",
@@ -39,4 +43,6 @@ pub const KEYWORDS: &[&str] = &[
     "aliases",
     "name",
     "url",
+    "plural",
+    "canonical",
 ];
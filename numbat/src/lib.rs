@@ -1,33 +1,30 @@
-mod arithmetic;
-mod ast;
+pub mod ast;
 #[cfg(feature = "html-formatter")]
 pub mod buffered_writer;
 mod bytecode_interpreter;
 mod column_formatter;
+mod compiled_function;
 mod currency;
 mod datetime;
 mod decorator;
+mod definition_summary;
 pub mod diagnostic;
 mod dimension;
+mod display_system;
 mod ffi;
 mod gamma;
 pub mod help;
 #[cfg(feature = "html-formatter")]
 pub mod html_formatter;
 mod interpreter;
+mod interval;
 pub mod keywords;
-pub mod markup;
 mod math;
 pub mod module_importer;
 mod name_resolution;
-mod number;
 mod parser;
-mod prefix;
 mod prefix_parser;
 mod prefix_transformer;
-pub mod pretty_print;
-mod product;
-mod quantity;
 mod registry;
 pub mod resolver;
 mod span;
@@ -38,11 +35,15 @@ mod type_variable;
 mod typechecker;
 mod typed_ast;
 pub mod unicode_input;
-mod unit;
 mod unit_registry;
 pub mod value;
 mod vm;
+mod warning;
 
+use numbat_core::{arithmetic, format, number, prefix, quantity, unit};
+pub use numbat_core::{markup, pretty_print};
+
+use arithmetic::{Exponent, Power};
 use bytecode_interpreter::BytecodeInterpreter;
 use column_formatter::ColumnFormatter;
 use currency::ExchangeRatesCache;
@@ -53,27 +54,51 @@ use keywords::KEYWORDS;
 use markup as m;
 use markup::FormatType;
 use markup::Markup;
+use markup::{Formatter, PlainTextFormatter};
 use module_importer::{ModuleImporter, NullImporter};
+use prefix::Prefix;
 use prefix_transformer::Transformer;
+use pretty_print::PrettyPrint;
 
 use resolver::CodeSource;
 use resolver::Resolver;
 use resolver::ResolverError;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 use thiserror::Error;
+use typechecker::type_scheme::TypeScheme;
 use typechecker::{TypeCheckError, TypeChecker};
+use unicode_normalization::UnicodeNormalization;
 
+pub use compiled_function::CompiledFunction;
+pub use compiled_function::CompiledFunctionError;
+pub use definition_summary::{DefinitionSummary, DimensionSummary, FunctionSummary, UnitSummary};
 pub use diagnostic::Diagnostic;
+pub use display_system::DisplaySystem;
+pub use ffi::ForeignFunctionFilter;
+pub use format::DivisionStyle;
+pub use format::FormatOptions;
+pub use format::PrefixStyle;
 pub use interpreter::InterpreterResult;
 pub use interpreter::InterpreterSettings;
 pub use interpreter::RuntimeError;
+pub use interval::Interval;
 pub use name_resolution::NameResolutionError;
+pub use number::RoundingMode;
 pub use parser::ParseError;
+pub use quantity::Quantity;
+pub use quantity::QuantityError;
 pub use registry::BaseRepresentation;
 pub use registry::BaseRepresentationFactor;
+pub use span::Span;
 pub use typed_ast::Statement;
 pub use typed_ast::Type;
 use unit::BaseUnitAndFactor;
+pub use unit::Unit;
 use unit_registry::UnitMetadata;
+use value::Value;
+pub use vm::LazyEvaluation;
+pub use warning::Warning;
 
 use crate::prefix_parser::PrefixParserResult;
 use crate::unicode_input::UNICODE_INPUT;
@@ -92,6 +117,28 @@ pub enum NumbatError {
 
 type Result<T> = std::result::Result<T, NumbatError>;
 
+/// Parse Numbat code into a list of (untyped) statements, without resolving modules,
+/// names or types. If the code contains errors, the first one is returned.
+///
+/// ```
+/// use numbat::pretty_print::PrettyPrint;
+///
+/// let statements = numbat::parse("let v=2 m/s").unwrap();
+/// assert_eq!(statements[0].pretty_print().to_string(), "let v = 2 m / s");
+/// ```
+pub fn parse(code: &str) -> std::result::Result<Vec<ast::Statement>, ParseError> {
+    parser::parse(code, 0).map_err(|(_, mut errors)| errors.remove(0))
+}
+
+/// The version of this Numbat implementation, as checked by `#!version …` directives.
+///
+/// ```
+/// assert_eq!(numbat::version(), env!("CARGO_PKG_VERSION"));
+/// ```
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
 #[derive(Clone)]
 pub struct Context {
     prefix_transformer: Transformer,
@@ -100,6 +147,8 @@ pub struct Context {
     resolver: Resolver,
     load_currency_module_on_demand: bool,
     terminal_width: Option<usize>,
+    display_system: Option<DisplaySystem>,
+    warnings: Vec<Warning>,
 }
 
 impl Context {
@@ -111,6 +160,8 @@ impl Context {
             resolver: Resolver::new(module_importer),
             load_currency_module_on_demand: false,
             terminal_width: None,
+            display_system: None,
+            warnings: vec![],
         }
     }
 
@@ -122,6 +173,57 @@ impl Context {
         self.interpreter.set_debug(activate);
     }
 
+    /// The maximum number of nested function calls, 100 000 by default. Deeper recursion
+    /// is aborted with [`RuntimeError::StackOverflow`], instead of exhausting the memory.
+    pub fn set_max_stack_depth(&mut self, max_stack_depth: usize) {
+        self.interpreter.set_max_stack_depth(max_stack_depth);
+    }
+
+    /// Whether or not calls to small functions are inlined by the compiler (the default).
+    /// This has no effect on the results, only on performance.
+    pub fn set_function_inlining(&mut self, activate: bool) {
+        self.interpreter.set_function_inlining(activate);
+    }
+
+    /// In strict mode, dimensionless values are no longer accepted in places where they
+    /// would otherwise stand in for quantities of any dimension:
+    ///
+    /// - The literals `0`, `inf` and `NaN` are dimensionless, like all other numbers.
+    ///   Expressions like `2 m + 0` or `let x: Length = 0` are rejected (use `0 m`).
+    /// - The tolerance in `assert_eq(q1, q2, ε)` needs to have the same dimension as
+    ///   the compared quantities. Dimensionless relative tolerances like `1%` are
+    ///   rejected for quantities with a dimension.
+    /// - Exponents of quantities with a dimension may not contain decimal numbers,
+    ///   e.g. `length^0.5` is rejected (use `length^(1/2)`).
+    ///
+    /// This only affects code that is interpreted afterwards, so it should be activated
+    /// after loading the prelude.
+    pub fn set_strict_dimensions(&mut self, strict: bool) {
+        self.typechecker.set_strict_dimensions(strict);
+    }
+
+    /// Allow calling functions with fewer arguments than parameters, which results in a
+    /// partially applied function: if `f` is a function of type `Fn[(A, B, C) -> R]`,
+    /// then `f(a)` has the type `Fn[(B, C) -> R]`, and `f(a)(b, c)` is the same as
+    /// `f(a, b, c)`. At least one argument needs to be supplied. This only applies to
+    /// calls of named functions, not to calls of function values.
+    ///
+    /// Like strict mode, this only affects code that is interpreted afterwards.
+    pub fn set_partial_application(&mut self, activate: bool) {
+        self.typechecker.set_partial_application(activate);
+    }
+
+    /// Restrict the foreign functions and procedures that are available, e.g. to run
+    /// untrusted code without access to `input` or `print`. Declaring a function that
+    /// is not available is allowed, so the filter can be set before or after loading
+    /// the prelude, but calling or referencing it results in a type check error. Code
+    /// that has been compiled before the filter was set fails at runtime instead.
+    /// Compiled functions that use it can not be imported with [`Context::import_function`].
+    pub fn set_foreign_function_filter(&mut self, filter: ForeignFunctionFilter) {
+        self.typechecker.set_foreign_function_filter(filter.clone());
+        self.interpreter.set_foreign_function_filter(filter);
+    }
+
     pub fn load_currency_module_on_demand(&mut self, yes: bool) {
         self.load_currency_module_on_demand = yes;
     }
@@ -135,6 +237,21 @@ impl Context {
         ExchangeRatesCache::set_from_xml(xml_content);
     }
 
+    /// Provide exchange rates for this context from a custom source, instead of fetching
+    /// them from the European Central Bank. Rates are given relative to the Euro (e.g.
+    /// `("USD", 1.08)` means that 1 EUR = 1.08 USD). The table can be replaced at any
+    /// time. If the `units::currencies` module has already been loaded, the currency
+    /// units are updated to the new rates. Values that were computed before keep the
+    /// rates that were valid at that time.
+    pub fn set_exchange_rate_table(
+        &mut self,
+        rates: impl IntoIterator<Item = (String, f64)>,
+    ) -> Result<()> {
+        self.interpreter
+            .set_exchange_rates(rates.into_iter().collect())
+            .map_err(NumbatError::RuntimeError)
+    }
+
     pub fn variable_names(&self) -> impl Iterator<Item = String> + '_ {
         self.prefix_transformer
             .variable_names
@@ -183,6 +300,24 @@ impl Context {
             })
     }
 
+    /// All builtin (foreign) functions with their arity and, if the function has been
+    /// declared (e.g. in the prelude), its signature.
+    pub fn foreign_functions(
+        &self,
+    ) -> impl Iterator<Item = (String, RangeInclusive<usize>, Option<String>)> + '_ {
+        ffi::function_arities().map(move |(name, arity)| {
+            let signature = self
+                .typechecker
+                .lookup_function(name)
+                .map(|(signature, _)| {
+                    signature
+                        .pretty_print(self.dimension_registry())
+                        .to_string()
+                });
+            (name.to_string(), arity, signature)
+        })
+    }
+
     pub fn unit_names(&self) -> &[Vec<String>] {
         &self.prefix_transformer.unit_names
     }
@@ -317,6 +452,11 @@ impl Context {
         if keyword.is_empty() {
             return m::text("Usage: info <unit or variable>");
         }
+
+        // Identifiers are NFC-normalized by the tokenizer, so we do the same here
+        let keyword: String = keyword.nfc().collect();
+        let keyword = keyword.as_str();
+
         let reg = self.interpreter.get_unit_registry();
 
         if let PrefixParserResult::UnitIdentifier(_span, prefix, _, full_name) =
@@ -330,7 +470,7 @@ impl Context {
             {
                 let mut help = m::text("Unit: ") + m::unit(md.name.as_deref().unwrap_or(keyword));
                 if let Some(url) = &md.url {
-                    help += m::text(" (") + m::string(url_encode(url)) + m::text(")");
+                    help += m::text(" (") + m::link(url_encode(url)) + m::text(")");
                 }
                 help += m::nl();
                 if md.aliases.len() > 1 {
@@ -417,7 +557,7 @@ impl Context {
                 help += m::identifier(keyword);
             }
             if let Some(url) = &l.metadata.url {
-                help += m::text(" (") + m::string(url_encode(url)) + m::text(")");
+                help += m::text(" (") + m::link(url_encode(url)) + m::text(")");
             }
             help += m::nl();
 
@@ -462,7 +602,7 @@ impl Context {
                 help += m::identifier(keyword);
             }
             if let Some(url) = &metadata.url {
-                help += m::text(" (") + m::string(url_encode(url)) + m::text(")");
+                help += m::text(" (") + m::link(url_encode(url)) + m::text(")");
             }
             help += m::nl();
 
@@ -488,6 +628,55 @@ impl Context {
         m::text("Not found")
     }
 
+    /// Like [`Context::print_info_for_keyword`], but also live-evaluates the `@example`s
+    /// of the unit, variable or function and shows their results. The examples are run
+    /// in a copy of this context, such that they can not modify it.
+    pub fn help_for(&mut self, name: &str) -> Markup {
+        let mut help = self.print_info_for_keyword(name);
+
+        let examples = self.examples_for_keyword(name);
+        if examples.is_empty() {
+            return help;
+        }
+
+        help += m::nl() + m::text("Examples:") + m::nl() + m::nl();
+
+        let mut example_context = self.clone();
+        for example in examples {
+            help += m::text(">>> ") + m::text(&example) + m::nl();
+            help += help::evaluate_example(&mut example_context, &example) + m::nl();
+        }
+
+        help
+    }
+
+    fn examples_for_keyword(&self, keyword: &str) -> Vec<String> {
+        let keyword: String = keyword.nfc().collect();
+
+        if let PrefixParserResult::UnitIdentifier(_, _, _, full_name) =
+            self.prefix_transformer.prefix_parser.parse(&keyword)
+        {
+            if let Ok((_, md)) = self
+                .interpreter
+                .get_unit_registry()
+                .inner
+                .get_base_representation_for_name(&full_name)
+            {
+                return md.examples;
+            }
+        }
+
+        if let Some(l) = self.interpreter.lookup_global(&keyword) {
+            return l.metadata.examples.clone();
+        }
+
+        if let Some((_, fn_metadata)) = self.typechecker.lookup_function(&keyword) {
+            return fn_metadata.examples.clone();
+        }
+
+        vec![]
+    }
+
     pub fn list_modules(&self) -> impl Iterator<Item = String> {
         let modules = self.resolver.get_importer().list_modules();
         modules.into_iter().map(|m| m.0.join("::"))
@@ -497,6 +686,12 @@ impl Context {
         self.typechecker.registry()
     }
 
+    /// All known dimensions (e.g. `Length` or `Energy`), along with their representation
+    /// in terms of base dimensions and whether they are base dimensions themselves.
+    pub fn dimensions(&self) -> impl Iterator<Item = (String, BaseRepresentation, bool)> + '_ {
+        self.dimension_registry().iter_dimensions()
+    }
+
     pub fn base_units(&self) -> impl Iterator<Item = String> + '_ {
         self.interpreter
             .get_unit_registry()
@@ -523,10 +718,397 @@ impl Context {
         })
     }
 
+    /// Get the representation of a dimension (e.g. `Pressure`) in terms of base dimensions,
+    /// or the representation of a (possibly prefixed) unit (e.g. `kPa`) in terms of base units.
+    /// Returns `None` if there is no dimension or unit with the given name.
+    pub fn base_representation_of(&self, dimension_or_unit: &str) -> Option<BaseRepresentation> {
+        // Identifiers are NFC-normalized by the tokenizer, so we do the same here
+        let name: String = dimension_or_unit.nfc().collect();
+
+        if let Ok(representation) = self
+            .dimension_registry()
+            .get_base_representation_for_name(&name)
+        {
+            return Some(representation);
+        }
+
+        let PrefixParserResult::UnitIdentifier(_, _, _, full_name) =
+            self.prefix_transformer.prefix_parser.parse(&name)
+        else {
+            return None;
+        };
+
+        self.interpreter
+            .get_unit_registry()
+            .inner
+            .get_base_representation_for_name(&full_name)
+            .ok()
+            .map(|(representation, _)| representation)
+    }
+
     pub fn resolver(&self) -> &Resolver {
         &self.resolver
     }
 
+    /// Format a quantity as a plain string, according to the given options.
+    pub fn format(
+        &self,
+        quantity: &Quantity,
+        options: &FormatOptions,
+    ) -> std::result::Result<String, QuantityError> {
+        let quantity = match &options.target_unit {
+            Some(target_unit) => quantity.convert_to(target_unit)?,
+            None => quantity.clone(),
+        };
+        Ok(PlainTextFormatter {}
+            .format(&quantity.pretty_print_with_options(options), false)
+            .trim()
+            .to_string())
+    }
+
+    /// Normalize the formatting of Numbat code, e.g. `2*  3+4` becomes `2 × 3 + 4`. Every
+    /// statement is pretty-printed on a line of its own, and parsing the result yields the
+    /// same statements again. Identifiers and units are kept as written (`km` is not
+    /// expanded to `kilometer`), but comments are removed.
+    pub fn canonical_form(&self, code: &str) -> std::result::Result<String, ParseError> {
+        let statements = parse(code)?;
+        Ok(statements
+            .iter()
+            .map(|statement| statement.pretty_print().to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Choose a metric prefix for the unit of the given quantity, such that its value
+    /// is in the range from 1 to 1000 (e.g. `10 µm` instead of `0.00001 m`). Quantities
+    /// that are zero, whose unit does not accept metric prefixes, or whose unit is not a
+    /// single unit (like `m/s`) are returned unchanged.
+    pub fn suggest_unit_for(&self, quantity: &Quantity) -> Quantity {
+        let value = quantity.unsafe_value().to_f64();
+        if value == 0.0 || !value.is_finite() {
+            return quantity.clone();
+        }
+
+        let factors: Vec<_> = quantity.unit().iter().collect();
+        let [factor] = factors.as_slice() else {
+            return quantity.clone();
+        };
+        let Prefix::Metric(current_exponent) = factor.prefix else {
+            return quantity.clone();
+        };
+        if factor.exponent != Exponent::from_integer(1)
+            || !self
+                .prefix_transformer
+                .prefix_parser
+                .accepts_metric_prefixes(&factor.unit_id.name)
+        {
+            return quantity.clone();
+        }
+
+        // Only use prefixes for powers of 1000, like `milli` or `kilo`, but not `centi`
+        let magnitude = value.abs().log10() + current_exponent as f64;
+        let exponent = (3 * (magnitude / 3.0).floor() as i32).clamp(-30, 30);
+
+        let mut unit = (*factor).clone();
+        unit.prefix = Prefix::Metric(exponent);
+
+        // Multiplying with (instead of dividing by) an inexact small factor like 1e-6
+        // would introduce rounding errors, e.g. `10.000000000000002 µm`.
+        let shift = current_exponent - exponent;
+        let value = if shift >= 0 {
+            value * 10f64.powi(shift)
+        } else {
+            value / 10f64.powi(-shift)
+        };
+
+        Quantity::new_f64(value, Unit::from_factor(unit))
+    }
+
+    /// Type check the given code without executing it, and without modifying
+    /// the state of this context. In contrast to [`Context::interpret`], this
+    /// does not stop at the first type check error but reports all errors
+    /// from independent statements. This is mainly useful for editor integration.
+    pub fn check_all(&self, code: &str, code_source: CodeSource) -> Vec<NumbatError> {
+        let mut resolver = self.resolver.clone();
+        let statements = match resolver.resolve(code, code_source) {
+            Ok(statements) => statements,
+            Err(e) => return vec![NumbatError::ResolverError(e)],
+        };
+
+        let mut prefix_transformer = self.prefix_transformer.clone();
+        let transformed_statements = match prefix_transformer.transform(statements) {
+            Ok(statements) => statements,
+            Err(e) => return vec![NumbatError::NameResolutionError(e)],
+        };
+
+        let mut typechecker = self.typechecker.clone();
+        match typechecker.check_all(transformed_statements) {
+            Ok(_) => vec![],
+            Err(errors) => errors
+                .into_iter()
+                .map(NumbatError::TypeCheckError)
+                .collect(),
+        }
+    }
+
+    /// Type check the given code without executing it, and without modifying the state
+    /// of this context. Returns the signatures of all functions, units and dimensions that
+    /// are defined in the code (including those from modules that it imports for the first
+    /// time). This is mainly useful for generating documentation.
+    pub fn type_check_file(
+        &self,
+        code: &str,
+        code_source: CodeSource,
+    ) -> Result<DefinitionSummary> {
+        let statements = self
+            .resolver
+            .clone()
+            .resolve(code, code_source)
+            .map_err(NumbatError::ResolverError)?;
+
+        let transformed_statements = self
+            .prefix_transformer
+            .clone()
+            .transform(statements)
+            .map_err(NumbatError::NameResolutionError)?;
+
+        let mut typechecker = self.typechecker.clone();
+        let typed_statements = typechecker
+            .check(transformed_statements)
+            .map_err(NumbatError::TypeCheckError)?;
+
+        Ok(DefinitionSummary::from_statements(
+            &typed_statements,
+            typechecker.registry(),
+        ))
+    }
+
+    /// Check whether two expressions have the same physical dimension, e.g. `N * m`
+    /// and `J`, without evaluating them. The expressions are type checked in this
+    /// context, but definitions and variables are not modified.
+    pub fn are_dimensionally_equal(&self, expr_a: &str, expr_b: &str) -> Result<bool> {
+        let dimension_a = self.dimension_of(expr_a)?;
+        let dimension_b = self.dimension_of(expr_b)?;
+        Ok(dimension_a == dimension_b)
+    }
+
+    /// The type of the expression `code`, without evaluating it. The expression is type
+    /// checked in this context, but definitions and variables (including `ans` and `_`)
+    /// are not modified. Code that is not a single expression results in a parse error.
+    ///
+    /// The code is not added to the sources of this context, so errors can not be shown
+    /// with [`Context::print_diagnostic`].
+    ///
+    /// ```
+    /// use numbat::{resolver::CodeSource, Context};
+    ///
+    /// let mut ctx = Context::new_without_importer();
+    /// ctx.interpret(
+    ///     "dimension Length
+    ///      dimension Time
+    ///      dimension Velocity = Length / Time
+    ///      unit meter: Length
+    ///      unit second: Time",
+    ///     CodeSource::Internal,
+    /// )
+    /// .unwrap();
+    ///
+    /// let type_ = ctx.type_of("2 meter / second").unwrap();
+    /// assert_eq!(type_.to_string(), "Length / Time");
+    /// assert_eq!(
+    ///     type_
+    ///         .to_readable_type(ctx.dimension_registry())
+    ///         .to_string(),
+    ///     "Velocity"
+    /// );
+    /// ```
+    pub fn type_of(&self, code: &str) -> Result<Type> {
+        let expr = self.parse_expression(code)?;
+        self.typechecker
+            .type_of(&expr)
+            .map_err(NumbatError::TypeCheckError)
+    }
+
+    fn dimension_of(&self, expr: &str) -> Result<BaseRepresentation> {
+        let expr = self.parse_expression(expr)?;
+        let type_ = self
+            .typechecker
+            .type_of(&expr)
+            .map_err(NumbatError::TypeCheckError)?;
+
+        match &type_ {
+            Type::Dimension(dtype) if type_.is_closed() => Ok(dtype.to_base_representation()),
+            _ => Err(NumbatError::TypeCheckError(
+                TypeCheckError::ExpectedDimensionType(expr.full_span(), type_),
+            )),
+        }
+    }
+
+    /// Parse a single expression and resolve the names in it
+    fn parse_expression(&self, code: &str) -> Result<ast::Expression> {
+        let expr = self
+            .resolver
+            .resolve_expression(code)
+            .map_err(NumbatError::ResolverError)?;
+
+        let mut transformed_statements = self
+            .prefix_transformer
+            .clone()
+            .transform([ast::Statement::Expression(expr)])
+            .map_err(NumbatError::NameResolutionError)?;
+
+        match transformed_statements.pop() {
+            Some(ast::Statement::Expression(expr)) => Ok(expr),
+            _ => unreachable!("expressions are transformed into expressions"),
+        }
+    }
+
+    /// Extract a function that has been defined in this context, such that it can be
+    /// added to other contexts with [`Context::import_function`] without type checking
+    /// and compiling it again. Functions that make use of structs are not supported.
+    pub fn export_function(
+        &self,
+        name: &str,
+    ) -> std::result::Result<CompiledFunction, CompiledFunctionError> {
+        let (signature, metadata) = self
+            .typechecker
+            .lookup_function(name)
+            .ok_or_else(|| CompiledFunctionError::UnknownFunction(name.into()))?;
+
+        if compiled_function::contains_struct_type(&signature.fn_type) {
+            return Err(CompiledFunctionError::UsesStructs(name.into()));
+        }
+
+        let chunk = self.interpreter.export_function(name)?;
+
+        let dependencies = chunk
+            .dependencies()
+            .map(|dependency| {
+                let type_scheme = self
+                    .typechecker
+                    .lookup_identifier_type(dependency)
+                    .expect("dependencies of a function have been type checked");
+                (dependency.to_owned(), type_scheme)
+            })
+            .collect();
+
+        let code_source_id = signature.definition_span.code_source_id;
+        let file = self
+            .resolver
+            .files
+            .get(code_source_id)
+            .expect("functions are defined in a known code source");
+
+        Ok(CompiledFunction {
+            signature: signature.clone(),
+            metadata: metadata.clone(),
+            chunk,
+            dependencies,
+            code_source: self.resolver.get_code_source(code_source_id),
+            code_source_name: file.name().clone(),
+            code: file.source().clone(),
+        })
+    }
+
+    /// Add a function that has been extracted from a different context with
+    /// [`Context::export_function`]. The functions and global variables that it refers
+    /// to are looked up by name, and need to have the same types as in the original
+    /// context. If the function can not be imported, this context is not modified.
+    pub fn import_function(
+        &mut self,
+        function: &CompiledFunction,
+    ) -> std::result::Result<(), CompiledFunctionError> {
+        let mut resolver = self.resolver.clone();
+        let code_source_id = resolver.add_imported_code_source(
+            function.code_source.clone(),
+            function.code_source_name.clone(),
+            function.code.clone(),
+        );
+        let signature = function.signature.with_code_source_id(code_source_id);
+        let arity = signature.parameters.len();
+
+        let mut prefix_transformer = self.prefix_transformer.clone();
+        prefix_transformer
+            .prefix_parser
+            .add_other_identifier(function.name(), signature.definition_span)
+            .map_err(CompiledFunctionError::NameResolutionError)?;
+        prefix_transformer
+            .function_names
+            .push(function.name().into());
+
+        let mut typechecker = self.typechecker.clone();
+        typechecker.import_function(
+            signature,
+            function.metadata.clone(),
+            &function.dependencies,
+        )?;
+
+        let mut interpreter = self.interpreter.clone();
+        interpreter.import_function(&function.chunk, arity, code_source_id)?;
+
+        self.resolver = resolver;
+        self.prefix_transformer = prefix_transformer;
+        self.typechecker = typechecker;
+        self.interpreter = interpreter;
+
+        Ok(())
+    }
+
+    /// Define a global variable whose value is computed by `compute` whenever it is
+    /// accessed, e.g. for values that depend on the environment of the host application.
+    /// `type_annotation` is the type of the variable in Numbat syntax (e.g. `Length` or
+    /// `DateTime`), and `compute` needs to return values of that type. With
+    /// [`LazyEvaluation::Once`], the value is only computed on the first access.
+    ///
+    /// ```
+    /// use numbat::{resolver::CodeSource, value::Value, Context, LazyEvaluation};
+    ///
+    /// let mut ctx = Context::new_without_importer();
+    /// ctx.define_lazy_global("verbose", "Bool", LazyEvaluation::Once, || {
+    ///     Value::Boolean(std::env::var_os("VERBOSE").is_some())
+    /// })
+    /// .unwrap();
+    ///
+    /// let (_, result) = ctx.interpret("verbose || true", CodeSource::Internal).unwrap();
+    /// assert_eq!(result.value_as_string().unwrap(), "true");
+    /// ```
+    pub fn define_lazy_global(
+        &mut self,
+        name: &str,
+        type_annotation: &str,
+        evaluation: LazyEvaluation,
+        compute: impl Fn() -> Value + Send + Sync + 'static,
+    ) -> Result<()> {
+        // The declaration is added as a code source, such that errors can refer to it
+        let declaration = format!("{name}: {type_annotation}");
+        let code_source_id = self
+            .resolver
+            .add_code_source(CodeSource::Internal, &declaration);
+        let (definition_span, name, type_annotation) =
+            parser::parse_global_declaration(&declaration, code_source_id)
+                .map_err(|e| NumbatError::ResolverError(ResolverError::ParseErrors(vec![e])))?;
+
+        let mut prefix_transformer = self.prefix_transformer.clone();
+        prefix_transformer
+            .prefix_parser
+            .add_other_identifier(&name, definition_span)
+            .map_err(NumbatError::NameResolutionError)?;
+        prefix_transformer.variable_names.push(name.clone());
+
+        let mut typechecker = self.typechecker.clone();
+        typechecker
+            .add_lazy_global(&name, definition_span, &type_annotation)
+            .map_err(NumbatError::TypeCheckError)?;
+
+        self.interpreter
+            .add_lazy_global(&name, evaluation, Arc::new(compute));
+
+        self.prefix_transformer = prefix_transformer;
+        self.typechecker = typechecker;
+
+        Ok(())
+    }
+
     pub fn interpret(
         &mut self,
         code: &str,
@@ -541,11 +1123,19 @@ impl Context {
         code: &str,
         code_source: CodeSource,
     ) -> Result<(Vec<typed_ast::Statement>, InterpreterResult)> {
+        self.warnings.clear();
+
         let statements = self
             .resolver
             .resolve(code, code_source.clone())
             .map_err(NumbatError::ResolverError)?;
 
+        // A trailing semicolon suppresses the result of the last statement (`x^2;`)
+        let suppress_result = matches!(
+            statements.last(),
+            Some(ast::Statement::SuppressedExpression(_))
+        );
+
         let prefix_transformer_old = self.prefix_transformer.clone();
 
         let result = self
@@ -719,12 +1309,13 @@ impl Context {
                                 move |_: &m::Markup| { // ignore any print statements when loading this module asynchronously
                                 },
                             ),
+                            ..InterpreterSettings::default()
                         };
 
                         // We also call this from a thread at program startup, so if a user only starts
                         // to use currencies later on, this will already be available and return immediately.
                         // Otherwise, we fetch it now and make sure to block on this call.
-                        {
+                        if !self.interpreter.has_exchange_rates() {
                             let erc = ExchangeRatesCache::fetch();
 
                             if erc.is_none() {
@@ -755,6 +1346,8 @@ impl Context {
 
         let typed_statements = result?;
 
+        self.warnings = self.typechecker.take_warnings();
+
         let interpreter_old = self.interpreter.clone();
 
         let result = self.interpreter.interpret_statements(
@@ -779,7 +1372,20 @@ impl Context {
             self.interpreter = interpreter_old;
         }
 
-        let result = result.map_err(NumbatError::RuntimeError)?;
+        let mut result = result.map_err(NumbatError::RuntimeError)?;
+
+        if suppress_result {
+            result = InterpreterResult::Continue;
+        }
+
+        if let InterpreterResult::Value(Value::Quantity(quantity)) = &result {
+            if let Some(converted) = typed_statements
+                .last()
+                .and_then(|s| self.convert_to_display_system(s, quantity))
+            {
+                result = InterpreterResult::Value(Value::Quantity(converted));
+            }
+        }
 
         Ok((typed_statements, result))
     }
@@ -801,7 +1407,95 @@ impl Context {
         }
     }
 
+    /// Warnings from the last call to [`Context::interpret`] (or one of its
+    /// variants). Warnings do not prevent the code from being executed.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
     pub fn set_terminal_width(&mut self, width: Option<usize>) {
         self.terminal_width = width;
     }
+
+    /// Set the system of units in which results are displayed. See [`DisplaySystem`]
+    /// for details on how results are converted. Use `None` to display results in
+    /// the units they were computed in.
+    pub fn set_display_system(&mut self, display_system: Option<DisplaySystem>) {
+        self.display_system = display_system;
+    }
+
+    pub fn display_system(&self) -> Option<&DisplaySystem> {
+        self.display_system.as_ref()
+    }
+
+    fn lookup_unit(&self, name: &str) -> Option<Unit> {
+        let name: String = name.nfc().collect();
+
+        let PrefixParserResult::UnitIdentifier(_, prefix, unit_name, _) =
+            self.prefix_transformer.prefix_parser.parse(&name)
+        else {
+            return None;
+        };
+
+        self.interpreter
+            .get_defining_unit(&unit_name)
+            .map(|unit| unit.clone().with_prefix(prefix))
+    }
+
+    /// Convert the result of the given statement to the active display system, if possible.
+    fn convert_to_display_system(
+        &self,
+        statement: &typed_ast::Statement,
+        quantity: &Quantity,
+    ) -> Option<Quantity> {
+        let display_system = self.display_system.as_ref()?;
+
+        let expression = statement.as_expression()?;
+        if matches!(
+            expression,
+            typed_ast::Expression::BinaryOperator(_, typed_ast::BinaryOperator::ConvertTo, ..)
+        ) {
+            return None;
+        }
+
+        let TypeScheme::Quantified(0, qualified_type) = expression.get_type_scheme() else {
+            return None;
+        };
+        let Type::Dimension(dtype) = qualified_type.inner else {
+            return None;
+        };
+        if dtype.is_scalar() || !dtype.type_variables(true).is_empty() {
+            return None;
+        }
+        let dimension = dtype.to_base_representation();
+
+        let entries: Vec<(BaseRepresentation, Unit)> = display_system
+            .units()
+            .filter_map(|(dimension_name, unit_name)| {
+                let representation = self
+                    .dimension_registry()
+                    .get_base_representation_for_name(dimension_name)
+                    .ok()?;
+                Some((representation, self.lookup_unit(unit_name)?))
+            })
+            .collect();
+
+        let target_unit = match entries.iter().find(|(r, _)| r == &dimension) {
+            Some((_, unit)) => unit.clone(),
+            None => {
+                let mut unit = Unit::scalar();
+                for BaseRepresentationFactor(base_dimension, exponent) in dimension.iter() {
+                    let base = BaseRepresentation::from_factor(BaseRepresentationFactor(
+                        base_dimension.clone(),
+                        Exponent::from_integer(1),
+                    ));
+                    let (_, base_unit) = entries.iter().find(|(r, _)| r == &base)?;
+                    unit = unit * base_unit.clone().power(*exponent);
+                }
+                unit
+            }
+        };
+
+        quantity.convert_to(&target_unit).ok()
+    }
 }
@@ -91,9 +91,58 @@ impl ModuleImporter for FileSystemImporter {
 #[folder = "$CARGO_MANIFEST_DIR/modules/"]
 struct BuiltinAssets;
 
+/// Optional parts of the builtin prelude, see [`BuiltinModuleImporter::without`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PreludeFeature {
+    /// The `Money` dimension and the Euro (`units::currency`).
+    Currencies,
+    /// Humorous and otherwise obscure units like `smoot` or `furlong`.
+    ObscureUnits,
+    /// The `element` function for chemical elements.
+    ChemicalElements,
+}
+
+impl PreludeFeature {
+    fn modules(self) -> &'static [&'static str] {
+        match self {
+            PreludeFeature::Currencies => &["units::currency"],
+            PreludeFeature::ObscureUnits => &["units::humorous", "units::fff"],
+            PreludeFeature::ChemicalElements => &["chemistry::elements"],
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
+pub struct BuiltinModuleImporter {
+    excluded_features: Vec<PreludeFeature>,
+}
+
+impl BuiltinModuleImporter {
+    /// Do not load the modules of the given feature from the prelude. They can
+    /// still be imported explicitly.
+    pub fn without(mut self, feature: PreludeFeature) -> Self {
+        self.excluded_features.push(feature);
+        self
+    }
 
-pub struct BuiltinModuleImporter {}
+    fn filter_prelude(&self, code: String) -> String {
+        if self.excluded_features.is_empty() {
+            return code;
+        }
+
+        let excluded_imports: Vec<String> = self
+            .excluded_features
+            .iter()
+            .flat_map(|feature| feature.modules())
+            .map(|module| format!("use {module}"))
+            .collect();
+
+        code.lines()
+            .filter(|line| !excluded_imports.iter().any(|import| line.trim() == import))
+            .map(|line| format!("{line}\n"))
+            .collect()
+    }
+}
 
 impl ModuleImporter for BuiltinModuleImporter {
     fn import(&self, module_path: &ModulePath) -> Option<(String, Option<PathBuf>)> {
@@ -109,6 +158,13 @@ impl ModuleImporter for BuiltinModuleImporter {
                 let content = embedded_file.data.into_owned();
                 String::from_utf8(content).expect("Numbat modules are properly UTF-8 encoded")
             })
+            .map(|content| {
+                if module_path.0 == ["prelude"] {
+                    self.filter_prelude(content)
+                } else {
+                    content
+                }
+            })
             .map(|content| {
                 let user_facing_path = PathBuf::from("<builtin>").join("modules").join(path);
                 (content, Some(user_facing_path))
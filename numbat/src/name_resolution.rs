@@ -17,8 +17,13 @@ pub enum NameResolutionError {
         original_item_type: Option<String>,
     },
 
-    #[error("Reserved identifier")]
-    ReservedIdentifier(Span),
+    #[error("Reserved identifier '{0}'")]
+    ReservedIdentifier(String, Span),
+
+    #[error(
+        "Decorators of the forward-declared unit '{0}' need to be attached to its declaration"
+    )]
+    DecoratorsOnDeclaredUnit(String, Span),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -61,7 +66,11 @@ impl Namespace {
                 return Ok(());
             }
 
-            if allow_override && original_item_type == &item_type {
+            // Functions with a body may also shadow foreign (builtin) functions
+            let is_shadowed_foreign_function =
+                item_type == "function" && original_item_type == "foreign function";
+            if allow_override && (original_item_type == &item_type || is_shadowed_foreign_function)
+            {
                 return Ok(());
             }
 
@@ -2,19 +2,22 @@
 //!
 //! Grammar:
 //! ```txt
-//! statement       ::=   variable_decl | struct_decl | function_decl | dimension_decl | unit_decl | module_import | procedure_call | expression
+//! statement       ::=   variable_decl | struct_decl | function_decl | dimension_decl | unit_decl | operator_decl | module_import | procedure_call | expression
 //!
 //! variable_decl   ::=   "let" identifier ( ":" type_annotation ) ? "=" expression
 //! struct_decl     ::=   "struct" identifier "{" ( identifier ":" type_annotation "," )* ( identifier ":" type_annotation "," ? ) ? "}"
-//! function_decl   ::=   "fn" identifier ( fn_decl_generic ) ? fn_decl_param ( "->" type_annotation ) ? ( "=" expression ) ?
+//! function_decl   ::=   "fn" identifier ( fn_decl_generic ) ? fn_decl_param ( "->" type_annotation ) ? ( fn_decl_where ) ? ( "=" ( expression | fn_body_block ) ) ?
+//! fn_body_block   ::=   "{" ( ( "let" identifier ( ":" type_annotation ) ? "=" expression | function_decl ) ( ";" | "\n" ) ) * expression "}"
 //! fn_decl_generic ::=   "<" ( identifier "," ) * identifier ">"
+//! fn_decl_where   ::=   "where" ( identifier ":" dimension_expr "," ) * identifier ":" dimension_expr
 //! fn_decl_param   ::=   "(" ( identifier ( ":" type_annotation ) ? "," )* ( identifier ( ":" type_annotation ) ) ? ")"
 //! dimension_decl  ::=   "dimension" identifier ( "=" dimension_expr ) *
 //! unit_decl       ::=   decorator * "unit" ( ":" dimension_expr ) ? ( "=" expression ) ?
+//! operator_decl   ::=   "operator" user_operator "=" identifier
 //! module_import   ::=   "use" ident ( "::" ident) *
-//! procedure_call  ::=   ( "print" | "assert" | "assert_eq" | "type" ) "(" arguments? ")"
+//! procedure_call  ::=   ( "print" | "assert" | "assert_eq" | "type" | "debug" | "csv" ) "(" arguments? ")"
 //!
-//! decorator       ::=   "@" ( "metric_prefixes" | "binary_prefixes" | ( "aliases(" list_of_aliases ")" ) )
+//! decorator       ::=   "@" ( "metric_prefixes" | "binary_prefixes" | ( "aliases(" list_of_aliases ")" ) | ( "canonical(" identifier ")" ) )
 //!
 //! type_annotation ::=   "Bool" | "String" | "List<" type ">" | dimension_expr
 //! dimension_expr  ::=   dim_factor
@@ -30,18 +33,20 @@
 //! logical_or      ::=   logical_and ( "||" logical_and ) *
 //! logical_and     ::=   logical_neg ( "&&" logical_neg ) *
 //! logical_neg     ::=   ( "!" logical_neg) | comparison
-//! comparison      ::=   term ( (">" | ">="| "≥" | "<" | "<=" | "≤" | "==" | "!=" | "≠" ) term ) *
+//! comparison      ::=   user_op_expr ( (">" | ">="| "≥" | "<" | "<=" | "≤" | "==" | "!=" | "≠" ) user_op_expr ) *
+//! user_op_expr    ::=   term ( user_operator term ) *
 //! term            ::=   factor ( ( "+" | "-") factor ) *
-//! factor          ::=   unary ( ( "*" | "/") per_factor ) *
+//! factor          ::=   unary ( ( "*" | "/" | "mod" ) per_factor ) *
 //! per_factor      ::=   unary ( "per" unary ) *
 //! unary           ::=   ( ( minus | plus ) unary ) | ifactor
 //! ifactor         ::=   power ( " " power ) *
 //! power           ::=   factorial ( "^" "-" ? power ) ?
 //! factorial       ::=   unicode_power "!" *
 //! unicode_power   ::=   call ( "⁻" ? ( "¹" | "²" | "³" | "⁴" | "⁵" | "⁶" | "⁷" | "⁸" | "⁹" ) ) ?
-//! call            ::=   primary ( ( "(" arguments? ")" ) | "." identifier ) *
+//! call            ::=   primary ( ( "(" arguments? ")" ) | "." "to" "(" expression ")" | "." identifier ) *
 //! arguments       ::=   expression ( "," expression ) *
-//! primary         ::=   boolean | string | hex_number | oct_number | bin_number | number | identifier ( struct_expr ? ) | typed_hole | list_expr | "(" expression ")"
+//! primary         ::=   boolean | string | hex_number | oct_number | bin_number | number ( unit_annot ? ) | identifier ( struct_expr ? ) | typed_hole | list_expr | "(" expression ")"
+//! unit_annot      ::=   "[" expression "]"   (directly following the number, without whitespace)
 //! struct_expr     ::=   "{" ( identifier ":" type_annotation "," )* ( identifier ":" expression "," ? ) ? "}"
 //! list_expr       ::=   "[]" | "[" expression ( "," expression ) * "]"
 //!
@@ -59,20 +64,23 @@
 //! minus           ::=   "-"
 //! multiply        ::=   "*" | "×" | "·"
 //! divide          ::=   "/" | "÷"
+//! user_operator   ::=   "<>" | "<*>" | "<|>"
 //! string          ::=   '"' [^"]* '"'
 //! ```
 
 use crate::arithmetic::{Exponent, Rational};
 use crate::ast::{
-    BinaryOperator, Expression, ProcedureKind, Statement, StringPart, TypeAnnotation,
-    TypeExpression, TypeParameterBound, UnaryOperator,
+    BinaryOperator, Expression, LocalDefinition, LocalVariable, ProcedureKind, Statement,
+    StringPart, TypeAnnotation, TypeExpression, TypeParameterBound, UnaryOperator,
 };
 use crate::decorator::{self, Decorator};
 use crate::number::Number;
-use crate::prefix_parser::AcceptsPrefix;
+use crate::prefix::AcceptsPrefix;
 use crate::resolver::ModulePath;
 use crate::span::Span;
-use crate::tokenizer::{Token, TokenKind, TokenizerError, TokenizerErrorKind};
+use crate::tokenizer::{
+    self, Token, TokenKind, TokenizerError, TokenizerErrorKind, VERSION_DIRECTIVE,
+};
 
 use num_traits::{CheckedDiv, FromPrimitive, Zero};
 use thiserror::Error;
@@ -90,6 +98,9 @@ pub enum ParseErrorKind {
     #[error("Missing closing parenthesis ')'")]
     MissingClosingParen,
 
+    #[error("Missing closing bracket ']' in unit annotation")]
+    MissingClosingBracketInUnitAnnotation,
+
     #[error("Trailing characters: '{0}'")]
     TrailingCharacters(String),
 
@@ -141,6 +152,19 @@ pub enum ParseErrorKind {
     #[error("Expected '=' or ':' after identifier in unit definition")]
     ExpectedColonOrEqualAfterUnitIdentifier,
 
+    #[error(
+        "Expected dimension annotation in unit declaration (`unit <name>: <Dimension> = ...`)"
+    )]
+    ExpectedDimensionInUnitDeclaration,
+
+    #[error(
+        "Invalid version '{0}' in version directive, expected a version like `#!version 1.12`"
+    )]
+    InvalidVersionDirective(String),
+
+    #[error("This code requires Numbat {0} or newer, but this is Numbat {1}")]
+    UnsupportedLanguageVersion(String, String),
+
     #[error("Expected ':' after a field name")]
     ExpectedColonAfterFieldName,
 
@@ -222,14 +246,47 @@ pub enum ParseErrorKind {
     #[error("Expected ',' or ']' in list expression")]
     ExpectedCommaOrRightBracketInList,
 
+    #[error("Missing closing bracket ']' in interval expression")]
+    MissingClosingBracketInInterval,
+
     #[error("Unknown bound '{0}' in type parameter definition")]
     UnknownBound(String),
 
     #[error("Expected bound in type parameter definition")]
     ExpectedBoundInTypeParameterDefinition,
 
+    #[error("Unknown type parameter '{0}' in where clause")]
+    UnknownTypeParameterInWhereClause(String),
+
+    #[error("Expected type parameter name in where clause")]
+    ExpectedTypeParameterNameInWhereClause,
+
+    #[error("Expected ':' after type parameter name in where clause")]
+    ExpectedColonInWhereClause,
+
     #[error("Empty string interpolation")]
     EmptyStringInterpolation,
+
+    #[error("Expected function name after '=' in operator definition")]
+    ExpectedFunctionNameInOperatorDefinition,
+
+    #[error("Expected opening parenthesis '(' after '.to'")]
+    ExpectedLeftParenAfterTo,
+
+    #[error("Canonical name '{0}' is neither the name of the unit nor one of its aliases")]
+    UnknownCanonicalName(String),
+
+    #[error("Expected ';' or a new line after the definition of a local variable")]
+    ExpectedSemicolonOrNewlineAfterLocalVariable,
+
+    #[error("Expected ';' or a new line after the definition of a local function")]
+    ExpectedSemicolonOrNewlineAfterLocalFunction,
+
+    #[error("Functions that are defined in a function body need a body themselves")]
+    LocalFunctionWithoutBody,
+
+    #[error("Expected '}}' at the end of the function body")]
+    ExpectedRightCurlyAfterFunctionBody,
 }
 
 #[derive(Debug, Clone, Error)]
@@ -253,6 +310,8 @@ static PROCEDURES: &[TokenKind] = &[
     TokenKind::ProcedureAssert,
     TokenKind::ProcedureAssertEq,
     TokenKind::ProcedureType,
+    TokenKind::ProcedureDebug,
+    TokenKind::ProcedureCsv,
 ];
 
 struct Parser<'a> {
@@ -286,8 +345,13 @@ impl<'a> Parser<'a> {
         self.skip_empty_lines();
 
         while !self.is_at_end() {
-            match self.statement() {
-                Ok(statement) => statements.push(statement),
+            let statement = match self.match_exact(TokenKind::VersionDirective) {
+                Some(directive) => Self::check_version_directive(directive).map(|()| None),
+                None => self.statement().map(Some),
+            };
+
+            match statement {
+                Ok(statement) => statements.extend(statement),
                 Err(e) => {
                     errors.push(e);
                     self.recover_from_error();
@@ -295,9 +359,25 @@ impl<'a> Parser<'a> {
             }
 
             match self.peek().kind {
-                TokenKind::Newline => {
-                    // Skip over empty lines
-                    while self.match_exact(TokenKind::Newline).is_some() {}
+                TokenKind::Newline | TokenKind::Semicolon => {
+                    // Statements are separated by new lines or semicolons. Skip over
+                    // empty lines.
+                    let mut has_semicolon = false;
+                    while let Some(token) =
+                        self.match_any(&[TokenKind::Newline, TokenKind::Semicolon])
+                    {
+                        has_semicolon |= token.kind == TokenKind::Semicolon;
+                    }
+
+                    // A semicolon after the last expression suppresses its result
+                    if has_semicolon && self.is_at_end() {
+                        match statements.pop() {
+                            Some(Statement::Expression(expr)) => {
+                                statements.push(Statement::SuppressedExpression(expr))
+                            }
+                            statement => statements.extend(statement),
+                        }
+                    }
                 }
                 TokenKind::Eof => {
                     break;
@@ -328,6 +408,32 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Makes sure that the version required by a `#!version …` directive is not newer
+    /// than the version of this crate.
+    fn check_version_directive(directive: &Token) -> Result<()> {
+        let required = directive.lexeme[VERSION_DIRECTIVE.len()..].trim();
+
+        let required_version = parse_version(required).ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::InvalidVersionDirective(required.into()),
+                directive.span,
+            )
+        })?;
+        let current_version = parse_version(crate::version()).expect("valid crate version");
+
+        if required_version > current_version {
+            return Err(ParseError::new(
+                ParseErrorKind::UnsupportedLanguageVersion(
+                    required.into(),
+                    crate::version().into(),
+                ),
+                directive.span,
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Must be called after encountering an error.
     fn recover_from_error(&mut self) {
         // Skip all the tokens until we encounter a newline or EoF.
@@ -363,9 +469,9 @@ impl<'a> Parser<'a> {
         }
 
         let mut identifiers: Vec<(String, Option<AcceptsPrefix>)> =
-            vec![(self.identifier()?, self.accepts_prefix()?)];
+            vec![(self.alias_name()?, self.accepts_prefix()?)];
         while self.match_exact(TokenKind::Comma).is_some() {
-            identifiers.push((self.identifier()?, self.accepts_prefix()?));
+            identifiers.push((self.alias_name()?, self.accepts_prefix()?));
         }
 
         if self.match_exact(TokenKind::RightParen).is_none() {
@@ -378,6 +484,109 @@ impl<'a> Parser<'a> {
         Ok(identifiers)
     }
 
+    /// Parse the remainder of a function body block (after the opening '{'), i.e.
+    /// a number of local variable and function definitions, followed by the actual
+    /// body expression.
+    fn function_body_block(&mut self) -> Result<(Vec<LocalDefinition>, Expression)> {
+        let mut local_definitions = vec![];
+
+        loop {
+            while self
+                .match_any(&[TokenKind::Newline, TokenKind::Semicolon])
+                .is_some()
+            {}
+
+            if self.peek().kind == TokenKind::Fn {
+                // The decorators of the enclosing function are only attached to it after its
+                // body has been parsed, so they must not end up on the local function.
+                let decorators = std::mem::take(&mut self.decorator_stack);
+                let span = self.peek().span;
+                let statement = self.statement()?;
+                self.decorator_stack = decorators;
+
+                if let Statement::DefineFunction { body: None, .. } = statement {
+                    return Err(ParseError::new(
+                        ParseErrorKind::LocalFunctionWithoutBody,
+                        span,
+                    ));
+                }
+
+                if self
+                    .match_any(&[TokenKind::Newline, TokenKind::Semicolon])
+                    .is_none()
+                {
+                    return Err(ParseError::new(
+                        ParseErrorKind::ExpectedSemicolonOrNewlineAfterLocalFunction,
+                        self.peek().span,
+                    ));
+                }
+
+                local_definitions.push(LocalDefinition::Function(Box::new(statement)));
+                continue;
+            }
+
+            if self.match_exact(TokenKind::Let).is_none() {
+                break;
+            }
+
+            let Some(identifier) = self.match_exact(TokenKind::Identifier) else {
+                return Err(ParseError::new(
+                    ParseErrorKind::ExpectedIdentifierAfterLet,
+                    self.peek().span,
+                ));
+            };
+            let identifier_span = identifier.span;
+            let identifier = identifier.lexeme.clone();
+
+            let type_annotation = if self.match_exact(TokenKind::Colon).is_some() {
+                Some(self.type_annotation()?)
+            } else {
+                None
+            };
+
+            if self.match_exact(TokenKind::Equal).is_none() {
+                return Err(ParseError::new(
+                    ParseErrorKind::ExpectedEqualOrColonAfterLetIdentifier,
+                    self.peek().span,
+                ));
+            }
+            self.skip_empty_lines();
+            let expr = self.expression()?;
+
+            if self
+                .match_any(&[TokenKind::Newline, TokenKind::Semicolon])
+                .is_none()
+            {
+                return Err(ParseError::new(
+                    ParseErrorKind::ExpectedSemicolonOrNewlineAfterLocalVariable,
+                    self.peek().span,
+                ));
+            }
+
+            local_definitions.push(LocalDefinition::Variable(LocalVariable {
+                identifier_span,
+                identifier,
+                expr,
+                type_annotation,
+            }));
+        }
+
+        let body = self.expression()?;
+
+        while self
+            .match_any(&[TokenKind::Newline, TokenKind::Semicolon])
+            .is_some()
+        {}
+        if self.match_exact(TokenKind::RightCurly).is_none() {
+            return Err(ParseError::new(
+                ParseErrorKind::ExpectedRightCurlyAfterFunctionBody,
+                self.peek().span,
+            ));
+        }
+
+        Ok((local_definitions, body))
+    }
+
     fn statement(&mut self) -> Result<Statement> {
         if !(self.peek().kind == TokenKind::At
             || self.peek().kind == TokenKind::Unit
@@ -392,6 +601,14 @@ impl<'a> Parser<'a> {
         }
 
         if self.match_exact(TokenKind::Let).is_some() {
+            // 'mut' is not a keyword, such that it can still be used as an identifier
+            let mutable = self.peek().kind == TokenKind::Identifier
+                && self.peek().lexeme == "mut"
+                && self.peek_next().kind == TokenKind::Identifier;
+            if mutable {
+                self.advance();
+            }
+
             if let Some(identifier) = self.match_exact(TokenKind::Identifier) {
                 let identifier_span = self.last().unwrap().span;
 
@@ -425,6 +642,7 @@ impl<'a> Parser<'a> {
                         expr,
                         type_annotation,
                         decorators,
+                        mutable,
                     })
                 }
             } else {
@@ -536,11 +754,59 @@ impl<'a> Parser<'a> {
                     None
                 };
 
-                let body = if self.match_exact(TokenKind::Equal).is_none() {
-                    None
+                // Parsing the (optional) where clause. Note that 'where' is not a
+                // keyword, similar to the 'Dim' bound above.
+                if self.peek().kind == TokenKind::Identifier && self.peek().lexeme == "where" {
+                    self.advance();
+                    loop {
+                        let Some(type_parameter_name) = self.match_exact(TokenKind::Identifier)
+                        else {
+                            return Err(ParseError {
+                                kind: ParseErrorKind::ExpectedTypeParameterNameInWhereClause,
+                                span: self.peek().span,
+                            });
+                        };
+                        let type_parameter_name_span = type_parameter_name.span;
+                        let type_parameter_name = type_parameter_name.lexeme.clone();
+
+                        if self.match_exact(TokenKind::Colon).is_none() {
+                            return Err(ParseError {
+                                kind: ParseErrorKind::ExpectedColonInWhereClause,
+                                span: self.peek().span,
+                            });
+                        }
+
+                        let dexpr = self.dimension_expression()?;
+
+                        let Some((_, _, bound)) = type_parameters
+                            .iter_mut()
+                            .find(|(_, name, _)| *name == type_parameter_name)
+                        else {
+                            return Err(ParseError {
+                                kind: ParseErrorKind::UnknownTypeParameterInWhereClause(
+                                    type_parameter_name,
+                                ),
+                                span: type_parameter_name_span,
+                            });
+                        };
+                        *bound = Some(TypeParameterBound::Equal(dexpr));
+
+                        if self.match_exact(TokenKind::Comma).is_none() {
+                            break;
+                        }
+                    }
+                }
+
+                let (local_definitions, body) = if self.match_exact(TokenKind::Equal).is_none() {
+                    (vec![], None)
                 } else {
                     self.skip_empty_lines();
-                    Some(self.expression()?)
+                    if self.match_exact(TokenKind::LeftCurly).is_some() {
+                        let (local_definitions, body) = self.function_body_block()?;
+                        (local_definitions, Some(body))
+                    } else {
+                        (vec![], Some(self.expression()?))
+                    }
                 };
 
                 if decorator::contains_aliases(&self.decorator_stack) {
@@ -558,6 +824,7 @@ impl<'a> Parser<'a> {
                     function_name: fn_name.lexeme.clone(),
                     type_parameters,
                     parameters,
+                    local_definitions,
                     body,
                     return_type_annotation,
                     decorators,
@@ -620,7 +887,24 @@ impl<'a> Parser<'a> {
                             });
                         }
                     }
-                    "url" | "name" | "description" => {
+                    "canonical" => {
+                        if self.match_exact(TokenKind::LeftParen).is_some() {
+                            let canonical = self.identifier()?;
+                            if self.match_exact(TokenKind::RightParen).is_none() {
+                                return Err(ParseError::new(
+                                    ParseErrorKind::MissingClosingParen,
+                                    self.peek().span,
+                                ));
+                            }
+                            Decorator::Canonical(canonical)
+                        } else {
+                            return Err(ParseError {
+                                kind: ParseErrorKind::ExpectedLeftParenAfterDecorator,
+                                span: self.peek().span,
+                            });
+                        }
+                    }
+                    "url" | "name" | "description" | "plural" | "example" => {
                         if self.match_exact(TokenKind::LeftParen).is_some() {
                             if let Some(token) = self.match_exact(TokenKind::StringFixed) {
                                 if self.match_exact(TokenKind::RightParen).is_none() {
@@ -636,6 +920,8 @@ impl<'a> Parser<'a> {
                                     "url" => Decorator::Url(content),
                                     "name" => Decorator::Name(content),
                                     "description" => Decorator::Description(content),
+                                    "plural" => Decorator::Plural(content),
+                                    "example" => Decorator::Example(content),
                                     _ => unreachable!(),
                                 }
                             } else {
@@ -671,7 +957,7 @@ impl<'a> Parser<'a> {
                 })
             }
         } else if self.match_exact(TokenKind::Unit).is_some() {
-            if let Some(identifier) = self.match_exact(TokenKind::Identifier) {
+            if let Some(identifier) = self.unit_name() {
                 let identifier_span = self.last().unwrap().span;
                 let (type_annotation_span, dexpr) = if self.match_exact(TokenKind::Colon).is_some()
                 {
@@ -686,8 +972,36 @@ impl<'a> Parser<'a> {
                 let mut decorators = vec![];
                 std::mem::swap(&mut decorators, &mut self.decorator_stack);
 
+                if let Some(canonical) = decorator::canonical(&decorators) {
+                    if !decorator::name_and_aliases(&unit_name, &decorators)
+                        .any(|(name, _)| *name == canonical)
+                    {
+                        return Err(ParseError {
+                            kind: ParseErrorKind::UnknownCanonicalName(canonical),
+                            span: identifier_span,
+                        });
+                    }
+                }
+
                 if self.match_exact(TokenKind::Equal).is_some() {
                     self.skip_empty_lines();
+
+                    if self.match_exact(TokenKind::Ellipsis).is_some() {
+                        // `unit foo: Length = ...` declares a unit whose value is defined later
+                        let Some(dexpr) = dexpr else {
+                            return Err(ParseError {
+                                kind: ParseErrorKind::ExpectedDimensionInUnitDeclaration,
+                                span: self.last().unwrap().span,
+                            });
+                        };
+                        return Ok(Statement::DeclareUnit(
+                            identifier_span,
+                            unit_name,
+                            dexpr,
+                            decorators,
+                        ));
+                    }
+
                     let expr = self.expression()?;
                     Ok(Statement::DefineDerivedUnit {
                         identifier_span,
@@ -812,6 +1126,8 @@ impl<'a> Parser<'a> {
                 TokenKind::ProcedureAssert => ProcedureKind::Assert,
                 TokenKind::ProcedureAssertEq => ProcedureKind::AssertEq,
                 TokenKind::ProcedureType => ProcedureKind::Type,
+                TokenKind::ProcedureDebug => ProcedureKind::Debug,
+                TokenKind::ProcedureCsv => ProcedureKind::Csv,
                 _ => unreachable!(),
             };
 
@@ -827,6 +1143,42 @@ impl<'a> Parser<'a> {
                     span: self.peek().span,
                 })
             }
+        } else if self.peek().kind == TokenKind::Identifier
+            && self.peek().lexeme == "operator"
+            && self.peek_next().kind == TokenKind::UserOperator
+            && self.tokens.get(self.current + 2).map(|t| t.kind) == Some(TokenKind::Equal)
+        {
+            // 'operator' is not a keyword, such that it can still be used as an identifier
+            self.advance();
+            let symbol = self.match_exact(TokenKind::UserOperator).unwrap();
+            self.advance(); // '='
+
+            if let Some(function_name) = self.match_exact(TokenKind::Identifier) {
+                Ok(Statement::DefineOperator {
+                    symbol_span: symbol.span,
+                    symbol: symbol.lexeme.clone(),
+                    function_name_span: function_name.span,
+                    function_name: function_name.lexeme.clone(),
+                })
+            } else {
+                Err(ParseError {
+                    kind: ParseErrorKind::ExpectedFunctionNameInOperatorDefinition,
+                    span: self.peek().span,
+                })
+            }
+        } else if self.peek().kind == TokenKind::Identifier
+            && self.peek_next().kind == TokenKind::Equal
+        {
+            let identifier = self.match_exact(TokenKind::Identifier).unwrap();
+            let identifier_span = identifier.span;
+            self.advance(); // '='
+            self.skip_empty_lines();
+
+            Ok(Statement::ReassignVariable {
+                identifier_span,
+                identifier: identifier.lexeme.clone(),
+                expr: self.expression()?,
+            })
         } else {
             Ok(Statement::Expression(self.expression()?))
         }
@@ -861,6 +1213,29 @@ impl<'a> Parser<'a> {
         self.postfix_apply()
     }
 
+    /// Match the name of a unit or one of its aliases. Keywords are accepted here, such
+    /// that they are reported as reserved identifiers when the unit is defined.
+    fn unit_name(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        if token.kind == TokenKind::Identifier || tokenizer::is_keyword(&token.lexeme) {
+            self.advance();
+            Some(token)
+        } else {
+            None
+        }
+    }
+
+    fn alias_name(&mut self) -> Result<String> {
+        if let Some(name) = self.unit_name() {
+            Ok(name.lexeme.clone())
+        } else {
+            Err(ParseError::new(
+                ParseErrorKind::ExpectedIdentifier,
+                self.peek().span,
+            ))
+        }
+    }
+
     fn identifier(&mut self) -> Result<String> {
         if let Some(identifier) = self.match_exact(TokenKind::Identifier) {
             Ok(identifier.lexeme.clone())
@@ -933,11 +1308,29 @@ impl<'a> Parser<'a> {
     }
 
     fn conversion(&mut self) -> Result<Expression> {
-        self.parse_binop(
-            &[TokenKind::Arrow, TokenKind::To],
-            |_| BinaryOperator::ConvertTo,
-            Self::logical_or,
-        )
+        let mut expr = self.logical_or()?;
+        while self.match_any(&[TokenKind::Arrow, TokenKind::To]).is_some() {
+            let span_op = self.last().unwrap().span;
+
+            if let Some(si) = self.match_exact(TokenKind::SI) {
+                // `-> SI` converts to the base unit representation
+                expr = Expression::UnaryOperator {
+                    op: UnaryOperator::ConvertToBaseUnits,
+                    expr: Box::new(expr),
+                    span_op: span_op.extend(&si.span),
+                };
+            } else {
+                let rhs = self.logical_or()?;
+
+                expr = Expression::BinaryOperator {
+                    op: BinaryOperator::ConvertTo,
+                    lhs: Box::new(expr),
+                    rhs: Box::new(rhs),
+                    span_op: Some(span_op),
+                };
+            }
+        }
+        Ok(expr)
     }
 
     fn logical_or(&mut self) -> Result<Expression> {
@@ -990,10 +1383,25 @@ impl<'a> Parser<'a> {
                 TokenKind::NotEqual => BinaryOperator::NotEqual,
                 _ => unreachable!(),
             },
-            Self::term,
+            Self::user_operator,
         )
     }
 
+    fn user_operator(&mut self) -> Result<Expression> {
+        let mut expr = self.term()?;
+        while let Some(symbol) = self.match_exact(TokenKind::UserOperator) {
+            let rhs = self.term()?;
+
+            expr = Expression::UserOperator {
+                symbol: symbol.lexeme.clone(),
+                lhs: Box::new(expr),
+                rhs: Box::new(rhs),
+                span_op: symbol.span,
+            };
+        }
+        Ok(expr)
+    }
+
     fn term(&mut self) -> Result<Expression> {
         self.parse_binop(
             &[TokenKind::Plus, TokenKind::Minus],
@@ -1007,15 +1415,38 @@ impl<'a> Parser<'a> {
     }
 
     fn factor(&mut self) -> Result<Expression> {
-        self.parse_binop(
-            &[TokenKind::Multiply, TokenKind::Divide],
-            |matched| match matched {
-                TokenKind::Multiply => BinaryOperator::Mul,
-                TokenKind::Divide => BinaryOperator::Div,
-                _ => unreachable!(),
-            },
-            Self::per_factor,
-        )
+        let mut expr = self.per_factor()?;
+        while let Some(op) = self.match_factor_operator() {
+            let span_op = Some(self.last().unwrap().span);
+            let rhs = self.per_factor()?;
+
+            expr = Expression::BinaryOperator {
+                op,
+                lhs: Box::new(expr),
+                rhs: Box::new(rhs),
+                span_op,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn match_factor_operator(&mut self) -> Option<BinaryOperator> {
+        if self.match_exact(TokenKind::Multiply).is_some() {
+            Some(BinaryOperator::Mul)
+        } else if self.match_exact(TokenKind::Divide).is_some() {
+            Some(BinaryOperator::Div)
+        } else if self.next_token_is_mod_operator() {
+            self.advance();
+            Some(BinaryOperator::Mod)
+        } else {
+            None
+        }
+    }
+
+    /// `mod` is not a keyword, such that the `mod` function can still be called. It is
+    /// only treated as the modulo operator if it follows an operand, as in `7 mod 3`.
+    fn next_token_is_mod_operator(&self) -> bool {
+        self.peek().kind == TokenKind::Identifier && self.peek().lexeme == "mod"
     }
 
     fn per_factor(&mut self) -> Result<Expression> {
@@ -1167,10 +1598,40 @@ impl<'a> Parser<'a> {
                     args,
                 );
             } else if self.match_exact(TokenKind::Period).is_some() {
+                if self.match_exact(TokenKind::To).is_some() {
+                    // `x.to(unit)` is sugar for `x -> unit`
+                    let span_op = self.last().unwrap().span;
+                    if self.match_exact(TokenKind::LeftParen).is_none() {
+                        return Err(ParseError::new(
+                            ParseErrorKind::ExpectedLeftParenAfterTo,
+                            self.peek().span,
+                        ));
+                    }
+                    self.match_exact(TokenKind::Newline);
+                    let rhs = self.expression()?;
+                    self.match_exact(TokenKind::Newline);
+                    if self.match_exact(TokenKind::RightParen).is_none() {
+                        return Err(ParseError::new(
+                            ParseErrorKind::MissingClosingParen,
+                            self.peek().span,
+                        ));
+                    }
+
+                    expr = Expression::BinaryOperator {
+                        op: BinaryOperator::ConvertTo,
+                        lhs: Box::new(expr),
+                        rhs: Box::new(rhs),
+                        span_op: Some(span_op),
+                    };
+                    continue;
+                }
+
                 let ident = self.identifier()?;
                 let ident_span = self.last().unwrap().span;
                 let full_span = expr.full_span().extend(&ident_span);
 
+                // A call `x.f(a, b)` is parsed as a call of the field `f`. If `x` has no
+                // such field, the type checker turns it into `f(x, a, b)`.
                 expr = Expression::AccessField(full_span, ident_span, Box::new(expr), ident)
             } else {
                 return Ok(expr);
@@ -1212,10 +1673,11 @@ impl<'a> Parser<'a> {
 
         if let Some(num) = self.match_exact(TokenKind::Number) {
             let num_string = num.lexeme.replace('_', "");
-            Ok(Expression::Scalar(
+            let scalar = Expression::Scalar(
                 self.last().unwrap().span,
                 Number::from_f64(num_string.parse::<f64>().unwrap()),
-            ))
+            );
+            self.unit_annotation(scalar)
         } else if let Some(hex_int) = self.match_exact(TokenKind::IntegerWithBase(16)) {
             let span = self.last().unwrap().span;
             Ok(Expression::Scalar(
@@ -1261,6 +1723,10 @@ impl<'a> Parser<'a> {
 
                 self.skip_empty_lines();
 
+                if elements.len() == 1 && self.match_exact(TokenKind::DotDot).is_some() {
+                    return self.interval(span, elements.remove(0));
+                }
+
                 if self.match_exact(TokenKind::Comma).is_none()
                     && self.peek().kind != TokenKind::RightBracket
                 {
@@ -1445,6 +1911,58 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    /// Parses the rest of an interval `[lo .. hi]`, after the `..`. This is sugar for
+    /// a call to the `interval(lo, hi)` function from the prelude.
+    fn interval(&mut self, span: Span, lower: Expression) -> Result<Expression> {
+        self.skip_empty_lines();
+
+        let upper = self.expression()?;
+
+        self.skip_empty_lines();
+
+        if self.match_exact(TokenKind::RightBracket).is_none() {
+            return Err(ParseError::new(
+                ParseErrorKind::MissingClosingBracketInInterval,
+                self.peek().span,
+            ));
+        }
+
+        Ok(Expression::FunctionCall(
+            span,
+            span.extend(&self.last().unwrap().span),
+            Box::new(Expression::Identifier(span, "interval".into())),
+            vec![lower, upper],
+        ))
+    }
+
+    /// Parse an optional unit annotation like `[m/s]` that directly follows a number literal.
+    /// `5[m/s]` is equivalent to `5 (m/s)`, but binds tighter than any other operator.
+    fn unit_annotation(&mut self, scalar: Expression) -> Result<Expression> {
+        let next = self.peek();
+        if next.kind != TokenKind::LeftBracket
+            || next.span.start.byte != scalar.full_span().end.byte
+        {
+            return Ok(scalar);
+        }
+        self.advance();
+
+        let unit = self.expression()?;
+
+        if self.match_exact(TokenKind::RightBracket).is_none() {
+            return Err(ParseError::new(
+                ParseErrorKind::MissingClosingBracketInUnitAnnotation,
+                self.peek().span,
+            ));
+        }
+
+        Ok(Expression::BinaryOperator {
+            op: BinaryOperator::Mul,
+            lhs: Box::new(scalar),
+            rhs: Box::new(unit),
+            span_op: None,
+        })
+    }
+
     /// Returns true iff the upcoming token indicates the beginning of a 'power'
     /// expression (which needs to start with a 'primary' expression).
     fn next_token_could_start_power_expression(&self) -> bool {
@@ -1456,7 +1974,7 @@ impl<'a> Parser<'a> {
                 | TokenKind::Identifier
                 | TokenKind::LeftParen
                 | TokenKind::QuestionMark
-        )
+        ) && !self.next_token_is_mod_operator()
     }
 
     fn type_annotation(&mut self) -> Result<TypeAnnotation> {
@@ -1715,6 +2233,15 @@ impl<'a> Parser<'a> {
         &self.tokens[self.current]
     }
 
+    /// The token after the one returned by [`Parser::peek`]
+    fn peek_next(&self) -> &'a Token {
+        if self.is_at_end() {
+            self.peek()
+        } else {
+            &self.tokens[self.current + 1]
+        }
+    }
+
     fn last(&self) -> Option<&'a Token> {
         if self.current == 0 {
             None
@@ -1768,6 +2295,23 @@ fn strip_and_escape(s: &str) -> String {
     result
 }
 
+/// Parses versions like `1`, `1.12` or `1.12.0` into their (major, minor, patch) components.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut components = [0; 3];
+    let mut parts = version.split('.');
+    for (component, part) in components.iter_mut().zip(parts.by_ref()) {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        *component = part.parse().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    let [major, minor, patch] = components;
+    Some((major, minor, patch))
+}
+
 /// Parse a string.
 /// If an error is encountered and `stop_on_error` is set to false, the parser
 /// will try to recover from the error and parse as many statements as possible
@@ -1785,18 +2329,77 @@ pub fn parse(input: &str, code_source_id: usize) -> ParseResult {
     parser.parse()
 }
 
-#[cfg(test)]
-pub fn parse_dexpr(input: &str) -> TypeExpression {
-    let tokens = crate::tokenizer::tokenize(input, 0).expect("No tokenizer errors in tests");
-    let mut parser = crate::parser::Parser::new(&tokens);
-    let expr = parser
-        .dimension_expression()
-        .expect("No parser errors in tests");
-    assert!(parser.is_at_end());
-    expr
-}
+/// Parse a single expression, see [`crate::Context::type_of`]. Anything but surrounding
+/// newlines after the expression is an error.
+pub(crate) fn parse_expression(input: &str, code_source_id: usize) -> Result<Expression> {
+    let tokens =
+        tokenizer::tokenize(input, code_source_id).map_err(|TokenizerError { kind, span }| {
+            ParseError::new(ParseErrorKind::TokenizerError(kind), span)
+        })?;
+    let mut parser = Parser::new(&tokens);
 
-#[cfg(test)]
+    parser.skip_empty_lines();
+    let expr = parser.expression()?;
+    parser.skip_empty_lines();
+    if !parser.is_at_end() {
+        return Err(ParseError::new(
+            ParseErrorKind::TrailingCharacters(parser.peek().lexeme.clone()),
+            parser.peek().span,
+        ));
+    }
+
+    Ok(expr)
+}
+
+/// Parse the declaration `name: Type` of a global variable that is defined by the host
+/// application, see [`crate::Context::define_lazy_global`].
+pub(crate) fn parse_global_declaration(
+    input: &str,
+    code_source_id: usize,
+) -> Result<(Span, String, TypeAnnotation)> {
+    let tokens =
+        tokenizer::tokenize(input, code_source_id).map_err(|TokenizerError { kind, span }| {
+            ParseError::new(ParseErrorKind::TokenizerError(kind), span)
+        })?;
+    let mut parser = Parser::new(&tokens);
+
+    let Some(identifier) = parser.match_exact(TokenKind::Identifier) else {
+        return Err(ParseError::new(
+            ParseErrorKind::ExpectedIdentifier,
+            parser.peek().span,
+        ));
+    };
+
+    let trailing_characters = |parser: &Parser| {
+        ParseError::new(
+            ParseErrorKind::TrailingCharacters(parser.peek().lexeme.clone()),
+            parser.peek().span,
+        )
+    };
+
+    if parser.match_exact(TokenKind::Colon).is_none() {
+        return Err(trailing_characters(&parser));
+    }
+    let type_annotation = parser.type_annotation()?;
+    if !parser.is_at_end() {
+        return Err(trailing_characters(&parser));
+    }
+
+    Ok((identifier.span, identifier.lexeme.clone(), type_annotation))
+}
+
+#[cfg(test)]
+pub fn parse_dexpr(input: &str) -> TypeExpression {
+    let tokens = crate::tokenizer::tokenize(input, 0).expect("No tokenizer errors in tests");
+    let mut parser = crate::parser::Parser::new(&tokens);
+    let expr = parser
+        .dimension_expression()
+        .expect("No parser errors in tests");
+    assert!(parser.is_at_end());
+    expr
+}
+
+#[cfg(test)]
 mod tests {
     use insta::assert_snapshot;
     use std::fmt::Write;
@@ -1998,6 +2601,40 @@ mod tests {
         parse_as_expression(&["1ee"], binop!(scalar!(1.0), Mul, identifier!("ee")));
         parse_as_expression(&["1eV"], binop!(scalar!(1.0), Mul, identifier!("eV")));
         parse_as_expression(&["1erg"], binop!(scalar!(1.0), Mul, identifier!("erg")));
+
+        // The exponent is part of the number, the unit is multiplied implicitly
+        parse_as_expression(
+            &["1.6e-19 C", "1.6e-19C", "1.6e-19 × C"],
+            binop!(scalar!(1.6e-19), Mul, identifier!("C")),
+        );
+        parse_as_expression(
+            &["6.022e23 / mol", "6.022e23/mol"],
+            binop!(scalar!(6.022e23), Div, identifier!("mol")),
+        );
+        parse_as_expression(
+            &["2e3 m/s", "(2e3 m) / s"],
+            binop!(
+                binop!(scalar!(2.0e3), Mul, identifier!("m")),
+                Div,
+                identifier!("s")
+            ),
+        );
+        parse_as_expression(
+            &["1e3 m^2", "1e3 m²"],
+            binop!(
+                scalar!(1.0e3),
+                Mul,
+                binop!(identifier!("m"), Power, scalar!(2.0))
+            ),
+        );
+        parse_as_expression(
+            &["3e C"],
+            binop!(
+                binop!(scalar!(3.0), Mul, identifier!("e")),
+                Mul,
+                identifier!("C")
+            ),
+        );
     }
 
     #[test]
@@ -2087,6 +2724,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn modulo() {
+        parse_as_expression(
+            &["7 mod 3", "  7   mod  3    ", "(7)mod(3)"],
+            binop!(scalar!(7.0), Mod, scalar!(3.0)),
+        );
+
+        // 'mod' has the same precedence as '*' and '/', and is left-associative
+        parse_as_expression(
+            &["2 * 7 mod 3", "(2 * 7) mod 3"],
+            binop!(binop!(scalar!(2.0), Mul, scalar!(7.0)), Mod, scalar!(3.0)),
+        );
+        parse_as_expression(
+            &["7 mod 3 / 2", "(7 mod 3) / 2"],
+            binop!(binop!(scalar!(7.0), Mod, scalar!(3.0)), Div, scalar!(2.0)),
+        );
+        parse_as_expression(
+            &["1 + 7 mod 3"],
+            binop!(scalar!(1.0), Add, binop!(scalar!(7.0), Mod, scalar!(3.0))),
+        );
+        parse_as_expression(
+            &["7 m mod 3 m"],
+            binop!(
+                binop!(scalar!(7.0), Mul, identifier!("m")),
+                Mod,
+                binop!(scalar!(3.0), Mul, identifier!("m"))
+            ),
+        );
+
+        // 'mod' is not a keyword, the function of the same name can still be called
+        parse_as_expression(
+            &["mod(7, 3)"],
+            Expression::FunctionCall(
+                Span::dummy(),
+                Span::dummy(),
+                Box::new(identifier!("mod")),
+                vec![scalar!(7.0), scalar!(3.0)],
+            ),
+        );
+
+        should_fail(&["7 mod", "7 mod * 3"]);
+    }
+
     #[test]
     fn addition_subtraction_multiplication_division_precedence() {
         parse_as_expression(
@@ -2115,6 +2795,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unit_annotations() {
+        parse_as_expression(
+            &["5[m]", "5[ m ]", "5 m"],
+            binop!(scalar!(5.0), Mul, identifier!("m")),
+        );
+
+        parse_as_expression(
+            &["5[m/s]", "5 × (m/s)"],
+            binop!(
+                scalar!(5.0),
+                Mul,
+                binop!(identifier!("m"), Div, identifier!("s"))
+            ),
+        );
+
+        parse_as_expression(
+            &["5[1/s]"],
+            binop!(
+                scalar!(5.0),
+                Mul,
+                binop!(scalar!(1.0), Div, identifier!("s"))
+            ),
+        );
+
+        // binds tighter than exponentiation
+        parse_as_expression(
+            &["5[m]^2", "(5 m)^2"],
+            binop!(
+                binop!(scalar!(5.0), Mul, identifier!("m")),
+                Power,
+                scalar!(2.0)
+            ),
+        );
+
+        parse_as_expression(
+            &["1/2[s]"],
+            binop!(
+                scalar!(1.0),
+                Div,
+                binop!(scalar!(2.0), Mul, identifier!("s"))
+            ),
+        );
+
+        should_fail_with(
+            &["5[m", "5[m/s"],
+            ParseErrorKind::MissingClosingBracketInUnitAnnotation,
+        );
+
+        // The annotation needs to directly follow the number
+        should_fail(&["5 [m]"]);
+    }
+
     #[test]
     fn exponentiation() {
         parse_as_expression(
@@ -2213,6 +2946,30 @@ mod tests {
         );
 
         should_fail(&["1 - > 2", "1 -> -> 2"]);
+
+        parse_as_expression(
+            &["1 kWh -> SI", "1 kWh → SI", "1 kWh to SI"],
+            Expression::UnaryOperator {
+                op: UnaryOperator::ConvertToBaseUnits,
+                expr: Box::new(binop!(scalar!(1.0), Mul, identifier!("kWh"))),
+                span_op: Span::dummy(),
+            },
+        );
+
+        parse_as_expression(
+            &["1 -> SI -> 2"],
+            binop!(
+                Expression::UnaryOperator {
+                    op: UnaryOperator::ConvertToBaseUnits,
+                    expr: Box::new(scalar!(1.0)),
+                    span_op: Span::dummy(),
+                },
+                ConvertTo,
+                scalar!(2.0)
+            ),
+        );
+
+        should_fail(&["SI", "1 -> SI²", "let SI = 1"]);
     }
 
     #[test]
@@ -2225,6 +2982,7 @@ mod tests {
                 expr: scalar!(1.0),
                 type_annotation: None,
                 decorators: Vec::new(),
+                mutable: false,
             },
         );
 
@@ -2238,6 +2996,7 @@ mod tests {
                     TypeExpression::TypeIdentifier(Span::dummy(), "Length".into()),
                 )),
                 decorators: Vec::new(),
+                mutable: false,
             },
         );
 
@@ -2255,6 +3014,32 @@ mod tests {
                     decorator::Decorator::Name("myvar".into()),
                     decorator::Decorator::Aliases(vec![("foo".into(), None), ("bar".into(), None)]),
                 ],
+                mutable: false,
+            },
+        );
+
+        parse_as(
+            &["let mut x = 1"],
+            Statement::DefineVariable {
+                identifier_span: Span::dummy(),
+                identifier: "x".into(),
+                expr: scalar!(1.0),
+                type_annotation: None,
+                decorators: Vec::new(),
+                mutable: true,
+            },
+        );
+
+        // 'mut' can still be used as a variable name
+        parse_as(
+            &["let mut = 1"],
+            Statement::DefineVariable {
+                identifier_span: Span::dummy(),
+                identifier: "mut".into(),
+                expr: scalar!(1.0),
+                type_annotation: None,
+                decorators: Vec::new(),
+                mutable: false,
             },
         );
 
@@ -2269,7 +3054,7 @@ mod tests {
         );
 
         should_fail_with(
-            &["foo = 2"],
+            &["2 foo = 2"],
             ParseErrorKind::TrailingEqualSign("foo".into()),
         );
 
@@ -2281,6 +3066,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn variable_reassignment() {
+        parse_as(
+            &["x = 2", "x=2", "x =\n  2"],
+            Statement::ReassignVariable {
+                identifier_span: Span::dummy(),
+                identifier: "x".into(),
+                expr: scalar!(2.0),
+            },
+        );
+
+        parse_as(
+            &["x == 2"],
+            Statement::Expression(binop!(identifier!("x"), Equal, scalar!(2.0))),
+        );
+
+        should_fail(&["x = ", "@name(\"foo\") x = 2"]);
+    }
+
     #[test]
     fn dimension_definition() {
         parse_as(
@@ -2407,6 +3211,7 @@ mod tests {
                 function_name: "foo".into(),
                 type_parameters: vec![],
                 parameters: vec![],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: None,
                 decorators: vec![],
@@ -2420,6 +3225,7 @@ mod tests {
                 function_name: "foo".into(),
                 type_parameters: vec![],
                 parameters: vec![],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: Some(TypeAnnotation::TypeExpression(
                     TypeExpression::TypeIdentifier(Span::dummy(), "Scalar".into()),
@@ -2435,6 +3241,7 @@ mod tests {
                 function_name: "foo".into(),
                 type_parameters: vec![],
                 parameters: vec![(Span::dummy(), "x".into(), None)],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: None,
                 decorators: vec![],
@@ -2452,6 +3259,7 @@ mod tests {
                     (Span::dummy(), "y".into(), None),
                     (Span::dummy(), "z".into(), None),
                 ],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: None,
                 decorators: vec![],
@@ -2505,6 +3313,7 @@ mod tests {
                         ))),
                     ),
                 ],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: Some(TypeAnnotation::TypeExpression(
                     TypeExpression::TypeIdentifier(Span::dummy(), "Scalar".into()),
@@ -2526,6 +3335,7 @@ mod tests {
                         TypeExpression::TypeIdentifier(Span::dummy(), "X".into()),
                     )),
                 )],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: None,
                 decorators: vec![],
@@ -2545,12 +3355,54 @@ mod tests {
                         TypeExpression::TypeIdentifier(Span::dummy(), "X".into()),
                     )),
                 )],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: None,
                 decorators: vec![],
             },
         );
 
+        parse_as(
+            &[
+                "fn foo<X>(x: X) -> X where X: Length = x",
+                "fn foo<X: Dim>(x: X) -> X where X: Length = x",
+            ],
+            Statement::DefineFunction {
+                function_name_span: Span::dummy(),
+                function_name: "foo".into(),
+                type_parameters: vec![(
+                    Span::dummy(),
+                    "X".into(),
+                    Some(TypeParameterBound::Equal(TypeExpression::TypeIdentifier(
+                        Span::dummy(),
+                        "Length".into(),
+                    ))),
+                )],
+                parameters: vec![(
+                    Span::dummy(),
+                    "x".into(),
+                    Some(TypeAnnotation::TypeExpression(
+                        TypeExpression::TypeIdentifier(Span::dummy(), "X".into()),
+                    )),
+                )],
+                local_definitions: vec![],
+                body: Some(identifier!("x")),
+                return_type_annotation: Some(TypeAnnotation::TypeExpression(
+                    TypeExpression::TypeIdentifier(Span::dummy(), "X".into()),
+                )),
+                decorators: vec![],
+            },
+        );
+
+        should_fail_with(
+            &["fn foo<X>(x: X) -> X where Y: Length = x"],
+            ParseErrorKind::UnknownTypeParameterInWhereClause("Y".into()),
+        );
+        should_fail_with(
+            &["fn foo<X>(x: X) -> X where X Length = x"],
+            ParseErrorKind::ExpectedColonInWhereClause,
+        );
+
         parse_as(
             &["@name(\"Some function\") @description(\"This is a description of some_function.\") fn some_function(x) = 1"],
             Statement::DefineFunction {
@@ -2558,6 +3410,7 @@ mod tests {
                 function_name: "some_function".into(),
                 type_parameters: vec![],
                 parameters: vec![(Span::dummy(), "x".into(), None)],
+                local_definitions: vec![],
                 body: Some(scalar!(1.0)),
                 return_type_annotation: None,
                 decorators: vec![
@@ -2575,6 +3428,130 @@ mod tests {
         );
     }
 
+    #[test]
+    fn function_body_blocks() {
+        parse_as(
+            &[
+                "fn foo(x) = { let y = 2 * x; y + x }",
+                "fn foo(x) = { let y = 2 * x\n y + x }",
+                "fn foo(x) =\n  {\n    let y = 2 * x\n\n    y + x\n  }",
+                "fn foo(x) = { ;let y = 2 * x;; y + x; }",
+            ],
+            Statement::DefineFunction {
+                function_name_span: Span::dummy(),
+                function_name: "foo".into(),
+                type_parameters: vec![],
+                parameters: vec![(Span::dummy(), "x".into(), None)],
+                local_definitions: vec![LocalDefinition::Variable(LocalVariable {
+                    identifier_span: Span::dummy(),
+                    identifier: "y".into(),
+                    expr: binop!(scalar!(2.0), Mul, identifier!("x")),
+                    type_annotation: None,
+                })],
+                body: Some(binop!(identifier!("y"), Add, identifier!("x"))),
+                return_type_annotation: None,
+                decorators: vec![],
+            },
+        );
+
+        parse_as(
+            &["fn foo(x) = { let y: Length = x; let z = y; z }"],
+            Statement::DefineFunction {
+                function_name_span: Span::dummy(),
+                function_name: "foo".into(),
+                type_parameters: vec![],
+                parameters: vec![(Span::dummy(), "x".into(), None)],
+                local_definitions: vec![
+                    LocalDefinition::Variable(LocalVariable {
+                        identifier_span: Span::dummy(),
+                        identifier: "y".into(),
+                        expr: identifier!("x"),
+                        type_annotation: Some(TypeAnnotation::TypeExpression(
+                            TypeExpression::TypeIdentifier(Span::dummy(), "Length".into()),
+                        )),
+                    }),
+                    LocalDefinition::Variable(LocalVariable {
+                        identifier_span: Span::dummy(),
+                        identifier: "z".into(),
+                        expr: identifier!("y"),
+                        type_annotation: None,
+                    }),
+                ],
+                body: Some(identifier!("z")),
+                return_type_annotation: None,
+                decorators: vec![],
+            },
+        );
+
+        should_fail_with(
+            &[
+                "fn foo(x) = { let y = x; y, x }",
+                "fn foo(x) = { let y = x; y",
+            ],
+            ParseErrorKind::ExpectedRightCurlyAfterFunctionBody,
+        );
+        should_fail_with(
+            &["fn foo(x) = { let y = x, y }"],
+            ParseErrorKind::ExpectedSemicolonOrNewlineAfterLocalVariable,
+        );
+        should_fail_with(
+            &["fn foo(x) = { let = x; x }"],
+            ParseErrorKind::ExpectedIdentifierAfterLet,
+        );
+        should_fail_with(
+            &["fn foo(x) = { let y; x }"],
+            ParseErrorKind::ExpectedEqualOrColonAfterLetIdentifier,
+        );
+
+        parse_as(
+            &[
+                "fn foo(x) = { let y = 2 * x; fn bar(z) = y * z; bar(x) }",
+                "fn foo(x) = {\n  let y = 2 * x\n  fn bar(z) = y * z\n  bar(x)\n}",
+            ],
+            Statement::DefineFunction {
+                function_name_span: Span::dummy(),
+                function_name: "foo".into(),
+                type_parameters: vec![],
+                parameters: vec![(Span::dummy(), "x".into(), None)],
+                local_definitions: vec![
+                    LocalDefinition::Variable(LocalVariable {
+                        identifier_span: Span::dummy(),
+                        identifier: "y".into(),
+                        expr: binop!(scalar!(2.0), Mul, identifier!("x")),
+                        type_annotation: None,
+                    }),
+                    LocalDefinition::Function(Box::new(Statement::DefineFunction {
+                        function_name_span: Span::dummy(),
+                        function_name: "bar".into(),
+                        type_parameters: vec![],
+                        parameters: vec![(Span::dummy(), "z".into(), None)],
+                        local_definitions: vec![],
+                        body: Some(binop!(identifier!("y"), Mul, identifier!("z"))),
+                        return_type_annotation: None,
+                        decorators: vec![],
+                    })),
+                ],
+                body: Some(Expression::FunctionCall(
+                    Span::dummy(),
+                    Span::dummy(),
+                    Box::new(identifier!("bar")),
+                    vec![identifier!("x")],
+                )),
+                return_type_annotation: None,
+                decorators: vec![],
+            },
+        );
+
+        should_fail_with(
+            &["fn foo(x) = { fn bar(z: Scalar) -> Scalar; bar(x) }"],
+            ParseErrorKind::LocalFunctionWithoutBody,
+        );
+        should_fail_with(
+            &["fn foo(x) = { fn bar(z) = z, bar(x) }"],
+            ParseErrorKind::ExpectedSemicolonOrNewlineAfterLocalFunction,
+        );
+    }
+
     #[test]
     fn function_call() {
         parse_as_expression(
@@ -2643,6 +3620,112 @@ mod tests {
         );
     }
 
+    #[test]
+    fn method_calls() {
+        // `x.f(a)` is parsed as a call of a field, see `Expression::AccessField`
+        let method_call = |receiver: Expression, name: &str, args: Vec<Expression>| {
+            Expression::FunctionCall(
+                Span::dummy(),
+                Span::dummy(),
+                Box::new(Expression::AccessField(
+                    Span::dummy(),
+                    Span::dummy(),
+                    Box::new(receiver),
+                    name.to_owned(),
+                )),
+                args,
+            )
+        };
+
+        parse_as_expression(&["x.sqrt()"], method_call(identifier!("x"), "sqrt", vec![]));
+
+        parse_as_expression(
+            &["(2 m).sqrt()"],
+            method_call(binop!(scalar!(2.0), Mul, identifier!("m")), "sqrt", vec![]),
+        );
+
+        parse_as_expression(
+            &["(4m^2).sqrt()"],
+            method_call(
+                binop!(
+                    scalar!(4.0),
+                    Mul,
+                    binop!(identifier!("m"), Power, scalar!(2.0))
+                ),
+                "sqrt",
+                vec![],
+            ),
+        );
+
+        parse_as_expression(
+            &["x.atan2(y)"],
+            method_call(identifier!("x"), "atan2", vec![identifier!("y")]),
+        );
+
+        parse_as_expression(
+            &["x.f().g(y)"],
+            method_call(
+                method_call(identifier!("x"), "f", vec![]),
+                "g",
+                vec![identifier!("y")],
+            ),
+        );
+
+        // Without parentheses, `.` is a field access
+        parse_as_expression(
+            &["x.foo"],
+            Expression::AccessField(
+                Span::dummy(),
+                Span::dummy(),
+                Box::new(identifier!("x")),
+                "foo".to_owned(),
+            ),
+        );
+
+        // Method calls bind tighter than exponentiation
+        parse_as_expression(
+            &["2^x.sqrt()"],
+            binop!(
+                scalar!(2.0),
+                Power,
+                method_call(identifier!("x"), "sqrt", vec![])
+            ),
+        );
+
+        parse_as_expression(
+            &["x.to(km)", "x -> km", "x.to(\nkm\n)"],
+            binop!(identifier!("x"), ConvertTo, identifier!("km")),
+        );
+        parse_as_expression(
+            &["(2 m).to(cm)", "2 m -> cm"],
+            binop!(
+                binop!(scalar!(2.0), Mul, identifier!("m")),
+                ConvertTo,
+                identifier!("cm")
+            ),
+        );
+        parse_as_expression(
+            &["x.to(km / h).sqrt()", "(x -> km / h).sqrt()"],
+            method_call(
+                binop!(
+                    identifier!("x"),
+                    ConvertTo,
+                    binop!(identifier!("km"), Div, identifier!("h"))
+                ),
+                "sqrt",
+                vec![],
+            ),
+        );
+
+        // A `.` after a number literal belongs to the number, and a `.` with
+        // whitespace around it is not a method call.
+        should_fail(&["4m^2 . sqrt()", "x . sqrt()", "x.sqrt(", "x.to(km", "x.()"]);
+        should_fail_with(
+            &["x.to", "x.to km"],
+            ParseErrorKind::ExpectedLeftParenAfterTo,
+        );
+    }
+
     #[test]
     fn postfix_apply() {
         parse_as_expression(
@@ -3024,6 +4107,230 @@ mod tests {
         should_fail_with(&["[1,\n2,\n,\n"], ParseErrorKind::ExpectedPrimary);
     }
 
+    #[test]
+    fn intervals() {
+        parse_as_expression(
+            &["[1 .. 2]", "[1..2]", "[ 1 ..\n 2 ]", "interval(1, 2)"],
+            Expression::FunctionCall(
+                Span::dummy(),
+                Span::dummy(),
+                Box::new(identifier!("interval")),
+                vec![scalar!(1.0), scalar!(2.0)],
+            ),
+        );
+
+        parse_as_expression(
+            &["[-1 .. 2] m"],
+            binop!(
+                Expression::FunctionCall(
+                    Span::dummy(),
+                    Span::dummy(),
+                    Box::new(identifier!("interval")),
+                    vec![negate!(scalar!(1.0)), scalar!(2.0)],
+                ),
+                Mul,
+                identifier!("m")
+            ),
+        );
+
+        should_fail_with(
+            &["[1 .. 2", "[1 .. 2, 3]"],
+            ParseErrorKind::MissingClosingBracketInInterval,
+        );
+        should_fail_with(
+            &["[1, 2 .. 3]"],
+            ParseErrorKind::ExpectedCommaOrRightBracketInList,
+        );
+    }
+
+    #[test]
+    fn user_operators() {
+        parse_as(
+            &["operator <> = combine", "operator<>=combine"],
+            Statement::DefineOperator {
+                symbol_span: Span::dummy(),
+                symbol: "<>".into(),
+                function_name_span: Span::dummy(),
+                function_name: "combine".into(),
+            },
+        );
+
+        let user_operator = |symbol: &str, lhs, rhs| Expression::UserOperator {
+            symbol: symbol.into(),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            span_op: Span::dummy(),
+        };
+
+        parse_as_expression(
+            &["a <> b", "a<>b"],
+            user_operator("<>", identifier!("a"), identifier!("b")),
+        );
+
+        // Left-associative, binds less tightly than arithmetic operators,
+        // but more tightly than comparisons
+        parse_as_expression(
+            &["a <*> b <|> c"],
+            user_operator(
+                "<|>",
+                user_operator("<*>", identifier!("a"), identifier!("b")),
+                identifier!("c"),
+            ),
+        );
+        parse_as_expression(
+            &["a + 1 <> 2 * b"],
+            user_operator(
+                "<>",
+                binop!(identifier!("a"), Add, scalar!(1.0)),
+                binop!(scalar!(2.0), Mul, identifier!("b")),
+            ),
+        );
+        parse_as_expression(
+            &["a <> b == c"],
+            binop!(
+                user_operator("<>", identifier!("a"), identifier!("b")),
+                Equal,
+                identifier!("c")
+            ),
+        );
+
+        // 'operator' can still be used as an identifier
+        parse_as_expression(
+            &["operator <> b"],
+            user_operator("<>", identifier!("operator"), identifier!("b")),
+        );
+
+        should_fail_with(
+            &["operator <> = 2", "operator <> ="],
+            ParseErrorKind::ExpectedFunctionNameInOperatorDefinition,
+        );
+        should_fail(&["a <>", "<> b"]);
+    }
+
+    #[test]
+    fn version_directives() {
+        parse_as(
+            &["#!version 1\n1", "#!version 0.5\n1", "#!version 1.0.0\n\n1"],
+            Statement::Expression(scalar!(1.0)),
+        );
+        parse_as(
+            &[&format!("#!version {}\n1", crate::version())],
+            Statement::Expression(scalar!(1.0)),
+        );
+
+        should_fail_with(
+            &[
+                "#!version 999.0",
+                "#!version 999.0\n1",
+                "1\n#!version 999.0",
+            ],
+            ParseErrorKind::UnsupportedLanguageVersion("999.0".into(), crate::version().into()),
+        );
+
+        for version in ["", "1.", ".1", "1.2.3.4", "one", "1.x", "+1", "1 2"] {
+            should_fail_with(
+                &[&format!("#!version {version}")],
+                ParseErrorKind::InvalidVersionDirective(version.into()),
+            );
+        }
+    }
+
+    #[test]
+    fn semicolon_separated_statements() {
+        let statements = parse("let x = 2 m; x^2", 0).unwrap().replace_spans();
+        assert_eq!(statements.len(), 2);
+        assert!(
+            matches!(&statements[0], Statement::DefineVariable { identifier, .. } if identifier == "x")
+        );
+        assert_eq!(
+            statements[1],
+            Statement::Expression(binop!(identifier!("x"), Power, scalar!(2.0)))
+        );
+
+        assert_eq!(parse("1; 2;3", 0).unwrap().len(), 3);
+        assert_eq!(parse("1;\n2", 0).unwrap().len(), 2);
+        assert_eq!(parse("1;", 0).unwrap().len(), 1);
+        assert_eq!(parse("fn f(x) = x; f(2)", 0).unwrap().len(), 2);
+
+        should_fail(&[";", "let x = ; 2"]);
+
+        let ends_suppressed = |input| {
+            matches!(
+                parse(input, 0).unwrap().last(),
+                Some(Statement::SuppressedExpression(_))
+            )
+        };
+        assert!(ends_suppressed("x^2;"));
+        assert!(ends_suppressed("x^2; # comment\n"));
+        assert!(ends_suppressed("x^2;\n;\n"));
+        assert!(!ends_suppressed("let x = 2 m; x^2"));
+        assert!(!ends_suppressed("x^2;\ny"));
+        assert!(!ends_suppressed("fn f(x) = {\n let y = x;\n y\n}"));
+    }
+
+    #[test]
+    fn unit_declarations() {
+        parse_as(
+            &["unit foo: Length = ...", "unit foo: Length =\n  …"],
+            Statement::DeclareUnit(
+                Span::dummy(),
+                "foo".into(),
+                TypeExpression::TypeIdentifier(Span::dummy(), "Length".into()),
+                vec![],
+            ),
+        );
+
+        parse_as(
+            &["@aliases(f)\nunit foo: Length = ..."],
+            Statement::DeclareUnit(
+                Span::dummy(),
+                "foo".into(),
+                TypeExpression::TypeIdentifier(Span::dummy(), "Length".into()),
+                vec![Decorator::Aliases(vec![("f".into(), None)])],
+            ),
+        );
+
+        should_fail_with(
+            &["unit foo = ..."],
+            ParseErrorKind::ExpectedDimensionInUnitDeclaration,
+        );
+    }
+
+    #[test]
+    fn canonical_decorator() {
+        parse_as(
+            &["@aliases(metre, m: short)\n@canonical(metre)\nunit meter: Length"],
+            Statement::DefineBaseUnit(
+                Span::dummy(),
+                "meter".into(),
+                Some(TypeExpression::TypeIdentifier(
+                    Span::dummy(),
+                    "Length".into(),
+                )),
+                vec![
+                    decorator::Decorator::Aliases(vec![
+                        ("metre".into(), None),
+                        ("m".into(), Some(AcceptsPrefix::only_short())),
+                    ]),
+                    decorator::Decorator::Canonical("metre".into()),
+                ],
+            ),
+        );
+
+        should_fail_with(
+            &["@canonical(metre)\nunit meter: Length"],
+            ParseErrorKind::UnknownCanonicalName("metre".into()),
+        );
+        should_fail_with(
+            &["@canonical metre\nunit meter: Length"],
+            ParseErrorKind::ExpectedLeftParenAfterDecorator,
+        );
+        should_fail_with(
+            &["@canonical(\"metre\")\nunit meter: Length"],
+            ParseErrorKind::ExpectedIdentifier,
+        );
+    }
+
     #[test]
     fn accumulate_errors() {
         // error on the last character of a line
@@ -3043,7 +4350,7 @@ mod tests {
             assert_eq(tamo + cool == 80)
             30m"), @r###"
         Successfully parsed:
-        DefineVariable { identifier_span: Span { start: SourceCodePositition { byte: 17, line: 2, position: 17 }, end: SourceCodePositition { byte: 21, line: 2, position: 21 }, code_source_id: 0 }, identifier: "cool", expr: Scalar(Span { start: SourceCodePositition { byte: 24, line: 2, position: 24 }, end: SourceCodePositition { byte: 26, line: 2, position: 26 }, code_source_id: 0 }, Number(50.0)), type_annotation: None, decorators: [] }
+        DefineVariable { identifier_span: Span { start: SourceCodePositition { byte: 17, line: 2, position: 17 }, end: SourceCodePositition { byte: 21, line: 2, position: 21 }, code_source_id: 0 }, identifier: "cool", expr: Scalar(Span { start: SourceCodePositition { byte: 24, line: 2, position: 24 }, end: SourceCodePositition { byte: 26, line: 2, position: 26 }, code_source_id: 0 }, Number(50.0)), type_annotation: None, decorators: [], mutable: false }
         ProcedureCall(Span { start: SourceCodePositition { byte: 68, line: 4, position: 13 }, end: SourceCodePositition { byte: 77, line: 4, position: 22 }, code_source_id: 0 }, AssertEq, [BinaryOperator { op: Equal, lhs: BinaryOperator { op: Add, lhs: Identifier(Span { start: SourceCodePositition { byte: 78, line: 4, position: 23 }, end: SourceCodePositition { byte: 82, line: 4, position: 27 }, code_source_id: 0 }, "tamo"), rhs: Identifier(Span { start: SourceCodePositition { byte: 85, line: 4, position: 30 }, end: SourceCodePositition { byte: 89, line: 4, position: 34 }, code_source_id: 0 }, "cool"), span_op: Some(Span { start: SourceCodePositition { byte: 83, line: 4, position: 28 }, end: SourceCodePositition { byte: 84, line: 4, position: 29 }, code_source_id: 0 }) }, rhs: Scalar(Span { start: SourceCodePositition { byte: 93, line: 4, position: 38 }, end: SourceCodePositition { byte: 95, line: 4, position: 40 }, code_source_id: 0 }, Number(80.0)), span_op: Some(Span { start: SourceCodePositition { byte: 90, line: 4, position: 35 }, end: SourceCodePositition { byte: 92, line: 4, position: 37 }, code_source_id: 0 }) }])
         Expression(BinaryOperator { op: Mul, lhs: Scalar(Span { start: SourceCodePositition { byte: 109, line: 5, position: 13 }, end: SourceCodePositition { byte: 111, line: 5, position: 15 }, code_source_id: 0 }, Number(30.0)), rhs: Identifier(Span { start: SourceCodePositition { byte: 111, line: 5, position: 15 }, end: SourceCodePositition { byte: 112, line: 5, position: 16 }, code_source_id: 0 }, "m"), span_op: None })
         Errors encountered:
@@ -3075,11 +4382,11 @@ mod tests {
 
         // #260
         assert_snapshot!(snap_parse(
-            "x = 3"), @r###"
+            "2 x = 3"), @r###"
         Successfully parsed:
-        Expression(Identifier(Span { start: SourceCodePositition { byte: 0, line: 1, position: 1 }, end: SourceCodePositition { byte: 1, line: 1, position: 2 }, code_source_id: 0 }, "x"))
+        Expression(BinaryOperator { op: Mul, lhs: Scalar(Span { start: SourceCodePositition { byte: 0, line: 1, position: 1 }, end: SourceCodePositition { byte: 1, line: 1, position: 2 }, code_source_id: 0 }, Number(2.0)), rhs: Identifier(Span { start: SourceCodePositition { byte: 2, line: 1, position: 3 }, end: SourceCodePositition { byte: 3, line: 1, position: 4 }, code_source_id: 0 }, "x"), span_op: None })
         Errors encountered:
-        Trailing '=' sign. Use `let x = …` if you intended to define a new constant. - ParseError { kind: TrailingEqualSign("x"), span: Span { start: SourceCodePositition { byte: 2, line: 1, position: 3 }, end: SourceCodePositition { byte: 3, line: 1, position: 4 }, code_source_id: 0 } }
+        Trailing '=' sign. Use `let x = …` if you intended to define a new constant. - ParseError { kind: TrailingEqualSign("x"), span: Span { start: SourceCodePositition { byte: 4, line: 1, position: 5 }, end: SourceCodePositition { byte: 5, line: 1, position: 6 }, code_source_id: 0 } }
         "###);
     }
 }
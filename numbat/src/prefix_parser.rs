@@ -3,7 +3,11 @@ use std::collections::HashMap;
 use std::sync::OnceLock;
 
 use crate::span::Span;
-use crate::{name_resolution::NameResolutionError, prefix::Prefix};
+use crate::{
+    name_resolution::NameResolutionError,
+    prefix::{AcceptsPrefix, Prefix},
+    tokenizer,
+};
 
 static PREFIXES: OnceLock<Vec<(&'static str, &'static [&'static str], Prefix)>> = OnceLock::new();
 
@@ -16,42 +20,6 @@ pub enum PrefixParserResult {
 
 type Result<T> = std::result::Result<T, NameResolutionError>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct AcceptsPrefix {
-    pub short: bool,
-    pub long: bool,
-}
-
-impl AcceptsPrefix {
-    pub fn only_long() -> Self {
-        Self {
-            long: true,
-            short: false,
-        }
-    }
-
-    pub fn only_short() -> Self {
-        Self {
-            long: false,
-            short: true,
-        }
-    }
-
-    pub fn both() -> Self {
-        Self {
-            long: true,
-            short: true,
-        }
-    }
-
-    pub fn none() -> Self {
-        Self {
-            long: false,
-            short: false,
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 struct UnitInfo {
     definition_span: Span,
@@ -149,15 +117,25 @@ impl PrefixParser {
         }
     }
 
+    /// Reserved identifiers like `ans` and keywords like `if` can not be used as names
+    pub fn ensure_name_is_not_reserved(&self, name: &str, span: Span) -> Result<()> {
+        if self.reserved_identifiers.contains(&name) || tokenizer::is_keyword(name) {
+            Err(NameResolutionError::ReservedIdentifier(
+                name.to_string(),
+                span,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
     fn ensure_name_is_available(
         &self,
         name: &str,
         conflict_span: Span,
         clash_with_other_identifiers: bool,
     ) -> Result<()> {
-        if self.reserved_identifiers.contains(&name) {
-            return Err(NameResolutionError::ReservedIdentifier(conflict_span));
-        }
+        self.ensure_name_is_not_reserved(name, conflict_span)?;
 
         if clash_with_other_identifiers {
             if let Some(original_span) = self.other_identifiers.get(name) {
@@ -228,6 +206,13 @@ impl PrefixParser {
         Ok(())
     }
 
+    /// Whether the unit with the given name (or alias) accepts metric prefixes.
+    pub fn accepts_metric_prefixes(&self, unit_name: &str) -> bool {
+        self.units
+            .get(unit_name)
+            .is_some_and(|info| info.metric_prefixes)
+    }
+
     pub fn parse(&self, input: &str) -> PrefixParserResult {
         if let Some(info) = self.units.get(input) {
             return PrefixParserResult::UnitIdentifier(
@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+
 use crate::{
-    ast::{Expression, Statement, StringPart},
+    ast::{Expression, LocalDefinition, LocalVariable, Statement, StringPart},
     decorator::{self, Decorator},
     name_resolution::NameResolutionError,
     prefix_parser::{PrefixParser, PrefixParserResult},
@@ -16,6 +18,9 @@ pub(crate) struct Transformer {
     pub function_names: Vec<String>,
     pub unit_names: Vec<Vec<String>>,
     pub dimension_names: Vec<String>,
+
+    /// Decorators of forward-declared units whose value has not been defined yet
+    declared_units: HashMap<String, Vec<Decorator>>,
 }
 
 impl Transformer {
@@ -26,6 +31,7 @@ impl Transformer {
             function_names: vec![],
             unit_names: vec![],
             dimension_names: vec![],
+            declared_units: HashMap::new(),
         }
     }
 
@@ -64,10 +70,25 @@ impl Transformer {
                 rhs: Box::new(self.transform_expression(*rhs)),
                 span_op,
             },
-            Expression::FunctionCall(span, full_span, name, args) => Expression::FunctionCall(
+            Expression::UserOperator {
+                symbol,
+                lhs,
+                rhs,
+                span_op,
+            } => Expression::UserOperator {
+                symbol,
+                lhs: Box::new(self.transform_expression(*lhs)),
+                rhs: Box::new(self.transform_expression(*rhs)),
+                span_op,
+            },
+            Expression::FunctionCall(span, full_span, callable, args) => Expression::FunctionCall(
                 span,
                 full_span,
-                name,
+                // Callables like `f(1 m)` in `f(1 m)(2 m)` can contain units as well
+                match *callable {
+                    callable @ Expression::Identifier(..) => Box::new(callable),
+                    callable => Box::new(self.transform_expression(callable)),
+                },
                 args.into_iter()
                     .map(|arg| self.transform_expression(arg))
                     .collect(),
@@ -162,10 +183,18 @@ impl Transformer {
     fn transform_statement(&mut self, statement: Statement) -> Result<Statement> {
         Ok(match statement {
             Statement::Expression(expr) => Statement::Expression(self.transform_expression(expr)),
+            Statement::SuppressedExpression(expr) => {
+                Statement::SuppressedExpression(self.transform_expression(expr))
+            }
             Statement::DefineBaseUnit(span, name, dexpr, decorators) => {
                 self.register_name_and_aliases(&name, &decorators, span)?;
                 Statement::DefineBaseUnit(span, name, dexpr, decorators)
             }
+            Statement::DeclareUnit(span, name, dexpr, decorators) => {
+                self.register_name_and_aliases(&name, &decorators, span)?;
+                self.declared_units.insert(name.clone(), decorators.clone());
+                Statement::DeclareUnit(span, name, dexpr, decorators)
+            }
             Statement::DefineDerivedUnit {
                 identifier_span,
                 identifier,
@@ -174,7 +203,23 @@ impl Transformer {
                 type_annotation,
                 decorators,
             } => {
-                self.register_name_and_aliases(&identifier, &decorators, identifier_span)?;
+                // The value definition of a forward-declared unit reuses the names and
+                // decorators of its declaration
+                let decorators = match self.declared_units.remove(&identifier) {
+                    Some(declared_decorators) => {
+                        if !decorators.is_empty() {
+                            return Err(NameResolutionError::DecoratorsOnDeclaredUnit(
+                                identifier,
+                                identifier_span,
+                            ));
+                        }
+                        declared_decorators
+                    }
+                    None => {
+                        self.register_name_and_aliases(&identifier, &decorators, identifier_span)?;
+                        decorators
+                    }
+                };
                 Statement::DefineDerivedUnit {
                     identifier_span,
                     identifier,
@@ -190,8 +235,11 @@ impl Transformer {
                 expr,
                 type_annotation,
                 decorators,
+                mutable,
             } => {
                 for (name, _) in decorator::name_and_aliases(&identifier, &decorators) {
+                    self.prefix_parser
+                        .ensure_name_is_not_reserved(name, identifier_span)?;
                     self.variable_names.push(name.clone());
                 }
                 self.prefix_parser
@@ -202,13 +250,24 @@ impl Transformer {
                     expr: self.transform_expression(expr),
                     type_annotation,
                     decorators,
+                    mutable,
                 }
             }
+            Statement::ReassignVariable {
+                identifier_span,
+                identifier,
+                expr,
+            } => Statement::ReassignVariable {
+                identifier_span,
+                identifier,
+                expr: self.transform_expression(expr),
+            },
             Statement::DefineFunction {
                 function_name_span,
                 function_name,
                 type_parameters,
                 parameters,
+                local_definitions,
                 body,
                 return_type_annotation,
                 decorators,
@@ -231,12 +290,31 @@ impl Transformer {
                         .prefix_parser
                         .add_other_identifier(param, *param_span)?;
                 }
+                let mut local_definitions_transformed = vec![];
+                for local_definition in local_definitions {
+                    local_definitions_transformed.push(match local_definition {
+                        LocalDefinition::Variable(local_variable) => {
+                            fn_body_transformer.prefix_parser.add_other_identifier(
+                                &local_variable.identifier,
+                                local_variable.identifier_span,
+                            )?;
+                            LocalDefinition::Variable(LocalVariable {
+                                expr: self.transform_expression(local_variable.expr),
+                                ..local_variable
+                            })
+                        }
+                        LocalDefinition::Function(statement) => LocalDefinition::Function(
+                            Box::new(fn_body_transformer.transform_statement(*statement)?),
+                        ),
+                    });
+                }
 
                 Statement::DefineFunction {
                     function_name_span,
                     function_name,
                     type_parameters,
                     parameters,
+                    local_definitions: local_definitions_transformed,
                     body: body.map(|expr| self.transform_expression(expr)),
                     return_type_annotation,
                     decorators,
@@ -263,6 +341,7 @@ impl Transformer {
                     .collect(),
             ),
             statement @ Statement::ModuleImport(_, _) => statement,
+            statement @ Statement::DefineOperator { .. } => statement,
         })
     }
 
@@ -1,15 +1,10 @@
-use std::{collections::HashMap, fmt::Display};
+use std::collections::HashMap;
 
 use itertools::Itertools;
-use num_traits::Zero;
 use thiserror::Error;
 
-use crate::{
-    arithmetic::{pretty_exponent, Exponent, Power, Rational},
-    pretty_print::PrettyPrint,
-    product::{Canonicalize, Product},
-    suggestion,
-};
+use crate::{arithmetic::Rational, suggestion};
+pub use numbat_core::base_representation::{BaseRepresentation, BaseRepresentationFactor};
 
 #[derive(Clone, Error, Debug, PartialEq, Eq)]
 pub enum RegistryError {
@@ -22,66 +17,9 @@ pub enum RegistryError {
 
 pub type Result<T> = std::result::Result<T, RegistryError>;
 
-pub type BaseEntry = String;
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BaseIndex(isize);
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct BaseRepresentationFactor(pub BaseEntry, pub Exponent);
-
-impl Display for BaseRepresentationFactor {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.0, pretty_exponent(&self.1))
-    }
-}
-
-impl Canonicalize for BaseRepresentationFactor {
-    type MergeKey = BaseEntry;
-
-    fn merge_key(&self) -> Self::MergeKey {
-        self.0.clone() // TODO(minor): can cloning be prevented here?
-    }
-
-    fn merge(self, other: Self) -> Self {
-        BaseRepresentationFactor(self.0, self.1 + other.1)
-    }
-
-    fn is_trivial(&self) -> bool {
-        self.1 == Rational::zero()
-    }
-}
-
-impl Power for BaseRepresentationFactor {
-    fn power(self, e: Exponent) -> Self {
-        let BaseRepresentationFactor(entry, exp) = self;
-        BaseRepresentationFactor(entry, exp * e)
-    }
-}
-
-// TODO(minor): this could be represented with a base index in the first tuple component instead of a cloned string
-pub type BaseRepresentation = Product<BaseRepresentationFactor, true>;
-
-impl Display for BaseRepresentation {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.iter().count() == 0 {
-            f.write_str("Scalar")
-        } else {
-            f.write_str(&self.as_string(|f| f.1, '×', '/', true))
-        }
-    }
-}
-
-impl PrettyPrint for BaseRepresentation {
-    fn pretty_print(&self) -> crate::markup::Markup {
-        if self.iter().count() == 0 {
-            crate::markup::type_identifier("Scalar")
-        } else {
-            self.pretty_print_with(|f| f.1, '×', '/', true, None)
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct Registry<Metadata> {
     base_entries: Vec<(String, Metadata)>,
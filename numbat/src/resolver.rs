@@ -1,7 +1,11 @@
 use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use crate::{
-    ast::Statement, module_importer::ModuleImporter, parser::parse, span::Span, ParseError,
+    ast::{Expression, Statement},
+    module_importer::ModuleImporter,
+    parser::{parse, parse_expression},
+    span::Span,
+    ParseError,
 };
 
 use codespan_reporting::files::SimpleFiles;
@@ -65,7 +69,7 @@ impl Resolver {
         }
     }
 
-    fn add_code_source(&mut self, code_source: CodeSource, content: &str) -> usize {
+    pub(crate) fn add_code_source(&mut self, code_source: CodeSource, content: &str) -> usize {
         let code_source_name = match &code_source {
             CodeSource::Text => {
                 self.text_code_source_count += 1;
@@ -92,6 +96,19 @@ impl Resolver {
         id
     }
 
+    /// Add code that has been resolved by a different [Resolver], under its original name
+    pub(crate) fn add_imported_code_source(
+        &mut self,
+        code_source: CodeSource,
+        name: String,
+        content: String,
+    ) -> usize {
+        let id = self.files.add(name, content);
+        self.codesources.insert(id, code_source);
+
+        id
+    }
+
     pub fn get_code_source(&self, id: usize) -> CodeSource {
         self.codesources.get(&id).cloned().unwrap()
     }
@@ -138,6 +155,14 @@ impl Resolver {
         self.inlining_pass(&statements)
     }
 
+    /// Parse a single expression. Unlike [`Resolver::resolve`], this can not import modules,
+    /// and the code is not added as a code source. Spans in the expression refer to the
+    /// code source that would be added next.
+    pub(crate) fn resolve_expression(&self, code: &str) -> Result<Expression> {
+        let code_source_id = self.codesources.len();
+        parse_expression(code, code_source_id).map_err(|e| ResolverError::ParseErrors(vec![e]))
+    }
+
     pub fn get_importer(&self) -> &dyn ModuleImporter {
         self.importer.as_ref()
     }
@@ -195,6 +220,7 @@ mod tests {
                     expr: Expression::Scalar(Span::dummy(), Number::from_f64(1.0)),
                     type_annotation: None,
                     decorators: Vec::new(),
+                    mutable: false,
                 },
                 Statement::Expression(Expression::Identifier(Span::dummy(), "a".into()))
             ]
@@ -225,6 +251,7 @@ mod tests {
                     expr: Expression::Scalar(Span::dummy(), Number::from_f64(1.0)),
                     type_annotation: None,
                     decorators: Vec::new(),
+                    mutable: false,
                 },
                 Statement::Expression(Expression::Identifier(Span::dummy(), "a".into()))
             ]
@@ -254,6 +281,7 @@ mod tests {
                     expr: Expression::Scalar(Span::dummy(), Number::from_f64(1.0)),
                     type_annotation: None,
                     decorators: Vec::new(),
+                    mutable: false,
                 },
                 Statement::DefineVariable {
                     identifier_span: Span::dummy(),
@@ -261,6 +289,7 @@ mod tests {
                     expr: Expression::Identifier(Span::dummy(), "y".into()),
                     type_annotation: None,
                     decorators: Vec::new(),
+                    mutable: false,
                 },
             ]
         );
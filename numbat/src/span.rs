@@ -33,6 +33,14 @@ pub struct Span {
 }
 
 impl Span {
+    /// The same position in a different code source
+    pub(crate) fn with_code_source_id(self, code_source_id: usize) -> Span {
+        Span {
+            code_source_id,
+            ..self
+        }
+    }
+
     pub fn extend(&self, other: &Span) -> Span {
         assert_eq!(self.code_source_id, other.code_source_id);
         Span {
@@ -50,6 +58,29 @@ impl Span {
         )
     }
 
+    /// The (1-based) line and column of the start of this span in the given source code.
+    /// Columns are counted in characters, not bytes. The source code needs to be the code
+    /// that this span refers to (see [`Span::code_source_id`]).
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        let offset = (self.start.byte as usize).min(source.len());
+        let before = source
+            .char_indices()
+            .take_while(|(i, _)| *i < offset)
+            .map(|(_, c)| c);
+
+        let mut line = 1;
+        let mut column = 1;
+        for c in before {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        (line, column)
+    }
+
     #[cfg(test)]
     pub fn dummy() -> Span {
         Self {
@@ -59,3 +90,44 @@ impl Span {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span_at(byte: u32) -> Span {
+        let position = SourceCodePositition {
+            byte,
+            ..SourceCodePositition::start()
+        };
+        position.single_character_span(0)
+    }
+
+    #[test]
+    fn line_col() {
+        let source = "let x = 1\nlet y = 2\n\nx + y";
+
+        assert_eq!(span_at(0).line_col(source), (1, 1));
+        assert_eq!(span_at(4).line_col(source), (1, 5));
+        assert_eq!(span_at(9).line_col(source), (1, 10));
+        assert_eq!(span_at(10).line_col(source), (2, 1));
+        assert_eq!(span_at(14).line_col(source), (2, 5));
+        assert_eq!(span_at(20).line_col(source), (3, 1));
+        assert_eq!(span_at(25).line_col(source), (4, 5));
+
+        // Offsets past the end are clamped
+        assert_eq!(span_at(100).line_col(source), (4, 6));
+    }
+
+    #[test]
+    fn line_col_multibyte() {
+        let source = "let µ = 1\n\"äöü\" + 2 π";
+
+        assert_eq!(span_at(4).line_col(source), (1, 5));
+        assert_eq!(span_at(9).line_col(source), (1, 9));
+        let plus = source.find('+').unwrap() as u32;
+        assert_eq!(span_at(plus).line_col(source), (2, 7));
+        let pi = source.find('π').unwrap() as u32;
+        assert_eq!(span_at(pi).line_col(source), (2, 11));
+    }
+}
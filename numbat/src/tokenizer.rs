@@ -3,6 +3,7 @@ use crate::span::{SourceCodePositition, Span};
 use std::collections::HashMap;
 use std::sync::OnceLock;
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
 
 #[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum TokenizerErrorKind {
@@ -64,6 +65,7 @@ pub enum TokenKind {
     Power,
     Divide,
     Comma,
+    Semicolon,
     Arrow,
     Equal,
     Colon,
@@ -72,6 +74,9 @@ pub enum TokenKind {
     UnicodeExponent,
     At,
     Ellipsis,
+    DotDot,
+    /// A `#!version …` directive, spanning the rest of the line
+    VersionDirective,
     ExclamationMark,
     EqualEqual,
     NotEqual,
@@ -83,6 +88,8 @@ pub enum TokenKind {
     LogicalOr,
     Period,
     QuestionMark,
+    /// One of the symbols that can be bound to a function with `operator … = …`
+    UserOperator,
 
     // Keywords
     Per,
@@ -108,6 +115,9 @@ pub enum TokenKind {
     NaN,
     Inf,
 
+    /// Conversion target for the base unit representation (`-> SI`)
+    SI,
+
     // Type names
     Bool,
     String,
@@ -120,6 +130,8 @@ pub enum TokenKind {
     ProcedureAssert,
     ProcedureAssertEq,
     ProcedureType,
+    ProcedureDebug,
+    ProcedureCsv,
 
     // Variable-length tokens
     Number,
@@ -149,6 +161,57 @@ pub struct Token {
     pub span: Span,
 }
 
+fn keywords() -> &'static HashMap<&'static str, TokenKind> {
+    static KEYWORDS: OnceLock<HashMap<&'static str, TokenKind>> = OnceLock::new();
+    KEYWORDS.get_or_init(|| {
+        let mut m = HashMap::new();
+        // keywords
+        m.insert("per", TokenKind::Per);
+        m.insert("to", TokenKind::To);
+        m.insert("let", TokenKind::Let);
+        m.insert("fn", TokenKind::Fn);
+        m.insert("dimension", TokenKind::Dimension);
+        m.insert("unit", TokenKind::Unit);
+        m.insert("use", TokenKind::Use);
+        m.insert("struct", TokenKind::Struct);
+        m.insert("long", TokenKind::Long);
+        m.insert("short", TokenKind::Short);
+        m.insert("both", TokenKind::Both);
+        m.insert("none", TokenKind::None);
+        m.insert("if", TokenKind::If);
+        m.insert("then", TokenKind::Then);
+        m.insert("else", TokenKind::Else);
+        m.insert("true", TokenKind::True);
+        m.insert("false", TokenKind::False);
+        m.insert("NaN", TokenKind::NaN);
+        m.insert("inf", TokenKind::Inf);
+        m.insert("SI", TokenKind::SI);
+
+        // procedures
+        m.insert("print", TokenKind::ProcedurePrint);
+        m.insert("assert", TokenKind::ProcedureAssert);
+        m.insert("assert_eq", TokenKind::ProcedureAssertEq);
+        m.insert("type", TokenKind::ProcedureType);
+        m.insert("debug", TokenKind::ProcedureDebug);
+        m.insert("csv", TokenKind::ProcedureCsv);
+
+        // type names
+        m.insert("Bool", TokenKind::Bool);
+        m.insert("String", TokenKind::String);
+        m.insert("DateTime", TokenKind::DateTime);
+        m.insert("Fn", TokenKind::CapitalFn);
+        m.insert("List", TokenKind::List);
+
+        // Keep this list in sync with keywords::KEYWORDS!
+        m
+    })
+}
+
+/// Whether the given word is a keyword, which can not be used as an identifier
+pub(crate) fn is_keyword(word: &str) -> bool {
+    keywords().contains_key(word)
+}
+
 fn is_exponent_char(c: char) -> bool {
     matches!(c, '¹' | '²' | '³' | '⁴' | '⁵' | '⁶' | '⁷' | '⁸' | '⁹')
 }
@@ -230,6 +293,10 @@ impl InterpolationState {
     }
 }
 
+/// Comments starting with this prefix are not skipped, but declare the minimum
+/// language version that is required to run the code.
+pub const VERSION_DIRECTIVE: &str = "#!version";
+
 struct Tokenizer {
     input: Vec<char>,
     current: SourceCodePositition,
@@ -366,51 +433,17 @@ impl Tokenizer {
     }
 
     fn scan_single_token(&mut self) -> Result<Option<Token>> {
-        static KEYWORDS: OnceLock<HashMap<&'static str, TokenKind>> = OnceLock::new();
-        let keywords = KEYWORDS.get_or_init(|| {
-            let mut m = HashMap::new();
-            // keywords
-            m.insert("per", TokenKind::Per);
-            m.insert("to", TokenKind::To);
-            m.insert("let", TokenKind::Let);
-            m.insert("fn", TokenKind::Fn);
-            m.insert("dimension", TokenKind::Dimension);
-            m.insert("unit", TokenKind::Unit);
-            m.insert("use", TokenKind::Use);
-            m.insert("struct", TokenKind::Struct);
-            m.insert("long", TokenKind::Long);
-            m.insert("short", TokenKind::Short);
-            m.insert("both", TokenKind::Both);
-            m.insert("none", TokenKind::None);
-            m.insert("if", TokenKind::If);
-            m.insert("then", TokenKind::Then);
-            m.insert("else", TokenKind::Else);
-            m.insert("true", TokenKind::True);
-            m.insert("false", TokenKind::False);
-            m.insert("NaN", TokenKind::NaN);
-            m.insert("inf", TokenKind::Inf);
-
-            // procedures
-            m.insert("print", TokenKind::ProcedurePrint);
-            m.insert("assert", TokenKind::ProcedureAssert);
-            m.insert("assert_eq", TokenKind::ProcedureAssertEq);
-            m.insert("type", TokenKind::ProcedureType);
-
-            // type names
-            m.insert("Bool", TokenKind::Bool);
-            m.insert("String", TokenKind::String);
-            m.insert("DateTime", TokenKind::DateTime);
-            m.insert("Fn", TokenKind::CapitalFn);
-            m.insert("List", TokenKind::List);
-
-            // Keep this list in sync with keywords::KEYWORDS!
-            m
-        });
-
         if self.peek() == Some('#') {
+            let is_version_directive = self.input[self.current_index..]
+                .iter()
+                .copied()
+                .take(VERSION_DIRECTIVE.len())
+                .eq(VERSION_DIRECTIVE.chars());
+
             // skip over comment until newline
             loop {
                 match self.peek() {
+                    None if is_version_directive => break,
                     None => return Ok(None),
                     Some('\n') => break,
                     _ => {
@@ -418,6 +451,18 @@ impl Tokenizer {
                     }
                 }
             }
+
+            if is_version_directive {
+                return Ok(Some(Token {
+                    kind: TokenKind::VersionDirective,
+                    lexeme: self.lexeme(),
+                    span: Span {
+                        start: self.token_start,
+                        end: self.current,
+                        code_source_id: self.code_source_id,
+                    },
+                }));
+            }
         }
 
         let current_char = self.advance();
@@ -438,6 +483,12 @@ impl Tokenizer {
             '{' if !self.interpolation_state.is_inside() => TokenKind::LeftCurly,
             '}' if !self.interpolation_state.is_inside() => TokenKind::RightCurly,
             '≤' => TokenKind::LessOrEqual,
+            '<' if self.match_char('>') => TokenKind::UserOperator,
+            '<' if matches!(self.peek(), Some('*' | '|')) && self.peek2() == Some('>') => {
+                self.advance();
+                self.advance();
+                TokenKind::UserOperator
+            }
             '<' if self.match_char('=') => TokenKind::LessOrEqual,
             '<' => TokenKind::LessThan,
             '≥' => TokenKind::GreaterOrEqual,
@@ -502,8 +553,8 @@ impl Tokenizer {
             c if c.is_ascii_digit() => {
                 self.consume_stream_of_digits(false, false, false)?;
 
-                // decimal part
-                if self.match_char('.') {
+                // decimal part (but not the start of a `..` range operator)
+                if self.peek2() != Some('.') && self.match_char('.') {
                     self.consume_stream_of_digits(false, true, true)?;
                 }
 
@@ -517,6 +568,7 @@ impl Tokenizer {
 
                 TokenKind::Ellipsis
             }
+            '.' if self.match_char('.') => TokenKind::DotDot,
             '.' if self.peek().map_or(false, is_identifier_start) => TokenKind::Period,
             '.' => {
                 self.consume_stream_of_digits(true, true, true)?;
@@ -538,6 +590,7 @@ impl Tokenizer {
             '÷' => TokenKind::Divide,
             '^' => TokenKind::Power,
             ',' => TokenKind::Comma,
+            ';' => TokenKind::Semicolon,
             '⩵' => TokenKind::EqualEqual,
             '=' if self.match_char('=') => TokenKind::EqualEqual,
             '=' => TokenKind::Equal,
@@ -669,7 +722,7 @@ impl Tokenizer {
                     );
                 }
 
-                if let Some(kind) = keywords.get(self.lexeme().as_str()) {
+                if let Some(kind) = keywords().get(self.lexeme().as_str()) {
                     *kind
                 } else {
                     TokenKind::Identifier
@@ -685,9 +738,18 @@ impl Tokenizer {
             }
         };
 
+        // Identifiers are normalized, such that different encodings of the same
+        // name (e.g. a precomposed 'Å' vs. 'A' followed by a combining ring) are
+        // considered equal.
+        let lexeme = if kind == TokenKind::Identifier {
+            self.lexeme().nfc().collect()
+        } else {
+            self.lexeme()
+        };
+
         let token = Some(Token {
             kind,
-            lexeme: self.lexeme(),
+            lexeme,
             span: Span {
                 start: self.token_start,
                 end: self.current,
@@ -775,6 +837,32 @@ fn tokenize_reduced_pretty(input: &str) -> Result<String, String> {
     Ok(ret)
 }
 
+#[test]
+fn test_tokenize_normalizes_identifiers() {
+    use TokenKind::*;
+
+    // NFC: precomposed 'Å' (U+00C5)
+    // NFD: 'A' followed by a combining ring above (U+030A)
+    // Angstrom sign (U+212B), which is normalized to U+00C5
+    for input in ["\u{00C5}", "A\u{030A}", "\u{212B}"] {
+        assert_eq!(
+            tokenize_reduced(input).unwrap()[0],
+            ("\u{00C5}".to_string(), Identifier, (1, 1))
+        );
+    }
+
+    assert_eq!(
+        tokenize_reduced("la\u{0308}nge").unwrap()[0],
+        ("l\u{00E4}nge".to_string(), Identifier, (1, 1))
+    );
+
+    // Strings are not normalized
+    assert_eq!(
+        tokenize_reduced("\"A\u{030A}\"").unwrap()[0],
+        ("\"A\u{030A}\"".to_string(), StringFixed, (1, 1))
+    );
+}
+
 #[test]
 fn test_tokenize_basic() {
     use TokenKind::*;
@@ -869,6 +957,26 @@ fn test_tokenize_basic() {
         ]
     );
 
+    assert_eq!(
+        tokenize_reduced("1..2.5").unwrap(),
+        [
+            ("1".to_string(), Number, (1, 1)),
+            ("..".to_string(), DotDot, (1, 2)),
+            ("2.5".to_string(), Number, (1, 4)),
+            ("".to_string(), Eof, (1, 7))
+        ]
+    );
+
+    assert_eq!(
+        tokenize_reduced("#!version 1.2\n# comment\n#!/usr/bin/env numbat").unwrap(),
+        [
+            ("#!version 1.2".to_string(), VersionDirective, (1, 1)),
+            ("\n".to_string(), Newline, (1, 14)),
+            ("# comment\n".to_string(), Newline, (2, 1)),
+            ("".to_string(), Eof, (3, 22))
+        ]
+    );
+
     insta::assert_snapshot!(
         tokenize_reduced_pretty("~").unwrap_err(),
     @"Error at (1, 1): `Unexpected character: '~'`");
@@ -1179,6 +1287,33 @@ fn test_logical_operators() {
     );
 }
 
+#[test]
+fn test_user_operators() {
+    insta::assert_snapshot!(
+        tokenize_reduced_pretty("a <> b <*> c<|>d").unwrap(),
+        @r###"
+    "a", Identifier, (1, 1)
+    "<>", UserOperator, (1, 3)
+    "b", Identifier, (1, 6)
+    "<*>", UserOperator, (1, 8)
+    "c", Identifier, (1, 12)
+    "<|>", UserOperator, (1, 13)
+    "d", Identifier, (1, 16)
+    "", Eof, (1, 17)
+    "###
+    );
+
+    insta::assert_snapshot!(
+        tokenize_reduced_pretty("a <= b").unwrap(),
+        @r###"
+    "a", Identifier, (1, 1)
+    "<=", LessOrEqual, (1, 3)
+    "b", Identifier, (1, 6)
+    "", Eof, (1, 7)
+    "###
+    );
+}
+
 #[test]
 fn test_is_currency_char() {
     assert!(is_currency_char('€'));
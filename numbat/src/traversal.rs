@@ -1,5 +1,5 @@
 use crate::typechecker::type_scheme::TypeScheme;
-use crate::typed_ast::{Expression, Statement, StructInfo};
+use crate::typed_ast::{Expression, LocalDefinition, Statement, StringPart, StructInfo};
 
 pub trait ForAllTypeSchemes {
     fn for_all_type_schemes(&mut self, f: &mut dyn FnMut(&mut TypeScheme));
@@ -83,14 +83,21 @@ impl ForAllTypeSchemes for Statement {
                 expr.for_all_type_schemes(f);
                 f(type_);
             }
-            Statement::DefineFunction(_, _, _, _, body, fn_type, _, _) => {
+            Statement::ReassignVariable(_, expr) => expr.for_all_type_schemes(f),
+            Statement::DefineFunction(_, _, _, _, local_definitions, body, fn_type, _, _) => {
+                for local_definition in local_definitions {
+                    match local_definition {
+                        LocalDefinition::Variable(_, _, expr) => expr.for_all_type_schemes(f),
+                        LocalDefinition::Function(statement) => statement.for_all_type_schemes(f),
+                    }
+                }
                 if let Some(body) = body {
                     body.for_all_type_schemes(f);
                 }
                 f(fn_type);
             }
             Statement::DefineDimension(_, _) => {}
-            Statement::DefineBaseUnit(_, _, _annotation, type_) => {
+            Statement::DefineBaseUnit(_, _, _, type_) | Statement::DeclareUnit(_, _, _, type_) => {
                 f(type_);
             }
             Statement::DefineDerivedUnit(_, expr, _, _annotation, type_, _) => {
@@ -103,6 +110,7 @@ impl ForAllTypeSchemes for Statement {
                 }
             }
             Statement::DefineStruct(info) => info.for_all_type_schemes(f),
+            Statement::DefineOperator(_, _) => {}
         }
     }
 }
@@ -116,13 +124,21 @@ impl ForAllExpressions for Statement {
         match self {
             Statement::Expression(expr) => expr.for_all_expressions(f),
             Statement::DefineVariable(_, _, expr, _, _, _) => expr.for_all_expressions(f),
-            Statement::DefineFunction(_, _, _, _, body, _, _, _) => {
+            Statement::ReassignVariable(_, expr) => expr.for_all_expressions(f),
+            Statement::DefineFunction(_, _, _, _, local_definitions, body, _, _, _) => {
+                for local_definition in local_definitions {
+                    match local_definition {
+                        LocalDefinition::Variable(_, _, expr) => expr.for_all_expressions(f),
+                        LocalDefinition::Function(statement) => statement.for_all_expressions(f),
+                    }
+                }
                 if let Some(body) = body {
                     body.for_all_expressions(f);
                 }
             }
             Statement::DefineDimension(_, _) => {}
             Statement::DefineBaseUnit(_, _, _, _) => {}
+            Statement::DeclareUnit(_, _, _, _) => {}
             Statement::DefineDerivedUnit(_, expr, _, _, _, _) => expr.for_all_expressions(f),
             Statement::ProcedureCall(_, args) => {
                 for arg in args {
@@ -130,6 +146,7 @@ impl ForAllExpressions for Statement {
                 }
             }
             Statement::DefineStruct(_) => {}
+            Statement::DefineOperator(_, _) => {}
         }
     }
 }
@@ -185,3 +202,21 @@ impl ForAllExpressions for Expression {
         }
     }
 }
+
+/// Like [ForAllExpressions::for_all_expressions], but also visits the expressions in
+/// string interpolations.
+pub(crate) fn for_all_expressions_and_interpolations(
+    node: &impl ForAllExpressions,
+    f: &mut dyn FnMut(&Expression),
+) {
+    node.for_all_expressions(&mut |e| {
+        f(e);
+        if let Expression::String(_, parts) = e {
+            for part in parts {
+                if let StringPart::Interpolation { expr, .. } = part {
+                    for_all_expressions_and_interpolations(expr.as_ref(), f);
+                }
+            }
+        }
+    });
+}
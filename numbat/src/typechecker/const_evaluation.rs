@@ -1,4 +1,8 @@
-use crate::arithmetic::{Exponent, Rational};
+use crate::arithmetic::{approximate_ratio, Exponent, Rational};
+use crate::quantity::Quantity;
+use crate::span::Span;
+use crate::traversal::ForAllExpressions;
+use crate::unit::Unit;
 use crate::{ast, typed_ast};
 
 use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, FromPrimitive, Zero};
@@ -9,17 +13,110 @@ fn to_rational_exponent(exponent_f64: f64) -> Option<Exponent> {
     Rational::from_f64(exponent_f64)
 }
 
+/// Makes sure that the numerator and denominator of an exponent fit into an `i32`. Larger
+/// exponents are almost certainly a mistake, and would quickly lead to overflows when
+/// multiplying dimensions.
+pub fn ensure_exponent_in_range(span: Span, exponent: Exponent) -> Result<Exponent> {
+    let limit = i128::from(i32::MAX);
+    if exponent.numer().abs() > limit || *exponent.denom() > limit {
+        Err(TypeCheckError::ExponentTooLarge(span, exponent))
+    } else {
+        Ok(exponent)
+    }
+}
+
+fn contains_unit_identifier(expr: &typed_ast::Expression) -> bool {
+    let mut found = false;
+    expr.for_all_expressions(&mut |e| {
+        found |= matches!(e, typed_ast::Expression::UnitIdentifier(..));
+    });
+    found
+}
+
+/// Evaluates an expression that involves units (like `2 m / 1 cm`, or the value of
+/// a unit definition) to a quantity. Parts of the expression that do not involve
+/// units are evaluated by [`evaluate_const_expr`].
+pub fn evaluate_const_quantity(
+    expr: &typed_ast::Expression,
+    constant_value: &dyn Fn(&str) -> Option<Exponent>,
+    unit_value: &dyn Fn(&str) -> Option<Unit>,
+) -> Result<Quantity> {
+    let evaluate = |e| evaluate_const_quantity(e, constant_value, unit_value);
+    let incompatible =
+        |_| TypeCheckError::UnsupportedConstEvalExpression(expr.full_span(), "incompatible units");
+
+    match expr {
+        typed_ast::Expression::Scalar(_, n, _) => Ok(Quantity::from_scalar(n.to_f64())),
+        typed_ast::Expression::UnitIdentifier(span, prefix, name, _, _) => {
+            let unit = unit_value(name).ok_or(TypeCheckError::UnsupportedConstEvalExpression(
+                *span,
+                "unit identifier with a value that is not known at compile time",
+            ))?;
+            Ok(Quantity::new_f64(1.0, unit.with_prefix(*prefix)))
+        }
+        typed_ast::Expression::UnaryOperator(_, ast::UnaryOperator::Negate, expr, _) => {
+            Ok(-evaluate(expr)?)
+        }
+        typed_ast::Expression::BinaryOperator(_, typed_ast::BinaryOperator::Add, lhs, rhs, _) => {
+            (&evaluate(lhs)? + &evaluate(rhs)?).map_err(incompatible)
+        }
+        typed_ast::Expression::BinaryOperator(_, typed_ast::BinaryOperator::Sub, lhs, rhs, _) => {
+            (&evaluate(lhs)? - &evaluate(rhs)?).map_err(incompatible)
+        }
+        typed_ast::Expression::BinaryOperator(_, typed_ast::BinaryOperator::Mul, lhs, rhs, _) => {
+            Ok(evaluate(lhs)? * evaluate(rhs)?)
+        }
+        typed_ast::Expression::BinaryOperator(_, typed_ast::BinaryOperator::Div, lhs, rhs, _) => {
+            evaluate(lhs)?.checked_div(evaluate(rhs)?).ok_or(
+                TypeCheckError::DivisionByZeroInConstEvalExpression(expr.full_span()),
+            )
+        }
+        typed_ast::Expression::BinaryOperator(_, typed_ast::BinaryOperator::Power, lhs, rhs, _) => {
+            let exponent = evaluate_const_expr(rhs, constant_value, unit_value)?;
+            evaluate(lhs)?
+                .power(Quantity::from_scalar(rational_to_f64(exponent)))
+                .map_err(incompatible)
+        }
+        typed_ast::Expression::BinaryOperator(
+            _,
+            typed_ast::BinaryOperator::ConvertTo,
+            lhs,
+            rhs,
+            _,
+        ) => evaluate(lhs)?
+            .convert_to(evaluate(rhs)?.unit())
+            .map_err(incompatible),
+        _ => {
+            let value = evaluate_const_expr(expr, constant_value, unit_value)?;
+            Ok(Quantity::from_scalar(rational_to_f64(value)))
+        }
+    }
+}
+
+fn rational_to_f64(value: Exponent) -> f64 {
+    *value.numer() as f64 / *value.denom() as f64
+}
+
 /// Evaluates a limited set of expressions *at compile time*. This is needed to
 /// support type checking of expressions like `(2 * meter)^(2*3 - 4)` where we
 /// need to know not just the *type* but also the *value* of the exponent.
-pub fn evaluate_const_expr(expr: &typed_ast::Expression) -> Result<Exponent> {
+///
+/// Identifiers are looked up via `constant_value`, which returns the value of
+/// variables that have themselves been defined by a constant expression. Units
+/// are looked up via `unit_value`, such that dimensionless ratios of quantities
+/// (like `1 km / 1 m`) can be evaluated as well.
+pub fn evaluate_const_expr(
+    expr: &typed_ast::Expression,
+    constant_value: &dyn Fn(&str) -> Option<Exponent>,
+    unit_value: &dyn Fn(&str) -> Option<Unit>,
+) -> Result<Exponent> {
     match expr {
         typed_ast::Expression::Scalar(span, n, _type) => {
             Ok(to_rational_exponent(n.to_f64())
                 .ok_or(TypeCheckError::NonRationalExponent(*span))?)
         }
         typed_ast::Expression::UnaryOperator(_, ast::UnaryOperator::Negate, ref expr, _) => {
-            Ok(-evaluate_const_expr(expr)?)
+            Ok(-evaluate_const_expr(expr, constant_value, unit_value)?)
         }
         e @ typed_ast::Expression::UnaryOperator(_, ast::UnaryOperator::Factorial, _, _) => Err(
             TypeCheckError::UnsupportedConstEvalExpression(e.full_span(), "factorial"),
@@ -27,9 +124,40 @@ pub fn evaluate_const_expr(expr: &typed_ast::Expression) -> Result<Exponent> {
         e @ typed_ast::Expression::UnaryOperator(_, ast::UnaryOperator::LogicalNeg, _, _) => Err(
             TypeCheckError::UnsupportedConstEvalExpression(e.full_span(), "logical"),
         ),
+        e @ typed_ast::Expression::UnaryOperator(
+            _,
+            ast::UnaryOperator::ConvertToBaseUnits,
+            _,
+            _,
+        ) => Err(TypeCheckError::UnsupportedConstEvalExpression(
+            e.full_span(),
+            "conversion",
+        )),
+        e @ typed_ast::Expression::BinaryOperator(
+            _,
+            typed_ast::BinaryOperator::Add
+            | typed_ast::BinaryOperator::Sub
+            | typed_ast::BinaryOperator::Mul
+            | typed_ast::BinaryOperator::Div
+            | typed_ast::BinaryOperator::Power
+            | typed_ast::BinaryOperator::ConvertTo,
+            _,
+            _,
+            _,
+        ) if contains_unit_identifier(e) => {
+            let quantity = evaluate_const_quantity(e, constant_value, unit_value)?;
+            let value = quantity.as_scalar().map_err(|_| {
+                TypeCheckError::UnsupportedConstEvalExpression(
+                    e.full_span(),
+                    "quantity with a physical dimension",
+                )
+            })?;
+            approximate_ratio(value.to_f64())
+                .ok_or(TypeCheckError::NonRationalExponent(e.full_span()))
+        }
         e @ typed_ast::Expression::BinaryOperator(_span_op, op, lhs_expr, rhs_expr, _) => {
-            let lhs = evaluate_const_expr(lhs_expr)?;
-            let rhs = evaluate_const_expr(rhs_expr)?;
+            let lhs = evaluate_const_expr(lhs_expr, constant_value, unit_value)?;
+            let rhs = evaluate_const_expr(rhs_expr, constant_value, unit_value)?;
             match op {
                 typed_ast::BinaryOperator::Add => Ok(lhs
                     .checked_add(&rhs)
@@ -53,13 +181,20 @@ pub fn evaluate_const_expr(expr: &typed_ast::Expression) -> Result<Exponent> {
                 }
                 typed_ast::BinaryOperator::Power => {
                     if rhs.is_integer() {
-                        Ok(num_traits::checked_pow(
-                            lhs,
-                            rhs.to_integer().try_into().map_err(|_| {
-                                TypeCheckError::OverflowInConstExpr(expr.full_span())
-                            })?,
-                        )
-                        .ok_or_else(|| TypeCheckError::OverflowInConstExpr(expr.full_span()))?)
+                        let exponent = i32::try_from(rhs.to_integer()).map_err(|_| {
+                            TypeCheckError::ExponentTooLarge(rhs_expr.full_span(), rhs)
+                        })?;
+                        let power = num_traits::checked_pow(lhs, exponent.unsigned_abs() as usize)
+                            .ok_or_else(|| TypeCheckError::OverflowInConstExpr(expr.full_span()))?;
+                        if exponent >= 0 {
+                            Ok(power)
+                        } else if power == Rational::zero() {
+                            Err(TypeCheckError::DivisionByZeroInConstEvalExpression(
+                                e.full_span(),
+                            ))
+                        } else {
+                            Ok(power.recip())
+                        }
                     } else {
                         Err(TypeCheckError::UnsupportedConstEvalExpression(
                             e.full_span(),
@@ -67,6 +202,9 @@ pub fn evaluate_const_expr(expr: &typed_ast::Expression) -> Result<Exponent> {
                         ))
                     }
                 }
+                typed_ast::BinaryOperator::Mod => Err(
+                    TypeCheckError::UnsupportedConstEvalExpression(e.full_span(), "modulo"),
+                ),
                 typed_ast::BinaryOperator::ConvertTo => Err(
                     TypeCheckError::UnsupportedConstEvalExpression(e.full_span(), "conversion"),
                 ),
@@ -86,9 +224,11 @@ pub fn evaluate_const_expr(expr: &typed_ast::Expression) -> Result<Exponent> {
                 }
             }
         }
-        e @ typed_ast::Expression::Identifier(..) => Err(
-            TypeCheckError::UnsupportedConstEvalExpression(e.full_span(), "variable"),
-        ),
+        e @ typed_ast::Expression::Identifier(_, name, _) => {
+            constant_value(name).ok_or_else(|| {
+                TypeCheckError::UnsupportedConstEvalExpression(e.full_span(), "variable")
+            })
+        }
         e @ typed_ast::Expression::UnitIdentifier(..) => Err(
             TypeCheckError::UnsupportedConstEvalExpression(e.full_span(), "unit identifier"),
         ),
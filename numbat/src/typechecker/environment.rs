@@ -3,13 +3,14 @@ use crate::dimension::DimensionRegistry;
 use crate::pretty_print::PrettyPrint;
 use crate::span::Span;
 use crate::type_variable::TypeVariable;
-use crate::typed_ast::pretty_print_function_signature;
+use crate::typed_ast::{pretty_print_function_signature, where_clauses, DType};
 use crate::Type;
 
+use super::qualified_type::{Bound, Bounds};
 use super::substitutions::{ApplySubstitution, Substitution, SubstitutionError};
 use super::type_scheme::TypeScheme;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 type Identifier = String;
 
@@ -21,10 +22,48 @@ pub struct FunctionSignature {
     pub type_parameters: Vec<(Span, String, Option<TypeParameterBound>)>,
     pub parameters: Vec<(Span, String, Option<TypeAnnotation>)>,
     pub return_type_annotation: Option<TypeAnnotation>,
+    /// Dimensions that type parameters are required to be equal to (`where D: Length`)
+    pub where_clauses: Vec<(String, DType)>,
     pub fn_type: TypeScheme,
 }
 
 impl FunctionSignature {
+    /// The same signature, with all spans moved to a different code source
+    pub(crate) fn with_code_source_id(&self, id: usize) -> Self {
+        FunctionSignature {
+            name: self.name.clone(),
+            definition_span: self.definition_span.with_code_source_id(id),
+            type_parameters: self
+                .type_parameters
+                .iter()
+                .map(|(span, name, bound)| {
+                    (
+                        span.with_code_source_id(id),
+                        name.clone(),
+                        bound.as_ref().map(|b| b.with_code_source_id(id)),
+                    )
+                })
+                .collect(),
+            parameters: self
+                .parameters
+                .iter()
+                .map(|(span, name, annotation)| {
+                    (
+                        span.with_code_source_id(id),
+                        name.clone(),
+                        annotation.as_ref().map(|a| a.with_code_source_id(id)),
+                    )
+                })
+                .collect(),
+            return_type_annotation: self
+                .return_type_annotation
+                .as_ref()
+                .map(|a| a.with_code_source_id(id)),
+            where_clauses: self.where_clauses.clone(),
+            fn_type: self.fn_type.clone(),
+        }
+    }
+
     pub fn pretty_print(&self, registry: &DimensionRegistry) -> crate::markup::Markup {
         let (fn_type, type_parameters) = self.fn_type.instantiate_for_printing(Some(
             self.type_parameters
@@ -60,6 +99,11 @@ impl FunctionSignature {
             &type_parameters,
             parameters,
             &readable_return_type,
+            &where_clauses(
+                self.type_parameters
+                    .iter()
+                    .map(|(_, name, bound)| (name, bound)),
+            ),
         )
     }
 }
@@ -69,13 +113,14 @@ pub struct FunctionMetadata {
     pub name: Option<String>,
     pub url: Option<String>,
     pub description: Option<String>,
+    pub examples: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
 pub enum IdentifierKind {
     /// A normal identifier (variable, unit) with the place where it has been defined.
     /// The boolean flag signifies whether the identifier is a unit or not
-    Normal(TypeScheme, #[allow(dead_code)] Span, bool),
+    Normal(TypeScheme, Span, bool),
     /// A function
     Function(FunctionSignature, FunctionMetadata),
     /// Identifiers that are defined by the language: `_` and `ans` (see LAST_RESULT_IDENTIFIERS)
@@ -95,17 +140,38 @@ impl IdentifierKind {
 #[derive(Clone, Debug, Default)]
 pub struct Environment {
     identifiers: HashMap<Identifier, IdentifierKind>,
+    /// Variables that have been defined with `let mut`
+    mutable_variables: HashSet<Identifier>,
 }
 
 impl Environment {
     pub fn add(&mut self, i: Identifier, type_: Type, span: Span, is_unit: bool) {
+        self.mutable_variables.remove(&i);
         self.identifiers.insert(
             i,
             IdentifierKind::Normal(TypeScheme::Concrete(type_), span, is_unit),
         );
     }
 
+    pub(crate) fn add_mutable(&mut self, i: Identifier, type_: Type, span: Span) {
+        self.add(i.clone(), type_, span, false);
+        self.mutable_variables.insert(i);
+    }
+
+    pub(crate) fn is_mutable(&self, i: &str) -> bool {
+        self.mutable_variables.contains(i)
+    }
+
+    /// The place where a (non-function) identifier has been defined
+    pub(crate) fn get_definition_span(&self, i: &str) -> Option<Span> {
+        match self.identifiers.get(i) {
+            Some(IdentifierKind::Normal(_, span, _)) => Some(*span),
+            _ => None,
+        }
+    }
+
     pub fn add_scheme(&mut self, i: Identifier, scheme: TypeScheme, span: Span, is_unit: bool) {
+        self.mutable_variables.remove(&i);
         self.identifiers
             .insert(i, IdentifierKind::Normal(scheme, span, is_unit));
     }
@@ -116,11 +182,13 @@ impl Environment {
         signature: FunctionSignature,
         metadata: FunctionMetadata,
     ) {
+        self.mutable_variables.remove(&v);
         self.identifiers
             .insert(v, IdentifierKind::Function(signature, metadata));
     }
 
     pub fn add_predefined(&mut self, v: Identifier, type_: TypeScheme) {
+        self.mutable_variables.remove(&v);
         self.identifiers
             .insert(v, IdentifierKind::Predefined(type_));
     }
@@ -155,14 +223,30 @@ impl Environment {
         }
     }
 
-    pub(crate) fn generalize_types(&mut self, dtype_variables: &[TypeVariable]) {
+    /// Generalize all types in the environment. The `additional_bounds` are added to
+    /// the type schemes of all identifiers whose types contain the bounded variables.
+    pub(crate) fn generalize_types(
+        &mut self,
+        dtype_variables: &[TypeVariable],
+        additional_bounds: &Bounds,
+    ) {
         for (_, kind) in self.identifiers.iter_mut() {
             match kind {
                 IdentifierKind::Normal(t, _, _) => {
-                    t.generalize(dtype_variables);
+                    t.generalize_with_bounds(dtype_variables, additional_bounds.clone());
                 }
                 IdentifierKind::Function(signature, _) => {
-                    signature.fn_type.generalize(dtype_variables);
+                    let where_bounds: Bounds = signature
+                        .where_clauses
+                        .iter()
+                        .map(|(name, dtype)| {
+                            Bound::EqualTo(name.clone(), Type::TPar(name.clone()), dtype.clone())
+                        })
+                        .collect();
+                    signature.fn_type.generalize_with_bounds(
+                        dtype_variables,
+                        where_bounds.union(additional_bounds.clone()),
+                    );
                 }
                 IdentifierKind::Predefined(t) => {
                     t.generalize(dtype_variables);
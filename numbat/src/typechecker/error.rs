@@ -1,4 +1,5 @@
-use crate::ffi::ArityRange;
+use crate::arithmetic::Exponent;
+use crate::ffi::{describe_arity, ArityRange};
 use crate::registry::RegistryError;
 use crate::span::Span;
 use crate::typed_ast::BinaryOperator;
@@ -41,7 +42,7 @@ pub enum TypeCheckError {
         BaseRepresentation,
     ),
 
-    #[error("Function or procedure '{callable_name}' called with {num_args} arguments(s), but needs {}..{}", arity.start(), arity.end())]
+    #[error("Function or procedure '{callable_name}' called with {}, but needs {}", describe_arity(&(*num_args..=*num_args)), describe_arity(arity))]
     WrongArity {
         callable_span: Span,
         callable_name: String,
@@ -59,12 +60,26 @@ pub enum TypeCheckError {
     #[error("Unknown foreign function (without body) '{1}'")]
     UnknownForeignFunction(Span, String),
 
+    #[error("Foreign function or procedure '{1}' is not available in this context")]
+    ForeignFunctionNotAvailable(Span, String),
+
+    #[error("Foreign function (without body) '{function_name}' is declared with {}, but the builtin takes {}", describe_arity(&(*num_parameters..=*num_parameters)), describe_arity(expected_arity))]
+    ForeignFunctionSignatureMismatch {
+        span: Span,
+        function_name: String,
+        num_parameters: usize,
+        expected_arity: ArityRange,
+    },
+
     #[error("Out-of bounds or non-rational exponent value")]
     NonRationalExponent(Span),
 
     #[error("Numerical overflow in const-eval expression")]
     OverflowInConstExpr(Span),
 
+    #[error("Exponent {1} is too large")]
+    ExponentTooLarge(Span, Exponent),
+
     #[error("Expected dimension type, got {1} instead")]
     ExpectedDimensionType(Span, Type),
 
@@ -83,6 +98,12 @@ pub enum TypeCheckError {
     #[error("Incompatible types in {0}")]
     IncompatibleTypesInAnnotation(String, Span, Type, Span, Type, Span),
 
+    #[error("Unknown variable '{1}'. Use `let {1} = …` if you intended to define a new constant.")]
+    AssignmentToUnknownVariable(Span, String),
+
+    #[error("Can not assign to '{2}', since it is not a mutable variable. Use `let mut {2} = …` to define a mutable variable.")]
+    AssignmentToImmutableIdentifier(Span, Option<Span>, String),
+
     #[error("Incompatible types in comparison operator")]
     IncompatibleTypesInComparison(Span, Type, Span, Type, Span),
 
@@ -143,9 +164,15 @@ pub enum TypeCheckError {
     #[error("Missing dimension bound for type parameter")]
     MissingDimBound(Span),
 
+    #[error("Type parameter '{1}' is required to be '{2}', but '{3}' was inferred")]
+    UnsatisfiedConstraint(Span, String, String, String),
+
     #[error("Type for exponentiation operation can not be inferred for this case, consider adding a type annotation for the base")]
     ExponentiationNeedsTypeAnnotation(Span),
 
+    #[error("Decimal numbers are not allowed in exponents of quantities with a dimension in strict mode. Use a fraction like '1/2' instead")]
+    DecimalExponentInStrictMode(Span),
+
     #[error("Derived unit definitions may not contain generic types. Use a variable instead")]
     DerivedUnitDefinitionMustNotBeGeneric(Span),
 
@@ -154,6 +181,15 @@ pub enum TypeCheckError {
 
     #[error("Multiple typed holes in statement")]
     MultipleTypedHoles(Span),
+
+    #[error("Unknown operator '{1}'. Use `operator {1} = …` to bind it to a function.")]
+    UnknownOperator(Span, String),
+
+    #[error("Operators can only be bound to functions with two parameters, but '{1}' has {2}")]
+    OperatorFunctionNotBinary(Span, String, usize),
+
+    #[error("Function '{1}' is defined in a function body and can not call itself")]
+    RecursiveLocalFunction(Span, String),
 }
 
 pub type Result<T> = std::result::Result<T, TypeCheckError>;
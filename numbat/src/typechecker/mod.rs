@@ -11,33 +11,40 @@ pub mod qualified_type;
 mod substitutions;
 pub mod type_scheme;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
+use std::sync::Arc;
 
 use crate::arithmetic::Exponent;
 use crate::ast::{
     self, BinaryOperator, ProcedureKind, StringPart, TypeAnnotation, TypeExpression,
     TypeParameterBound,
 };
+use crate::compiled_function::CompiledFunctionError;
 use crate::dimension::DimensionRegistry;
+use crate::ffi::ForeignFunctionFilter;
 use crate::name_resolution::Namespace;
 use crate::name_resolution::LAST_RESULT_IDENTIFIERS;
 use crate::pretty_print::PrettyPrint;
 use crate::span::Span;
+use crate::traversal::{for_all_expressions_and_interpolations, ForAllExpressions};
 use crate::type_variable::TypeVariable;
 use crate::typed_ast::{self, DType, DTypeFactor, Expression, StructInfo, Type};
+use crate::unit::Unit;
+use crate::warning::Warning;
 use crate::{decorator, ffi, suggestion};
 
-use const_evaluation::evaluate_const_expr;
+use const_evaluation::{ensure_exponent_in_range, evaluate_const_expr, evaluate_const_quantity};
 use constraints::{Constraint, ConstraintSet, ConstraintSolverError, TrivialResultion};
-use environment::{Environment, FunctionMetadata, FunctionSignature};
+use environment::Environment;
+pub(crate) use environment::{FunctionMetadata, FunctionSignature};
 use itertools::Itertools;
 use name_generator::NameGenerator;
 use num_traits::Zero;
 
 pub use error::{Result, TypeCheckError};
 pub use incompatible_dimensions::IncompatibleDimensionsError;
-use qualified_type::Bound;
+use qualified_type::{Bound, Bounds};
 use substitutions::{ApplySubstitution, Substitution};
 use type_scheme::TypeScheme;
 
@@ -48,17 +55,72 @@ fn dtype(e: &Expression) -> Result<DType> {
     }
 }
 
+/// The type that the right hand side of a definition or assignment needs to have
+struct ExpectedType {
+    type_: Type,
+    span: Span,
+    /// Describes where the type comes from in error messages, e.g. "specified dimension"
+    name: &'static str,
+}
+
 #[derive(Clone, Default)]
 pub struct TypeChecker {
     structs: HashMap<String, StructInfo>,
-    registry: DimensionRegistry,
 
-    type_namespace: Namespace,
-    value_namespace: Namespace,
+    // The registry, the namespaces and the environment are shared between clones of the
+    // type checker, and only copied once a clone modifies them. This makes it cheap to
+    // check an expression in a scoped copy of the type checker (see `TypeChecker::type_of`).
+    registry: Arc<DimensionRegistry>,
 
-    env: Environment,
+    type_namespace: Arc<Namespace>,
+    value_namespace: Arc<Namespace>,
+
+    env: Arc<Environment>,
     name_generator: NameGenerator,
     constraints: ConstraintSet,
+
+    /// Type parameter instantiations that need to be checked against the dimension
+    /// from a `where` clause once all constraints have been solved.
+    where_clause_checks: Vec<(Span, String, Type, DType)>,
+
+    /// Functions that user-defined infix operators are bound to (`operator <> = combine`)
+    operators: HashMap<String, String>,
+
+    /// Identifiers that are redefined in the current statement, along with the
+    /// place and the type of their previous definition.
+    redefinitions: Vec<(String, Span, Span, Type)>,
+
+    /// Values of immutable variables that have been defined by a constant expression
+    /// (`let n = 3`), along with the place of their definition. These can be used in
+    /// exponents of dimensionful quantities.
+    constant_exponents: HashMap<String, (Span, Exponent)>,
+
+    /// Units whose value is known at compile time, by each of their names. These are
+    /// used to evaluate dimensionless ratios like `1 km / 1 m` in exponents. Shared,
+    /// since the type checker is cloned for every function definition.
+    unit_values: Arc<HashMap<String, Unit>>,
+
+    /// Dimensions of forward-declared units whose value has not been defined yet
+    unit_declarations: HashMap<String, TypeAnnotation>,
+
+    /// Non-fatal diagnostics that have been collected since the last call to
+    /// [`TypeChecker::take_warnings`]
+    warnings: Vec<Warning>,
+
+    /// Whether dimensionless values are rejected in places where they would otherwise
+    /// be accepted for quantities of any dimension (see [`TypeChecker::set_strict_dimensions`]).
+    strict_dimensions: bool,
+
+    /// Foreign functions and procedures that may be called
+    ffi_filter: ForeignFunctionFilter,
+
+    /// Names of the declared foreign functions that have not been shadowed by a
+    /// function with a body. Calls to these are checked against `ffi_filter`.
+    foreign_functions: HashSet<String>,
+
+    /// Whether calling a function with fewer arguments than parameters results in a
+    /// partially applied function (see [`TypeChecker::set_partial_application`])
+    partial_application: bool,
 }
 
 impl TypeChecker {
@@ -66,6 +128,61 @@ impl TypeChecker {
         Type::TVar(self.name_generator.fresh_type_variable())
     }
 
+    fn check_foreign_function_available(&self, span: Span, name: &str) -> Result<()> {
+        if self.ffi_filter.allows(name) {
+            Ok(())
+        } else {
+            Err(TypeCheckError::ForeignFunctionNotAvailable(
+                span,
+                name.to_string(),
+            ))
+        }
+    }
+
+    /// Like [`TypeChecker::check_foreign_function_available`], but `name` may also refer
+    /// to a function with a body (which is always available).
+    fn check_function_available(&self, span: Span, name: &str) -> Result<()> {
+        if self.foreign_functions.contains(name) {
+            self.check_foreign_function_available(span, name)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// In strict mode, exponents of quantities with a (possibly) non-trivial dimension
+    /// may not contain decimal numbers like `0.33`, which only approximate the intended
+    /// rational exponent (`1/3`).
+    fn check_exact_exponent(&self, exponent: &typed_ast::Expression) -> Result<()> {
+        if !self.strict_dimensions {
+            return Ok(());
+        }
+
+        let mut decimal_span = None;
+        exponent.for_all_expressions(&mut |expr: &typed_ast::Expression| {
+            if let typed_ast::Expression::Scalar(span, n, _) = expr {
+                if n.to_f64().fract() != 0.0 {
+                    decimal_span.get_or_insert(*span);
+                }
+            }
+        });
+
+        match decimal_span {
+            Some(span) => Err(TypeCheckError::DecimalExponentInStrictMode(span)),
+            None => Ok(()),
+        }
+    }
+
+    /// The compile-time value of the variable `name`, if it refers to a variable
+    /// that has been defined by a constant expression (and not been shadowed since).
+    fn constant_exponent(&self, name: &str) -> Option<Exponent> {
+        let (span, value) = self.constant_exponents.get(name)?;
+        (self.env.get_definition_span(name) == Some(*span)).then_some(*value)
+    }
+
+    fn unit_value(&self, name: &str) -> Option<Unit> {
+        self.unit_values.get(name).cloned()
+    }
+
     fn add_equal_constraint(&mut self, lhs: &Type, rhs: &Type) -> TrivialResultion {
         self.constraints
             .add(Constraint::Equal(lhs.clone(), rhs.clone()))
@@ -75,6 +192,25 @@ impl TypeChecker {
         self.constraints.add(Constraint::IsDType(type_.clone()))
     }
 
+    fn add_bound_constraints(&mut self, span: Span, bounds: &Bounds) {
+        for bound in bounds.iter() {
+            match bound {
+                Bound::IsDim(t) => {
+                    self.add_dtype_constraint(t).ok();
+                }
+                Bound::EqualTo(type_parameter, t, dtype) => {
+                    self.add_dtype_constraint(t).ok();
+                    self.where_clause_checks.push((
+                        span,
+                        type_parameter.clone(),
+                        t.clone(),
+                        dtype.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
     fn enforce_dtype(&mut self, type_: &Type, span: Span) -> Result<()> {
         if self
             .constraints
@@ -166,6 +302,192 @@ impl TypeChecker {
         }
     }
 
+    fn elaborate_function_call(
+        &mut self,
+        span: &Span,
+        full_span: &Span,
+        callable: &ast::Expression,
+        arguments_checked: Vec<typed_ast::Expression>,
+    ) -> Result<typed_ast::Expression> {
+        let argument_types = arguments_checked
+            .iter()
+            .map(|e| e.get_type())
+            .collect::<Vec<Type>>();
+
+        // There are two options here. The 'callable' can either be a direct reference
+        // to a (proper) function, or it can be an arbitrary complicated expression
+        // that evaluates to a function "pointer".
+
+        if let Some((name, signature)) = self.get_proper_function_reference(callable) {
+            let name = name.clone(); // TODO: there is probably a better way to get around borrowing issues here
+            let signature = signature.clone(); // TODO: same
+            self.proper_function_call(
+                span,
+                full_span,
+                &name,
+                &signature,
+                arguments_checked,
+                argument_types,
+            )
+        } else {
+            let callable_checked = self.elaborate_expression(callable)?;
+            self.callable_call(span, full_span, callable_checked, arguments_checked)
+        }
+    }
+
+    fn callable_call(
+        &mut self,
+        span: &Span,
+        full_span: &Span,
+        callable_checked: typed_ast::Expression,
+        arguments_checked: Vec<typed_ast::Expression>,
+    ) -> Result<typed_ast::Expression> {
+        let argument_types = arguments_checked
+            .iter()
+            .map(|e| e.get_type())
+            .collect::<Vec<Type>>();
+        let callable_type = callable_checked.get_type();
+
+        let parameter_types = (0..arguments_checked.len())
+            .map(|_| self.fresh_type_variable())
+            .collect::<Vec<_>>();
+        let return_type = self.fresh_type_variable();
+
+        if self
+            .add_equal_constraint(
+                &callable_type,
+                &Type::Fn(parameter_types.clone(), Box::new(return_type.clone())),
+            )
+            .is_trivially_violated()
+        {
+            match callable_type {
+                Type::Fn(concrete_parameter_types, _) => {
+                    let num_parameters = concrete_parameter_types.len();
+                    let num_arguments = arguments_checked.len();
+
+                    if num_parameters != num_arguments {
+                        return Err(TypeCheckError::WrongArity {
+                            callable_span: *span,
+                            callable_name: "function".into(),
+                            callable_definition_span: None,
+                            arity: num_parameters..=num_parameters,
+                            num_args: num_arguments,
+                        });
+                    }
+
+                    // for (param_type, arg_checked) in
+                    //     concrete_parameter_types.iter().zip(&arguments_checked)
+                    // {
+                    //     if &arg_checked.get_type() != param_type {
+                    //         return Err(
+                    //             TypeCheckError::IncompatibleTypesInFunctionCall(
+                    //                 None,
+                    //                 param_type.clone(),
+                    //                 arg_checked.full_span(),
+                    //                 arg_checked.get_type(),
+                    //             ),
+                    //         );
+                    //     }
+                    // }
+                }
+                _ => {
+                    return Err(TypeCheckError::OnlyFunctionsAndReferencesCanBeCalled(
+                        callable_checked.full_span(),
+                    ));
+                }
+            }
+        }
+
+        for ((argument_type, arguments_checked), parameter_type) in argument_types
+            .iter()
+            .zip(&arguments_checked)
+            .zip(&parameter_types)
+        {
+            if self
+                .add_equal_constraint(argument_type, parameter_type)
+                .is_trivially_violated()
+            {
+                return Err(TypeCheckError::IncompatibleTypesInFunctionCall(
+                    Some(arguments_checked.full_span()),
+                    argument_type.clone(),
+                    callable_checked.full_span(),
+                    parameter_type.clone(),
+                ));
+            }
+        }
+
+        Ok(typed_ast::Expression::CallableCall(
+            *full_span,
+            Box::new(callable_checked),
+            arguments_checked,
+            TypeScheme::concrete(return_type),
+        ))
+    }
+
+    /// Whether `x.f(…)` calls the field `f` of `x`, rather than being sugar for `f(x, …)`.
+    /// If the type of `x` is not known yet, it is a field access unless `f` is in scope.
+    fn has_field(&self, type_: &Type, field_name: &str) -> bool {
+        if type_.is_closed() {
+            matches!(type_, Type::Struct(struct_info) if struct_info.fields.contains_key(field_name))
+        } else {
+            self.env.get_identifier_type(field_name).is_none()
+        }
+    }
+
+    fn elaborate_field_access(
+        &mut self,
+        full_span: Span,
+        ident_span: Span,
+        expr_checked: typed_ast::Expression,
+        expr_span: Span,
+        field_name: &str,
+    ) -> Result<typed_ast::Expression> {
+        let type_ = expr_checked.get_type();
+
+        let field_type = if type_.is_closed() {
+            let Type::Struct(ref struct_info) = type_ else {
+                return Err(TypeCheckError::FieldAccessOfNonStructType(
+                    ident_span,
+                    expr_span,
+                    field_name.to_string(),
+                    type_.clone(),
+                ));
+            };
+
+            let Some((_, field_type)) = struct_info.fields.get(field_name) else {
+                return Err(TypeCheckError::UnknownFieldAccess(
+                    ident_span,
+                    expr_span,
+                    field_name.to_string(),
+                    type_.clone(),
+                ));
+            };
+
+            field_type.clone()
+        } else {
+            let field_type = self.fresh_type_variable();
+
+            self.constraints
+                .add(Constraint::HasField(
+                    type_.clone(),
+                    field_name.to_owned(),
+                    field_type.clone(),
+                ))
+                .ok();
+
+            field_type
+        };
+
+        Ok(Expression::AccessField(
+            ident_span,
+            full_span,
+            Box::new(expr_checked),
+            field_name.to_owned(),
+            TypeScheme::concrete(type_),
+            TypeScheme::concrete(field_type),
+        ))
+    }
+
     fn proper_function_call(
         &mut self,
         span: &Span,
@@ -181,9 +503,12 @@ impl TypeChecker {
             type_parameters: _,
             parameters,
             return_type_annotation: _,
+            where_clauses: _,
             fn_type,
         } = signature;
 
+        self.check_function_available(*span, function_name)?;
+
         let fn_type = match fn_type {
             TypeScheme::Concrete(t) => {
                 // This branch is needed for recursive functions, where the type of the function
@@ -193,9 +518,7 @@ impl TypeChecker {
             TypeScheme::Quantified(_, _) => {
                 let qt = fn_type.instantiate(&mut self.name_generator);
 
-                for Bound::IsDim(t) in qt.bounds.iter() {
-                    self.add_dtype_constraint(t).ok();
-                }
+                self.add_bound_constraints(*full_span, &qt.bounds);
 
                 qt.inner
             }
@@ -207,7 +530,10 @@ impl TypeChecker {
 
         let arity_range = parameters.len()..=parameters.len();
 
-        if !arity_range.contains(&arguments.len()) {
+        let is_partial_application =
+            self.partial_application && !arguments.is_empty() && arguments.len() < parameters.len();
+
+        if !arity_range.contains(&arguments.len()) && !is_partial_application {
             return Err(TypeCheckError::WrongArity {
                 callable_span: *span,
                 callable_name: function_name.into(),
@@ -217,6 +543,53 @@ impl TypeChecker {
             });
         }
 
+        // Arguments for parameters of the same generic type need to have the same
+        // dimension, e.g. `x` and `target` in `value_in<T: Dim>(x: T, target: T)`. This
+        // is checked here to report a readable error instead of unsolvable constraints.
+        for idx in 0..argument_types.len().min(parameter_types.len()) {
+            if parameter_types[idx].is_closed() {
+                continue;
+            }
+            let Some(previous_idx) = (0..idx).find(|&i| parameter_types[i] == parameter_types[idx])
+            else {
+                continue;
+            };
+
+            if let (Type::Dimension(previous_dtype), Type::Dimension(argument_dtype)) =
+                (&argument_types[previous_idx], &argument_types[idx])
+            {
+                if previous_dtype.type_variables(true).is_empty()
+                    && argument_dtype.type_variables(true).is_empty()
+                    && previous_dtype.to_base_representation()
+                        != argument_dtype.to_base_representation()
+                {
+                    return Err(TypeCheckError::IncompatibleDimensions(
+                        IncompatibleDimensionsError {
+                            span_operation: *span,
+                            operation: format!(
+                                "argument {num} of function call to '{name}'",
+                                num = idx + 1,
+                                name = function_name
+                            ),
+                            span_expected: arguments[previous_idx].full_span(),
+                            expected_name: "previous argument",
+                            expected_dimensions: self.registry.get_derived_entry_names_for(
+                                &previous_dtype.to_base_representation(),
+                            ),
+                            expected_type: previous_dtype.to_base_representation(),
+                            span_actual: arguments[idx].full_span(),
+                            actual_name: "         argument",
+                            actual_name_for_fix: "function argument",
+                            actual_dimensions: self.registry.get_derived_entry_names_for(
+                                &argument_dtype.to_base_representation(),
+                            ),
+                            actual_type: argument_dtype.to_base_representation(),
+                        },
+                    ));
+                }
+            }
+        }
+
         for (idx, ((parameter_span, parameter_type), argument_type)) in parameters
             .iter()
             .map(|p| p.0)
@@ -266,19 +639,30 @@ impl TypeChecker {
             }
         }
 
+        // A partial application `f(a₁, …, aₖ)` of a function `f: Fn[(T₁, …, Tₙ) -> R]`
+        // with k < n has the type `Fn[(Tₖ₊₁, …, Tₙ) -> R]`
+        let result_type = if is_partial_application {
+            Type::Fn(parameter_types[arguments.len()..].to_vec(), return_type)
+        } else {
+            *return_type
+        };
+
         Ok(typed_ast::Expression::FunctionCall(
             *span,
             *full_span,
             function_name.into(),
             arguments,
-            TypeScheme::concrete(return_type.as_ref().clone()),
+            TypeScheme::concrete(result_type),
         ))
     }
 
     fn elaborate_expression(&mut self, ast: &ast::Expression) -> Result<typed_ast::Expression> {
         Ok(match ast {
             ast::Expression::Scalar(span, n)
-                if n.to_f64().is_zero() || n.to_f64().is_infinite() || n.to_f64().is_nan() =>
+                if !self.strict_dimensions
+                    && (n.to_f64().is_zero()
+                        || n.to_f64().is_infinite()
+                        || n.to_f64().is_nan()) =>
             {
                 let polymorphic_zero_type = self.fresh_type_variable();
                 self.add_dtype_constraint(&polymorphic_zero_type).ok();
@@ -293,12 +677,11 @@ impl TypeChecker {
             }
             ast::Expression::Identifier(span, name) => {
                 let type_scheme = self.identifier_type(*span, name)?.clone();
+                self.check_function_available(*span, name)?;
 
                 let qt = type_scheme.instantiate(&mut self.name_generator);
 
-                for Bound::IsDim(t) in qt.bounds.iter() {
-                    self.constraints.add(Constraint::IsDType(t.clone())).ok();
-                }
+                self.add_bound_constraints(*span, &qt.bounds);
 
                 typed_ast::Expression::Identifier(
                     *span,
@@ -311,9 +694,7 @@ impl TypeChecker {
 
                 let qt = type_scheme.instantiate(&mut self.name_generator);
 
-                for Bound::IsDim(t) in qt.bounds.iter() {
-                    self.constraints.add(Constraint::IsDType(t.clone())).ok();
-                }
+                self.add_bound_constraints(*span, &qt.bounds);
 
                 typed_ast::Expression::UnitIdentifier(
                     *span,
@@ -339,7 +720,7 @@ impl TypeChecker {
                             ));
                         }
                     }
-                    ast::UnaryOperator::Negate => {
+                    ast::UnaryOperator::Negate | ast::UnaryOperator::ConvertToBaseUnits => {
                         self.enforce_dtype(&type_, expr.full_span())?;
                     }
                     ast::UnaryOperator::LogicalNeg => {
@@ -480,6 +861,7 @@ impl TypeChecker {
                                         typed_ast::BinaryOperator::Sub => "subtraction".into(),
                                         typed_ast::BinaryOperator::Mul => "multiplication".into(),
                                         typed_ast::BinaryOperator::Div => "division".into(),
+                                        typed_ast::BinaryOperator::Mod => "modulo".into(),
                                         typed_ast::BinaryOperator::Power => "exponentiation".into(),
                                         typed_ast::BinaryOperator::ConvertTo => {
                                             "unit conversion".into()
@@ -521,6 +903,7 @@ impl TypeChecker {
                     let type_ = match op {
                         typed_ast::BinaryOperator::Add => get_type_and_assert_equal_dtypes()?,
                         typed_ast::BinaryOperator::Sub => get_type_and_assert_equal_dtypes()?,
+                        typed_ast::BinaryOperator::Mod => get_type_and_assert_equal_dtypes()?,
                         typed_ast::BinaryOperator::Mul | typed_ast::BinaryOperator::Div => {
                             let type_lhs = lhs_checked.get_type();
                             let type_rhs = rhs_checked.get_type();
@@ -634,11 +1017,30 @@ impl TypeChecker {
                                     Type::Dimension(base_dtype)
                                 }
                                 Type::Dimension(base_dtype) => {
-                                    let exponent = evaluate_const_expr(&rhs_checked)?;
+                                    self.check_exact_exponent(&rhs_checked)?;
+                                    let exponent = evaluate_const_expr(
+                                        &rhs_checked,
+                                        &|name| self.constant_exponent(name),
+                                        &|name| self.unit_value(name),
+                                    )?;
+                                    let exponent = ensure_exponent_in_range(
+                                        rhs_checked.full_span(),
+                                        exponent,
+                                    )?;
                                     Type::Dimension(base_dtype.power(exponent))
                                 }
                                 _ => {
-                                    if let Ok(exponent) = evaluate_const_expr(&rhs_checked) {
+                                    self.check_exact_exponent(&rhs_checked)?;
+                                    if let Ok(exponent) = evaluate_const_expr(
+                                        &rhs_checked,
+                                        &|name| self.constant_exponent(name),
+                                        &|name| self.unit_value(name),
+                                    ) {
+                                        let exponent = ensure_exponent_in_range(
+                                            rhs_checked.full_span(),
+                                            exponent,
+                                        )?;
+
                                         // Type inference in this case follows a similar pattern to multiplication/division. See
                                         // there for an explanation
 
@@ -732,109 +1134,71 @@ impl TypeChecker {
                     )
                 }
             }
-            ast::Expression::FunctionCall(span, full_span, callable, args) => {
-                let arguments_checked = args
-                    .iter()
-                    .map(|a| self.elaborate_expression(a))
-                    .collect::<Result<Vec<_>>>()?;
-                let argument_types = arguments_checked
-                    .iter()
-                    .map(|e| e.get_type())
-                    .collect::<Vec<Type>>();
-
-                // There are two options here. The 'callable' can either be a direct reference
-                // to a (proper) function, or it can be an arbitrary complicated expression
-                // that evaluates to a function "pointer".
-
-                if let Some((name, signature)) = self.get_proper_function_reference(callable) {
-                    let name = name.clone(); // TODO: there is probably a better way to get around borrowing issues here
-                    let signature = signature.clone(); // TODO: same
-                    self.proper_function_call(
-                        span,
-                        full_span,
-                        &name,
-                        &signature,
-                        arguments_checked,
-                        argument_types,
-                    )?
-                } else {
-                    let callable_checked = self.elaborate_expression(callable)?;
-                    let callable_type = callable_checked.get_type();
-
-                    let parameter_types = (0..arguments_checked.len())
-                        .map(|_| self.fresh_type_variable())
-                        .collect::<Vec<_>>();
-                    let return_type = self.fresh_type_variable();
-
-                    if self
-                        .add_equal_constraint(
-                            &callable_type,
-                            &Type::Fn(parameter_types.clone(), Box::new(return_type.clone())),
-                        )
-                        .is_trivially_violated()
-                    {
-                        match callable_type {
-                            Type::Fn(concrete_parameter_types, _) => {
-                                let num_parameters = concrete_parameter_types.len();
-                                let num_arguments = arguments_checked.len();
-
-                                if num_parameters != num_arguments {
-                                    return Err(TypeCheckError::WrongArity {
-                                        callable_span: *span,
-                                        callable_name: "function".into(),
-                                        callable_definition_span: None,
-                                        arity: num_parameters..=num_parameters,
-                                        num_args: num_arguments,
-                                    });
-                                }
+            ast::Expression::UserOperator {
+                symbol,
+                lhs,
+                rhs,
+                span_op,
+            } => {
+                let Some(function_name) = self.operators.get(symbol) else {
+                    return Err(TypeCheckError::UnknownOperator(*span_op, symbol.clone()));
+                };
 
-                                // for (param_type, arg_checked) in
-                                //     concrete_parameter_types.iter().zip(&arguments_checked)
-                                // {
-                                //     if &arg_checked.get_type() != param_type {
-                                //         return Err(
-                                //             TypeCheckError::IncompatibleTypesInFunctionCall(
-                                //                 None,
-                                //                 param_type.clone(),
-                                //                 arg_checked.full_span(),
-                                //                 arg_checked.get_type(),
-                                //             ),
-                                //         );
-                                //     }
-                                // }
-                            }
-                            _ => {
-                                return Err(TypeCheckError::OnlyFunctionsAndReferencesCanBeCalled(
-                                    callable.full_span(),
-                                ));
+                // Desugar `a <> b` to `combine(a, b)`
+                let call = ast::Expression::FunctionCall(
+                    *span_op,
+                    ast.full_span(),
+                    Box::new(ast::Expression::Identifier(*span_op, function_name.clone())),
+                    vec![lhs.as_ref().clone(), rhs.as_ref().clone()],
+                );
+                self.elaborate_expression(&call)?
+            }
+            ast::Expression::FunctionCall(span, full_span, callable, args) => {
+                match callable.as_ref() {
+                    ast::Expression::AccessField(field_span, ident_span, receiver, field_name) => {
+                        let receiver_checked = self.elaborate_expression(receiver)?;
+
+                        if self.has_field(&receiver_checked.get_type(), field_name) {
+                            let callable_checked = self.elaborate_field_access(
+                                *field_span,
+                                *ident_span,
+                                receiver_checked,
+                                receiver.full_span(),
+                                field_name,
+                            )?;
+                            let arguments_checked = args
+                                .iter()
+                                .map(|a| self.elaborate_expression(a))
+                                .collect::<Result<Vec<_>>>()?;
+                            self.callable_call(
+                                span,
+                                full_span,
+                                callable_checked,
+                                arguments_checked,
+                            )?
+                        } else {
+                            // `x.f(a, b)` is sugar for `f(x, a, b)`, unless `x` has a field `f`
+                            let mut arguments_checked = vec![receiver_checked];
+                            for arg in args {
+                                arguments_checked.push(self.elaborate_expression(arg)?);
                             }
+                            let function =
+                                ast::Expression::Identifier(*ident_span, field_name.clone());
+                            self.elaborate_function_call(
+                                ident_span,
+                                full_span,
+                                &function,
+                                arguments_checked,
+                            )?
                         }
                     }
-
-                    for ((argument_type, arguments_checked), parameter_type) in argument_types
-                        .iter()
-                        .zip(&arguments_checked)
-                        .zip(&parameter_types)
-                    {
-                        if self
-                            .add_equal_constraint(argument_type, parameter_type)
-                            .is_trivially_violated()
-                        {
-                            return Err(TypeCheckError::IncompatibleTypesInFunctionCall(
-                                Some(arguments_checked.full_span()),
-                                argument_type.clone(),
-                                callable.full_span(),
-                                parameter_type.clone(),
-                            ));
-                        }
+                    _ => {
+                        let arguments_checked = args
+                            .iter()
+                            .map(|a| self.elaborate_expression(a))
+                            .collect::<Result<Vec<_>>>()?;
+                        self.elaborate_function_call(span, full_span, callable, arguments_checked)?
                     }
-
-                    typed_ast::Expression::CallableCall(
-                        *full_span,
-                        Box::new(callable_checked),
-                        arguments_checked,
-                        TypeScheme::concrete(return_type),
-                    )
                 }
             }
             ast::Expression::Boolean(span, val) => typed_ast::Expression::Boolean(*span, *val),
@@ -968,51 +1332,13 @@ impl TypeChecker {
             }
             ast::Expression::AccessField(full_span, ident_span, expr, field_name) => {
                 let expr_checked = self.elaborate_expression(expr)?;
-
-                let type_ = expr_checked.get_type();
-
-                let field_type = if type_.is_closed() {
-                    let Type::Struct(ref struct_info) = type_ else {
-                        return Err(TypeCheckError::FieldAccessOfNonStructType(
-                            *ident_span,
-                            expr.full_span(),
-                            field_name.to_string(),
-                            type_.clone(),
-                        ));
-                    };
-
-                    let Some((_, field_type)) = struct_info.fields.get(field_name) else {
-                        return Err(TypeCheckError::UnknownFieldAccess(
-                            *ident_span,
-                            expr.full_span(),
-                            field_name.to_string(),
-                            type_.clone(),
-                        ));
-                    };
-
-                    field_type.clone()
-                } else {
-                    let field_type = self.fresh_type_variable();
-
-                    self.constraints
-                        .add(Constraint::HasField(
-                            type_.clone(),
-                            field_name.clone(),
-                            field_type.clone(),
-                        ))
-                        .ok();
-
-                    field_type
-                };
-
-                Expression::AccessField(
-                    *ident_span,
+                self.elaborate_field_access(
                     *full_span,
-                    Box::new(expr_checked),
-                    field_name.to_owned(),
-                    TypeScheme::concrete(type_),
-                    TypeScheme::concrete(field_type),
-                )
+                    *ident_span,
+                    expr_checked,
+                    expr.full_span(),
+                    field_name,
+                )?
             }
             ast::Expression::List(span, elements) => {
                 let elements_checked = elements
@@ -1064,12 +1390,141 @@ impl TypeChecker {
         })
     }
 
+    /// Elaborates the right hand side of a variable or derived unit definition and
+    /// checks it against the (optional) type annotation. `operation` is used in
+    /// error messages for incompatible dimensions, e.g. "variable definition", and
+    /// `type_operation` in error messages for other incompatible types.
+    fn check_annotated_definition(
+        &mut self,
+        operation: &str,
+        type_operation: &str,
+        identifier_span: Span,
+        expr: &ast::Expression,
+        type_annotation: Option<&TypeAnnotation>,
+    ) -> Result<(typed_ast::Expression, Type)> {
+        let expr_checked = self.elaborate_expression(expr)?;
+        let type_deduced = expr_checked.get_type();
+
+        if let Some(type_annotation) = type_annotation {
+            self.check_against_annotation(
+                operation,
+                type_operation,
+                identifier_span,
+                expr,
+                &expr_checked,
+                type_annotation,
+            )?;
+        }
+
+        Ok((expr_checked, type_deduced))
+    }
+
+    fn check_against_annotation(
+        &mut self,
+        operation: &str,
+        type_operation: &str,
+        identifier_span: Span,
+        expr: &ast::Expression,
+        expr_checked: &typed_ast::Expression,
+        type_annotation: &TypeAnnotation,
+    ) -> Result<()> {
+        let expected = ExpectedType {
+            type_: self.type_from_annotation(type_annotation)?,
+            span: type_annotation.full_span(),
+            name: "specified dimension",
+        };
+        self.check_against_expected_type(
+            operation,
+            type_operation,
+            identifier_span,
+            expr,
+            expr_checked,
+            expected,
+        )
+    }
+
+    /// Checks the type of the right hand side of a definition or assignment against the
+    /// type it is expected to have. See [`TypeChecker::check_annotated_definition`] for
+    /// the meaning of `operation` and `type_operation`.
+    fn check_against_expected_type(
+        &mut self,
+        operation: &str,
+        type_operation: &str,
+        identifier_span: Span,
+        expr: &ast::Expression,
+        expr_checked: &typed_ast::Expression,
+        expected: ExpectedType,
+    ) -> Result<()> {
+        let type_deduced = expr_checked.get_type();
+
+        match (&type_deduced, &expected.type_) {
+            (Type::Dimension(dexpr_deduced), Type::Dimension(dexpr_expected))
+                if type_deduced.is_closed() && expected.type_.is_closed() =>
+            {
+                if dexpr_deduced != dexpr_expected {
+                    return Err(TypeCheckError::IncompatibleDimensions(
+                        IncompatibleDimensionsError {
+                            span_operation: identifier_span,
+                            operation: operation.into(),
+                            span_expected: expected.span,
+                            expected_name: expected.name,
+                            expected_dimensions: self.registry.get_derived_entry_names_for(
+                                &dexpr_expected.to_base_representation(),
+                            ),
+                            expected_type: dexpr_expected.to_base_representation(),
+                            span_actual: expr.full_span(),
+                            actual_name: "   actual dimension",
+                            actual_name_for_fix: "right hand side expression",
+                            actual_dimensions: self.registry.get_derived_entry_names_for(
+                                &dexpr_deduced.to_base_representation(),
+                            ),
+                            actual_type: dexpr_deduced.to_base_representation(),
+                        },
+                    ));
+                }
+            }
+            (deduced, expected_type) => {
+                if self
+                    .add_equal_constraint(deduced, expected_type)
+                    .is_trivially_violated()
+                {
+                    return Err(TypeCheckError::IncompatibleTypesInAnnotation(
+                        type_operation.into(),
+                        identifier_span,
+                        expected_type.clone(),
+                        expected.span,
+                        deduced.clone(),
+                        expr_checked.full_span(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remember the type of an identifier that is about to be redefined, such that
+    /// we can warn about a change of its type once all constraints have been solved.
+    fn record_redefinition(&mut self, name: &str, span: Span) {
+        if let (Some(original_type), Some(original_span)) = (
+            self.env.get_identifier_type(name),
+            self.env.get_definition_span(name),
+        ) {
+            self.redefinitions.push((
+                name.to_string(),
+                span,
+                original_span,
+                original_type.to_concrete_type(),
+            ));
+        }
+    }
+
     fn elaborate_statement(&mut self, ast: &ast::Statement) -> Result<typed_ast::Statement> {
         Ok(match ast {
-            ast::Statement::Expression(expr) => {
+            ast::Statement::Expression(expr) | ast::Statement::SuppressedExpression(expr) => {
                 let checked_expr = self.elaborate_expression(expr)?;
                 for &identifier in LAST_RESULT_IDENTIFIERS {
-                    self.env.add_predefined(
+                    Arc::make_mut(&mut self.env).add_predefined(
                         identifier.into(),
                         TypeScheme::concrete(checked_expr.get_type()),
                     );
@@ -1082,66 +1537,56 @@ impl TypeChecker {
                 expr,
                 type_annotation,
                 decorators,
+                mutable,
             } => {
-                let expr_checked = self.elaborate_expression(expr)?;
-                let type_deduced = expr_checked.get_type();
+                let (expr_checked, type_deduced) = self.check_annotated_definition(
+                    "variable definition",
+                    "definition",
+                    *identifier_span,
+                    expr,
+                    type_annotation.as_ref(),
+                )?;
 
-                if let Some(ref type_annotation) = type_annotation {
-                    let type_annotated = self.type_from_annotation(type_annotation)?;
+                let constant_exponent = if *mutable {
+                    None
+                } else {
+                    evaluate_const_expr(
+                        &expr_checked,
+                        &|name| self.constant_exponent(name),
+                        &|name| self.unit_value(name),
+                    )
+                    .ok()
+                };
 
-                    match (&type_deduced, &type_annotated) {
-                        (Type::Dimension(dexpr_deduced), Type::Dimension(dexpr_specified))
-                            if type_deduced.is_closed() && type_annotated.is_closed() =>
-                        {
-                            if dexpr_deduced != dexpr_specified {
-                                return Err(TypeCheckError::IncompatibleDimensions(
-                                    IncompatibleDimensionsError {
-                                        span_operation: *identifier_span,
-                                        operation: "variable definition".into(),
-                                        span_expected: type_annotation.full_span(),
-                                        expected_name: "specified dimension",
-                                        expected_dimensions: self
-                                            .registry
-                                            .get_derived_entry_names_for(
-                                                &dexpr_specified.to_base_representation(),
-                                            ),
-                                        expected_type: dexpr_specified.to_base_representation(),
-                                        span_actual: expr.full_span(),
-                                        actual_name: "   actual dimension",
-                                        actual_name_for_fix: "right hand side expression",
-                                        actual_dimensions: self
-                                            .registry
-                                            .get_derived_entry_names_for(
-                                                &dexpr_deduced.to_base_representation(),
-                                            ),
-                                        actual_type: dexpr_deduced.to_base_representation(),
-                                    },
-                                ));
-                            }
+                for (name, _) in decorator::name_and_aliases(identifier, decorators) {
+                    self.record_redefinition(name, *identifier_span);
+
+                    match constant_exponent {
+                        Some(value) => {
+                            self.constant_exponents
+                                .insert(name.clone(), (*identifier_span, value));
                         }
-                        (deduced, annotated) => {
-                            if self
-                                .add_equal_constraint(deduced, annotated)
-                                .is_trivially_violated()
-                            {
-                                return Err(TypeCheckError::IncompatibleTypesInAnnotation(
-                                    "definition".into(),
-                                    *identifier_span,
-                                    annotated.clone(),
-                                    type_annotation.full_span(),
-                                    deduced.clone(),
-                                    expr_checked.full_span(),
-                                ));
-                            }
+                        None => {
+                            self.constant_exponents.remove(name);
                         }
                     }
-                }
 
-                for (name, _) in decorator::name_and_aliases(identifier, decorators) {
-                    self.env
-                        .add(name.clone(), type_deduced.clone(), *identifier_span, false);
+                    if *mutable {
+                        Arc::make_mut(&mut self.env).add_mutable(
+                            name.clone(),
+                            type_deduced.clone(),
+                            *identifier_span,
+                        );
+                    } else {
+                        Arc::make_mut(&mut self.env).add(
+                            name.clone(),
+                            type_deduced.clone(),
+                            *identifier_span,
+                            false,
+                        );
+                    }
 
-                    self.value_namespace.add_identifier_allow_override(
+                    Arc::make_mut(&mut self.value_namespace).add_identifier_allow_override(
                         name.clone(),
                         *identifier_span,
                         "constant".to_owned(),
@@ -1157,6 +1602,48 @@ impl TypeChecker {
                     crate::markup::empty(),
                 )
             }
+            ast::Statement::ReassignVariable {
+                identifier_span,
+                identifier,
+                expr,
+            } => {
+                let Some(type_scheme) = self.env.get_identifier_type(identifier) else {
+                    return Err(TypeCheckError::AssignmentToUnknownVariable(
+                        *identifier_span,
+                        identifier.clone(),
+                    ));
+                };
+                let definition_span = self.env.get_definition_span(identifier);
+                if !self.env.is_mutable(identifier) {
+                    return Err(TypeCheckError::AssignmentToImmutableIdentifier(
+                        *identifier_span,
+                        definition_span,
+                        identifier.clone(),
+                    ));
+                }
+                let definition_span = definition_span.unwrap_or(*identifier_span);
+
+                let qt = type_scheme.instantiate(&mut self.name_generator);
+                self.add_bound_constraints(*identifier_span, &qt.bounds);
+                let type_current = qt.inner;
+
+                // A mutable variable needs to keep its type
+                let expr_checked = self.elaborate_expression(expr)?;
+                self.check_against_expected_type(
+                    "variable assignment",
+                    "assignment",
+                    *identifier_span,
+                    expr,
+                    &expr_checked,
+                    ExpectedType {
+                        type_: type_current,
+                        span: definition_span,
+                        name: " variable dimension",
+                    },
+                )?;
+
+                typed_ast::Statement::ReassignVariable(identifier.clone(), expr_checked)
+            }
             ast::Statement::DefineBaseUnit(span, unit_name, type_annotation, decorators) => {
                 let type_specified = if let Some(dexpr) = type_annotation {
                     let dtype: DType = self
@@ -1178,18 +1665,23 @@ impl TypeChecker {
                     // In a unit definition like 'unit pixel' without a specified type,
                     // we add a new type for the user
                     let type_name = unit_name.to_upper_camel_case();
-                    self.registry
+                    Arc::make_mut(&mut self.registry)
                         .add_base_dimension(&type_name)
                         .map_err(TypeCheckError::RegistryError)?
                         .into()
                 };
+                let unit = Unit::new_base(
+                    unit_name,
+                    decorator::get_canonical_unit_name(unit_name, decorators),
+                );
                 for (name, _) in decorator::name_and_aliases(unit_name, decorators) {
-                    self.env.add(
+                    Arc::make_mut(&mut self.env).add(
                         name.clone(),
                         Type::Dimension(type_specified.clone()),
                         *span,
                         true,
                     );
+                    Arc::make_mut(&mut self.unit_values).insert(name.clone(), unit.clone());
                 }
 
                 typed_ast::Statement::DefineBaseUnit(
@@ -1199,80 +1691,96 @@ impl TypeChecker {
                     TypeScheme::concrete(Type::Dimension(type_specified)),
                 )
             }
+            ast::Statement::DeclareUnit(span, unit_name, dexpr, decorators) => {
+                let dtype: DType = self
+                    .registry
+                    .get_base_representation(dexpr)
+                    .map_err(TypeCheckError::RegistryError)?
+                    .into();
+
+                for (name, _) in decorator::name_and_aliases(unit_name, decorators) {
+                    Arc::make_mut(&mut self.env).add(
+                        name.clone(),
+                        Type::Dimension(dtype.clone()),
+                        *span,
+                        true,
+                    );
+                }
+
+                let annotation = TypeAnnotation::TypeExpression(dexpr.clone());
+                self.unit_declarations
+                    .insert(unit_name.clone(), annotation.clone());
+
+                typed_ast::Statement::DeclareUnit(
+                    unit_name.clone(),
+                    decorators.clone(),
+                    annotation,
+                    TypeScheme::concrete(Type::Dimension(dtype)),
+                )
+            }
             ast::Statement::DefineDerivedUnit {
                 identifier_span,
                 identifier,
                 expr,
-                type_annotation_span,
+                type_annotation_span: _,
                 type_annotation,
                 decorators,
             } => {
-                // TODO: this is the *exact same code* that we have above for
-                // variable definitions => deduplicate this somehow
-
-                let expr_checked = self.elaborate_expression(expr)?;
-                let type_deduced = expr_checked.get_type();
-
-                if let Some(ref type_annotation) = type_annotation {
-                    let type_annotated = self.type_from_annotation(type_annotation)?;
+                let (expr_checked, type_deduced) = self.check_annotated_definition(
+                    "unit definition",
+                    "unit definition",
+                    *identifier_span,
+                    expr,
+                    type_annotation.as_ref(),
+                )?;
 
-                    match (&type_deduced, &type_annotated) {
-                        (Type::Dimension(dexpr_deduced), Type::Dimension(dexpr_specified))
-                            if type_deduced.is_closed() && type_annotated.is_closed() =>
-                        {
-                            if dexpr_deduced != dexpr_specified {
-                                return Err(TypeCheckError::IncompatibleDimensions(
-                                    IncompatibleDimensionsError {
-                                        span_operation: *identifier_span,
-                                        operation: "unit definition".into(),
-                                        span_expected: type_annotation_span.unwrap(),
-                                        expected_name: "specified dimension",
-                                        expected_dimensions: self
-                                            .registry
-                                            .get_derived_entry_names_for(
-                                                &dexpr_specified.to_base_representation(),
-                                            ),
-                                        expected_type: dexpr_specified.to_base_representation(),
-                                        span_actual: expr.full_span(),
-                                        actual_name: "   actual dimension",
-                                        actual_name_for_fix: "right hand side expression",
-                                        actual_dimensions: self
-                                            .registry
-                                            .get_derived_entry_names_for(
-                                                &dexpr_deduced.to_base_representation(),
-                                            ),
-                                        actual_type: dexpr_deduced.to_base_representation(),
-                                    },
-                                ));
-                            }
-                        }
-                        (deduced, annotated) => {
-                            if self
-                                .add_equal_constraint(deduced, annotated)
-                                .is_trivially_violated()
-                            {
-                                return Err(TypeCheckError::IncompatibleTypesInAnnotation(
-                                    "unit definition".into(),
-                                    *identifier_span,
-                                    annotated.clone(),
-                                    type_annotation.full_span(),
-                                    deduced.clone(),
-                                    expr_checked.full_span(),
-                                ));
-                            }
-                        }
-                    }
+                // The value of a forward-declared unit needs to match the declared dimension
+                let declared_annotation = self.unit_declarations.remove(identifier);
+                if let Some(declared_annotation) = &declared_annotation {
+                    self.check_against_annotation(
+                        "unit definition",
+                        "unit definition",
+                        *identifier_span,
+                        expr,
+                        &expr_checked,
+                        declared_annotation,
+                    )?;
                 }
 
+                // Like in the VM, the unit is defined in terms of the (unsimplified) unit
+                // of its defining expression.
+                let unit = evaluate_const_quantity(
+                    &expr_checked,
+                    &|name| self.constant_exponent(name),
+                    &|name| self.unit_value(name),
+                )
+                .ok()
+                .filter(|value| value.unsafe_value().to_f64().is_normal())
+                .map(|value| {
+                    Unit::new_derived(
+                        identifier,
+                        decorator::get_canonical_unit_name(identifier, decorators),
+                        *value.unsafe_value(),
+                        value.unit().clone(),
+                    )
+                });
+
                 for (name, _) in decorator::name_and_aliases(identifier, decorators) {
-                    self.env
-                        .add(name.clone(), type_deduced.clone(), *identifier_span, true);
+                    Arc::make_mut(&mut self.env).add(
+                        name.clone(),
+                        type_deduced.clone(),
+                        *identifier_span,
+                        true,
+                    );
+                    if let Some(unit) = &unit {
+                        Arc::make_mut(&mut self.unit_values).insert(name.clone(), unit.clone());
+                    }
                 }
                 typed_ast::Statement::DefineDerivedUnit(
                     identifier.clone(),
                     expr_checked,
                     decorators.clone(),
-                    type_annotation.clone(),
+                    type_annotation.clone().or(declared_annotation),
                     TypeScheme::Concrete(type_deduced),
                     crate::markup::empty(),
                 )
@@ -1282,22 +1790,37 @@ impl TypeChecker {
                 function_name,
                 type_parameters,
                 parameters,
+                local_definitions,
                 body,
                 return_type_annotation,
                 decorators,
             } => {
                 if body.is_none() {
-                    self.value_namespace.add_identifier(
+                    Arc::make_mut(&mut self.value_namespace).add_identifier(
                         function_name.clone(),
                         *function_name_span,
                         "foreign function".to_owned(),
                     )?;
+                    self.foreign_functions.insert(function_name.clone());
                 } else {
-                    self.value_namespace.add_identifier_allow_override(
+                    Arc::make_mut(&mut self.value_namespace).add_identifier_allow_override(
                         function_name.clone(),
                         *function_name_span,
                         "function".to_owned(),
                     )?;
+                    self.foreign_functions.remove(function_name);
+                }
+
+                let mut where_clauses = vec![];
+                for (_, type_parameter, bound) in type_parameters {
+                    if let Some(TypeParameterBound::Equal(dexpr)) = bound {
+                        let dtype: DType = self
+                            .registry
+                            .get_base_representation(dexpr)
+                            .map(|br| br.into())
+                            .map_err(TypeCheckError::RegistryError)?;
+                        where_clauses.push((type_parameter.clone(), dtype));
+                    }
                 }
 
                 let mut typechecker_fn = self.clone();
@@ -1311,19 +1834,16 @@ impl TypeChecker {
                         ));
                     }
 
-                    typechecker_fn
-                        .type_namespace
+                    Arc::make_mut(&mut typechecker_fn.type_namespace)
                         .add_identifier(type_parameter.clone(), *span, "type parameter".to_owned())
                         .ok(); // TODO: is this call even correct?
 
-                    typechecker_fn.registry.introduced_type_parameters.push((
-                        *span,
-                        type_parameter.clone(),
-                        bound.clone(),
-                    ));
+                    Arc::make_mut(&mut typechecker_fn.registry)
+                        .introduced_type_parameters
+                        .push((*span, type_parameter.clone(), bound.clone()));
 
                     match bound {
-                        Some(TypeParameterBound::Dim) => {
+                        Some(TypeParameterBound::Dim) | Some(TypeParameterBound::Equal(_)) => {
                             typechecker_fn
                                 .add_dtype_constraint(&Type::TPar(type_parameter.clone()))
                                 .ok();
@@ -1351,7 +1871,7 @@ impl TypeChecker {
                         ));
                     }
 
-                    typechecker_fn.env.add_scheme(
+                    Arc::make_mut(&mut typechecker_fn.env).add_scheme(
                         parameter.clone(),
                         TypeScheme::make_quantified(parameter_type.clone()),
                         *parameter_span,
@@ -1389,23 +1909,111 @@ impl TypeChecker {
                 let fn_type =
                     TypeScheme::Concrete(Type::Fn(parameter_types, Box::new(return_type.clone())));
 
-                typechecker_fn.env.add_function(
+                let signature = FunctionSignature {
+                    name: function_name.clone(),
+                    definition_span: *function_name_span,
+                    type_parameters: type_parameters.clone(),
+                    parameters,
+                    return_type_annotation: return_type_annotation.clone(),
+                    where_clauses,
+                    fn_type: fn_type.clone(),
+                };
+                let metadata = FunctionMetadata {
+                    name: crate::decorator::name(decorators),
+                    url: crate::decorator::url(decorators),
+                    description: crate::decorator::description(decorators),
+                    examples: crate::decorator::examples(decorators),
+                };
+                Arc::make_mut(&mut typechecker_fn.env).add_function(
                     function_name.clone(),
-                    FunctionSignature {
-                        name: function_name.clone(),
-                        definition_span: *function_name_span,
-                        type_parameters: type_parameters.clone(),
-                        parameters,
-                        return_type_annotation: return_type_annotation.clone(),
-                        fn_type: fn_type.clone(),
-                    },
-                    FunctionMetadata {
-                        name: crate::decorator::name(decorators),
-                        url: crate::decorator::url(decorators),
-                        description: crate::decorator::description(decorators),
-                    },
+                    signature.clone(),
+                    metadata.clone(),
                 );
 
+                let mut local_definitions_checked = vec![];
+                for local_definition in local_definitions {
+                    match local_definition {
+                        ast::LocalDefinition::Variable(local_variable) => {
+                            let (expr_checked, type_deduced) = typechecker_fn
+                                .check_annotated_definition(
+                                    "local variable definition",
+                                    "local variable definition",
+                                    local_variable.identifier_span,
+                                    &local_variable.expr,
+                                    local_variable.type_annotation.as_ref(),
+                                )?;
+
+                            Arc::make_mut(&mut typechecker_fn.env).add_scheme(
+                                local_variable.identifier.clone(),
+                                TypeScheme::make_quantified(type_deduced),
+                                local_variable.identifier_span,
+                                false,
+                            );
+                            local_definitions_checked.push(typed_ast::LocalDefinition::Variable(
+                                local_variable.identifier.clone(),
+                                local_variable.type_annotation.clone(),
+                                expr_checked,
+                            ));
+                        }
+                        ast::LocalDefinition::Function(statement) => {
+                            let ast::Statement::DefineFunction {
+                                function_name_span: local_function_name_span,
+                                function_name: local_function_name,
+                                ..
+                            } = statement.as_ref()
+                            else {
+                                unreachable!("Local definitions of functions are parsed as such")
+                            };
+
+                            let statement_checked =
+                                typechecker_fn.elaborate_statement(statement)?;
+
+                            // Local functions capture the values of the local variables that they
+                            // use when they are defined. They have no access to their own value.
+                            let mut is_recursive = false;
+                            for_all_expressions_and_interpolations(&statement_checked, &mut |e| {
+                                if let typed_ast::Expression::FunctionCall(_, _, name, _, _)
+                                | typed_ast::Expression::Identifier(_, name, _) = e
+                                {
+                                    is_recursive |= name == local_function_name;
+                                }
+                            });
+                            if is_recursive {
+                                return Err(TypeCheckError::RecursiveLocalFunction(
+                                    *local_function_name_span,
+                                    local_function_name.clone(),
+                                ));
+                            }
+
+                            // In the rest of the function body, the local function is a variable
+                            // with a function type
+                            let typed_ast::Statement::DefineFunction(
+                                _,
+                                _,
+                                _,
+                                _,
+                                _,
+                                _,
+                                fn_type,
+                                _,
+                                _,
+                            ) = &statement_checked
+                            else {
+                                unreachable!()
+                            };
+                            Arc::make_mut(&mut typechecker_fn.env).add_scheme(
+                                local_function_name.clone(),
+                                TypeScheme::make_quantified(fn_type.unsafe_as_concrete()),
+                                *local_function_name_span,
+                                false,
+                            );
+                            local_definitions_checked.push(typed_ast::LocalDefinition::Function(
+                                Box::new(statement_checked),
+                            ));
+                        }
+                    }
+                }
+
                 let body_checked = body
                     .as_ref()
                     .map(|expr| typechecker_fn.elaborate_expression(expr))
@@ -1469,11 +2077,21 @@ impl TypeChecker {
                     }
                     return_type_inferred
                 } else {
-                    if !ffi::functions().contains_key(function_name.as_str()) {
+                    let Some(foreign_function) = ffi::functions().get(function_name.as_str())
+                    else {
                         return Err(TypeCheckError::UnknownForeignFunction(
                             *function_name_span,
                             function_name.clone(),
                         ));
+                    };
+
+                    if foreign_function.arity != (typed_parameters.len()..=typed_parameters.len()) {
+                        return Err(TypeCheckError::ForeignFunctionSignatureMismatch {
+                            span: *function_name_span,
+                            function_name: function_name.clone(),
+                            num_parameters: typed_parameters.len(),
+                            expected_arity: foreign_function.arity.clone(),
+                        });
                     }
 
                     annotated_return_type.ok_or_else(|| {
@@ -1491,11 +2109,14 @@ impl TypeChecker {
                 self.constraints = typechecker_fn.constraints;
                 self.name_generator = typechecker_fn.name_generator;
                 self.registry = typechecker_fn.registry;
-                // Copy identifier for the new function into local env:
-                let (signature, metadata) =
-                    typechecker_fn.env.get_function_info(function_name).unwrap();
-                self.env
-                    .add_function(function_name.clone(), signature.clone(), metadata.clone());
+                self.where_clause_checks = typechecker_fn.where_clause_checks;
+                // Copy identifier for the new function into local env. It might be shadowed
+                // by a local function in the environment of the function body.
+                Arc::make_mut(&mut self.env).add_function(
+                    function_name.clone(),
+                    signature,
+                    metadata,
+                );
 
                 typed_ast::Statement::DefineFunction(
                     function_name.clone(),
@@ -1515,6 +2136,7 @@ impl TypeChecker {
                             )
                         })
                         .collect(),
+                    local_definitions_checked,
                     body_checked,
                     fn_type,
                     return_type_annotation.clone(),
@@ -1522,14 +2144,14 @@ impl TypeChecker {
                 )
             }
             ast::Statement::DefineDimension(name_span, name, dexprs) => {
-                self.type_namespace.add_identifier(
+                Arc::make_mut(&mut self.type_namespace).add_identifier(
                     name.clone(),
                     *name_span,
                     "dimension".to_owned(),
                 )?;
 
                 if let Some(dexpr) = dexprs.first() {
-                    self.registry
+                    Arc::make_mut(&mut self.registry)
                         .add_derived_dimension(name, dexpr)
                         .map_err(TypeCheckError::RegistryError)?;
 
@@ -1556,13 +2178,15 @@ impl TypeChecker {
                         }
                     }
                 } else {
-                    self.registry
+                    Arc::make_mut(&mut self.registry)
                         .add_base_dimension(name)
                         .map_err(TypeCheckError::RegistryError)?;
                 }
                 typed_ast::Statement::DefineDimension(name.clone(), dexprs.clone())
             }
             ast::Statement::ProcedureCall(span, kind @ ProcedureKind::Type, args) => {
+                self.check_foreign_function_available(*span, "type")?;
+
                 if args.len() != 1 {
                     return Err(TypeCheckError::WrongArity {
                         callable_span: *span,
@@ -1580,8 +2204,34 @@ impl TypeChecker {
 
                 typed_ast::Statement::ProcedureCall(kind.clone(), checked_args)
             }
+            ast::Statement::ProcedureCall(span, kind @ ProcedureKind::Debug, args) => {
+                self.check_foreign_function_available(*span, "debug")?;
+
+                // The arity of the foreign 'debug' procedure is different, as the compiler
+                // passes the type of the argument as an additional argument.
+                if args.len() != 1 {
+                    return Err(TypeCheckError::WrongArity {
+                        callable_span: *span,
+                        callable_name: "debug".into(),
+                        callable_definition_span: None,
+                        arity: 1..=1,
+                        num_args: args.len(),
+                    });
+                }
+
+                let checked_arg = self.elaborate_expression(&args[0])?;
+
+                let type_ = checked_arg.get_type();
+                if type_ != Type::Boolean {
+                    self.enforce_dtype(&type_, checked_arg.full_span())?;
+                }
+
+                typed_ast::Statement::ProcedureCall(kind.clone(), vec![checked_arg])
+            }
             ast::Statement::ProcedureCall(span, kind, args) => {
                 let procedure = ffi::procedures().get(kind).unwrap();
+                self.check_foreign_function_available(*span, &procedure.name)?;
+
                 if !procedure.arity.contains(&args.len()) {
                     return Err(TypeCheckError::WrongArity {
                         callable_span: *span,
@@ -1622,12 +2272,18 @@ impl TypeChecker {
                             self.enforce_dtype(type_first, checked_args[0].full_span())?;
                         }
 
-                        for arg in &checked_args[1..] {
+                        for (i, arg) in checked_args.iter().enumerate().skip(1) {
                             let type_arg = arg.get_type();
                             if needs_dtypes {
                                 self.enforce_dtype(&type_arg, arg.full_span())?;
                             }
 
+                            // A dimensionless tolerance is a relative one (e.g. `1%`),
+                            // which can be used for quantities of any dimension:
+                            if i == 2 && type_arg.is_scalar() && !self.strict_dimensions {
+                                continue;
+                            }
+
                             if self
                                 .add_equal_constraint(type_first, &type_arg)
                                 .is_trivially_violated()
@@ -1642,8 +2298,32 @@ impl TypeChecker {
                             }
                         }
                     }
-                    ProcedureKind::Type => {
-                        unreachable!("type() calls have a special handling above")
+                    ProcedureKind::Csv => {
+                        let element_type = self.fresh_type_variable();
+                        let expected_types = [
+                            Type::List(Box::new(element_type.clone())),
+                            Type::String,
+                            Type::Boolean,
+                        ];
+
+                        for (arg, expected_type) in checked_args.iter().zip(expected_types) {
+                            if self
+                                .add_equal_constraint(&arg.get_type(), &expected_type)
+                                .is_trivially_violated()
+                            {
+                                return Err(TypeCheckError::IncompatibleTypesInFunctionCall(
+                                    None,
+                                    expected_type,
+                                    arg.full_span(),
+                                    arg.get_type(),
+                                ));
+                            }
+                        }
+
+                        self.enforce_dtype(&element_type, checked_args[0].full_span())?;
+                    }
+                    ProcedureKind::Type | ProcedureKind::Debug => {
+                        unreachable!("type() and debug() calls have a special handling above")
                     }
                 }
 
@@ -1652,12 +2332,34 @@ impl TypeChecker {
             ast::Statement::ModuleImport(_, _) => {
                 unreachable!("Modules should have been inlined by now")
             }
+            ast::Statement::DefineOperator {
+                symbol_span: _,
+                symbol,
+                function_name_span,
+                function_name,
+            } => {
+                self.identifier_type(*function_name_span, function_name)?;
+
+                if let Some((signature, _)) = self.env.get_function_info(function_name) {
+                    if signature.parameters.len() != 2 {
+                        return Err(TypeCheckError::OperatorFunctionNotBinary(
+                            *function_name_span,
+                            function_name.clone(),
+                            signature.parameters.len(),
+                        ));
+                    }
+                }
+
+                self.operators.insert(symbol.clone(), function_name.clone());
+
+                typed_ast::Statement::DefineOperator(symbol.clone(), function_name.clone())
+            }
             ast::Statement::DefineStruct {
                 struct_name_span,
                 struct_name,
                 fields,
             } => {
-                self.type_namespace.add_identifier(
+                Arc::make_mut(&mut self.type_namespace).add_identifier(
                     struct_name.clone(),
                     *struct_name_span,
                     "struct".to_owned(),
@@ -1697,13 +2399,44 @@ impl TypeChecker {
 
     fn check_statement(&mut self, statement: &ast::Statement) -> Result<typed_ast::Statement> {
         self.constraints.clear();
-        self.registry.introduced_type_parameters.clear();
+        Arc::make_mut(&mut self.registry)
+            .introduced_type_parameters
+            .clear();
+        self.where_clause_checks.clear();
+        self.redefinitions.clear();
 
         // Elaborate the program/statement: turn the AST into a typed AST, possibly
         // with unification variables, i.e. type variables that will only later be
         // filled in after the constraints have been solved.
-        let mut elaborated_statement = self.elaborate_statement(statement)?;
+        let elaborated_statement = self.elaborate_statement(statement)?;
+
+        self.solve_statement(elaborated_statement, true)
+    }
+
+    /// Elaborate a single expression and solve its constraints. In contrast to
+    /// [`TypeChecker::check_statement`], this does not modify the environment.
+    fn check_expression(&mut self, expr: &ast::Expression) -> Result<typed_ast::Expression> {
+        self.constraints.clear();
+        self.where_clause_checks.clear();
+        self.redefinitions.clear();
+
+        let elaborated_expr = self.elaborate_expression(expr)?;
+
+        match self.solve_statement(typed_ast::Statement::Expression(elaborated_expr), false)? {
+            typed_ast::Statement::Expression(checked_expr) => Ok(checked_expr),
+            _ => unreachable!("expression statements are checked to expressions"),
+        }
+    }
 
+    /// Solve the constraints that have been collected while elaborating the given
+    /// statement, and fill in the types. The environment is only updated with the
+    /// solution if `update_environment` is set. Otherwise, the statement must not
+    /// have introduced any definitions.
+    fn solve_statement(
+        &mut self,
+        mut elaborated_statement: typed_ast::Statement,
+        update_environment: bool,
+    ) -> Result<typed_ast::Statement> {
         // Solve constraints
         let (substitution, dtype_variables) =
             self.constraints.solve().map_err(|inner| match inner {
@@ -1725,9 +2458,16 @@ impl TypeChecker {
             TypeCheckError::SubstitutionError(elaborated_statement.pretty_print().to_string(), e)
         })?;
 
-        self.env.apply(&substitution).map_err(|e| {
-            TypeCheckError::SubstitutionError(elaborated_statement.pretty_print().to_string(), e)
-        })?;
+        if update_environment {
+            Arc::make_mut(&mut self.env)
+                .apply(&substitution)
+                .map_err(|e| {
+                    TypeCheckError::SubstitutionError(
+                        elaborated_statement.pretty_print().to_string(),
+                        e,
+                    )
+                })?;
+        }
 
         if let typed_ast::Statement::DefineDerivedUnit(_, expr, _, _annotation, type_, _) =
             &elaborated_statement
@@ -1739,10 +2479,18 @@ impl TypeChecker {
             }
         }
 
+        // Type parameters are only introduced by definitions. The list is not reset after
+        // a statement, so it must not be used when checking an expression.
+        let introduced_type_parameters: &[_] = if update_environment {
+            &self.registry.introduced_type_parameters
+        } else {
+            &[]
+        };
+
         // Make sure that the user-specified type parameter bounds are properly reflected:
-        for (span, type_parameter, bound) in &self.registry.introduced_type_parameters {
+        for (span, type_parameter, bound) in introduced_type_parameters {
             match bound {
-                Some(TypeParameterBound::Dim) => {
+                Some(TypeParameterBound::Dim) | Some(TypeParameterBound::Equal(_)) => {
                     // The type parameter might be over-constrained, but that's okay
                 }
                 None => {
@@ -1758,6 +2506,52 @@ impl TypeChecker {
             }
         }
 
+        // Make sure that the dimension constraints from `where` clauses are satisfied.
+        // Within the body of a function, type parameters that are constrained by a
+        // `where` clause themselves are replaced by the corresponding dimension.
+        let mut where_clause_substitution = Substitution::empty();
+        for (_, type_parameter, bound) in introduced_type_parameters {
+            if let Some(TypeParameterBound::Equal(dexpr)) = bound {
+                let dtype: DType = self
+                    .registry
+                    .get_base_representation(dexpr)
+                    .map(|br| br.into())
+                    .map_err(TypeCheckError::RegistryError)?;
+                where_clause_substitution
+                    .0
+                    .push((TypeVariable::new(type_parameter), Type::Dimension(dtype)));
+            }
+        }
+        let mut deferred_where_bounds = vec![];
+        for (span, type_parameter, type_, dtype_expected) in &self.where_clause_checks {
+            let mut type_ = type_.clone();
+            type_.apply(&substitution).ok();
+            type_.apply(&where_clause_substitution).ok();
+
+            if !type_.is_closed() {
+                // We can not decide this yet, since the type parameter has been instantiated
+                // with a generic type (in `let g = f` or in the body of a generic function).
+                // The bound becomes part of the generalized type of `g`, and is checked when
+                // that is instantiated.
+                deferred_where_bounds.push(Bound::EqualTo(
+                    type_parameter.clone(),
+                    type_,
+                    dtype_expected.clone(),
+                ));
+                continue;
+            }
+
+            let type_expected = Type::Dimension(dtype_expected.clone());
+            if type_ != type_expected {
+                return Err(TypeCheckError::UnsatisfiedConstraint(
+                    *span,
+                    type_parameter.clone(),
+                    type_expected.to_readable_type(&self.registry).to_string(),
+                    type_.to_readable_type(&self.registry).to_string(),
+                ));
+            }
+        }
+
         // For all dimension type variables that are still free, check all of their occurences
         // within type_, and then multiply the corresponding exponents with the least common
         // multiple of the denominators of the exponents. For example, this will turn
@@ -1784,7 +2578,12 @@ impl TypeChecker {
 
         elaborated_statement.update_readable_types(&self.registry);
 
-        self.env.generalize_types(&dtype_variables);
+        if update_environment {
+            Arc::make_mut(&mut self.env).generalize_types(
+                &dtype_variables,
+                &deferred_where_bounds.into_iter().collect(),
+            );
+        }
 
         // Check if there is a typed hole in the statement
         if let Some((span, type_of_hole)) = elaborated_statement.find_typed_hole()? {
@@ -1802,6 +2601,22 @@ impl TypeChecker {
             ));
         }
 
+        for (name, span, original_span, original_type) in std::mem::take(&mut self.redefinitions) {
+            let Some(new_type) = self.env.get_identifier_type(&name) else {
+                continue;
+            };
+            let new_type = new_type.to_concrete_type();
+            if original_type.is_closed() && new_type.is_closed() && original_type != new_type {
+                self.warnings.push(Warning::RedefinitionChangesType {
+                    identifier: name,
+                    span,
+                    original_span,
+                    original_type: original_type.to_readable_type(&self.registry).to_string(),
+                    new_type: new_type.to_readable_type(&self.registry).to_string(),
+                });
+            }
+        }
+
         Ok(elaborated_statement)
     }
 
@@ -1818,6 +2633,81 @@ impl TypeChecker {
         Ok(checked_statements)
     }
 
+    /// Like [`TypeChecker::check`], but does not stop at the first error. If a
+    /// statement fails to type check, its error is recorded and checking continues
+    /// with the next statement, in the environment from before the failing statement.
+    ///
+    /// To be able to restore that environment, a snapshot of the type checker is taken
+    /// at the start, and again after every error. On an error, the type checker is rolled
+    /// back to the last snapshot, and the statements that have been checked successfully
+    /// since then are checked again.
+    pub fn check_all(
+        &mut self,
+        statements: impl IntoIterator<Item = ast::Statement>,
+    ) -> std::result::Result<Vec<typed_ast::Statement>, Vec<TypeCheckError>> {
+        let mut checked_statements = vec![];
+        let mut errors = vec![];
+
+        let mut snapshot = self.clone();
+        let mut checked_since_snapshot = vec![];
+
+        for statement in statements.into_iter() {
+            match self.check_statement(&statement) {
+                Ok(checked_statement) => {
+                    checked_statements.push(checked_statement);
+                    checked_since_snapshot.push(statement);
+                }
+                Err(e) => {
+                    errors.push(e);
+
+                    *self = snapshot;
+                    for statement in checked_since_snapshot.drain(..) {
+                        self.check_statement(&statement)
+                            .expect("statements that have been checked before still type check");
+                    }
+                    snapshot = self.clone();
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(checked_statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The type of the given expression in the current environment. In contrast to
+    /// [`TypeChecker::check`], this does not modify the state of the type checker.
+    /// In particular, the result is not assigned to `ans` and `_`.
+    pub fn type_of(&self, expr: &ast::Expression) -> Result<Type> {
+        // The expression is checked in a scoped copy, which shares the environment
+        // with this type checker. Only the constraints are specific to the copy.
+        let mut scoped = self.clone();
+        let checked_expr = scoped.check_expression(expr)?;
+        Ok(checked_expr.get_type_scheme().to_concrete_type())
+    }
+
+    /// See [`crate::Context::set_strict_dimensions`]
+    pub fn set_strict_dimensions(&mut self, strict: bool) {
+        self.strict_dimensions = strict;
+    }
+
+    /// See [`crate::Context::set_partial_application`]
+    pub fn set_partial_application(&mut self, activate: bool) {
+        self.partial_application = activate;
+    }
+
+    /// See [`crate::Context::set_foreign_function_filter`]
+    pub fn set_foreign_function_filter(&mut self, filter: ForeignFunctionFilter) {
+        self.ffi_filter = filter;
+    }
+
+    /// Remove and return the warnings that have been collected so far
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
     pub(crate) fn registry(&self) -> &DimensionRegistry {
         &self.registry
     }
@@ -1825,4 +2715,72 @@ impl TypeChecker {
     pub fn lookup_function(&self, name: &str) -> Option<(&FunctionSignature, &FunctionMetadata)> {
         self.env.get_function_info(name)
     }
+
+    pub(crate) fn lookup_identifier_type(&self, name: &str) -> Option<TypeScheme> {
+        self.env.get_identifier_type(name)
+    }
+
+    /// Add a global variable that is defined by the host application, with a value that
+    /// is only known at runtime, see [`crate::Context::define_lazy_global`].
+    pub(crate) fn add_lazy_global(
+        &mut self,
+        name: &str,
+        definition_span: Span,
+        type_annotation: &TypeAnnotation,
+    ) -> Result<()> {
+        let type_ = self.type_from_annotation(type_annotation)?;
+
+        // Existing variables and functions can not be replaced, as the bytecode
+        // interpreter would keep resolving the name to them
+        Arc::make_mut(&mut self.value_namespace).add_identifier(
+            name.to_owned(),
+            definition_span,
+            "constant".to_owned(),
+        )?;
+        Arc::make_mut(&mut self.env).add_scheme(
+            name.into(),
+            TypeScheme::make_quantified(type_),
+            definition_span,
+            false,
+        );
+
+        Ok(())
+    }
+
+    /// Add a function that has been type checked in a different context. The functions
+    /// and global variables that it refers to need to have the same types as in that
+    /// context.
+    pub(crate) fn import_function(
+        &mut self,
+        signature: FunctionSignature,
+        metadata: FunctionMetadata,
+        dependencies: &[(String, TypeScheme)],
+    ) -> std::result::Result<(), CompiledFunctionError> {
+        for (dependency, type_scheme) in dependencies {
+            let error = match self.env.get_identifier_type(dependency) {
+                Some(ref t) if t == type_scheme => continue,
+                Some(_) => CompiledFunctionError::IncompatibleDependency {
+                    function: signature.name.clone(),
+                    dependency: dependency.clone(),
+                },
+                None => CompiledFunctionError::MissingDependency {
+                    function: signature.name.clone(),
+                    dependency: dependency.clone(),
+                },
+            };
+            return Err(error);
+        }
+
+        Arc::make_mut(&mut self.value_namespace)
+            .add_identifier_allow_override(
+                signature.name.clone(),
+                signature.definition_span,
+                "function".to_owned(),
+            )
+            .map_err(CompiledFunctionError::NameResolutionError)?;
+
+        Arc::make_mut(&mut self.env).add_function(signature.name.clone(), signature, metadata);
+
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{type_variable::TypeVariable, Type};
+use crate::{type_variable::TypeVariable, typed_ast::DType, Type};
 
 use super::{
     substitutions::{ApplySubstitution, Substitution, SubstitutionError},
@@ -9,6 +9,9 @@ use super::{
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Bound {
     IsDim(Type),
+    /// The type needs to be equal to the given dimension. This is the result of
+    /// a `where D: Length` clause for the type parameter with the given name.
+    EqualTo(String, Type, DType),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -93,6 +96,9 @@ impl QualifiedType {
                 .iter()
                 .map(|b| match b {
                     Bound::IsDim(t) => Bound::IsDim(t.instantiate(type_variables)),
+                    Bound::EqualTo(name, t, dtype) => {
+                        Bound::EqualTo(name.clone(), t.instantiate(type_variables), dtype.clone())
+                    }
                 })
                 .collect(),
         }
@@ -103,8 +109,10 @@ impl ApplySubstitution for QualifiedType {
     fn apply(&mut self, substitution: &Substitution) -> Result<(), SubstitutionError> {
         self.inner.apply(substitution)?;
 
-        for Bound::IsDim(v) in self.bounds.iter_mut() {
-            v.apply(substitution)?;
+        for bound in self.bounds.iter_mut() {
+            match bound {
+                Bound::IsDim(v) | Bound::EqualTo(_, v, _) => v.apply(substitution)?,
+            }
         }
         Ok(())
     }
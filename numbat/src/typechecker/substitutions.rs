@@ -1,7 +1,7 @@
 use thiserror::Error;
 
 use crate::type_variable::TypeVariable;
-use crate::typed_ast::{DType, DTypeFactor, Expression, StructInfo, Type};
+use crate::typed_ast::{DType, DTypeFactor, Expression, LocalDefinition, StructInfo, Type};
 use crate::Statement;
 
 #[derive(Debug, Clone)]
@@ -218,14 +218,23 @@ impl ApplySubstitution for Statement {
                 e.apply(s)?;
                 type_.apply(s)
             }
-            Statement::DefineFunction(_, _, _, _, body, fn_type, _, _) => {
+            Statement::ReassignVariable(_, e) => e.apply(s),
+            Statement::DefineFunction(_, _, _, _, local_definitions, body, fn_type, _, _) => {
+                for local_definition in local_definitions {
+                    match local_definition {
+                        LocalDefinition::Variable(_, _, expr) => expr.apply(s)?,
+                        LocalDefinition::Function(statement) => statement.apply(s)?,
+                    }
+                }
                 if let Some(body) = body {
                     body.apply(s)?;
                 }
                 fn_type.apply(s)
             }
             Statement::DefineDimension(_, _) => Ok(()),
-            Statement::DefineBaseUnit(_, _, _annotation, type_) => type_.apply(s),
+            Statement::DefineBaseUnit(_, _, _, type_) | Statement::DeclareUnit(_, _, _, type_) => {
+                type_.apply(s)
+            }
             Statement::DefineDerivedUnit(_, e, _, _annotation, type_, _) => {
                 e.apply(s)?;
                 type_.apply(s)
@@ -241,6 +250,7 @@ impl ApplySubstitution for Statement {
 
                 Ok(())
             }
+            Statement::DefineOperator(_, _) => Ok(()),
         }
     }
 }
@@ -5,6 +5,7 @@ use crate::parser::parse;
 use crate::prefix_transformer::Transformer;
 use crate::typechecker::{Result, TypeCheckError};
 use crate::typed_ast::{self, DType};
+use crate::warning::Warning;
 use crate::Statement;
 
 use super::type_scheme::TypeScheme;
@@ -29,6 +30,7 @@ const TEST_PRELUDE: &str = "
     let callable = takes_a_returns_b
 
     fn atan2<T>(x: T, y: T) -> Scalar
+    fn hypot<T: Dim>(x: T, y: T) -> T
 
     fn len<T>(x: List<T>) -> Scalar
     fn head<T>(x: List<T>) -> T
@@ -59,6 +61,45 @@ fn run_typecheck(input: &str) -> Result<typed_ast::Statement> {
         .map(|mut statements_checked| statements_checked.pop().unwrap())
 }
 
+fn run_strict_typecheck(input: &str) -> Result<typed_ast::Statement> {
+    let code = &format!("{prelude}\n{input}", prelude = TEST_PRELUDE, input = input);
+    let statements = parse(code, 0).expect("No parse errors for inputs in this test suite");
+    let transformed_statements = Transformer::new().transform(statements)?;
+
+    let mut typechecker = TypeChecker::default();
+    typechecker.set_strict_dimensions(true);
+    typechecker
+        .check(transformed_statements)
+        .map(|mut statements_checked| statements_checked.pop().unwrap())
+}
+
+fn get_typecheck_errors(input: &str) -> Vec<TypeCheckError> {
+    let code = &format!("{prelude}\n{input}", prelude = TEST_PRELUDE, input = input);
+    let statements = parse(code, 0).expect("No parse errors for inputs in this test suite");
+    let transformed_statements = Transformer::new()
+        .transform(statements)
+        .expect("No name resolution errors for inputs in this test suite");
+
+    TypeChecker::default()
+        .check_all(transformed_statements)
+        .err()
+        .unwrap_or_default()
+}
+
+fn get_typecheck_warnings(input: &str) -> Vec<Warning> {
+    let code = &format!("{prelude}\n{input}", prelude = TEST_PRELUDE, input = input);
+    let statements = parse(code, 0).expect("No parse errors for inputs in this test suite");
+    let transformed_statements = Transformer::new()
+        .transform(statements)
+        .expect("No name resolution errors for inputs in this test suite");
+
+    let mut typechecker = TypeChecker::default();
+    typechecker
+        .check(transformed_statements)
+        .expect("Input was expected to type-check");
+    typechecker.take_warnings()
+}
+
 fn assert_successful_typecheck(input: &str) {
     if let Err(err) = dbg!(run_typecheck(input)) {
         panic!("Input was expected to typecheck successfully, but failed with: {err:?}")
@@ -68,7 +109,7 @@ fn assert_successful_typecheck(input: &str) {
 fn get_inferred_fn_type(input: &str) -> TypeScheme {
     let statement = run_typecheck(input).expect("Input was expected to type-check");
     match statement {
-        Statement::DefineFunction(_, _, _, _, _, fn_type, _, _) => fn_type,
+        Statement::DefineFunction(_, _, _, _, _, _, fn_type, _, _) => fn_type,
         _ => {
             unreachable!();
         }
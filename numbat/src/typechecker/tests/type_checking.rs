@@ -2,7 +2,10 @@ use crate::NameResolutionError;
 
 use super::super::*;
 
-use super::{assert_successful_typecheck, get_typecheck_error, type_a, type_b, type_c};
+use super::{
+    assert_successful_typecheck, get_typecheck_error, get_typecheck_errors, get_typecheck_warnings,
+    run_strict_typecheck, type_a, type_b, type_c,
+};
 
 #[test]
 fn basic_arithmetic() {
@@ -13,6 +16,8 @@ fn basic_arithmetic() {
     assert_successful_typecheck("a / b");
 
     assert_successful_typecheck("a * b + 2 c");
+    assert_successful_typecheck("2[a] + a");
+    assert_successful_typecheck("2[a/b] * b + a");
     assert_successful_typecheck("c / a + b");
 
     assert!(matches!(
@@ -28,6 +33,15 @@ fn polymorphic_zero() {
     assert_successful_typecheck("1 b + 0");
 
     assert_successful_typecheck("1 a + 0 * b");
+
+    assert_successful_typecheck("1 a - 0");
+    assert_successful_typecheck("0 - 1 a");
+
+    // Only a literal zero is polymorphic, not arbitrary dimensionless values
+    assert!(matches!(
+        get_typecheck_error("1 a + 2"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {expected_type, actual_type, ..}) if expected_type == type_a().to_base_representation() && actual_type == DType::scalar().to_base_representation()
+    ));
 }
 
 #[test]
@@ -59,16 +73,153 @@ fn exponentiation_with_dimensionful_base() {
         TypeCheckError::UnsupportedConstEvalExpression(_, desc) if desc == "unit identifier"
     ));
 
-    // TODO: if we add ("constexpr") constants later, it would be great to support those in exponents.
     assert!(matches!(
-        get_typecheck_error("let x=2
+        get_typecheck_error("a^(3/(1-1))"),
+        TypeCheckError::DivisionByZeroInConstEvalExpression(_)
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("a^(1e400)"),
+        TypeCheckError::NonRationalExponent(_)
+    ));
+    assert!(matches!(
+        get_typecheck_error("a^(-1e400)"),
+        TypeCheckError::NonRationalExponent(_)
+    ));
+    assert!(matches!(
+        get_typecheck_error("a^(1/3 * 1e300)"),
+        TypeCheckError::NonRationalExponent(_)
+    ));
+    assert!(matches!(
+        get_typecheck_error("a^(1e30 * 1e30)"),
+        TypeCheckError::OverflowInConstExpr(_)
+    ));
+
+    assert_successful_typecheck("let r: A^(1/8) = a^(2^-3)");
+    assert!(matches!(
+        get_typecheck_error("a^(0^-1)"),
+        TypeCheckError::DivisionByZeroInConstEvalExpression(_)
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("a^(2^40)"),
+        TypeCheckError::ExponentTooLarge(_, exponent) if exponent == Exponent::from_integer(1 << 40)
+    ));
+    assert!(matches!(
+        get_typecheck_error("a^(1 / 2^40)"),
+        TypeCheckError::ExponentTooLarge(..)
+    ));
+    assert!(matches!(
+        get_typecheck_error("a^(2^(2^40))"),
+        TypeCheckError::ExponentTooLarge(_, exponent) if exponent == Exponent::from_integer(1 << 40)
+    ));
+    assert!(matches!(
+        get_typecheck_error("fn f(x) = x^(2^40)"),
+        TypeCheckError::ExponentTooLarge(..)
+    ));
+}
+
+#[test]
+fn constant_variables_in_exponents() {
+    assert_successful_typecheck(
+        "let x = 3
+         let r: A^3 = a^x",
+    );
+    assert_successful_typecheck(
+        "let x = 2 * 3 - 4
+         let y = -x / 4
+         let r: A^(-1/2) = a^y",
+    );
+    assert_successful_typecheck(
+        "let x = 3
+         let r: A^4 = a^(x + 1)",
+    );
+
+    assert!(matches!(
+        get_typecheck_error(
+            "let x = 3
+                             let r: A^2 = a^x"
+        ),
+        TypeCheckError::IncompatibleDimensions(..)
+    ));
+
+    // Shadowing
+    assert_successful_typecheck(
+        "let x = 3
+         let x = 2
+         let r: A^2 = a^x",
+    );
+    assert!(matches!(
+        get_typecheck_error("let x = 3
+                             fn f(x) = a^x"),
+        TypeCheckError::UnsupportedConstEvalExpression(_, desc) if desc == "variable"
+    ));
+
+    // Dimensionful variables
+    assert!(matches!(
+        get_typecheck_error("let x = 2 b
+                             a^x"),
+        TypeCheckError::UnsupportedConstEvalExpression(_, desc) if desc == "variable"
+    ));
+    assert!(matches!(
+        get_typecheck_error("let x = 3
+                             let x = 2 b
                              a^x"),
         TypeCheckError::UnsupportedConstEvalExpression(_, desc) if desc == "variable"
     ));
 
+    // Mutable variables and variables that are not defined by a constant expression
     assert!(matches!(
-        get_typecheck_error("a^(3/(1-1))"),
-        TypeCheckError::DivisionByZeroInConstEvalExpression(_)
+        get_typecheck_error("let mut x = 3
+                             a^x"),
+        TypeCheckError::UnsupportedConstEvalExpression(_, desc) if desc == "variable"
+    ));
+    assert!(matches!(
+        get_typecheck_error("let x = returns_a() / a
+                             a^x"),
+        TypeCheckError::UnsupportedConstEvalExpression(_, desc) if desc == "variable"
+    ));
+}
+
+#[test]
+fn unit_ratios_in_exponents() {
+    assert_successful_typecheck("let r: A^2 = a^(2 a / 1 a)");
+    assert_successful_typecheck(
+        "unit ka: A = 1000 a
+         let r: A^1000 = a^(1 ka / 1 a)",
+    );
+    assert_successful_typecheck(
+        "unit ka: A = 1000 a
+         unit mega_a: A = 1000 ka
+         let r: A^(1/2) = a^(500 ka / 1 mega_a)",
+    );
+    assert_successful_typecheck(
+        "unit ka: A = 1000 a
+         let r: A^3 = a^((3 a -> ka) / 1 a)",
+    );
+    assert_successful_typecheck("let r: A^3 = a^((1 a + 2 a) * b / (a * b))");
+    assert_successful_typecheck(
+        "unit ka: A = 1000 a
+         let x = 1 ka / 500 a
+         let r: A^2 = a^x",
+    );
+
+    assert!(matches!(
+        get_typecheck_error("a^(1 a / 1 b)"),
+        TypeCheckError::UnsupportedConstEvalExpression(_, desc)
+            if desc == "quantity with a physical dimension"
+    ));
+    assert!(matches!(
+        get_typecheck_error(
+            "unit d: A = returns_a()
+             a^(1 d / 1 a)"
+        ),
+        TypeCheckError::UnsupportedConstEvalExpression(_, desc)
+            if desc == "unit identifier with a value that is not known at compile time"
+    ));
+    assert!(matches!(
+        get_typecheck_error("a^(1 a / 0 a)"),
+        TypeCheckError::DivisionByZeroInConstEvalExpression(..)
     ));
 }
 
@@ -81,6 +232,10 @@ fn equality() {
         get_typecheck_error("a == b"),
         TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError { .. })
     ));
+    assert_successful_typecheck("true == false");
+    assert_successful_typecheck("2 a != a");
+    assert_successful_typecheck("let r: Bool = 2 a == a");
+
     assert!(matches!(
         get_typecheck_error("a == true"),
         TypeCheckError::IncompatibleTypesInComparison(..)
@@ -100,11 +255,51 @@ fn equality() {
 fn comparisons() {
     assert_successful_typecheck("2 a > a");
     assert_successful_typecheck("2 a / (3 a) > 3");
+    assert_successful_typecheck("let r: Bool = 2 a < 3 a");
+    assert_successful_typecheck("2 a <= 3 a || 2 a >= 3 a");
 
     assert!(matches!(
         get_typecheck_error("a > b"),
         TypeCheckError::IncompatibleDimensions(..)
     ));
+    assert!(matches!(
+        get_typecheck_error("2 a < 3 b"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, ..}) if operation == "comparison"
+    ));
+    assert!(matches!(
+        get_typecheck_error("true < false"),
+        TypeCheckError::ExpectedDimensionType(..)
+    ));
+}
+
+#[test]
+fn comparisons_are_not_const_evaluated() {
+    let scalar = || {
+        Box::new(typed_ast::Expression::Scalar(
+            Span::dummy(),
+            crate::number::Number::from_f64(2.0),
+            TypeScheme::concrete(Type::scalar()),
+        ))
+    };
+
+    for op in [
+        typed_ast::BinaryOperator::LessThan,
+        typed_ast::BinaryOperator::GreaterOrEqual,
+        typed_ast::BinaryOperator::Equal,
+        typed_ast::BinaryOperator::NotEqual,
+    ] {
+        let expr = typed_ast::Expression::BinaryOperator(
+            None,
+            op,
+            scalar(),
+            scalar(),
+            TypeScheme::concrete(Type::Boolean),
+        );
+        assert!(matches!(
+            evaluate_const_expr(&expr, &|_| None, &|_| None),
+            Err(TypeCheckError::UnsupportedConstEvalExpression(_, desc)) if desc == "comparison"
+        ));
+    }
 }
 
 #[test]
@@ -155,6 +350,27 @@ fn unit_definitions() {
     ));
 }
 
+#[test]
+fn annotated_definitions_report_the_operation() {
+    assert!(matches!(
+        get_typecheck_error("let x: A = b"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, ..}) if operation == "variable definition"
+    ));
+    assert!(matches!(
+        get_typecheck_error("unit my_c: C = a"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, ..}) if operation == "unit definition"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("let x: A = true"),
+        TypeCheckError::IncompatibleTypesInAnnotation(operation, ..) if operation == "definition"
+    ));
+    assert!(matches!(
+        get_typecheck_error("unit my_c: C = true"),
+        TypeCheckError::IncompatibleTypesInAnnotation(operation, ..) if operation == "unit definition"
+    ));
+}
+
 #[test]
 fn function_definitions() {
     assert_successful_typecheck("fn f(x: A) -> A = x");
@@ -175,6 +391,38 @@ fn function_definitions() {
     ));
 }
 
+#[test]
+fn function_body_blocks() {
+    assert_successful_typecheck("fn f(x: A) -> A = { let y = 2 x; y + x }");
+    assert_successful_typecheck("fn f(x: A) -> A·B = { let y: B = b; let z = x * y; z }");
+    assert_successful_typecheck("fn f(x) = { let y = x * x; y * x }");
+
+    assert!(matches!(
+        get_typecheck_error("fn f(x: A) -> A = { let y: B = x; x }"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, ..}) if operation == "local variable definition"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("fn f(x: A) -> B = { let y = 2 x; y }"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {expected_type, actual_type, ..}) if expected_type == type_b().to_base_representation() && actual_type == type_a().to_base_representation()
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("fn f(x: A) -> A = { let y = 2 x; y }\ny"),
+        TypeCheckError::UnknownIdentifier(_, name, _) if name == "y"
+    ));
+
+    assert_successful_typecheck("fn f(x: A) -> A = { let y = 2; fn g(z: A) -> A = y * z; g(x) }");
+    assert!(matches!(
+        get_typecheck_error("fn f(x: A) -> B = { fn g(z: A) -> A = z; g(x) }"),
+        TypeCheckError::ConstraintSolverError(..)
+    ));
+    assert!(matches!(
+        get_typecheck_error("fn f(x: A) -> A = { fn g(z: A) -> A = g(z); g(x) }"),
+        TypeCheckError::RecursiveLocalFunction(_, name) if name == "g"
+    ));
+}
+
 #[test]
 fn recursive_functions() {
     assert_successful_typecheck("fn f(x: Scalar) -> Scalar = if x < 0 then f(-x) else x");
@@ -219,12 +467,71 @@ fn generics_basic() {
         ",
     );
 
+    assert_successful_typecheck(
+        "
+            fn ratio<A0: Dim, B0: Dim>(x: A0, y: B0) -> A0/B0 = x/y
+            let r1: A/B = ratio(2 a, 3 b)
+            let r2: Scalar = ratio(2 a, 3 a)
+            let r3: Scalar = ratio(2, 3)
+            ",
+    );
+
     assert!(matches!(
         get_typecheck_error("fn f<T1: Dim, T2: Dim>(x: T1, y: T2) -> T2/T1 = x/y"),
         TypeCheckError::IncompatibleDimensions(..)
     ));
 }
 
+#[test]
+fn generics_dimensional_homogeneity() {
+    // Sums involving a generic dimension are rejected in the function definition itself,
+    // without the need for a call that instantiates the type parameter.
+    assert!(matches!(
+        get_typecheck_error("fn f<D: Dim>(x: D) = x + 1 a"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, expected_type, actual_type, ..})
+            if operation == "addition" && expected_type.to_string() == "D" && actual_type == type_a().to_base_representation()
+    ));
+    assert!(matches!(
+        get_typecheck_error("fn f<D>(x: D) -> D = x + 1"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, ..}) if operation == "addition"
+    ));
+    assert!(matches!(
+        get_typecheck_error("fn f<D0: Dim, D1: Dim>(x: D0, y: D1) = x - y"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, ..}) if operation == "subtraction"
+    ));
+
+    assert_successful_typecheck("fn f<D: Dim>(x: D, y: D) = x + y");
+    assert_successful_typecheck("fn f<D: Dim>(x: D) -> D = x + x / 2");
+}
+
+#[test]
+fn generics_arguments_of_the_same_type() {
+    assert_successful_typecheck(
+        "
+        fn value_in<T: Dim>(x: T, target: T) -> Scalar = x / target
+        value_in(2 a, a)
+        value_in(2 c, a * b)
+        value_in(2, 3)
+        hypot(2 b, 3 b)
+        fn f<D: Dim>(x: D) = value_in(x, x)
+        ",
+    );
+
+    assert!(matches!(
+        get_typecheck_error("
+            fn value_in<T: Dim>(x: T, target: T) -> Scalar = x / target
+            value_in(2 a, b)
+        "),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, expected_type, actual_type, ..})
+            if operation == "argument 2 of function call to 'value_in'" && expected_type == type_a().to_base_representation() && actual_type == type_b().to_base_representation()
+    ));
+    assert!(matches!(
+        get_typecheck_error("hypot(2 a, 3)"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {operation, ..})
+            if operation == "argument 2 of function call to 'hypot'"
+    ));
+}
+
 // #[test]
 // fn generics_unused_type_parameter() {
 //     assert!(matches!(
@@ -271,6 +578,99 @@ fn generics_type_parameter_name_clash() {
     ));
 }
 
+#[test]
+fn generics_where_clause() {
+    assert_successful_typecheck(
+        "
+            fn f<D>(x: D) -> D where D: A = x
+            f(2 a)
+            f(a) + a
+            let g = f
+            g(a)
+            ",
+    );
+
+    assert_successful_typecheck(
+        "
+            fn f<D1, D2>(x: D1, y: D2) -> D1 * D2 where D1: A, D2: B = x * y
+            f(a, b) -> c
+            ",
+    );
+
+    // Constraints are also checked within the body of other (generic) functions
+    assert_successful_typecheck(
+        "
+            fn f<D>(x: D) -> D where D: A = x
+            fn g<E>(x: E) -> E where E: A = f(x)
+            fn h(x: A) -> A = f(x)
+            ",
+    );
+
+    assert!(matches!(
+        get_typecheck_error("
+                fn f<D>(x: D) -> D where D: A = x
+                f(b)
+            "),
+        TypeCheckError::UnsatisfiedConstraint(_, name, expected, actual) if name == "D" && expected == "A" && actual == "B"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("
+                fn f<D>(x: D) -> D where D: A = x
+                f(2)
+            "),
+        TypeCheckError::UnsatisfiedConstraint(_, name, _, _) if name == "D"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("
+                fn f<D1, D2>(x: D1, y: D2) -> D1 * D2 where D1: A, D2: B = x * y
+                f(a, a)
+            "),
+        TypeCheckError::UnsatisfiedConstraint(_, name, _, _) if name == "D2"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("
+                fn f<D>(x: D) -> D where D: A = x
+                fn g(x: B) -> B = f(x)
+            "),
+        TypeCheckError::UnsatisfiedConstraint(_, name, _, _) if name == "D"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("
+                fn f<D>(x: D) -> D where D: A = x
+                fn g<E>(x: E) -> E where E: B = f(x)
+            "),
+        TypeCheckError::UnsatisfiedConstraint(_, name, _, _) if name == "D"
+    ));
+
+    // Constraints are kept when a generic function is passed on
+    assert!(matches!(
+        get_typecheck_error("
+                fn f<D>(x: D) -> D where D: A = x
+                let g = f
+                g(b)
+            "),
+        TypeCheckError::UnsatisfiedConstraint(_, name, expected, actual) if name == "D" && expected == "A" && actual == "B"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("
+                fn f<D>(x: D) -> D where D: A = x
+                fn wrap(x) = f(x)
+                wrap(b)
+            "),
+        TypeCheckError::UnsatisfiedConstraint(_, name, _, _) if name == "D"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("fn f<D>(x: D) -> D where D: Unknown = x"),
+        TypeCheckError::RegistryError(_)
+    ));
+}
+
 #[test]
 fn unknown_identifier() {
     assert!(matches!(
@@ -325,6 +725,38 @@ fn wrong_arity() {
     ));
 }
 
+#[test]
+fn wrong_arity_error_messages() {
+    assert_eq!(
+        get_typecheck_error("fn f() = 1\nf(1)").to_string(),
+        "Function or procedure 'f' called with 1 argument, but needs 0 arguments"
+    );
+    assert_eq!(
+        get_typecheck_error("fn f(x: Scalar, y: Scalar) = x\nf(1)").to_string(),
+        "Function or procedure 'f' called with 1 argument, but needs 2 arguments"
+    );
+    assert_eq!(
+        get_typecheck_error("fn f(x: Scalar) = x\nf(1, 2)").to_string(),
+        "Function or procedure 'f' called with 2 arguments, but needs 1 argument"
+    );
+    assert_eq!(
+        get_typecheck_error("assert_eq(1)").to_string(),
+        "Function or procedure 'assert_eq' called with 1 argument, but needs 2..3 arguments"
+    );
+
+    let variadic = TypeCheckError::WrongArity {
+        callable_span: Span::dummy(),
+        callable_name: "f".into(),
+        callable_definition_span: None,
+        arity: 1..=usize::MAX,
+        num_args: 0,
+    };
+    assert_eq!(
+        variadic.to_string(),
+        "Function or procedure 'f' called with 0 arguments, but needs at least 1 argument"
+    );
+}
+
 #[test]
 fn foreign_function_with_missing_return_type() {
     assert!(matches!(
@@ -341,6 +773,20 @@ fn unknown_foreign_function() {
     ));
 }
 
+#[test]
+fn foreign_function_signature_mismatch() {
+    let error = get_typecheck_error("fn sin(x: Scalar, y: Scalar) -> Scalar");
+    assert!(matches!(
+        &error,
+        TypeCheckError::ForeignFunctionSignatureMismatch { function_name, num_parameters: 2, expected_arity, .. }
+            if function_name == "sin" && *expected_arity == (1..=1)
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Foreign function (without body) 'sin' is declared with 2 arguments, but the builtin takes 1 argument"
+    );
+}
+
 #[test]
 fn arity_checks_in_procedure_calls() {
     assert!(matches!(
@@ -363,21 +809,55 @@ fn boolean_values() {
     ));
 }
 
+#[test]
+fn logical_operators() {
+    assert_successful_typecheck("let r: Bool = 1 < 2 && 3 < 4");
+    assert_successful_typecheck("let r: Bool = a > 2 a || !(b == b)");
+    assert_successful_typecheck("!true && false");
+
+    assert!(matches!(
+        get_typecheck_error("1 a && true"),
+        TypeCheckError::ExpectedBool(_)
+    ));
+    assert!(matches!(
+        get_typecheck_error("true || 1"),
+        TypeCheckError::ExpectedBool(_)
+    ));
+    assert!(matches!(
+        get_typecheck_error("!a"),
+        TypeCheckError::ExpectedBool(_)
+    ));
+    assert!(matches!(
+        get_typecheck_error("let r: A = true && false"),
+        TypeCheckError::IncompatibleTypesInAnnotation(..)
+    ));
+}
+
 #[test]
 fn conditionals() {
     assert_successful_typecheck("if true then 1 else 2");
     assert_successful_typecheck("if true then true else false");
+    assert_successful_typecheck("let x: A = if 1 < 2 then 3 a else 4 a");
 
     assert!(matches!(
         get_typecheck_error("if 1 then 2 else 3"),
         TypeCheckError::ExpectedBool(_)
     ));
+    assert!(matches!(
+        get_typecheck_error("if 1 a then 3 else 4"),
+        TypeCheckError::ExpectedBool(_)
+    ));
 
     assert!(matches!(
         get_typecheck_error("if true then a else b"),
         TypeCheckError::IncompatibleTypesInCondition(_, t1, _, t2, _) if t1 == Type::Dimension(DType::base_dimension("A")) && t2 == Type::Dimension(DType::base_dimension("B"))
     ));
 
+    assert!(matches!(
+        get_typecheck_error("if true then 3 a else 4 b"),
+        TypeCheckError::IncompatibleTypesInCondition(_, t1, _, t2, _) if t1 == Type::Dimension(DType::base_dimension("A")) && t2 == Type::Dimension(DType::base_dimension("B"))
+    ));
+
     assert!(matches!(
         get_typecheck_error("if true then true else a"),
         TypeCheckError::IncompatibleTypesInCondition(_, t1, _, t2, _) if t1 == Type::Boolean && t2 == Type::Dimension(DType::base_dimension("A"))
@@ -741,3 +1221,212 @@ fn instantiation() {
         TypeCheckError::ConstraintSolverError(..)
     ));
 }
+
+#[test]
+fn multiple_errors_are_reported() {
+    let errors = get_typecheck_errors(
+        "
+        let x: A = b
+        let y = a
+        let z: B = y
+        let w: A = y
+        ",
+    );
+    assert_eq!(errors.len(), 2);
+    assert!(errors
+        .iter()
+        .all(|e| matches!(e, TypeCheckError::IncompatibleDimensions(..))));
+
+    // Statements after an error are checked in the environment from before the error
+    let errors = get_typecheck_errors(
+        "
+        let x: A = b
+        let y = x
+        ",
+    );
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        &errors[1],
+        TypeCheckError::UnknownIdentifier(_, name, _) if name == "x"
+    ));
+
+    assert!(get_typecheck_errors("let x = a\nlet y: A = x").is_empty());
+}
+
+#[test]
+fn variable_reassignment() {
+    assert_successful_typecheck(
+        "
+        let mut x = 2 a
+        x = 3 a
+        x = x + a
+        ",
+    );
+    assert_successful_typecheck(
+        "
+        @aliases(y)
+        let mut x: A = a
+        y = 2 a
+        ",
+    );
+    assert_successful_typecheck(
+        "
+        let mut s = \"foo\"
+        s = \"bar\"
+        ",
+    );
+
+    assert!(matches!(
+        get_typecheck_error("let x = a\nx = 2 a"),
+        TypeCheckError::AssignmentToImmutableIdentifier(_, Some(_), name) if name == "x"
+    ));
+    assert!(matches!(
+        get_typecheck_error("a = 2 a"),
+        TypeCheckError::AssignmentToImmutableIdentifier(_, _, name) if name == "a"
+    ));
+    // Redefining a variable with 'let' makes it immutable
+    assert!(matches!(
+        get_typecheck_error("let mut x = a\nlet x = a\nx = 2 a"),
+        TypeCheckError::AssignmentToImmutableIdentifier(..)
+    ));
+    assert!(matches!(
+        get_typecheck_error("x = 2 a"),
+        TypeCheckError::AssignmentToUnknownVariable(_, name) if name == "x"
+    ));
+
+    assert!(matches!(
+        get_typecheck_error("let mut x = a\nx = b"),
+        TypeCheckError::IncompatibleDimensions(IncompatibleDimensionsError {
+            expected_type,
+            actual_type,
+            ..
+        }) if expected_type == type_a().to_base_representation() && actual_type == type_b().to_base_representation()
+    ));
+    assert!(matches!(
+        get_typecheck_error("let mut x = a\nx = true"),
+        TypeCheckError::IncompatibleTypesInAnnotation(..)
+    ));
+}
+
+#[test]
+fn user_defined_operators() {
+    assert_successful_typecheck(
+        "
+        operator <> = takes_a_and_b_returns_c
+        let x: C = a <> b
+        ",
+    );
+    assert_successful_typecheck(
+        "
+        operator <*> = atan2
+        let x: Scalar = a <*> 2 a
+        ",
+    );
+    assert_successful_typecheck(
+        "
+        fn add_a(x: A, y: A) -> A = x + y
+        operator <|> = add_a
+        let x: A = a <|> a <|> a
+        ",
+    );
+
+    assert!(matches!(
+        get_typecheck_error("a <> b"),
+        TypeCheckError::UnknownOperator(_, symbol) if symbol == "<>"
+    ));
+    assert!(matches!(
+        get_typecheck_error("operator <> = does_not_exist"),
+        TypeCheckError::UnknownIdentifier(_, name, _) if name == "does_not_exist"
+    ));
+    assert!(matches!(
+        get_typecheck_error("operator <> = takes_a_returns_a"),
+        TypeCheckError::OperatorFunctionNotBinary(_, name, 1) if name == "takes_a_returns_a"
+    ));
+    assert!(matches!(
+        get_typecheck_error("operator <> = takes_a_and_b_returns_c\nb <> a"),
+        TypeCheckError::IncompatibleDimensions(..)
+    ));
+}
+
+#[test]
+fn redefinitions_with_a_different_type() {
+    assert!(get_typecheck_warnings("let x = a\nlet x = 2 a").is_empty());
+    assert_eq!(get_typecheck_warnings("let x = a\nlet x = [a]").len(), 1);
+
+    let warnings = get_typecheck_warnings("let x = a\nlet x = takes_a_returns_b(a)");
+    assert!(matches!(
+        &warnings[..],
+        [Warning::RedefinitionChangesType { identifier, original_type, new_type, .. }]
+            if identifier == "x" && original_type == "A" && new_type == "B"
+    ));
+}
+
+#[test]
+fn generic_foreign_function_hypot() {
+    assert_successful_typecheck("let x: A = hypot(3 a, 4 a)");
+    assert_successful_typecheck("let y: Scalar = hypot(3, 4)");
+    assert_successful_typecheck("let z: C = hypot(a * b, c)");
+
+    assert!(matches!(
+        get_typecheck_error("hypot(a, b)"),
+        TypeCheckError::IncompatibleDimensions(..)
+    ));
+    assert!(matches!(
+        get_typecheck_error("let x: B = hypot(a, a)"),
+        TypeCheckError::ConstraintSolverError(..)
+    ));
+    assert!(matches!(
+        get_typecheck_error("hypot(true, false)"),
+        TypeCheckError::ConstraintSolverError(..)
+    ));
+}
+
+#[test]
+fn strict_dimensions() {
+    // Accepted by default, but rejected in strict mode
+    for input in [
+        "a + 0",
+        "let x: A = 0",
+        "a > 0",
+        "let y: A = if true then a else inf",
+        "assert_eq(a, 2 a, 0.1)",
+        "let z: A = (a * a)^0.5",
+        "fn f<T: Dim>(x: T) -> T = (x * x)^0.5",
+    ] {
+        assert_successful_typecheck(input);
+        assert!(
+            run_strict_typecheck(input).is_err(),
+            "'{input}' was expected to fail in strict mode"
+        );
+    }
+
+    assert!(matches!(
+        run_strict_typecheck("a + 0"),
+        Err(TypeCheckError::IncompatibleDimensions(..))
+    ));
+    assert!(matches!(
+        run_strict_typecheck("assert_eq(a, 2 a, 0.1)"),
+        Err(TypeCheckError::IncompatibleTypesInAssertEq(..))
+    ));
+    assert!(matches!(
+        run_strict_typecheck("let z: A = (a * a)^0.5"),
+        Err(TypeCheckError::DecimalExponentInStrictMode(..))
+    ));
+
+    // Accepted in both modes
+    for input in [
+        "a + 0 a",
+        "let x: A = 0 a",
+        "0 + 1",
+        "2 * a",
+        "2^0.5",
+        "let z: A = (a * a)^(1/2)",
+        "assert_eq(a, 2 a, 0.1 a)",
+        "assert_eq(2, 3, 0.1)",
+    ] {
+        assert_successful_typecheck(input);
+        if let Err(err) = run_strict_typecheck(input) {
+            panic!("'{input}' was expected to pass in strict mode, but failed with: {err:?}");
+        }
+    }
+}
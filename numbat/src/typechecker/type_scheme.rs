@@ -139,6 +139,16 @@ impl TypeScheme {
     }
 
     pub(crate) fn generalize(&mut self, dtype_variables: &[TypeVariable]) {
+        self.generalize_with_bounds(dtype_variables, Bounds::none());
+    }
+
+    /// Like [`TypeScheme::generalize`], but with additional (user-specified) bounds
+    /// that are quantified together with the type.
+    pub(crate) fn generalize_with_bounds(
+        &mut self,
+        dtype_variables: &[TypeVariable],
+        additional_bounds: Bounds,
+    ) {
         let free_variables = self.type_variables(true);
 
         let TypeScheme::Concrete(type_) = self else {
@@ -153,7 +163,19 @@ impl TypeScheme {
             .iter()
             .filter(|v| type_.contains(v, true))
             .map(|v| Bound::IsDim(Type::TVar(v.clone())))
-            .collect();
+            .collect::<Bounds>()
+            .union(
+                additional_bounds
+                    .iter()
+                    .filter(|b| match b {
+                        Bound::IsDim(t) | Bound::EqualTo(_, t, _) => t
+                            .type_variables(true)
+                            .iter()
+                            .all(|v| free_variables.contains(v)),
+                    })
+                    .cloned()
+                    .collect(),
+            );
         let qualified_type = QualifiedType::new(type_.clone(), bounds);
 
         // Generalization: quantify over all free type variables
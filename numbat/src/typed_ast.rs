@@ -2,10 +2,13 @@ use indexmap::IndexMap;
 use itertools::Itertools;
 
 use crate::arithmetic::Exponent;
+use crate::ast::{
+    self, pretty_print_function_body, pretty_print_local_variable, pretty_print_type_annotation,
+    ProcedureKind, TypeAnnotation, TypeParameterBound,
+};
 pub use crate::ast::{BinaryOperator, TypeExpression, UnaryOperator};
-use crate::ast::{ProcedureKind, TypeAnnotation, TypeParameterBound};
+use crate::decorator::decorator_markup;
 use crate::dimension::DimensionRegistry;
-use crate::pretty_print::escape_numbat_string;
 use crate::traversal::{ForAllExpressions, ForAllTypeSchemes};
 use crate::type_variable::TypeVariable;
 use crate::typechecker::qualified_type::QualifiedType;
@@ -13,7 +16,7 @@ use crate::typechecker::type_scheme::TypeScheme;
 use crate::typechecker::TypeCheckError;
 use crate::{
     decorator::Decorator, markup::Markup, number::Number, prefix::Prefix,
-    prefix_parser::AcceptsPrefix, pretty_print::PrettyPrint, span::Span,
+    pretty_print::PrettyPrint, span::Span,
 };
 use crate::{markup as m, BaseRepresentation, BaseRepresentationFactor};
 
@@ -459,35 +462,6 @@ pub enum StringPart {
     },
 }
 
-impl PrettyPrint for StringPart {
-    fn pretty_print(&self) -> Markup {
-        match self {
-            StringPart::Fixed(s) => m::string(escape_numbat_string(s)),
-            StringPart::Interpolation {
-                span: _,
-                expr,
-                format_specifiers,
-            } => {
-                let mut markup = m::operator("{") + expr.pretty_print();
-
-                if let Some(format_specifiers) = format_specifiers {
-                    markup += m::text(format_specifiers);
-                }
-
-                markup += m::operator("}");
-
-                markup
-            }
-        }
-    }
-}
-
-impl PrettyPrint for &Vec<StringPart> {
-    fn pretty_print(&self) -> Markup {
-        m::operator("\"") + self.iter().map(|p| p.pretty_print()).sum() + m::operator("\"")
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Scalar(Span, Number, TypeScheme),
@@ -567,6 +541,67 @@ impl Expression {
     }
 }
 
+/// A definition at the beginning of a function body block
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocalDefinition {
+    Variable(String, Option<TypeAnnotation>, Expression),
+    /// A [Statement::DefineFunction] that can use the parameters and the local
+    /// definitions that precede it
+    Function(Box<Statement>),
+}
+
+impl PrettyPrint for LocalDefinition {
+    fn pretty_print(&self) -> Markup {
+        match self {
+            LocalDefinition::Variable(identifier, annotation, expr) => {
+                pretty_print_local_variable(identifier, annotation, expr.pretty_print())
+            }
+            LocalDefinition::Function(statement) => {
+                // The inferred types of a local function can refer to the type parameters
+                // of the enclosing function, so only the annotations are printed.
+                let Statement::DefineFunction(
+                    function_name,
+                    _,
+                    _,
+                    parameters,
+                    local_definitions,
+                    Some(body),
+                    _,
+                    return_type_annotation,
+                    _,
+                ) = statement.as_ref()
+                else {
+                    unreachable!("Local functions always have a body")
+                };
+
+                m::keyword("fn")
+                    + m::space()
+                    + m::identifier(function_name)
+                    + m::operator("(")
+                    + Itertools::intersperse(
+                        parameters.iter().map(|(_, name, annotation, _)| {
+                            m::identifier(name) + pretty_print_type_annotation(annotation)
+                        }),
+                        m::operator(", "),
+                    )
+                    .sum()
+                    + m::operator(")")
+                    + return_type_annotation
+                        .as_ref()
+                        .map(|a| m::space() + m::operator("->") + m::space() + a.pretty_print())
+                        .unwrap_or_default()
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + pretty_print_function_body(
+                        local_definitions.iter().map(|d| d.pretty_print()),
+                        body.pretty_print(),
+                    )
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Expression(Expression),
@@ -578,6 +613,7 @@ pub enum Statement {
         TypeScheme,
         Markup,
     ),
+    ReassignVariable(String, Expression),
     DefineFunction(
         String,
         Vec<Decorator>,                            // decorators
@@ -589,6 +625,7 @@ pub enum Statement {
             Option<TypeAnnotation>, // parameter type annotation
             Markup,                 // readable parameter type
         )>,
+        Vec<LocalDefinition>,   // local variables and functions
         Option<Expression>,     // function body
         TypeScheme,             // function type
         Option<TypeAnnotation>, // return type annotation
@@ -596,6 +633,7 @@ pub enum Statement {
     ),
     DefineDimension(String, Vec<TypeExpression>),
     DefineBaseUnit(String, Vec<Decorator>, Option<TypeAnnotation>, TypeScheme),
+    DeclareUnit(String, Vec<Decorator>, TypeAnnotation, TypeScheme),
     DefineDerivedUnit(
         String,
         Expression,
@@ -606,6 +644,8 @@ pub enum Statement {
     ),
     ProcedureCall(crate::ast::ProcedureKind, Vec<Expression>),
     DefineStruct(StructInfo),
+    /// Operator symbol and the name of the function it is bound to
+    DefineOperator(String, String),
 }
 
 impl Statement {
@@ -639,12 +679,14 @@ impl Statement {
             Statement::DefineVariable(_, _, _, type_annotation, type_, readable_type) => {
                 *readable_type = Self::create_readable_type(registry, type_, type_annotation);
             }
+            Statement::ReassignVariable(_, _) => {}
             Statement::DefineFunction(
                 _,
                 _,
                 type_parameters,
                 parameters,
                 _,
+                _,
                 fn_type,
                 return_type_annotation,
                 readable_return_type,
@@ -675,11 +717,13 @@ impl Statement {
             }
             Statement::DefineDimension(_, _) => {}
             Statement::DefineBaseUnit(_, _, _, _) => {}
+            Statement::DeclareUnit(_, _, _, _) => {}
             Statement::DefineDerivedUnit(_, _, _, type_annotation, type_, readable_type) => {
                 *readable_type = Self::create_readable_type(registry, type_, type_annotation);
             }
             Statement::ProcedureCall(_, _) => {}
             Statement::DefineStruct(_) => {}
+            Statement::DefineOperator(_, _) => {}
         }
     }
 
@@ -775,70 +819,6 @@ impl Expression {
     }
 }
 
-fn accepts_prefix_markup(accepts_prefix: &Option<AcceptsPrefix>) -> Markup {
-    if let Some(accepts_prefix) = accepts_prefix {
-        m::operator(":")
-            + m::space()
-            + match accepts_prefix {
-                AcceptsPrefix {
-                    short: true,
-                    long: true,
-                } => m::keyword("both"),
-                AcceptsPrefix {
-                    short: true,
-                    long: false,
-                } => m::keyword("short"),
-                AcceptsPrefix {
-                    short: false,
-                    long: true,
-                } => m::keyword("long"),
-                AcceptsPrefix {
-                    short: false,
-                    long: false,
-                } => m::keyword("none"),
-            }
-    } else {
-        m::empty()
-    }
-}
-
-fn decorator_markup(decorators: &Vec<Decorator>) -> Markup {
-    let mut markup_decorators = m::empty();
-    for decorator in decorators {
-        markup_decorators = markup_decorators
-            + match decorator {
-                Decorator::MetricPrefixes => m::decorator("@metric_prefixes"),
-                Decorator::BinaryPrefixes => m::decorator("@binary_prefixes"),
-                Decorator::Aliases(names) => {
-                    m::decorator("@aliases")
-                        + m::operator("(")
-                        + Itertools::intersperse(
-                            names.iter().map(|(name, accepts_prefix)| {
-                                m::unit(name) + accepts_prefix_markup(accepts_prefix)
-                            }),
-                            m::operator(", "),
-                        )
-                        .sum()
-                        + m::operator(")")
-                }
-                Decorator::Url(url) => {
-                    m::decorator("@url") + m::operator("(") + m::string(url) + m::operator(")")
-                }
-                Decorator::Name(name) => {
-                    m::decorator("@name") + m::operator("(") + m::string(name) + m::operator(")")
-                }
-                Decorator::Description(description) => {
-                    m::decorator("@description")
-                        + m::operator("(")
-                        + m::string(description)
-                        + m::operator(")")
-                }
-            }
-            + m::nl();
-    }
-    markup_decorators
-}
-
 pub fn pretty_print_function_signature(
     function_name: &str,
     fn_type: &QualifiedType,
@@ -850,6 +830,10 @@ pub fn pretty_print_function_signature(
         ),
     >,
     readable_return_type: &Markup,
+    where_clauses: &[(
+        String, // type parameter name
+        Markup, // required dimension
+    )],
 ) -> Markup {
     let markup_type_parameters = if type_parameters.is_empty() {
         m::empty()
@@ -881,6 +865,21 @@ pub fn pretty_print_function_signature(
     let markup_return_type =
         m::space() + m::operator("->") + m::space() + readable_return_type.clone();
 
+    let markup_where_clauses = if where_clauses.is_empty() {
+        m::empty()
+    } else {
+        m::space()
+            + m::keyword("where")
+            + m::space()
+            + Itertools::intersperse(
+                where_clauses.iter().map(|(name, dimension)| {
+                    m::type_identifier(name) + m::operator(":") + m::space() + dimension.clone()
+                }),
+                m::operator(", "),
+            )
+            .sum()
+    };
+
     m::keyword("fn")
         + m::space()
         + m::identifier(function_name)
@@ -889,6 +888,18 @@ pub fn pretty_print_function_signature(
         + markup_parameters
         + m::operator(")")
         + markup_return_type
+        + markup_where_clauses
+}
+
+pub(crate) fn where_clauses<'a>(
+    type_parameters: impl Iterator<Item = (&'a String, &'a Option<TypeParameterBound>)>,
+) -> Vec<(String, Markup)> {
+    type_parameters
+        .filter_map(|(name, bound)| match bound {
+            Some(TypeParameterBound::Equal(dexpr)) => Some((name.clone(), dexpr.pretty_print())),
+            _ => None,
+        })
+        .collect()
 }
 
 impl PrettyPrint for Statement {
@@ -913,31 +924,48 @@ impl PrettyPrint for Statement {
                     + m::space()
                     + expr.pretty_print()
             }
+            Statement::ReassignVariable(identifier, expr) => {
+                m::identifier(identifier)
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + expr.pretty_print()
+            }
             Statement::DefineFunction(
                 function_name,
                 _decorators,
                 type_parameters,
                 parameters,
+                local_definitions,
                 body,
                 fn_type,
                 _return_type_annotation,
                 readable_return_type,
             ) => {
-                let (fn_type, type_parameters) = fn_type.instantiate_for_printing(Some(
+                let (fn_type, type_variables) = fn_type.instantiate_for_printing(Some(
                     type_parameters.iter().map(|(n, _)| n.clone()).collect(),
                 ));
 
                 pretty_print_function_signature(
                     function_name,
                     &fn_type,
-                    &type_parameters,
+                    &type_variables,
                     parameters
                         .iter()
                         .map(|(_, name, _, type_)| (name.clone(), type_.clone())),
                     readable_return_type,
+                    &where_clauses(type_parameters.iter().map(|(n, b)| (n, b))),
                 ) + body
                     .as_ref()
-                    .map(|e| m::space() + m::operator("=") + m::space() + e.pretty_print())
+                    .map(|e| {
+                        m::space()
+                            + m::operator("=")
+                            + m::space()
+                            + pretty_print_function_body(
+                                local_definitions.iter().map(|d| d.pretty_print()),
+                                e.pretty_print(),
+                            )
+                    })
                     .unwrap_or_default()
             }
             Statement::Expression(expr) => expr.pretty_print(),
@@ -969,6 +997,19 @@ impl PrettyPrint for Statement {
                         .map(|a| a.pretty_print())
                         .unwrap_or(type_.pretty_print())
             }
+            Statement::DeclareUnit(identifier, decorators, annotation, _type) => {
+                decorator_markup(decorators)
+                    + m::keyword("unit")
+                    + m::space()
+                    + m::unit(identifier)
+                    + m::operator(":")
+                    + m::space()
+                    + annotation.pretty_print()
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + m::operator("...")
+            }
             Statement::DefineDerivedUnit(
                 identifier,
                 expr,
@@ -995,6 +1036,8 @@ impl PrettyPrint for Statement {
                     ProcedureKind::Assert => "assert",
                     ProcedureKind::AssertEq => "assert_eq",
                     ProcedureKind::Type => "type",
+                    ProcedureKind::Debug => "debug",
+                    ProcedureKind::Csv => "csv",
                 };
                 m::identifier(identifier)
                     + m::operator("(")
@@ -1005,6 +1048,15 @@ impl PrettyPrint for Statement {
                     .sum()
                     + m::operator(")")
             }
+            Statement::DefineOperator(symbol, function_name) => {
+                m::keyword("operator")
+                    + m::space()
+                    + m::operator(symbol)
+                    + m::space()
+                    + m::operator("=")
+                    + m::space()
+                    + m::identifier(function_name)
+            }
             Statement::DefineStruct(StructInfo { name, fields, .. }) => {
                 m::keyword("struct")
                     + m::space()
@@ -1033,240 +1085,104 @@ impl PrettyPrint for Statement {
     }
 }
 
-fn pretty_scalar(n: Number) -> Markup {
-    m::value(n.pretty_print())
-}
-
-fn with_parens(expr: &Expression) -> Markup {
-    match expr {
-        Expression::Scalar(..)
-        | Expression::Identifier(..)
-        | Expression::UnitIdentifier(..)
-        | Expression::FunctionCall(..)
-        | Expression::CallableCall(..)
-        | Expression::Boolean(..)
-        | Expression::String(..)
-        | Expression::InstantiateStruct(..)
-        | Expression::AccessField(..)
-        | Expression::List(..)
-        | Expression::TypedHole(_, _) => expr.pretty_print(),
-        Expression::UnaryOperator { .. }
-        | Expression::BinaryOperator { .. }
-        | Expression::BinaryOperatorForDate { .. }
-        | Expression::Condition(..) => m::operator("(") + expr.pretty_print() + m::operator(")"),
-    }
-}
-
-/// Add parens, if needed -- liberal version, can not be used for exponentiation.
-fn with_parens_liberal(expr: &Expression) -> Markup {
-    match expr {
-        Expression::BinaryOperator(_, BinaryOperator::Mul, lhs, rhs, _type)
-            if matches!(**lhs, Expression::Scalar(..))
-                && matches!(**rhs, Expression::UnitIdentifier(..)) =>
-        {
-            expr.pretty_print()
-        }
-        _ => with_parens(expr),
-    }
-}
-
-fn pretty_print_binop(op: &BinaryOperator, lhs: &Expression, rhs: &Expression) -> Markup {
-    match op {
-        BinaryOperator::ConvertTo => {
-            // never needs parens, it has the lowest precedence:
-            lhs.pretty_print() + op.pretty_print() + rhs.pretty_print()
-        }
-        BinaryOperator::Mul => match (lhs, rhs) {
-            (
-                Expression::Scalar(_, s, _type_scalar),
-                Expression::UnitIdentifier(_, prefix, _name, full_name, _type),
-            ) => {
-                // Fuse multiplication of a scalar and a unit to a quantity
-                pretty_scalar(*s)
-                    + m::space()
-                    + m::unit(format!("{}{}", prefix.as_string_long(), full_name))
-            }
-            (Expression::Scalar(_, s, _), Expression::Identifier(_, name, _type)) => {
-                // Fuse multiplication of a scalar and identifier
-                pretty_scalar(*s) + m::space() + m::identifier(name)
-            }
-            _ => {
-                let add_parens_if_needed = |expr: &Expression| {
-                    if matches!(
-                        expr,
-                        Expression::BinaryOperator(_, BinaryOperator::Power, ..)
-                            | Expression::BinaryOperator(_, BinaryOperator::Mul, ..)
-                    ) {
-                        expr.pretty_print()
-                    } else {
-                        with_parens_liberal(expr)
-                    }
-                };
-
-                add_parens_if_needed(lhs) + op.pretty_print() + add_parens_if_needed(rhs)
-            }
-        },
-        BinaryOperator::Div => {
-            let lhs_add_parens_if_needed = |expr: &Expression| {
-                if matches!(
-                    expr,
-                    Expression::BinaryOperator(_, BinaryOperator::Power, ..)
-                        | Expression::BinaryOperator(_, BinaryOperator::Mul, ..)
-                ) {
-                    expr.pretty_print()
-                } else {
-                    with_parens_liberal(expr)
-                }
-            };
-            let rhs_add_parens_if_needed = |expr: &Expression| {
-                if matches!(
-                    expr,
-                    Expression::BinaryOperator(_, BinaryOperator::Power, ..)
-                ) {
-                    expr.pretty_print()
-                } else {
-                    with_parens_liberal(expr)
-                }
-            };
-
-            lhs_add_parens_if_needed(lhs) + op.pretty_print() + rhs_add_parens_if_needed(rhs)
-        }
-        BinaryOperator::Add => {
-            let add_parens_if_needed = |expr: &Expression| {
-                if matches!(
-                    expr,
-                    Expression::BinaryOperator(_, BinaryOperator::Power, ..)
-                        | Expression::BinaryOperator(_, BinaryOperator::Mul, ..)
-                        | Expression::BinaryOperator(_, BinaryOperator::Add, ..)
-                ) {
-                    expr.pretty_print()
-                } else {
-                    with_parens_liberal(expr)
-                }
-            };
-
-            add_parens_if_needed(lhs) + op.pretty_print() + add_parens_if_needed(rhs)
-        }
-        BinaryOperator::Sub => {
-            let add_parens_if_needed = |expr: &Expression| {
-                if matches!(
-                    expr,
-                    Expression::BinaryOperator(_, BinaryOperator::Power, ..)
-                        | Expression::BinaryOperator(_, BinaryOperator::Mul, ..)
-                ) {
-                    expr.pretty_print()
-                } else {
-                    with_parens_liberal(expr)
-                }
-            };
-
-            add_parens_if_needed(lhs) + op.pretty_print() + add_parens_if_needed(rhs)
-        }
-        BinaryOperator::Power if matches!(rhs, Expression::Scalar(_, n, _type) if n.to_f64() == 2.0) => {
-            with_parens(lhs) + m::operator("²")
-        }
-        BinaryOperator::Power if matches!(rhs, Expression::Scalar(_, n, _type) if n.to_f64() == 3.0) => {
-            with_parens(lhs) + m::operator("³")
-        }
-        _ => with_parens(lhs) + op.pretty_print() + with_parens(rhs),
-    }
-}
-
-impl PrettyPrint for Expression {
-    fn pretty_print(&self) -> Markup {
-        use Expression::*;
+impl Expression {
+    /// Turn this back into an untyped expression, such that both share the same
+    /// pretty printer. Calls of proper functions become calls of an identifier.
+    fn to_untyped(&self) -> ast::Expression {
+        let boxed = |expr: &Expression| Box::new(expr.to_untyped());
+        let all = |exprs: &[Expression]| exprs.iter().map(|e| e.to_untyped()).collect();
 
         match self {
-            Scalar(_, n, _) => pretty_scalar(*n),
-            Identifier(_, name, _type) => m::identifier(name),
-            UnitIdentifier(_, prefix, _name, full_name, _type) => {
-                m::unit(format!("{}{}", prefix.as_string_long(), full_name))
-            }
-            UnaryOperator(_, self::UnaryOperator::Negate, expr, _type) => {
-                m::operator("-") + with_parens(expr)
-            }
-            UnaryOperator(_, self::UnaryOperator::Factorial, expr, _type) => {
-                with_parens(expr) + m::operator("!")
+            Expression::Scalar(span, n, _) => ast::Expression::Scalar(*span, *n),
+            Expression::Identifier(span, name, _) => {
+                ast::Expression::Identifier(*span, name.clone())
             }
-            UnaryOperator(_, self::UnaryOperator::LogicalNeg, expr, _type) => {
-                m::operator("!") + with_parens(expr)
+            Expression::UnitIdentifier(span, prefix, name, full_name, _) => {
+                ast::Expression::UnitIdentifier(*span, *prefix, name.clone(), full_name.clone())
             }
-            BinaryOperator(_, op, lhs, rhs, _type) => pretty_print_binop(op, lhs, rhs),
-            BinaryOperatorForDate(_, op, lhs, rhs, _type) => pretty_print_binop(op, lhs, rhs),
-            FunctionCall(_, _, name, args, _type) => {
-                m::identifier(name)
-                    + m::operator("(")
-                    + itertools::Itertools::intersperse(
-                        args.iter().map(|e| e.pretty_print()),
-                        m::operator(",") + m::space(),
-                    )
-                    .sum()
-                    + m::operator(")")
+            Expression::UnaryOperator(span, op, expr, _) => ast::Expression::UnaryOperator {
+                op: *op,
+                expr: boxed(expr),
+                span_op: *span,
+            },
+            Expression::BinaryOperator(span_op, op, lhs, rhs, _)
+            | Expression::BinaryOperatorForDate(span_op, op, lhs, rhs, _) => {
+                ast::Expression::BinaryOperator {
+                    op: *op,
+                    lhs: boxed(lhs),
+                    rhs: boxed(rhs),
+                    span_op: *span_op,
+                }
             }
-            CallableCall(_, expr, args, _type) => {
-                expr.pretty_print()
-                    + m::operator("(")
-                    + itertools::Itertools::intersperse(
-                        args.iter().map(|e| e.pretty_print()),
-                        m::operator(",") + m::space(),
-                    )
-                    .sum()
-                    + m::operator(")")
+            Expression::FunctionCall(span, full_span, name, args, _) => {
+                ast::Expression::FunctionCall(
+                    *span,
+                    *full_span,
+                    Box::new(ast::Expression::Identifier(*span, name.clone())),
+                    all(args),
+                )
             }
-            Boolean(_, val) => val.pretty_print(),
-            String(_, parts) => parts.pretty_print(),
-            Condition(_, condition, then, else_) => {
-                m::keyword("if")
-                    + m::space()
-                    + with_parens(condition)
-                    + m::space()
-                    + m::keyword("then")
-                    + m::space()
-                    + with_parens(then)
-                    + m::space()
-                    + m::keyword("else")
-                    + m::space()
-                    + with_parens(else_)
+            Expression::CallableCall(full_span, callable, args, _) => {
+                ast::Expression::FunctionCall(
+                    callable.full_span(),
+                    *full_span,
+                    boxed(callable),
+                    all(args),
+                )
             }
-            InstantiateStruct(_, exprs, struct_info) => {
-                m::type_identifier(struct_info.name.clone())
-                    + m::space()
-                    + m::operator("{")
-                    + if exprs.is_empty() {
-                        m::empty()
-                    } else {
-                        m::space()
-                            + itertools::Itertools::intersperse(
-                                exprs.iter().map(|(n, e)| {
-                                    m::identifier(n)
-                                        + m::operator(":")
-                                        + m::space()
-                                        + e.pretty_print()
-                                }),
-                                m::operator(",") + m::space(),
-                            )
-                            .sum()
-                            + m::space()
-                    }
-                    + m::operator("}")
+            Expression::Boolean(span, val) => ast::Expression::Boolean(*span, *val),
+            Expression::Condition(span, condition, then, else_) => {
+                ast::Expression::Condition(*span, boxed(condition), boxed(then), boxed(else_))
             }
-            AccessField(_, _, expr, attr, _, _) => {
-                expr.pretty_print() + m::operator(".") + m::identifier(attr)
+            Expression::String(span, parts) => ast::Expression::String(
+                *span,
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        StringPart::Fixed(s) => ast::StringPart::Fixed(s.clone()),
+                        StringPart::Interpolation {
+                            span,
+                            expr,
+                            format_specifiers,
+                        } => ast::StringPart::Interpolation {
+                            span: *span,
+                            expr: boxed(expr),
+                            format_specifiers: format_specifiers.clone(),
+                        },
+                    })
+                    .collect(),
+            ),
+            Expression::InstantiateStruct(span, fields, struct_info) => {
+                ast::Expression::InstantiateStruct {
+                    full_span: *span,
+                    ident_span: struct_info.definition_span,
+                    name: struct_info.name.clone(),
+                    fields: fields
+                        .iter()
+                        .map(|(name, expr)| (*span, name.clone(), expr.to_untyped()))
+                        .collect(),
+                }
             }
-            List(_, elements, _) => {
-                m::operator("[")
-                    + itertools::Itertools::intersperse(
-                        elements.iter().map(|e| e.pretty_print()),
-                        m::operator(",") + m::space(),
-                    )
-                    .sum()
-                    + m::operator("]")
+            Expression::AccessField(ident_span, full_span, expr, field_name, _, _) => {
+                ast::Expression::AccessField(
+                    *full_span,
+                    *ident_span,
+                    boxed(expr),
+                    field_name.clone(),
+                )
             }
-            TypedHole(_, _) => m::operator("?"),
+            Expression::List(span, elements, _) => ast::Expression::List(*span, all(elements)),
+            Expression::TypedHole(span, _) => ast::Expression::TypedHole(*span),
         }
     }
 }
 
+impl PrettyPrint for Expression {
+    fn pretty_print(&self) -> Markup {
+        self.to_untyped().pretty_print()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1432,10 +1348,18 @@ mod tests {
         roundtrip_check("2^3!");
         roundtrip_check("-3!");
         roundtrip_check("(-3)!");
+        roundtrip_check("7 mod 3");
+        roundtrip_check("a mod b * c mod (d mod e) + f");
+        roundtrip_check("5 meter mod 2 meter");
         roundtrip_check("megapoints");
         roundtrip_check("Foo { foo: 1 meter, bar: 1 second }");
         roundtrip_check("\"foo\"");
         roundtrip_check("\"newline: \\n\"");
+        roundtrip_check("(if a > b then 1 meter else 2 meter) -> centimeter");
+        roundtrip_check("(if a > b then 1 kilometer else 2 kilometer) -> SI");
+        roundtrip_check("2 kilometer + 3 meter -> SI");
+        roundtrip_check("(Foo { foo: 1 meter, bar: 1 second }).foo");
+        roundtrip_check("(if a > b then Foo { foo: 1 meter, bar: 1 second } else Foo { foo: 2 meter, bar: 1 second }).bar");
     }
 
     #[test]
@@ -1,5 +1,5 @@
 use crate::markup::Markup;
-use crate::prefix_parser::AcceptsPrefix;
+use crate::prefix::AcceptsPrefix;
 use crate::registry::{BaseRepresentation, BaseRepresentationFactor, Registry, RegistryError};
 use crate::typed_ast::Type;
 use crate::unit::{CanonicalName, Unit};
@@ -23,6 +23,7 @@ pub struct UnitMetadata {
     pub canonical_name: CanonicalName,
     pub url: Option<String>,
     pub description: Option<String>,
+    pub examples: Vec<String>,
     pub binary_prefixes: bool,
     pub metric_prefixes: bool,
 }
@@ -2,7 +2,10 @@ use std::{collections::VecDeque, sync::Arc};
 
 use itertools::Itertools;
 
-use crate::{pretty_print::PrettyPrint, quantity::Quantity, typed_ast::StructInfo};
+use crate::{
+    format::FormatOptions, interval::Interval, number::RoundingMode, pretty_print::PrettyPrint,
+    quantity::Quantity, typed_ast::StructInfo,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FunctionReference {
@@ -10,6 +13,8 @@ pub enum FunctionReference {
     Normal(String),
     // TODO: We can get rid of this variant once we implement closures:
     TzConversion(String),
+    /// A function with some of its leading arguments already supplied
+    Partial(Box<FunctionReference>, Vec<Value>),
 }
 
 impl std::fmt::Display for FunctionReference {
@@ -20,6 +25,13 @@ impl std::fmt::Display for FunctionReference {
             FunctionReference::TzConversion(tz) => {
                 write!(f, "<builtin timezone conversion function: {tz}>")
             }
+            FunctionReference::Partial(inner, args) => {
+                write!(
+                    f,
+                    "<partial application of {inner} to {}>",
+                    args.iter().join(", ")
+                )
+            }
         }
     }
 }
@@ -29,6 +41,8 @@ pub type NumbatList<T> = VecDeque<T>;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Value {
     Quantity(Quantity),
+    /// A range of quantities `[lower .. upper]`, used for tolerance analysis
+    Interval(Interval),
     Boolean(bool),
     String(String),
     /// A DateTime with an associated offset used when pretty printing
@@ -112,6 +126,7 @@ impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Quantity(q) => write!(f, "{}", q),
+            Value::Interval(i) => write!(f, "{}", i),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::String(s) => write!(f, "\"{}\"", s),
             Value::DateTime(dt) => write!(f, "datetime(\"{}\")", dt),
@@ -147,10 +162,16 @@ impl std::fmt::Display for Value {
     }
 }
 
-impl PrettyPrint for Value {
-    fn pretty_print(&self) -> crate::markup::Markup {
+impl Value {
+    /// Like [`PrettyPrint::pretty_print`], but numbers are rounded to the displayed
+    /// number of digits with the given rounding mode.
+    pub fn pretty_print_with_rounding(&self, rounding_mode: RoundingMode) -> crate::markup::Markup {
         match self {
-            Value::Quantity(q) => q.pretty_print(),
+            Value::Quantity(q) => q.pretty_print_with_options(&FormatOptions {
+                rounding_mode,
+                ..FormatOptions::default()
+            }),
+            Value::Interval(i) => i.pretty_print_with_rounding(rounding_mode),
             Value::Boolean(b) => b.pretty_print(),
             Value::String(s) => s.pretty_print(),
             Value::DateTime(dt) => crate::markup::string(crate::datetime::to_rfc2822_save(dt)),
@@ -170,7 +191,7 @@ impl PrettyPrint for Value {
                                     crate::markup::identifier(name)
                                         + crate::markup::operator(":")
                                         + crate::markup::space()
-                                        + val.pretty_print()
+                                        + val.pretty_print_with_rounding(rounding_mode)
                                 }),
                                 crate::markup::operator(",") + crate::markup::space(),
                             )
@@ -182,7 +203,9 @@ impl PrettyPrint for Value {
             Value::List(elements) => {
                 crate::markup::operator("[")
                     + itertools::Itertools::intersperse(
-                        elements.iter().map(|element| element.pretty_print()),
+                        elements
+                            .iter()
+                            .map(|element| element.pretty_print_with_rounding(rounding_mode)),
                         crate::markup::operator(",") + crate::markup::space(),
                     )
                     .sum()
@@ -191,3 +214,9 @@ impl PrettyPrint for Value {
         }
     }
 }
+
+impl PrettyPrint for Value {
+    fn pretty_print(&self) -> crate::markup::Markup {
+        self.pretty_print_with_rounding(RoundingMode::default())
+    }
+}
@@ -3,16 +3,21 @@ use std::sync::Arc;
 use std::{cmp::Ordering, fmt::Display};
 
 use indexmap::IndexMap;
+use itertools::Itertools;
+use numbat_exchange_rates::ExchangeRates;
 
 use crate::span::Span;
 use crate::typed_ast::StructInfo;
 use crate::value::NumbatList;
 use crate::{
-    ffi::{self, ArityRange, Callable, ForeignFunction},
-    interpreter::{InterpreterResult, PrintFunction, Result, RuntimeError},
+    ffi::{self, ArityRange, Callable, ForeignFunction, ForeignFunctionFilter},
+    interpreter::{
+        InputFunction, InterpreterResult, PrintFunction, PrintValueFunction, Result, RuntimeError,
+    },
+    interval::Interval,
     markup::Markup,
     math,
-    number::Number,
+    number::{Number, RoundingMode},
     prefix::Prefix,
     quantity::{Quantity, QuantityError},
     unit::Unit,
@@ -38,6 +43,11 @@ pub enum Op {
     /// `1 <new_unit>` to the constant with the given index.
     SetUnitConstant,
 
+    /// Like `SetUnitConstant`, but for a derived unit that has already been
+    /// defined: only the value of the constant is replaced. This is used to
+    /// re-evaluate units that depend on exchange rates.
+    UpdateUnitConstant,
+
     /// Push the value of the specified local variable onto the stack (even
     /// though it is already on the stack, somewhere lower down).
     GetLocal,
@@ -45,9 +55,26 @@ pub enum Op {
     /// Similar to GetLocal, but get variable from surrounding scope
     GetUpvalue,
 
+    /// Pop the value from the top of the stack and store it in the
+    /// specified local variable (used for reassignment of mutable variables)
+    SetLocal,
+
     /// Get the last stored result (_ and ans)
     GetLastResult,
 
+    /// Push the value of the specified lazy global, computing it if necessary
+    GetLazyGlobal,
+
+    /// Push a copy of the value on top of the stack
+    Dup,
+
+    /// Push a copy of the value that is the given number of slots below the
+    /// top of the stack. Used to access the arguments of inlined functions.
+    GetInlineArgument,
+    /// Remove the given number of arguments of an inlined function from the
+    /// stack, keeping the value on top of the stack (the result).
+    DropInlineArguments,
+
     /// Negate the top of the stack
     Negate,
 
@@ -62,10 +89,14 @@ pub enum Op {
     Multiply,
     /// Similar to Add.
     Divide,
+    /// Similar to Add. The remainder is computed in the unit of the left hand side.
+    Modulo,
     /// Similar to Add.
     Power,
     /// Similar to Add.
     ConvertTo,
+    /// Convert the quantity on top of the stack to its base unit representation (`-> SI`)
+    ConvertToBaseUnits,
     /// Similar to Add:
     LessThan,
     GreaterThan,
@@ -95,11 +126,14 @@ pub enum Op {
     /// Same as above, but call a foreign/native function
     FFICallFunction,
     /// Same as above, but call a procedure which does not return anything (does not push a value onto the stack)
-    /// It has a third argument which is an index to retrieve the source-span of the arguments
+    /// It has a third argument which is an index to retrieve the source-span of the arguments.
+    /// Execution continues with the next instruction unless the procedure fails.
     FFICallProcedure,
 
     /// Call a callable object
     CallCallable,
+    /// Bind the given number of arguments to the function reference on top of the stack
+    PartialApply,
 
     /// Print a compile-time string
     PrintString,
@@ -126,16 +160,25 @@ impl Op {
     fn num_operands(self) -> usize {
         match self {
             Op::FFICallProcedure => 3,
-            Op::SetUnitConstant | Op::Call | Op::FFICallFunction | Op::BuildStructInstance => 2,
+            Op::SetUnitConstant
+            | Op::UpdateUnitConstant
+            | Op::Call
+            | Op::FFICallFunction
+            | Op::BuildStructInstance => 2,
             Op::LoadConstant
             | Op::ApplyPrefix
             | Op::GetLocal
             | Op::GetUpvalue
+            | Op::GetLazyGlobal
+            | Op::SetLocal
+            | Op::GetInlineArgument
+            | Op::DropInlineArguments
             | Op::PrintString
             | Op::JoinString
             | Op::JumpIfFalse
             | Op::Jump
             | Op::CallCallable
+            | Op::PartialApply
             | Op::AccessStructField
             | Op::BuildList => 1,
             Op::Negate
@@ -147,8 +190,10 @@ impl Op {
             | Op::DiffDateTime
             | Op::Multiply
             | Op::Divide
+            | Op::Modulo
             | Op::Power
             | Op::ConvertTo
+            | Op::ConvertToBaseUnits
             | Op::LessThan
             | Op::GreaterThan
             | Op::LessOrEqual
@@ -160,7 +205,8 @@ impl Op {
             | Op::LogicalNeg
             | Op::FullSimplify
             | Op::Return
-            | Op::GetLastResult => 0,
+            | Op::GetLastResult
+            | Op::Dup => 0,
         }
     }
 
@@ -169,9 +215,15 @@ impl Op {
             Op::LoadConstant => "LoadConstant",
             Op::ApplyPrefix => "ApplyPrefix",
             Op::SetUnitConstant => "SetUnitConstant",
+            Op::UpdateUnitConstant => "UpdateUnitConstant",
             Op::GetLocal => "GetLocal",
             Op::GetUpvalue => "GetUpvalue",
+            Op::SetLocal => "SetLocal",
             Op::GetLastResult => "GetLastResult",
+            Op::GetLazyGlobal => "GetLazyGlobal",
+            Op::Dup => "Dup",
+            Op::GetInlineArgument => "GetInlineArgument",
+            Op::DropInlineArguments => "DropInlineArguments",
             Op::Negate => "Negate",
             Op::Factorial => "Factorial",
             Op::Add => "Add",
@@ -181,8 +233,10 @@ impl Op {
             Op::DiffDateTime => "DiffDateTime",
             Op::Multiply => "Multiply",
             Op::Divide => "Divide",
+            Op::Modulo => "Modulo",
             Op::Power => "Power",
             Op::ConvertTo => "ConvertTo",
+            Op::ConvertToBaseUnits => "ConvertToBaseUnits",
             Op::LessThan => "LessThan",
             Op::GreaterThan => "GreaterThan",
             Op::LessOrEqual => "LessOrEqual",
@@ -198,6 +252,7 @@ impl Op {
             Op::FFICallFunction => "FFICallFunction",
             Op::FFICallProcedure => "FFICallProcedure",
             Op::CallCallable => "CallCallable",
+            Op::PartialApply => "PartialApply",
             Op::PrintString => "PrintString",
             Op::JoinString => "JoinString",
             Op::FullSimplify => "FullSimplify",
@@ -213,10 +268,13 @@ impl Op {
 pub enum Constant {
     Scalar(f64),
     Unit(Unit),
+    Quantity(Quantity),
     Boolean(bool),
     String(String),
     FunctionReference(FunctionReference),
     FormatSpecifiers(Option<String>),
+    /// Placeholder for a forward-declared unit whose value has not been defined yet
+    DeclaredUnit(String),
 }
 
 impl Constant {
@@ -224,10 +282,12 @@ impl Constant {
         match self {
             Constant::Scalar(n) => Value::Quantity(Quantity::from_scalar(*n)),
             Constant::Unit(u) => Value::Quantity(Quantity::from_unit(u.clone())),
+            Constant::Quantity(q) => Value::Quantity(q.clone()),
             Constant::Boolean(b) => Value::Boolean(*b),
             Constant::String(s) => Value::String(s.clone()),
             Constant::FunctionReference(inner) => Value::FunctionReference(inner.clone()),
             Constant::FormatSpecifiers(s) => Value::FormatSpecifiers(s.clone()),
+            Constant::DeclaredUnit(_) => unreachable!("Declared units are checked when loaded"),
         }
     }
 }
@@ -237,12 +297,89 @@ impl Display for Constant {
         match self {
             Constant::Scalar(n) => write!(f, "{}", n),
             Constant::Unit(unit) => write!(f, "{}", unit),
+            Constant::Quantity(quantity) => write!(f, "{}", quantity),
             Constant::Boolean(val) => write!(f, "{}", val),
             Constant::String(val) => write!(f, "\"{}\"", val),
             Constant::FunctionReference(inner) => write!(f, "{}", inner),
             Constant::FormatSpecifiers(_) => write!(f, "<format specfiers>"),
+            Constant::DeclaredUnit(name) => write!(f, "<declared unit: {}>", name),
+        }
+    }
+}
+
+/// The bytecode of a single function, along with the constants, prefixes, … that it
+/// refers to. In contrast to the code in a [Vm], operands refer to the tables of this
+/// chunk or to other functions and global variables by name. This makes it possible to
+/// import the function into a different [Vm], see [Vm::export_function].
+#[derive(Debug, Clone)]
+pub struct RelocatableChunk {
+    name: String,
+    code: Vec<u8>,
+    constants: Vec<Constant>,
+    prefixes: Vec<Prefix>,
+    strings: Vec<Markup>,
+    procedure_arg_spans: Vec<Vec<Span>>,
+    /// Names of the called functions
+    functions: Vec<String>,
+    /// Names of the called foreign functions and procedures
+    ffi_callables: Vec<String>,
+    /// Names of the global variables that are accessed
+    globals: Vec<String>,
+    /// Names of the lazy globals that are accessed
+    lazy_globals: Vec<String>,
+}
+
+impl RelocatableChunk {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Names of the (non-foreign) functions and global variables that this function
+    /// refers to, without the function itself.
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        let function_references = self.constants.iter().filter_map(|c| match c {
+            Constant::FunctionReference(FunctionReference::Normal(name)) => Some(name),
+            _ => None,
+        });
+
+        self.functions
+            .iter()
+            .chain(function_references)
+            .chain(self.globals.iter())
+            .chain(self.lazy_globals.iter())
+            .map(String::as_str)
+            .filter(|name| *name != self.name)
+            .unique()
+    }
+}
+
+/// Replace every operand of the instructions in `code` by the result of
+/// `relocate(op, operand_number, operand)`.
+fn relocate_operands<E>(
+    code: &mut [u8],
+    mut relocate: impl FnMut(Op, usize, u16) -> std::result::Result<u16, E>,
+) -> std::result::Result<(), E> {
+    let mut offset = 0;
+    while offset < code.len() {
+        let op = unsafe { std::mem::transmute::<u8, Op>(code[offset]) };
+        offset += 1;
+
+        for operand_number in 0..op.num_operands() {
+            let operand = u16::from_le_bytes([code[offset], code[offset + 1]]);
+            let relocated = relocate(op, operand_number, operand)?;
+            code[offset..(offset + 2)].copy_from_slice(&relocated.to_le_bytes());
+            offset += 2;
         }
     }
+
+    Ok(())
+}
+
+/// Append `item` to `table` and return its index
+fn push_index<T>(table: &mut Vec<T>, item: T) -> u16 {
+    table.push(item);
+    assert!(table.len() <= u16::MAX as usize);
+    (table.len() - 1) as u16
 }
 
 #[derive(Clone)]
@@ -271,8 +408,75 @@ impl CallFrame {
 
 pub struct ExecutionContext<'a> {
     pub print_fn: &'a mut PrintFunction,
+    pub print_value_fn: Option<&'a mut PrintValueFunction>,
+    pub input_fn: Option<&'a mut InputFunction>,
+    pub max_steps: Option<u64>,
+    pub max_output_bytes: Option<usize>,
+    /// The number of bytes that have been passed to `print_fn` so far
+    pub output_bytes: usize,
+    pub rounding_mode: RoundingMode,
+    /// Exchange rates that have been provided for this context, see
+    /// [`crate::Context::set_exchange_rate_table`]. If this is not set, the global
+    /// exchange rate cache is used.
+    pub exchange_rates: Option<&'a ExchangeRates>,
 }
 
+impl ExecutionContext<'_> {
+    /// Pass the given output to `print_fn`, unless this would exceed `max_output_bytes`.
+    pub fn print(&mut self, m: &Markup) -> Result<()> {
+        if let Some(max_output_bytes) = self.max_output_bytes {
+            self.output_bytes += m.to_string().len();
+            if self.output_bytes > max_output_bytes {
+                return Err(RuntimeError::OutputLimitExceeded(max_output_bytes));
+            }
+        }
+
+        (self.print_fn)(m);
+        Ok(())
+    }
+}
+
+/// How often the value of a lazy global is computed, see
+/// [`Context::define_lazy_global`](crate::Context::define_lazy_global).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LazyEvaluation {
+    /// Compute the value every time the global is accessed
+    #[default]
+    EachAccess,
+    /// Compute the value on the first access, and reuse it afterwards
+    Once,
+}
+
+pub(crate) type LazyGlobalFunction = dyn Fn() -> Value + Send + Sync;
+
+/// A global variable whose value is computed by a function of the host application
+/// when it is accessed, instead of being stored on the stack.
+#[derive(Clone)]
+struct LazyGlobal {
+    name: String,
+    evaluation: LazyEvaluation,
+    compute: Arc<LazyGlobalFunction>,
+    cached_value: Option<Value>,
+}
+
+impl LazyGlobal {
+    fn get(&mut self) -> Value {
+        if let Some(value) = &self.cached_value {
+            return value.clone();
+        }
+
+        let value = (self.compute)();
+        if self.evaluation == LazyEvaluation::Once {
+            self.cached_value = Some(value.clone());
+        }
+        value
+    }
+}
+
+/// The default maximum number of nested function calls. Numbat has no loops, so
+/// this needs to be large enough for recursive functions that iterate over many values.
+pub const DEFAULT_MAX_STACK_DEPTH: usize = 100_000;
+
 #[derive(Clone)]
 pub struct Vm {
     /// The actual code of the program, structured by function name. The code
@@ -307,6 +511,12 @@ pub struct Vm {
     /// List of registered native/foreign functions
     ffi_callables: Vec<&'static ForeignFunction>,
 
+    /// Foreign functions that may be used by imported functions
+    ffi_filter: ForeignFunctionFilter,
+
+    /// Global variables whose values are computed by the host application
+    lazy_globals: Vec<LazyGlobal>,
+
     /// Spans for arguments of procedure calls. This is used for
     /// assertion error messages, for example.
     procedure_arg_spans: Vec<Vec<Span>>,
@@ -314,6 +524,9 @@ pub struct Vm {
     /// The call stack
     frames: Vec<CallFrame>,
 
+    /// The maximum number of nested function calls (see [`Vm::set_max_stack_depth`])
+    max_stack_depth: usize,
+
     /// The stack of the VM.
     stack: Vec<Value>,
 
@@ -335,8 +548,11 @@ impl Vm {
             unit_information: vec![],
             last_result: None,
             ffi_callables: ffi::procedures().iter().map(|(_, ff)| ff).collect(),
+            ffi_filter: ForeignFunctionFilter::default(),
+            lazy_globals: vec![],
             procedure_arg_spans: vec![],
             frames: vec![CallFrame::root()],
+            max_stack_depth: DEFAULT_MAX_STACK_DEPTH,
             stack: vec![],
             debug: false,
             unit_registry: UnitRegistry::new(),
@@ -346,6 +562,16 @@ impl Vm {
         self.debug = activate;
     }
 
+    /// Set the maximum number of nested function calls. Deeper recursion is aborted
+    /// with [`RuntimeError::StackOverflow`].
+    pub fn set_max_stack_depth(&mut self, max_stack_depth: usize) {
+        self.max_stack_depth = max_stack_depth;
+    }
+
+    pub(crate) fn set_foreign_function_filter(&mut self, filter: ForeignFunctionFilter) {
+        self.ffi_filter = filter;
+    }
+
     // The following functions are helpers for the compilation process
 
     fn current_chunk_mut(&mut self) -> &mut Vec<u8> {
@@ -383,12 +609,11 @@ impl Vm {
         Self::push_u16(current_chunk, arg3);
     }
 
-    pub fn current_offset(&self) -> u16 {
-        self.bytecode[self.current_chunk_index].1.len() as u16
+    pub fn current_offset(&self) -> usize {
+        self.bytecode[self.current_chunk_index].1.len()
     }
 
-    pub fn patch_u16_value_at(&mut self, offset: u16, arg: u16) {
-        let offset = offset as usize;
+    pub fn patch_u16_value_at(&mut self, offset: usize, arg: u16) {
         let chunk = self.current_chunk_mut();
         chunk[offset] = (arg & 0xff) as u8;
         chunk[offset + 1] = ((arg >> 8) & 0xff) as u8;
@@ -400,6 +625,10 @@ impl Vm {
         (self.constants.len() - 1) as u16 // TODO: this can overflow, see above
     }
 
+    pub(crate) fn constant(&self, index: u16) -> &Constant {
+        &self.constants[index as usize]
+    }
+
     pub fn add_struct_info(&mut self, struct_info: &StructInfo) -> usize {
         let e = self.struct_infos.entry(struct_info.name.clone());
         let idx = e.index();
@@ -451,18 +680,157 @@ impl Vm {
         self.current_chunk_index = 0;
     }
 
+    /// Compile into a separate chunk that can later be copied to the call sites
+    /// of an inlined function, see [Vm::add_inline_template].
+    pub(crate) fn begin_inline_template(&mut self) {
+        self.bytecode.push(("<inline>".into(), vec![]));
+        self.current_chunk_index = self.bytecode.len() - 1
+    }
+
+    pub(crate) fn end_inline_template(&mut self) -> Vec<u8> {
+        let (_, template) = self.bytecode.pop().unwrap();
+        self.current_chunk_index = 0;
+        template
+    }
+
+    pub(crate) fn add_inline_template(&mut self, template: &[u8]) {
+        self.current_chunk_mut().extend_from_slice(template);
+    }
+
     pub(crate) fn get_function_idx(&self, name: &str) -> u16 {
+        self.find_function_idx(name).unwrap()
+    }
+
+    fn find_function_idx(&self, name: &str) -> Option<u16> {
         // We search backwards to allow for functions
         // to be overwritten.
-        let rev_position = self
-            .bytecode
-            .iter()
-            .rev()
-            .position(|(n, _)| n == name)
-            .unwrap();
+        let rev_position = self.bytecode.iter().rev().position(|(n, _)| n == name)?;
         let position = self.bytecode.len() - 1 - rev_position;
         assert!(position <= u16::MAX as usize);
-        position as u16
+        Some(position as u16)
+    }
+
+    /// Extract the code of the given function, such that it can be imported into another
+    /// [Vm]. `global_name` maps indices of global variables to their names. Returns `None`
+    /// if the function builds struct instances, which can not be relocated.
+    pub(crate) fn export_function(
+        &self,
+        name: &str,
+        global_name: impl Fn(u16) -> String,
+    ) -> Option<RelocatableChunk> {
+        let (_, code) = &self.bytecode[self.find_function_idx(name)? as usize];
+
+        let mut chunk = RelocatableChunk {
+            name: name.into(),
+            code: code.clone(),
+            constants: vec![],
+            prefixes: vec![],
+            strings: vec![],
+            procedure_arg_spans: vec![],
+            functions: vec![],
+            ffi_callables: vec![],
+            globals: vec![],
+            lazy_globals: vec![],
+        };
+
+        let mut code = std::mem::take(&mut chunk.code);
+        relocate_operands(&mut code, |op, operand_number, idx| {
+            let idx = idx as usize;
+            Ok(match (op, operand_number) {
+                (Op::LoadConstant, 0) => {
+                    push_index(&mut chunk.constants, self.constants[idx].clone())
+                }
+                (Op::ApplyPrefix, 0) => push_index(&mut chunk.prefixes, self.prefixes[idx]),
+                (Op::PrintString, 0) => push_index(&mut chunk.strings, self.strings[idx].clone()),
+                (Op::Call, 0) => push_index(&mut chunk.functions, self.bytecode[idx].0.clone()),
+                (Op::FFICallFunction | Op::FFICallProcedure, 0) => push_index(
+                    &mut chunk.ffi_callables,
+                    self.ffi_callables[idx].name.clone(),
+                ),
+                (Op::FFICallProcedure, 2) => push_index(
+                    &mut chunk.procedure_arg_spans,
+                    self.procedure_arg_spans[idx].clone(),
+                ),
+                (Op::GetUpvalue, 0) => push_index(&mut chunk.globals, global_name(idx as u16)),
+                (Op::GetLazyGlobal, 0) => {
+                    push_index(&mut chunk.lazy_globals, self.lazy_globals[idx].name.clone())
+                }
+                (Op::BuildStructInstance, _) => return Err(()),
+                (_, _) => idx as u16,
+            })
+        })
+        .ok()?;
+        chunk.code = code;
+
+        Some(chunk)
+    }
+
+    /// Add a function that has been extracted from another [Vm] with [Vm::export_function].
+    /// Functions and global variables are looked up by name, `global_idx` maps names of
+    /// global variables to their index. The spans of procedure arguments are moved to the
+    /// given code source. If a function or global variable is not available, its name is
+    /// returned as an error.
+    pub(crate) fn import_function(
+        &mut self,
+        chunk: &RelocatableChunk,
+        code_source_id: usize,
+        global_idx: impl Fn(&str) -> Option<u16>,
+    ) -> std::result::Result<(), String> {
+        // The function is added at the end, which is also where recursive calls go
+        let function_idx = self.bytecode.len() as u16;
+
+        let mut code = chunk.code.clone();
+        relocate_operands(
+            &mut code,
+            |op, operand_number, idx| -> std::result::Result<_, String> {
+                let idx = idx as usize;
+                Ok(match (op, operand_number) {
+                    (Op::LoadConstant, 0) => self.add_constant(chunk.constants[idx].clone()),
+                    (Op::ApplyPrefix, 0) => self.add_prefix(chunk.prefixes[idx]),
+                    (Op::PrintString, 0) => self.add_string(chunk.strings[idx].clone()),
+                    (Op::Call, 0) => {
+                        let name = &chunk.functions[idx];
+                        if name == &chunk.name {
+                            function_idx
+                        } else {
+                            self.find_function_idx(name).ok_or_else(|| name.clone())?
+                        }
+                    }
+                    (Op::FFICallFunction | Op::FFICallProcedure, 0) => {
+                        let name = &chunk.ffi_callables[idx];
+                        if !self.ffi_filter.allows(name) {
+                            return Err(name.clone());
+                        }
+                        match self.get_ffi_callable_idx(name) {
+                            Some(idx) => idx,
+                            None => push_index(
+                                &mut self.ffi_callables,
+                                ffi::functions().get(name).ok_or_else(|| name.clone())?,
+                            ),
+                        }
+                    }
+                    (Op::FFICallProcedure, 2) => self.add_procedure_arg_span(
+                        chunk.procedure_arg_spans[idx]
+                            .iter()
+                            .map(|span| span.with_code_source_id(code_source_id))
+                            .collect(),
+                    ),
+                    (Op::GetUpvalue, 0) => {
+                        let name = &chunk.globals[idx];
+                        global_idx(name).ok_or_else(|| name.clone())?
+                    }
+                    (Op::GetLazyGlobal, 0) => {
+                        let name = &chunk.lazy_globals[idx];
+                        self.get_lazy_global_idx(name).ok_or_else(|| name.clone())?
+                    }
+                    (_, _) => idx as u16,
+                })
+            },
+        )?;
+
+        self.bytecode.push((chunk.name.clone(), code));
+
+        Ok(())
     }
 
     pub(crate) fn add_foreign_function(&mut self, name: &str, arity: ArityRange) {
@@ -478,6 +846,29 @@ impl Vm {
         Some(position as u16)
     }
 
+    /// Add a lazy global and return its index
+    pub(crate) fn add_lazy_global(
+        &mut self,
+        name: &str,
+        evaluation: LazyEvaluation,
+        compute: Arc<LazyGlobalFunction>,
+    ) -> u16 {
+        push_index(
+            &mut self.lazy_globals,
+            LazyGlobal {
+                name: name.into(),
+                evaluation,
+                compute,
+                cached_value: None,
+            },
+        )
+    }
+
+    pub(crate) fn get_lazy_global_idx(&self, name: &str) -> Option<u16> {
+        let position = self.lazy_globals.iter().rposition(|g| g.name == name)?;
+        Some(position as u16)
+    }
+
     pub(crate) fn add_procedure_arg_span(&mut self, spans: Vec<Span>) -> u16 {
         self.procedure_arg_spans.push(spans);
         assert!(self.procedure_arg_spans.len() <= u16::MAX as usize);
@@ -490,16 +881,25 @@ impl Vm {
         }
 
         eprintln!();
-        eprintln!(".CONSTANTS");
+        eprint!("{}", self.disassembly());
+        eprintln!();
+    }
+
+    pub(crate) fn disassembly(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+
+        writeln!(out, ".CONSTANTS").unwrap();
         for (idx, constant) in self.constants.iter().enumerate() {
-            eprintln!("  {:04} {}", idx, constant);
+            writeln!(out, "  {:04} {}", idx, constant).unwrap();
         }
-        eprintln!(".IDENTIFIERS");
+        writeln!(out, ".IDENTIFIERS").unwrap();
         for (idx, identifier) in self.unit_information.iter().enumerate() {
-            eprintln!("  {:04} {}", idx, identifier.0);
+            writeln!(out, "  {:04} {}", idx, identifier.0).unwrap();
         }
         for (idx, (function_name, bytecode)) in self.bytecode.iter().enumerate() {
-            eprintln!(".CODE {idx} ({name})", idx = idx, name = function_name);
+            writeln!(out, ".CODE {idx} ({name})", idx = idx, name = function_name).unwrap();
             let mut offset = 0;
             while offset < bytecode.len() {
                 let this_offset = offset;
@@ -521,25 +921,35 @@ impl Vm {
                     .collect::<Vec<String>>()
                     .join(" ");
 
-                eprint!(
+                write!(
+                    out,
                     "  {:04} {:<13} {}",
                     this_offset,
                     op.to_string(),
                     operands_str,
-                );
+                )
+                .unwrap();
 
                 if op == Op::LoadConstant {
-                    eprint!("     (value: {})", self.constants[operands[0] as usize]);
+                    write!(
+                        out,
+                        "     (value: {})",
+                        self.constants[operands[0] as usize]
+                    )
+                    .unwrap();
                 } else if op == Op::Call {
-                    eprint!(
+                    write!(
+                        out,
                         "   ({}, num_args={})",
                         self.bytecode[operands[0] as usize].0, operands[1] as usize
-                    );
+                    )
+                    .unwrap();
                 }
-                eprintln!();
+                writeln!(out).unwrap();
             }
         }
-        eprintln!();
+
+        out
     }
 
     // The following functions are helpers for the actual execution of the code
@@ -564,6 +974,17 @@ impl Vm {
         u16::from_le_bytes(bytes)
     }
 
+    /// All units that have been defined so far, without their aliases
+    fn defined_units(&self) -> Vec<&Unit> {
+        self.constants
+            .iter()
+            .filter_map(|constant| match constant {
+                Constant::Unit(unit) => Some(unit),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn push_quantity(&mut self, quantity: Quantity) {
         self.stack.push(Value::Quantity(quantity));
     }
@@ -577,9 +998,10 @@ impl Vm {
     }
 
     #[track_caller]
-    fn pop_quantity(&mut self) -> Quantity {
+    fn pop_quantity(&mut self) -> Result<Quantity> {
         match self.pop() {
-            Value::Quantity(q) => q,
+            Value::Quantity(q) => Ok(q),
+            Value::Interval(_) => Err(RuntimeError::UnsupportedIntervalOperation),
             _ => panic!("Expected quantity to be on the top of the stack"),
         }
     }
@@ -602,6 +1024,47 @@ impl Vm {
         self.stack.pop().expect("stack should not be empty")
     }
 
+    /// Interval-aware version of the arithmetic operations, used if at least one of
+    /// the operands is an interval. Quantities are treated as degenerate intervals.
+    fn interval_operation(op: Op, lhs: Value, rhs: Value) -> Result<Interval> {
+        let to_interval = |value| match value {
+            Value::Interval(i) => i,
+            value => Interval::degenerate(value.unsafe_as_quantity()),
+        };
+
+        match op {
+            Op::Add => to_interval(lhs).add(&to_interval(rhs)),
+            Op::Subtract => to_interval(lhs).sub(&to_interval(rhs)),
+            Op::Multiply => to_interval(lhs).mul(&to_interval(rhs)),
+            Op::Divide => to_interval(lhs).checked_div(&to_interval(rhs)),
+            Op::ConvertTo => match rhs {
+                Value::Quantity(target) => to_interval(lhs).convert_to(target.unit()),
+                _ => Err(RuntimeError::UnsupportedIntervalOperation),
+            },
+            _ => Err(RuntimeError::UnsupportedIntervalOperation),
+        }
+    }
+
+    /// Calls to denied foreign functions are rejected by the type checker. This is a
+    /// backstop for code that has been compiled before the filter was set.
+    fn ensure_foreign_function_allowed(&self, function_idx: usize) -> Result<()> {
+        let name = &self.ffi_callables[function_idx].name;
+        if self.ffi_filter.allows(name) {
+            Ok(())
+        } else {
+            Err(RuntimeError::ForeignFunctionNotAvailable(name.clone()))
+        }
+    }
+
+    /// Foreign functions only operate on plain quantities.
+    fn ensure_no_interval_arguments(args: &VecDeque<Value>) -> Result<()> {
+        if args.iter().any(|arg| matches!(arg, Value::Interval(_))) {
+            Err(RuntimeError::UnsupportedIntervalOperation)
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn run(&mut self, ctx: &mut ExecutionContext) -> Result<InterpreterResult> {
         let old_stack = self.stack.clone();
         let result = self.run_without_cleanup(ctx);
@@ -622,25 +1085,54 @@ impl Vm {
         result
     }
 
+    /// Start executing the given function, whose arguments are on top of the stack
+    fn push_call_frame(&mut self, function_idx: usize, num_args: usize) -> Result<()> {
+        // The root frame does not count as a function call
+        if self.frames.len() > self.max_stack_depth {
+            return Err(RuntimeError::StackOverflow(self.max_stack_depth));
+        }
+
+        self.frames.push(CallFrame {
+            function_idx,
+            ip: 0,
+            fp: self.stack.len() - num_args,
+        });
+
+        Ok(())
+    }
+
     fn is_at_the_end(&self) -> bool {
         self.current_frame().ip >= self.bytecode[self.current_frame().function_idx].1.len()
     }
 
     fn run_without_cleanup(&mut self, ctx: &mut ExecutionContext) -> Result<InterpreterResult> {
         let mut result_last_statement = None;
+        let mut steps: u64 = 0;
+        // Only expression statements end with a `Return` in the main chunk. Programs
+        // that end with a definition simply run until the end of the bytecode.
         while !self.is_at_the_end() {
             self.debug();
 
+            if let Some(max_steps) = ctx.max_steps {
+                steps += 1;
+                if steps > max_steps {
+                    return Err(RuntimeError::StepLimitExceeded(max_steps));
+                }
+            }
+
             let op = unsafe { std::mem::transmute::<u8, Op>(self.read_byte()) };
 
             match op {
                 Op::LoadConstant => {
                     let constant_idx = self.read_u16();
-                    self.stack
-                        .push(self.constants[constant_idx as usize].to_value());
+                    let constant = &self.constants[constant_idx as usize];
+                    if let Constant::DeclaredUnit(name) = constant {
+                        return Err(RuntimeError::UnitValueNotDefined(name.clone()));
+                    }
+                    self.stack.push(constant.to_value());
                 }
                 Op::ApplyPrefix => {
-                    let quantity = self.pop_quantity();
+                    let quantity = self.pop_quantity()?;
                     let prefix_idx = self.read_u16();
                     let prefix = self.prefixes[prefix_idx as usize];
                     self.push_quantity(Quantity::new(
@@ -648,24 +1140,39 @@ impl Vm {
                         quantity.unit().clone().with_prefix(prefix),
                     ));
                 }
-                Op::SetUnitConstant => {
+                Op::SetUnitConstant | Op::UpdateUnitConstant => {
                     let unit_information_idx = self.read_u16();
                     let constant_idx = self.read_u16();
 
-                    let conversion_value = self.pop_quantity();
+                    let conversion_value = self.pop_quantity()?;
 
                     let unit_information = &self.unit_information[unit_information_idx as usize];
-                    let defining_unit = conversion_value.unit();
 
-                    let (base_unit_representation, _) = defining_unit.to_base_unit_representation();
+                    // The value is used as a conversion factor, so it can not be zero or
+                    // infinite. Its unit can be arbitrarily complex. Note that NaN is
+                    // allowed, as it is used for values that are not available (e.g.
+                    // exchange rates without network access).
+                    let factor = conversion_value.unsafe_value().to_f64();
+                    if factor == 0.0 || factor.is_infinite() {
+                        return Err(RuntimeError::InvalidUnitDefinition(
+                            unit_information.0.clone(),
+                        ));
+                    }
+
+                    let defining_unit = conversion_value.unit();
 
-                    self.unit_registry
-                        .add_derived_unit(
-                            &unit_information.0,
-                            &base_unit_representation,
-                            unit_information.2.clone(),
-                        )
-                        .map_err(RuntimeError::UnitRegistryError)?;
+                    if op == Op::SetUnitConstant {
+                        let (base_unit_representation, _) =
+                            defining_unit.to_base_unit_representation();
+
+                        self.unit_registry
+                            .add_derived_unit(
+                                &unit_information.0,
+                                &base_unit_representation,
+                                unit_information.2.clone(),
+                            )
+                            .map_err(RuntimeError::UnitRegistryError)?;
+                    }
 
                     self.constants[constant_idx as usize] = Constant::Unit(Unit::new_derived(
                         &unit_information.0,
@@ -683,32 +1190,70 @@ impl Vm {
                     let stack_idx = self.read_u16() as usize;
                     self.push(self.stack[stack_idx].clone());
                 }
+                Op::SetLocal => {
+                    let slot_idx = self.read_u16() as usize;
+                    let stack_idx = self.current_frame().fp + slot_idx;
+                    self.stack[stack_idx] = self.pop();
+                }
+                Op::GetLazyGlobal => {
+                    let idx = self.read_u16() as usize;
+                    let value = self.lazy_globals[idx].get();
+                    self.push(value);
+                }
                 Op::GetLastResult => {
                     self.push(self.last_result.as_ref().unwrap().clone());
                 }
+                Op::Dup => {
+                    let value = self.stack.last().expect("stack is not empty").clone();
+                    self.push(value);
+                }
+                Op::GetInlineArgument => {
+                    let distance = self.read_u16() as usize;
+                    let stack_idx = self.stack.len() - 1 - distance;
+                    self.push(self.stack[stack_idx].clone());
+                }
+                Op::DropInlineArguments => {
+                    let num_args = self.read_u16() as usize;
+                    let result = self.pop();
+                    self.stack.truncate(self.stack.len() - num_args);
+                    self.push(result);
+                }
                 op @ (Op::Add
                 | Op::Subtract
                 | Op::Multiply
                 | Op::Divide
+                | Op::Modulo
                 | Op::Power
                 | Op::ConvertTo) => {
-                    let rhs = self.pop_quantity();
-                    let lhs = self.pop_quantity();
-                    let result = match op {
-                        Op::Add => &lhs + &rhs,
-                        Op::Subtract => &lhs - &rhs,
-                        Op::Multiply => Ok(lhs * rhs),
-                        Op::Divide => {
-                            Ok(lhs.checked_div(rhs).ok_or(RuntimeError::DivisionByZero)?)
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    match (lhs, rhs) {
+                        (Value::Quantity(lhs), Value::Quantity(rhs)) => {
+                            let result = match op {
+                                Op::Add => &lhs + &rhs,
+                                Op::Subtract => &lhs - &rhs,
+                                Op::Multiply => Ok(lhs * rhs),
+                                Op::Divide => {
+                                    Ok(lhs.checked_div(rhs).ok_or(RuntimeError::DivisionByZero)?)
+                                }
+                                Op::Modulo => Ok(lhs
+                                    .rem_euclid(&rhs)
+                                    .map_err(RuntimeError::QuantityError)?
+                                    .ok_or(RuntimeError::DivisionByZero)?),
+                                Op::Power => lhs.power(rhs),
+                                Op::ConvertTo => lhs.convert_to(rhs.unit()),
+                                _ => unreachable!(),
+                            };
+                            self.push_quantity(result.map_err(RuntimeError::QuantityError)?);
                         }
-                        Op::Power => lhs.power(rhs),
-                        Op::ConvertTo => lhs.convert_to(rhs.unit()),
-                        _ => unreachable!(),
-                    };
-                    self.push_quantity(result.map_err(RuntimeError::QuantityError)?);
+                        (lhs, rhs) => {
+                            let result = Self::interval_operation(op, lhs, rhs)?;
+                            self.push(Value::Interval(result));
+                        }
+                    }
                 }
                 op @ (Op::AddToDateTime | Op::SubFromDateTime) => {
-                    let rhs = self.pop_quantity();
+                    let rhs = self.pop_quantity()?;
                     let lhs = self.pop_datetime();
 
                     // for time, the base unit is in seconds
@@ -732,7 +1277,7 @@ impl Vm {
                     }));
                 }
                 Op::DiffDateTime => {
-                    let unit = self.pop_quantity();
+                    let unit = self.pop_quantity()?;
                     let rhs = self.pop_datetime();
                     let lhs = self.pop_datetime();
 
@@ -748,8 +1293,8 @@ impl Vm {
                     self.push(ret);
                 }
                 op @ (Op::LessThan | Op::GreaterThan | Op::LessOrEqual | Op::GreatorOrEqual) => {
-                    let rhs = self.pop_quantity();
-                    let lhs = self.pop_quantity();
+                    let rhs = self.pop_quantity()?;
+                    let lhs = self.pop_quantity()?;
 
                     let result = lhs.partial_cmp(&rhs).ok_or_else(|| {
                         RuntimeError::QuantityError(QuantityError::IncompatibleUnits(
@@ -794,13 +1339,20 @@ impl Vm {
                     let rhs = self.pop_bool();
                     self.push_bool(!rhs);
                 }
-                Op::Negate => {
-                    let rhs = self.pop_quantity();
-                    self.push_quantity(-rhs);
-                }
+                Op::Negate => match self.pop() {
+                    Value::Interval(i) => self.push(Value::Interval(-i)),
+                    v => self.push_quantity(-v.unsafe_as_quantity()),
+                },
+                Op::ConvertToBaseUnits => match self.pop() {
+                    Value::Interval(i) => {
+                        let si_unit = i.lower().to_si_base_units().unit().clone();
+                        self.push(Value::Interval(i.convert_to(&si_unit)?));
+                    }
+                    v => self.push_quantity(v.unsafe_as_quantity().to_si_base_units()),
+                },
                 Op::Factorial => {
                     let lhs = self
-                        .pop_quantity()
+                        .pop_quantity()?
                         .as_scalar()
                         .expect("Expected factorial operand to be scalar")
                         .to_f64();
@@ -826,15 +1378,12 @@ impl Vm {
                 Op::Call => {
                     let function_idx = self.read_u16() as usize;
                     let num_args = self.read_u16() as usize;
-                    self.frames.push(CallFrame {
-                        function_idx,
-                        ip: 0,
-                        fp: self.stack.len() - num_args,
-                    })
+                    self.push_call_frame(function_idx, num_args)?;
                 }
                 Op::FFICallFunction | Op::FFICallProcedure => {
                     let function_idx = self.read_u16() as usize;
                     let num_args = self.read_u16() as usize;
+                    self.ensure_foreign_function_allowed(function_idx)?;
                     let foreign_function = &self.ffi_callables[function_idx];
 
                     debug_assert!(foreign_function.arity.contains(&num_args));
@@ -844,17 +1393,34 @@ impl Vm {
                         args.push_front(self.pop());
                     }
 
+                    if !matches!(
+                        self.ffi_callables[function_idx].callable,
+                        Callable::Procedure(_)
+                    ) {
+                        Self::ensure_no_interval_arguments(&args)?;
+                    }
+
                     match &self.ffi_callables[function_idx].callable {
                         Callable::Function(function) => {
                             let result = (function)(args);
                             self.push(result?);
                         }
+                        Callable::FunctionWithContext(function) => {
+                            let result = (function)(ctx, args);
+                            self.push(result?);
+                        }
+                        Callable::FunctionWithUnits(function) => {
+                            let result = (function)(&self.defined_units(), args);
+                            self.push(result?);
+                        }
                         Callable::Procedure(procedure) => {
                             let span_idx = self.read_u16() as usize;
                             let spans = &self.procedure_arg_spans[span_idx];
 
                             let result = (procedure)(ctx, args, spans.clone());
 
+                            // Procedures leave nothing on the stack, so there is nothing to
+                            // clean up here. Simply proceed with the next instruction.
                             match result {
                                 std::ops::ControlFlow::Continue(()) => {}
                                 std::ops::ControlFlow::Break(runtime_error) => {
@@ -865,36 +1431,50 @@ impl Vm {
                     }
                 }
                 Op::CallCallable => {
-                    let num_args = self.read_u16() as usize;
+                    let mut num_args = self.read_u16() as usize;
 
-                    let callable = self.pop();
-                    match callable.unsafe_as_function_reference() {
+                    let mut callable = self.pop().unsafe_as_function_reference();
+
+                    // The arguments of partial applications go in front of the other arguments
+                    while let FunctionReference::Partial(inner, bound_args) = callable {
+                        let args = self.stack.split_off(self.stack.len() - num_args);
+                        num_args += bound_args.len();
+                        self.stack.extend(bound_args);
+                        self.stack.extend(args);
+                        callable = *inner;
+                    }
+
+                    match callable {
                         FunctionReference::Normal(ref name) => {
                             let function_idx = self.get_function_idx(name) as usize;
-
-                            // TODO: unify code with 'Op::Call'?
-                            self.frames.push(CallFrame {
-                                function_idx,
-                                ip: 0,
-                                fp: self.stack.len() - num_args,
-                            })
+                            self.push_call_frame(function_idx, num_args)?;
                         }
                         FunctionReference::Foreign(ref name) => {
                             let function_idx = self
                                 .get_ffi_callable_idx(name)
                                 .expect("Foreign function exists")
                                 as usize;
+                            self.ensure_foreign_function_allowed(function_idx)?;
 
                             let mut args = VecDeque::new();
                             for _ in 0..num_args {
                                 args.push_front(self.pop());
                             }
+                            Self::ensure_no_interval_arguments(&args)?;
 
                             match &self.ffi_callables[function_idx].callable {
                                 Callable::Function(function) => {
                                     let result = (function)(args);
                                     self.push(result?);
                                 }
+                                Callable::FunctionWithContext(function) => {
+                                    let result = (function)(ctx, args);
+                                    self.push(result?);
+                                }
+                                Callable::FunctionWithUnits(function) => {
+                                    let result = (function)(&self.defined_units(), args);
+                                    self.push(result?);
+                                }
                                 Callable::Procedure(..) => unreachable!("Foreign procedures can not be targeted by a function reference"),
                             }
                         }
@@ -911,18 +1491,31 @@ impl Vm {
 
                             self.push(Value::DateTime(dt));
                         }
+                        FunctionReference::Partial(..) => unreachable!(),
                     }
                 }
+                Op::PartialApply => {
+                    let num_args = self.read_u16() as usize;
+
+                    let function = self.pop().unsafe_as_function_reference();
+                    let args = self.stack.split_off(self.stack.len() - num_args);
+
+                    self.push(Value::FunctionReference(FunctionReference::Partial(
+                        Box::new(function),
+                        args,
+                    )));
+                }
                 Op::PrintString => {
                     let s_idx = self.read_u16() as usize;
                     let s = &self.strings[s_idx];
-                    self.print(ctx, s);
+                    ctx.print(s)?;
                 }
                 Op::JoinString => {
                     let num_parts = self.read_u16() as usize;
                     let mut joined = String::new();
                     let to_str = |value| match value {
                         Value::Quantity(q) => q.to_string(),
+                        Value::Interval(i) => i.to_string(),
                         Value::Boolean(b) => b.to_string(),
                         Value::String(s) => s,
                         Value::DateTime(dt) => crate::datetime::to_rfc2822_save(&dt),
@@ -980,6 +1573,7 @@ impl Vm {
                         let simplified = q.full_simplify();
                         self.push_quantity(simplified);
                     }
+                    Value::Interval(i) => self.push(Value::Interval(i.full_simplify())),
                     v => self.push(v),
                 },
                 Op::Return => {
@@ -1074,10 +1668,6 @@ impl Vm {
         assert!(self.strings.len() <= u16::MAX as usize);
         (self.strings.len() - 1) as u16 // TODO: this can overflow, see above
     }
-
-    fn print(&self, ctx: &mut ExecutionContext, m: &Markup) {
-        (ctx.print_fn)(m);
-    }
 }
 
 #[test]
@@ -1094,6 +1684,13 @@ fn vm_basic() {
     let mut print_fn = |_: &Markup| {};
     let mut ctx = ExecutionContext {
         print_fn: &mut print_fn,
+        print_value_fn: None,
+        input_fn: None,
+        max_steps: None,
+        max_output_bytes: None,
+        output_bytes: 0,
+        rounding_mode: RoundingMode::default(),
+        exchange_rates: None,
     };
 
     assert_eq!(
@@ -1101,3 +1698,36 @@ fn vm_basic() {
         InterpreterResult::Value(Value::Quantity(Quantity::from_scalar(42.0 + 1.0)))
     );
 }
+
+#[test]
+fn vm_dup() {
+    let mut vm = Vm::new();
+    vm.add_constant(Constant::Scalar(42.0));
+    vm.add_constant(Constant::Scalar(1.0));
+
+    vm.add_op1(Op::LoadConstant, 0);
+    vm.add_op(Op::Dup);
+    vm.add_op(Op::Multiply);
+    vm.add_op1(Op::LoadConstant, 1);
+    vm.add_op(Op::Dup);
+    vm.add_op(Op::Add);
+    vm.add_op(Op::Add);
+    vm.add_op(Op::Return);
+
+    let mut print_fn = |_: &Markup| {};
+    let mut ctx = ExecutionContext {
+        print_fn: &mut print_fn,
+        print_value_fn: None,
+        input_fn: None,
+        max_steps: None,
+        max_output_bytes: None,
+        output_bytes: 0,
+        rounding_mode: RoundingMode::default(),
+        exchange_rates: None,
+    };
+
+    assert_eq!(
+        vm.run(&mut ctx).unwrap(),
+        InterpreterResult::Value(Value::Quantity(Quantity::from_scalar(42.0 * 42.0 + 2.0)))
+    );
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+use crate::span::Span;
+
+/// A non-fatal diagnostic. In contrast to errors, warnings do not prevent
+/// the code from being executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A variable is redefined with a different type than before,
+    /// e.g. `let x = 1 m` followed by `let x = 2 s`.
+    RedefinitionChangesType {
+        identifier: String,
+        span: Span,
+        original_span: Span,
+        original_type: String,
+        new_type: String,
+    },
+}
+
+impl Warning {
+    /// The place in the code that the warning refers to
+    pub fn span(&self) -> Span {
+        match self {
+            Warning::RedefinitionChangesType { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::RedefinitionChangesType {
+                identifier,
+                original_type,
+                new_type,
+                ..
+            } => write!(
+                f,
+                "Redefinition of '{identifier}' changes its type from {original_type} to {new_type}"
+            ),
+        }
+    }
+}
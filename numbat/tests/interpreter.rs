@@ -3,10 +3,16 @@ mod common;
 use common::get_test_context;
 
 use insta::assert_snapshot;
-use numbat::markup::{Formatter, PlainTextFormatter};
+use numbat::markup::{FormatType, FormattedString, Formatter, PlainTextFormatter};
 use numbat::resolver::CodeSource;
+use numbat::value::Value;
 use numbat::NumbatError;
 use numbat::{pretty_print::PrettyPrint, Context, InterpreterResult};
+use numbat::{
+    BaseRepresentationFactor, CompiledFunctionError, DisplaySystem, DivisionStyle,
+    ForeignFunctionFilter, FormatOptions, FunctionSummary, InterpreterSettings, LazyEvaluation,
+    PrefixStyle, Quantity, RoundingMode, RuntimeError, UnitSummary,
+};
 
 #[track_caller]
 fn expect_output_with_context(ctx: &mut Context, code: &str, expected_output: impl AsRef<str>) {
@@ -135,6 +141,20 @@ fn simple_value() {
     expect_output("inf", "inf");
 }
 
+#[test]
+fn test_scientific_notation_with_units() {
+    expect_output("6.022e23 / mol", "6.022e+23 mol⁻¹");
+    expect_output("1.6e-19 C", "1.6e-19 C");
+    expect_output("1.6e-19C -> aC", "0.16 aC");
+    expect_output("2e3 m/s -> km/s", "2 km/s");
+    expect_output("1.5e+2 km", "150 km");
+    expect_output("1e3 m^2 -> are", "10 are");
+    expect_output("let q: ElectricCharge = 1.6e-19 C\nq -> zC", "160 zC");
+
+    // A bare `e` after a number is Euler's number, not an exponent
+    expect_output("2e m -> m", "5.43656 m");
+}
+
 #[test]
 fn test_factorial() {
     expect_output("0!", "1");
@@ -170,6 +190,22 @@ fn test_factorial() {
     );
 }
 
+#[test]
+fn test_integer_results() {
+    // Values that are exactly integral are printed without a decimal point
+    expect_output("6 / 3", "2");
+    expect_output("4.0", "4");
+    expect_output("4 m / 2 m", "2");
+    expect_output("10.0 m", "10 m");
+    expect_output("0.5 km -> m", "500 m");
+
+    expect_output("1 / 3", "0.333333");
+
+    // Non-integral values that look like integers after rounding keep the `.0`
+    expect_output("100.00001", "100.0");
+    expect_output("2 + 1e-9", "2.0");
+}
+
 #[test]
 fn test_exponentiation() {
     expect_output("3²*2", "18");
@@ -187,6 +223,82 @@ fn test_exponentiation() {
     expect_output("2⁻¹", "0.5");
     expect_output("2⁻²", "0.25");
     expect_output("10⁻⁵", "0.00001");
+
+    // Huge exponents are handled without overflows
+    expect_output("2^1000000000", "inf");
+    expect_output("0.5^1e300", "0");
+    expect_output("(-2)^1000000001", "-inf");
+    expect_output("(2 percent)^1e9", "0");
+    expect_output("(percent^1e20)^1e20", "0");
+}
+
+#[test]
+fn test_fractional_unit_exponents() {
+    expect_output("sqrt(4 m^2)", "2 m");
+    expect_output("(1 m^2)^(1/2)", "1 m");
+    expect_output("(8 m^3)^(1/3)", "2 m");
+
+    // Units with fractional exponents are displayed as such
+    expect_output("m^(1/2)", "1 m^(1/2)");
+    expect_output("(4 m)^(1/2)", "2 m^(1/2)");
+    expect_output("(4 m)^(-1/2)", "0.5 m^(-1/2)");
+    expect_output("sqrt(2 Hz)", "1.41421 Hz^(1/2)");
+
+    // Integer exponents are restored when combining them again
+    expect_output("(4 m)^(1/2) * (1 m)^(1/2)", "2 m");
+    expect_output("((2 m)^(1/3))^3", "2 m");
+    expect_output("(4 m)^(1/2) / (1 cm)^(1/2)", "20");
+
+    // Conversions and comparisons
+    expect_output("(4 m)^(1/2) -> cm^(1/2)", "20 cm^(1/2)");
+    expect_output("sqrt(1 km) -> sqrt(m)", "31.6228 m^(1/2)");
+    expect_output("(9 m)^(1/2) + 1 cm^(1/2)", "3.1 m^(1/2)");
+    expect_output("sqrt(1 m) == sqrt(100 cm)", "true");
+    expect_output(
+        "fn square_root_length_squared(x: Length^(1/2)) -> Length = x^2
+         square_root_length_squared(sqrt(4 m))",
+        "4 m",
+    );
+
+    expect_failure("(1 km)^(1/2) -> m", "left hand side: Length^(1/2)");
+    expect_output(
+        "let x = 2
+         (4 m)^(1/x)",
+        "2 m^(1/2)",
+    );
+    expect_failure(
+        "let mut x = 2
+         (1 m)^(1/x)",
+        "Unsupported expression in const-evaluation of exponent",
+    );
+}
+
+#[test]
+fn test_unit_ratios_in_exponents() {
+    expect_output("(3 m)^(2 m / 1 m)", "9 m²");
+    expect_output("(1 m)^(1 km / 1 m)", "1 m^1000");
+    expect_output("(2 m)^(30 cm / 20 cm)", "2.82843 m^(3/2)");
+    expect_output("(1 m)^(1 ft / 1 inch) -> m^12", "1 m^12");
+    expect_output(
+        "let q: Length^12 = (1 m)^(1 ft / 1 inch)
+         q -> m^12",
+        "1 m^12",
+    );
+    expect_output(
+        "fn square(a: Length) -> Length^2 = a^(2m/1m)
+         square(3 cm)",
+        "9 cm²",
+    );
+    expect_output(
+        "let n = 1 km / 500 m
+         (3 m)^n",
+        "9 m²",
+    );
+
+    expect_failure(
+        "(1 m)^(1 km / 1 s)",
+        "Unsupported expression in const-evaluation of exponent: quantity with a physical dimension",
+    );
 }
 
 #[test]
@@ -198,6 +310,19 @@ fn test_conversions() {
     expect_output("55! / (6! (55 - 6)!) -> million", "28.9897 million");
 }
 
+#[test]
+fn test_conversion_to_si_base_units() {
+    expect_output("1 kWh -> SI", "3_600_000 kg·m²/s²");
+    expect_output("3 km/h -> SI", "0.833333 m/s");
+    expect_output("1 tonne -> SI", "1000 kg");
+    expect_output("2 -> SI", "2");
+    expect_output("1 kWh -> SI -> kJ", "3600 kJ");
+    expect_output("[1 .. 2] km -> SI", "[1000 .. 2000] m");
+
+    expect_failure("true -> SI", "Expected dimension type");
+    expect_failure("unit SI", "Reserved identifier 'SI'");
+}
+
 #[test]
 fn test_implicit_conversion() {
     let mut ctx = get_test_context();
@@ -281,7 +406,7 @@ fn test_math() {
     expect_output("atan2(100 cm, 1 m) / (pi / 4)", "1");
     expect_failure(
         "atan2(100 cm, 1 m²)",
-        "Could not solve the following constraints",
+        "previous argument: Length\n         argument: Length²",
     );
 
     expect_output("mod(5, 3)", "2");
@@ -289,7 +414,90 @@ fn test_math() {
     expect_output("mod(8 cm, 5 cm)", "3 cm");
     expect_output("mod(235 cm, 1 m)", "35 cm");
     expect_output("mod(2 m, 7 cm)", "0.04 m");
-    expect_failure("mod(8 m, 5 s)", "Could not solve the following constraints")
+    expect_failure(
+        "mod(8 m, 5 s)",
+        "previous argument: Length\n         argument: Time",
+    );
+    expect_failure("mod(5, 0)", "Division by zero");
+    expect_failure("mod(5 m, 0 cm)", "Division by zero");
+
+    // Angle wrapping. The remainder is computed in the unit of the first argument,
+    // before the result is simplified (to radians).
+    expect_output("mod(370 deg, 360 deg) == 10 deg", "true");
+    expect_output("mod(370 deg, 360 deg) -> deg", "10°");
+    expect_output("mod(-30°, 360°) -> deg", "330°");
+    expect_output("mod(730 deg, 1 turn) -> deg", "10°");
+    expect_output("mod(400 deg, 2 turn) -> deg", "400°");
+    expect_output("mod(800 deg, 2 turn) == 80 deg", "true");
+    expect_output("mod(90.5°, 1°) -> arcmin", "30 arcminute");
+    expect_output("mod(7 rad, 2 pi rad) == 7 rad - 2 pi rad", "true");
+    expect_output("mod(3500 mrad, 1 rad) -> mrad", "500 mrad");
+    expect_output("mod(370 deg -> rad, 2 pi rad) -> deg", "10°");
+    expect_output(
+        "abs(mod(370 deg -> rad, 2 pi rad) - 10 deg) < 1e-15",
+        "true",
+    );
+    expect_output("mod(3 m × 2 cm, 1 m²)", "600 cm²");
+
+    expect_output("round_to(1234, 50)", "1250");
+    expect_output("round_to(1234 m, 50 m)", "1250 m");
+    expect_output("round_to(-1.26 m, 0.1 m)", "-1.3 m");
+    expect_output("round_to(1234 m, 1 km)", "1000 m");
+    expect_failure(
+        "round_to(1234 m, 50 s)",
+        "previous argument: Length\n         argument: Time",
+    );
+    expect_failure("round_to(1234 m, 0 m)", "Rounding step can not be zero");
+
+    expect_output("signif(123.456, 2)", "120");
+    expect_output("signif(123.456 m, 4)", "123.5 m");
+    expect_output("signif(-0.0012345 s, 3)", "-0.00123 s");
+    expect_output("signif(0, 3)", "0");
+    expect_failure("signif(123 m, 2 m)", "parameter type: Scalar");
+    expect_failure(
+        "signif(123, -1)",
+        "Expected number of significant figures to be a positive integer",
+    );
+    expect_failure(
+        "signif(123, 1.5)",
+        "Expected number of significant figures to be a positive integer",
+    );
+
+    expect_output("log_base(10, 1000)", "3");
+    expect_output("log_base(2, 8)", "3");
+    expect_output("log_base(3, 1)", "0");
+    expect_output("log_base(10, 1 km / m)", "3");
+    expect_failure("log_base(10, 2 m)", "parameter type: Scalar");
+    expect_failure("log_base(2 m, 10)", "parameter type: Scalar");
+    for base in ["0", "-2", "1"] {
+        expect_failure(
+            &format!("log_base({base}, 10)"),
+            "Expected logarithm base to be positive and different from one",
+        );
+    }
+    for x in ["0", "-1"] {
+        expect_failure(
+            &format!("log_base(10, {x})"),
+            "Expected logarithm argument to be positive",
+        );
+    }
+}
+
+#[test]
+fn test_modulo_operator() {
+    expect_output("7 mod 3", "1");
+    expect_output("(-7) mod 3", "2");
+    expect_output("7.5 mod 2", "1.5");
+    expect_output("2 * 7 mod 4", "2");
+    expect_output("1 + 7 mod 3", "2");
+    expect_output("8 cm mod 5 cm", "3 cm");
+    expect_output("235 cm mod 1 m", "35 cm");
+    expect_output("370 deg mod 360 deg -> deg", "10°");
+    expect_output("let x = 7\nx mod 3 == mod(x, 3)", "true");
+
+    expect_failure("5 mod 0", "Division by zero");
+    expect_failure("5 m mod 0 cm", "Division by zero");
+    expect_failure("8 m mod 5 s", "right hand side: Time");
 }
 
 #[test]
@@ -395,6 +603,205 @@ fn test_temperature_conversions() {
     expect_output("-40 -> from_fahrenheit -> celsius", "-40");
 }
 
+#[test]
+fn test_ratio_units() {
+    expect_output("1000 ppm -> percent", "0.1 %");
+    expect_output("5 % -> ‰", "50 ‰");
+    expect_output("2500 ppb -> ppm", "2.5 partspermillion");
+    expect_output("1 % -> ppm", "10000 partspermillion");
+    expect_output("50 % + 25 % -> %", "75 %");
+    expect_output("200 × 1 %", "2");
+    expect_output("120 ppm -> value_of", "0.00012");
+
+    expect_output("100 -> decibel", "20");
+    expect_output("from_decibel(30)", "1000");
+    expect_output("from_decibel(3)", "1.99526");
+    expect_output("from_decibel(-3) -> decibel", "-3.0");
+}
+
+#[test]
+fn test_angle_conversions() {
+    // (angle, number of degrees, number of gradians, number of turns, number of arcminutes, number of arcseconds)
+    let angles = [
+        ("1 turn", "360", "400", "1", "21600", "1296000"),
+        ("360°", "360", "400", "1", "21600", "1296000"),
+        ("400 grad", "360", "400", "1", "21600", "1296000"),
+        ("21600 arcmin", "360", "400", "1", "21600", "1296000"),
+        ("1296000 arcsec", "360", "400", "1", "21600", "1296000"),
+        ("90°", "90", "100", "1/4", "5400", "324000"),
+        ("100 grad", "90", "100", "1/4", "5400", "324000"),
+        ("3 grad", "2.7", "3", "3/400", "162", "9720"),
+        ("1°", "1", "10/9", "1/360", "60", "3600"),
+        ("1 arcmin", "1/60", "1/54", "1/21600", "1", "60"),
+        ("1 arcsec", "1/3600", "1/3240", "1/1296000", "1/60", "1"),
+    ];
+
+    for (angle, deg, grad, turn, arcmin, arcsec) in angles {
+        for (unit, expected) in [
+            ("deg", deg),
+            ("grad", grad),
+            ("turn", turn),
+            ("arcmin", arcmin),
+            ("arcsec", arcsec),
+        ] {
+            expect_output(
+                &format!("value_of({angle} -> {unit}) == {expected}"),
+                "true",
+            );
+        }
+    }
+
+    expect_output("value_of(1 turn -> rad) == 2 π", "true");
+    expect_output("value_of(180° -> rad) == π", "true");
+    expect_output("value_of(π rad -> deg) == 180", "true");
+    expect_output("value_of(2 π rad -> grad) == 400", "true");
+    expect_output("value_of(2 π rad -> turn) == 1", "true");
+
+    // Conversions through several angle units do not accumulate errors
+    expect_output(
+        "value_of(1 turn -> rad -> grad -> deg -> arcmin -> arcsec -> turn) == 1",
+        "true",
+    );
+    expect_output(
+        "value_of(45° -> grad -> turn -> arcsec -> arcmin -> deg) == 45",
+        "true",
+    );
+    expect_output("value_of(1 deg^2 -> arcmin^2) == 3600", "true");
+}
+
+#[test]
+fn test_strict_dimensions() {
+    let mut ctx = get_test_context();
+    expect_output_with_context(&mut ctx, "2 m + 0", "2 m");
+
+    ctx.set_strict_dimensions(true);
+    expect_failure_with_context(&mut ctx, "2 m + 0", "right hand side: Scalar");
+    expect_failure_with_context(
+        &mut ctx,
+        "(4 m^2)^0.5",
+        "Decimal numbers are not allowed in exponents",
+    );
+    expect_output_with_context(&mut ctx, "2 m + 0 m", "2 m");
+    expect_output_with_context(&mut ctx, "(4 m^2)^(1/2)", "2 m");
+    expect_output_with_context(&mut ctx, "sqrt(4 m^2)", "2 m");
+}
+
+#[test]
+fn test_hypot() {
+    expect_output("hypot(3 m, 4 m)", "5 m");
+    expect_output("hypot(3, 4)", "5");
+    expect_output("hypot(30 cm, 0.4 m)", "50 cm");
+    expect_output("hypot(-5 s, 12 s)", "13 s");
+
+    // No overflow of the intermediate squares
+    expect_output("hypot(3e200 m, 4e200 m)", "5.0e+200 m");
+    expect_output("hypot2(3e200 m, 4e200 m)", "inf m");
+
+    expect_failure(
+        "hypot(3 m, 4 s)",
+        "previous argument: Length\n         argument: Time",
+    );
+}
+
+#[test]
+fn test_rounding_modes() {
+    let evaluate = |rounding_mode, code: &str| {
+        let mut ctx = get_test_context();
+        let mut settings = InterpreterSettings {
+            print_fn: Box::new(|_: &numbat::markup::Markup| {}),
+            rounding_mode,
+            ..InterpreterSettings::default()
+        };
+        match ctx
+            .interpret_with_settings(&mut settings, code, CodeSource::Internal)
+            .unwrap()
+            .1
+        {
+            InterpreterResult::Value(val) => PlainTextFormatter {}
+                .format(&val.pretty_print(), false)
+                .trim()
+                .to_string(),
+            _ => panic!("Expected a value"),
+        }
+    };
+
+    let half_even = |code| evaluate(RoundingMode::HalfEven, code);
+    assert_eq!(half_even("round(2.5)"), "2");
+    assert_eq!(half_even("round(3.5)"), "4");
+    assert_eq!(half_even("round(-2.5 m)"), "-2 m");
+    assert_eq!(half_even("round(2.6)"), "3");
+    assert_eq!(half_even("round_to(25 m, 10 m)"), "20 m");
+    assert_eq!(half_even("round_to(35 m, 10 m)"), "40 m");
+    assert_eq!(half_even("signif(125 m, 2)"), "120 m");
+
+    let half_up = |code| evaluate(RoundingMode::HalfUp, code);
+    assert_eq!(half_up("round(2.5)"), "3");
+    assert_eq!(half_up("round(3.5)"), "4");
+    assert_eq!(half_up("round(-2.5 m)"), "-3 m");
+    assert_eq!(half_up("round_to(25 m, 10 m)"), "30 m");
+    assert_eq!(half_up("signif(125 m, 2)"), "130 m");
+
+    let truncate = |code| evaluate(RoundingMode::Truncate, code);
+    assert_eq!(truncate("round(2.5)"), "2");
+    assert_eq!(truncate("round(3.5)"), "3");
+    assert_eq!(truncate("round(-2.9 m)"), "-2 m");
+    assert_eq!(truncate("round_to(39 m, 10 m)"), "30 m");
+    assert_eq!(truncate("signif(129 m, 2)"), "120 m");
+
+    // Half-even is the default
+    expect_output("round(2.5)", "2");
+    expect_output("round(3.5)", "4");
+}
+
+#[test]
+fn test_rounding_mode_for_display() {
+    let display = |rounding_mode, code: &str| {
+        let mut ctx = get_test_context();
+        let printed = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let printed_c = printed.clone();
+        let mut settings = InterpreterSettings {
+            print_fn: Box::new(move |m: &numbat::markup::Markup| {
+                printed_c.lock().unwrap().push(m.clone());
+            }),
+            rounding_mode,
+            ..InterpreterSettings::default()
+        };
+        let (statements, result) = ctx
+            .interpret_with_settings(&mut settings, code, CodeSource::Internal)
+            .unwrap();
+        let result_markup = result.to_markup_with_rounding(
+            statements.last(),
+            ctx.dimension_registry(),
+            false,
+            false,
+            settings.rounding_mode,
+        );
+        let fmt = PlainTextFormatter {};
+        let printed = printed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|m| fmt.format(m, false).trim().to_string())
+            .collect::<Vec<_>>();
+        (
+            printed,
+            fmt.format(&result_markup, false).trim().to_string(),
+        )
+    };
+
+    let (printed, result) = display(RoundingMode::HalfEven, "print(2 m / 3)\n[1.234565, 2/3]");
+    assert_eq!(printed, ["0.666667 m"]);
+    assert_eq!(result, "[1.23456, 0.666667]");
+
+    let (printed, result) = display(RoundingMode::Truncate, "print(2 m / 3)\n[1.234565, 2/3]");
+    assert_eq!(printed, ["0.666666 m"]);
+    assert_eq!(result, "[1.23456, 0.666666]");
+
+    let (printed, result) = display(RoundingMode::HalfUp, "print(2 m / 3)\n[1.234565, 2/3]");
+    assert_eq!(printed, ["0.666667 m"]);
+    assert_eq!(result, "[1.23457, 0.666667]");
+}
+
 #[test]
 fn test_other_functions() {
     expect_output("sqrt(4)", "2");
@@ -405,6 +812,13 @@ fn test_other_functions() {
     expect_output("floor(3.9)", "3");
     expect_output("round(3.9)", "4");
     expect_output("round(3.1)", "3");
+    expect_output("abs(-3 m)", "3 m");
+    expect_output("abs(2 s)", "2 s");
+    expect_output("signum(-3 m)", "-1");
+    expect_output("signum(0 m)", "0");
+    expect_output("signum(5 km/h)", "1");
+    expect_output("signum(-0.1)", "-1");
+    expect_output("is_nan(signum(NaN))", "true");
     expect_output("is_nan(NaN)", "true");
     expect_output("is_nan(NaN cm)", "true");
     expect_output("is_nan(ln(-1))", "true");
@@ -425,6 +839,22 @@ fn test_last_result_identifier() {
     expect_output_with_context(&mut ctx, "_", "3");
 }
 
+#[test]
+fn test_semicolon_separated_statements() {
+    expect_output("let x = 2 m; x^2", "4 m²");
+    expect_output("1; 2; 3", "3");
+    expect_output("fn f(x) = 2 x; f(3)", "6");
+
+    // A trailing semicolon suppresses the result
+    let mut ctx = get_test_context();
+    assert!(ctx
+        .interpret("let y = 3 m; y^2;", CodeSource::Internal)
+        .unwrap()
+        .1
+        .is_continue());
+    expect_output_with_context(&mut ctx, "y", "3 m");
+}
+
 #[test]
 fn test_misc_examples() {
     expect_output("1920/16*9", "1080");
@@ -506,7 +936,20 @@ fn test_parse_errors() {
 fn test_name_clash_errors() {
     expect_failure("let kg=2", "Identifier is already in use: 'kg'");
     expect_failure("fn kg(x: Scalar) = 1", "Identifier is already in use: 'kg'");
-    expect_failure("fn _()=0", "Reserved identifier");
+    expect_failure("fn _()=0", "Reserved identifier '_'");
+}
+
+#[test]
+fn test_reserved_words_as_unit_names() {
+    expect_failure("@aliases(if) unit foo", "Reserved identifier 'if'");
+    expect_failure(
+        "@aliases(foos, then: short) unit foo = 2 m",
+        "Reserved identifier 'then'",
+    );
+    expect_failure("@aliases(foos, let) unit foo", "Reserved identifier 'let'");
+    expect_failure("unit else", "Reserved identifier 'else'");
+    expect_failure("@aliases(print) let foo = 2", "Reserved identifier 'print'");
+    expect_output("@aliases(foos) unit foo\n2 foos", "2 foo");
 }
 
 #[test]
@@ -522,7 +965,7 @@ fn test_type_check_errors() {
         "Identifier is already in use by the constant: 'pi'",
     );
     expect_failure(
-        "fn sin(x)=0",
+        "fn sin(x: Scalar) -> Scalar",
         "Identifier is already in use by the foreign function: 'sin'",
     );
 }
@@ -672,6 +1115,101 @@ fn test_overwrite_inner_function() {
     );
 }
 
+#[test]
+fn test_user_defined_functions_shadow_builtins() {
+    for inline_functions in [true, false] {
+        let mut ctx = get_test_context();
+        ctx.set_function_inlining(inline_functions);
+
+        let _ = ctx
+            .interpret(
+                "fn sin_before(x) = sin(x)
+                 fn sin(x: Scalar) -> Scalar = 42
+                 fn sin_after(x) = sin(x)",
+                CodeSource::Internal,
+            )
+            .unwrap();
+
+        expect_output_with_context(&mut ctx, "sin(0)", "42");
+        expect_output_with_context(&mut ctx, "sin_after(0)", "42");
+        expect_output_with_context(&mut ctx, "let f = sin\nf(0)", "42");
+        expect_output_with_context(&mut ctx, "map(sin, [0, 1])", "[42, 42]");
+
+        // Functions that have been defined before keep calling the builtin
+        expect_output_with_context(&mut ctx, "sin_before(0)", "0");
+
+        // Shadowing functions can be redefined
+        let _ = ctx
+            .interpret("fn sin(x: Scalar) -> Scalar = 43", CodeSource::Internal)
+            .unwrap();
+        expect_output_with_context(&mut ctx, "sin(0)", "43");
+        expect_output_with_context(&mut ctx, "sin_after(0)", "42");
+    }
+}
+
+#[test]
+fn test_squaring_local_variables() {
+    expect_output("let x = 2 m\nx * x", "4 m²");
+    expect_output("fn square(x) = x * x\nsquare(3 cm)", "9 cm²");
+    expect_output("fn square(x) = x * x\nsquare(-3)", "9");
+    expect_output("fn f(x, y) = x * y\nf(2 m, 3 s)", "6 m·s");
+    expect_output(
+        "fn shifted_square(x) = { let y = x + 1 m; y * y }\nshifted_square(2 m)",
+        "9 m²",
+    );
+    expect_output("fn square(x) = x * x\nsquare([-1 .. 2])", "[-2 .. 4]");
+
+    expect_output("let x = 3 m\nx^2", "9 m²");
+    expect_failure(
+        "let x = [-1 .. 2]\nx^2",
+        "Intervals can only be used in additions, subtractions, multiplications, divisions and unit conversions",
+    );
+    expect_failure(
+        "fn square(x) = x^2\nsquare([-1 .. 2])",
+        "Intervals can only be used in additions, subtractions, multiplications, divisions and unit conversions",
+    );
+}
+
+#[test]
+fn test_stack_depth_limit() {
+    let mut ctx = get_test_context();
+    ctx.set_max_stack_depth(100);
+
+    let _ = ctx
+        .interpret(
+            "fn countdown(n: Scalar) -> Scalar = if n == 0 then 0 else countdown(n - 1)
+             fn forever(n: Scalar) -> Scalar = forever(n + 1)",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    expect_output_with_context(&mut ctx, "countdown(50)", "0");
+    assert!(matches!(
+        ctx.interpret("countdown(1000)", CodeSource::Internal),
+        Err(NumbatError::RuntimeError(RuntimeError::StackOverflow(100)))
+    ));
+    assert!(matches!(
+        ctx.interpret("forever(0)", CodeSource::Internal),
+        Err(NumbatError::RuntimeError(RuntimeError::StackOverflow(100)))
+    ));
+
+    // Calls via function references are limited as well
+    assert!(matches!(
+        ctx.interpret("let f = forever\nf(0)", CodeSource::Internal),
+        Err(NumbatError::RuntimeError(RuntimeError::StackOverflow(100)))
+    ));
+
+    // The context can still be used after the call stack has been reset
+    expect_output_with_context(&mut ctx, "countdown(10) + 1", "1");
+
+    // Deep recursion works with the default limit
+    expect_output(
+        "fn countdown(n: Scalar) -> Scalar = if n == 0 then 0 else countdown(n - 1)
+         countdown(10_000)",
+        "0",
+    );
+}
+
 #[test]
 fn test_override_constants() {
     expect_output("let x = 1\nlet x = 2\nx", "2");
@@ -692,11 +1230,315 @@ fn test_overwrite_captured_constant() {
     );
 }
 
+#[test]
+fn test_local_functions() {
+    expect_output(
+        "fn foo(x: Length) -> Area = {\n  let k = 2 x\n  fn scale(y: Length) -> Area = k * y\n  scale(3 m)\n}\nfoo(5 m)",
+        "30 m²",
+    );
+    expect_output(
+        "fn foo(x) = { let a = x + 1; fn add_a(y) = y + a; fn twice(y) = add_a(add_a(y)); twice(x) }\nfoo(1)",
+        "5",
+    );
+    expect_output(
+        "fn foo(x) = { fn inner(x) = 10 x; inner(x + 1) }\nfoo(1)",
+        "20",
+    );
+    expect_output(
+        "fn foo(x) = { let a = x\n  fn inner(y) = \"{a} and {y}\"\n  inner(2) }\nfoo(1 m)",
+        "\"1 m and 2\"",
+    );
+    expect_output(
+        "fn adder(x) = { fn plus(y) = x + y; plus }\nadder(1 m)(2 m)",
+        "3 m",
+    );
+
+    // The captured value is the one at the time of the definition
+    expect_output(
+        "fn foo(x) = { let a = x; fn inner(y) = a * y; let a = 0; inner(2) + a }\nfoo(3)",
+        "6",
+    );
+
+    expect_failure(
+        "fn foo(n) = { fn inner(k) = if k <= 0 then 0 else inner(k - 1); inner(n) }",
+        "Function 'inner' is defined in a function body and can not call itself",
+    );
+    expect_failure(
+        "fn foo(x) = { fn inner(y) = y; x }\ninner(2)",
+        "Unknown identifier 'inner'",
+    );
+}
+
 #[test]
 fn test_pretty_print_prefixes() {
     expect_output("1 megabarn", "1 megabarn");
 }
 
+#[test]
+fn test_programs_without_final_expression() {
+    let mut ctx = get_test_context();
+
+    let (_, result) = ctx
+        .interpret("unit widget_length = 3 cm", CodeSource::Internal)
+        .unwrap();
+    assert!(result.is_continue());
+
+    let (_, result) = ctx
+        .interpret(
+            "2 widget_length
+             unit gadget_length = 2 widget_length
+             fn gadgets(n) = n gadget_length",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    assert!(result.is_continue());
+
+    expect_output_with_context(&mut ctx, "ans", "2 widget_length");
+    expect_output_with_context(&mut ctx, "gadgets(2) -> cm", "12 cm");
+}
+
+#[test]
+fn test_unit_definitions() {
+    let mut ctx = get_test_context();
+    let _ = ctx
+        .interpret(
+            "unit foo = 2 * meter + 3 * meter
+             unit foo2 = 1 km * 2 m / cm
+             unit foo3 = 2 m + 50 cm",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_output_with_context(&mut ctx, "1 foo -> m", "5 m");
+    expect_output_with_context(&mut ctx, "1 foo2 -> km", "200 km");
+    expect_output_with_context(&mut ctx, "2 foo3 -> m", "5 m");
+    expect_output_with_context(&mut ctx, "10 m -> foo", "2 foo");
+
+    expect_failure(
+        "unit foo = (1 - 1) meter",
+        "Unit 'foo' can not be defined by a value that is zero or infinite",
+    );
+    expect_failure(
+        "unit foo = 2 m - 200 cm",
+        "Unit 'foo' can not be defined by a value that is zero or infinite",
+    );
+    expect_failure(
+        "unit foo = 1 - 1",
+        "Unit 'foo' can not be defined by a value that is zero or infinite",
+    );
+    expect_failure(
+        "unit foo = 1e308 * 1e308 meter",
+        "Unit 'foo' can not be defined by a value that is zero or infinite",
+    );
+}
+
+#[track_caller]
+fn get_quantity(ctx: &mut Context, code: &str) -> Quantity {
+    match ctx.interpret(code, CodeSource::Internal).unwrap().1 {
+        InterpreterResult::Value(Value::Quantity(q)) => q,
+        _ => panic!("Expected a quantity"),
+    }
+}
+
+#[test]
+fn test_format() {
+    let mut ctx = get_test_context();
+    let speed = get_quantity(&mut ctx, "1234.5678 km/h");
+    let acceleration = get_quantity(&mut ctx, "9.81 m/s²");
+    let force_like = get_quantity(&mut ctx, "3 kg m / (s² A)");
+    let time = get_quantity(&mut ctx, "2 s");
+    let large = get_quantity(&mut ctx, "1234567 m");
+    let negative = get_quantity(&mut ctx, "-0.5 m");
+    let tiny = get_quantity(&mut ctx, "1.5e-9 m");
+    let tie_even = get_quantity(&mut ctx, "0.125 m");
+    let tie_odd = get_quantity(&mut ctx, "0.135 m");
+    let meter_per_second = get_quantity(&mut ctx, "m/s").unit().clone();
+
+    let format = |q: &Quantity, options: FormatOptions| ctx.format(q, &options).unwrap();
+
+    assert_eq!(format(&speed, FormatOptions::default()), "1234.57 km/h");
+    for q in [&speed, &acceleration, &force_like, &large, &tiny] {
+        assert_eq!(format(q, FormatOptions::default()), q.to_string());
+    }
+    assert_eq!(
+        format(
+            &speed,
+            FormatOptions {
+                precision: 3,
+                ..Default::default()
+            }
+        ),
+        "1230 km/h"
+    );
+    assert_eq!(
+        format(
+            &speed,
+            FormatOptions {
+                prefix_style: PrefixStyle::Long,
+                ..Default::default()
+            }
+        ),
+        "1234.57 kilometre/hour"
+    );
+    assert_eq!(
+        format(
+            &speed,
+            FormatOptions {
+                division_style: DivisionStyle::Reciprocal,
+                ..Default::default()
+            }
+        ),
+        "1234.57 km·h⁻¹"
+    );
+    assert_eq!(
+        format(
+            &speed,
+            FormatOptions {
+                precision: 4,
+                rounding_mode: RoundingMode::HalfUp,
+                prefix_style: PrefixStyle::Long,
+                division_style: DivisionStyle::Reciprocal,
+                target_unit: Some(meter_per_second.clone()),
+                decimal_separator: ',',
+                thousands_separator: '.',
+            }
+        ),
+        "342,9 metre·second⁻¹"
+    );
+    assert_eq!(
+        format(
+            &acceleration,
+            FormatOptions {
+                prefix_style: PrefixStyle::Long,
+                ..Default::default()
+            }
+        ),
+        "9.81 metre/second²"
+    );
+    assert_eq!(
+        format(&force_like, FormatOptions::default()),
+        "3 kg·m/(A·s²)"
+    );
+
+    let comma_decimal = || FormatOptions {
+        decimal_separator: ',',
+        thousands_separator: '.',
+        ..Default::default()
+    };
+    assert_eq!(format(&speed, comma_decimal()), "1234,57 km/h");
+    assert_eq!(format(&acceleration, comma_decimal()), "9,81 m/s²");
+    assert_eq!(format(&time, comma_decimal()), "2 s");
+    assert_eq!(format(&large, comma_decimal()), "1.234.567 m");
+    assert_eq!(format(&negative, comma_decimal()), "-0,5 m");
+    assert_eq!(format(&tiny, comma_decimal()), "1,5e-9 m");
+    assert_eq!(
+        format(
+            &large,
+            FormatOptions {
+                thousands_separator: ' ',
+                ..Default::default()
+            }
+        ),
+        "1 234 567 m"
+    );
+
+    let rounded = |q: &Quantity, rounding_mode| {
+        format(
+            q,
+            FormatOptions {
+                precision: 2,
+                rounding_mode,
+                ..Default::default()
+            },
+        )
+    };
+    assert_eq!(rounded(&tie_even, RoundingMode::HalfEven), "0.12 m");
+    assert_eq!(rounded(&tie_odd, RoundingMode::HalfEven), "0.14 m");
+    assert_eq!(rounded(&tie_even, RoundingMode::HalfUp), "0.13 m");
+    assert_eq!(rounded(&tie_odd, RoundingMode::HalfUp), "0.14 m");
+    assert_eq!(rounded(&tie_odd, RoundingMode::Truncate), "0.13 m");
+    assert_eq!(rounded(&speed, RoundingMode::Truncate), "1200 km/h");
+
+    assert!(ctx
+        .format(
+            &time,
+            &FormatOptions {
+                target_unit: Some(meter_per_second),
+                ..Default::default()
+            }
+        )
+        .is_err());
+}
+
+#[test]
+fn test_quantity_binary_representation() {
+    let mut ctx = get_test_context();
+    let acceleration = get_quantity(&mut ctx, "9.81 m/s²");
+    let time = get_quantity(&mut ctx, "2 s");
+    let speed = get_quantity(&mut ctx, "72 km/h");
+
+    let decoded = Quantity::from_bytes(&acceleration.to_bytes()).unwrap();
+    assert_eq!(decoded, acceleration);
+    assert_eq!(decoded.to_string(), "9.81 m/s²");
+    assert_eq!((decoded * time).full_simplify().to_string(), "19.62 m/s");
+
+    let decoded = Quantity::from_bytes(&speed.to_bytes()).unwrap();
+    assert_eq!(decoded.to_string(), "20 m/s");
+    assert_eq!((&decoded + &speed).unwrap().to_string(), "40 m/s");
+    assert_eq!(
+        decoded.convert_to(speed.unit()).unwrap().to_string(),
+        "72 km/h"
+    );
+}
+
+#[test]
+fn test_unicode_normalization_of_identifiers() {
+    // 'Å' in NFC, NFD, and as the Angstrom sign
+    for angstrom in ["\u{00C5}", "A\u{030A}", "\u{212B}"] {
+        expect_output(&format!("1 {angstrom} -> pm"), "100 pm");
+    }
+    // 'Ω' and the Ohm sign
+    for ohm in ["\u{03A9}", "\u{2126}"] {
+        expect_output(&format!("2 k{ohm} -> ohm"), "2000 Ω");
+    }
+
+    expect_output("let la\u{0308}nge = 2 m\nl\u{00E4}nge", "2 m");
+    expect_output(
+        "@aliases(A\u{030A}A\u{030A}: short) unit test_unit = 3 m\n1 \u{00C5}\u{00C5} -> m",
+        "3 m",
+    );
+}
+
+#[test]
+fn test_unit_annotations() {
+    expect_output("5[m/s] -> km/h", "18 km/h");
+    expect_output("5[1/s] -> Hz", "5 Hz");
+    expect_output("2[in] -> cm", "5.08 cm");
+    expect_output("3[m]^2", "9 m²");
+    expect_failure("5[m] + 2[s]", "left hand side: Length");
+}
+
+#[test]
+fn test_foreign_functions() {
+    let ctx = get_test_context();
+    let foreign_functions: Vec<_> = ctx.foreign_functions().collect();
+
+    let get = |name: &str| foreign_functions.iter().find(|(n, _, _)| n == name);
+
+    assert_eq!(
+        get("sin"),
+        Some(&(
+            "sin".to_string(),
+            1..=1,
+            Some("fn sin(x: Scalar) -> Scalar".to_string())
+        ))
+    );
+    assert_eq!(get("atan2").map(|(_, arity, _)| arity), Some(&(2..=2)));
+    assert_eq!(get("random").map(|(_, arity, _)| arity), Some(&(0..=0)));
+
+    // 'sqrt' is implemented in Numbat, not as a foreign function
+    assert!(get("sqrt").is_none());
+}
+
 #[test]
 fn test_full_simplify_for_function_calls() {
     expect_output("floor(1.2 hours / hour)", "1");
@@ -813,3 +1655,1397 @@ fn test_statement_pretty_printing() {
     // TODO:
     // expect_pretty_print("fn f<Z>(z: Z) = z", "fn f<Z>(z: Z) -> Z = z");
 }
+
+#[test]
+fn test_exchange_rate_table() {
+    let mut ctx = get_test_context();
+    ctx.set_exchange_rate_table([("USD".to_string(), 1.25), ("JPY".to_string(), 160.0)])
+        .unwrap();
+    let _ = ctx
+        .interpret("use units::currencies", CodeSource::Internal)
+        .unwrap();
+
+    expect_output_with_context(&mut ctx, "100 USD -> EUR", "80 €");
+    expect_output_with_context(&mut ctx, "100 EUR -> USD", "125 $");
+    expect_output_with_context(&mut ctx, "1000 JPY -> USD", "7.8125 $");
+    expect_output_with_context(&mut ctx, "is_nan(1 GBP / EUR)", "true");
+
+    // Each context has its own rate table
+    let mut other_ctx = get_test_context();
+    other_ctx
+        .set_exchange_rate_table([("USD".to_string(), 2.0)])
+        .unwrap();
+    let _ = other_ctx
+        .interpret("use units::currencies", CodeSource::Internal)
+        .unwrap();
+
+    expect_output_with_context(&mut other_ctx, "100 EUR -> USD", "200 $");
+    expect_output_with_context(&mut ctx, "100 EUR -> USD", "125 $");
+}
+
+#[test]
+fn test_exchange_rate_table_after_loading_currencies() {
+    let mut ctx = get_test_context();
+    ctx.set_exchange_rate_table([("USD".to_string(), 1.25)])
+        .unwrap();
+    let _ = ctx
+        .interpret(
+            "use units::currencies\nunit cent = 0.01 USD\nlet price = 100 USD",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_output_with_context(&mut ctx, "100 EUR -> USD", "125 $");
+
+    ctx.set_exchange_rate_table([("USD".to_string(), 2.0), ("GBP".to_string(), 0.8)])
+        .unwrap();
+    expect_output_with_context(&mut ctx, "100 EUR -> USD", "200 $");
+    expect_output_with_context(&mut ctx, "100 GBP -> EUR", "125 €");
+    expect_output_with_context(&mut ctx, "1 EUR -> cent", "200 cent");
+
+    // Values that were computed before keep the old rate
+    expect_output_with_context(&mut ctx, "price -> EUR", "80 €");
+}
+
+#[test]
+fn test_exchange_rate_table_in_function_bodies() {
+    let mut ctx = get_test_context();
+    ctx.set_exchange_rate_table([("USD".to_string(), 1.25)])
+        .unwrap();
+    let _ = ctx
+        .interpret("use units::currencies", CodeSource::Internal)
+        .unwrap();
+    let _ = ctx
+        .interpret("fn price() -> Money = 100 USD -> EUR", CodeSource::Internal)
+        .unwrap();
+    expect_output_with_context(&mut ctx, "price()", "80 €");
+
+    // The conversion is not evaluated at compile time, so the new rate is used
+    ctx.set_exchange_rate_table([("USD".to_string(), 2.0)])
+        .unwrap();
+    expect_output_with_context(&mut ctx, "price()", "50 €");
+}
+
+#[test]
+fn test_check_all_reports_all_type_errors() {
+    let ctx = get_test_context();
+
+    let errors = ctx.check_all(
+        "let a = 1 m + 2 s\nlet b = 2\nlet c = 3 kg + 4 A",
+        CodeSource::Internal,
+    );
+    assert_eq!(errors.len(), 2);
+    assert!(errors
+        .iter()
+        .all(|e| matches!(e, NumbatError::TypeCheckError(_))));
+
+    assert!(ctx
+        .check_all("let a = 1 m + 2 m", CodeSource::Internal)
+        .is_empty());
+}
+
+#[test]
+fn test_simplify() {
+    expect_output("simplify(5 cm/m)", "0.05");
+    expect_output("simplify(1 m^2/m)", "1 m");
+
+    // Conversions are not simplified automatically, but can be simplified explicitly
+    expect_output("3 km/m to km/m", "3 km/m");
+    expect_output("simplify(3 km/m to km/m)", "3000");
+    expect_output("simplify(1 m^2/m to m^2/m)", "1 m");
+    expect_output(
+        "fn f(x: Length) -> Scalar = simplify(x / m to cm/m)
+         f(2 m)",
+        "2",
+    );
+
+    // Units that are already canonical are left alone
+    expect_output("simplify(2 h to min)", "120 min");
+
+    // Products of units are expressed in a unit that is equal to them, if there is one
+    expect_output("simplify(1 N * 1 m)", "1 J");
+    expect_output("simplify(3 kg * 2 m / s^2)", "6 N");
+    expect_output("simplify(5 A * 2 s)", "10 C");
+    expect_output("simplify(2 W / 1 A)", "2 V");
+    expect_output("simplify(1 g * 1 cm / s^2)", "1 dyne");
+
+    // … but not if it would change the numerical value, or if it is ambiguous
+    expect_output("simplify(1 kN * 1 m)", "1 kN·m");
+    expect_output("simplify(1 m^2 / s^2)", "1 m²/s²");
+}
+
+#[test]
+fn test_raw() {
+    expect_output("3 m * 2 cm", "600 cm²");
+    expect_output("raw(3 m * 2 cm)", "6 m·cm");
+    expect_output("2 km / 4 m", "500");
+    expect_output("raw(2 km / 4 m)", "0.5 km/m");
+    expect_output("let x = raw(2 km / 4 m)\nx", "0.5 km/m");
+    expect_output("simplify(raw(2 km / 4 m))", "500");
+
+    // Only the argument of `raw` is left alone
+    expect_output("raw(2 km / 4 m) + 1", "501");
+}
+
+#[test]
+fn test_large_conditionals() {
+    let elements = vec!["1"; 10_000].join(", ");
+    expect_output(
+        &format!("fn f(x) = if x > 0 then len([{elements}]) else 0\nf(1)"),
+        "10000",
+    );
+
+    let elements = vec!["1"; 30_000].join(", ");
+    expect_failure(
+        &format!("fn f(x) = if x > 0 then len([{elements}]) else 0"),
+        "Conditional expression is too large",
+    );
+}
+
+#[test]
+fn test_base_representation_of() {
+    let ctx = get_test_context();
+
+    let factors = |name: &str| {
+        ctx.base_representation_of(name)
+            .unwrap()
+            .iter()
+            .map(|BaseRepresentationFactor(base, exponent)| (base.clone(), exponent.to_integer()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(factors("Length"), vec![("Length".into(), 1)]);
+    assert_eq!(
+        factors("Pressure"),
+        vec![
+            ("Length".into(), -1),
+            ("Mass".into(), 1),
+            ("Time".into(), -2)
+        ]
+    );
+    assert_eq!(
+        factors("pascal"),
+        vec![
+            ("gram".into(), 1),
+            ("metre".into(), -1),
+            ("second".into(), -2)
+        ]
+    );
+    assert_eq!(factors("kPa"), factors("pascal"));
+    assert_eq!(factors("Scalar"), vec![]);
+
+    assert!(ctx.base_representation_of("NonExisting").is_none());
+}
+
+#[test]
+fn test_dimensions() {
+    let ctx = get_test_context();
+
+    let dimensions: Vec<_> = ctx.dimensions().collect();
+    let find = |name: &str| {
+        let (_, base_representation, is_base) = dimensions
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .unwrap_or_else(|| panic!("dimension '{name}' not found"));
+        let factors = base_representation
+            .iter()
+            .map(|BaseRepresentationFactor(base, exponent)| (base.clone(), exponent.to_integer()))
+            .collect::<Vec<_>>();
+        (factors, *is_base)
+    };
+
+    assert_eq!(find("Length"), (vec![("Length".into(), 1)], true));
+    assert_eq!(
+        find("Energy"),
+        (
+            vec![
+                ("Length".into(), 2),
+                ("Mass".into(), 1),
+                ("Time".into(), -2)
+            ],
+            false
+        )
+    );
+    assert_eq!(find("Scalar"), (vec![], false));
+
+    // Base dimensions are listed before derived ones
+    let first_derived = dimensions.iter().position(|(_, _, is_base)| !is_base);
+    assert!(dimensions[first_derived.unwrap()..]
+        .iter()
+        .all(|(_, _, is_base)| !is_base));
+}
+
+#[test]
+fn test_are_dimensionally_equal() {
+    let ctx = get_test_context();
+
+    assert!(ctx.are_dimensionally_equal("N * m", "J").unwrap());
+    assert!(ctx.are_dimensionally_equal("3 N * 2 km", "5 kWh").unwrap());
+    assert!(ctx
+        .are_dimensionally_equal("kg * (m/s)^2 / 2", "J")
+        .unwrap());
+    assert!(ctx.are_dimensionally_equal("sqrt(m^2)", "ft").unwrap());
+    assert!(ctx.are_dimensionally_equal("2", "m/km").unwrap());
+
+    assert!(!ctx.are_dimensionally_equal("N", "J").unwrap());
+    assert!(!ctx.are_dimensionally_equal("m/s", "m/s^2").unwrap());
+
+    // The expressions are not evaluated, and not added to the context
+    assert!(ctx.are_dimensionally_equal("1 m / (1 - 1)", "m").unwrap());
+    assert!(!ctx.variable_names().any(|name| name == "ans"));
+
+    assert_eq!(
+        ctx.are_dimensionally_equal("true", "1")
+            .unwrap_err()
+            .to_string(),
+        "Expected dimension type, got Bool instead"
+    );
+    assert!(matches!(
+        ctx.are_dimensionally_equal("m + s", "m"),
+        Err(NumbatError::TypeCheckError(_))
+    ));
+    assert!(matches!(
+        ctx.are_dimensionally_equal("let x = 2 m", "m"),
+        Err(NumbatError::ResolverError(_))
+    ));
+}
+
+#[test]
+fn test_type_of() {
+    let mut ctx = get_test_context();
+    let readable_type = |ctx: &mut Context, code: &str| {
+        let type_ = ctx.type_of(code).unwrap();
+        type_.to_readable_type(ctx.dimension_registry()).to_string()
+    };
+
+    assert_eq!(readable_type(&mut ctx, "2 meter / second"), "Velocity");
+    assert_eq!(readable_type(&mut ctx, "3 N * 2 km"), "Energy or Torque");
+    assert_eq!(readable_type(&mut ctx, "m / km"), "Scalar");
+    assert_eq!(readable_type(&mut ctx, "1 < 2"), "Bool");
+    assert_eq!(readable_type(&mut ctx, "\"x\""), "String");
+    assert_eq!(readable_type(&mut ctx, "[1 s, 2 min]"), "List<Time>");
+
+    // The expression is not evaluated, and its result is not assigned to `ans`
+    assert_eq!(readable_type(&mut ctx, "1 m / (1 - 1)"), "Length");
+    assert!(!ctx.variable_names().any(|name| name == "ans"));
+    expect_output_with_context(&mut ctx, "2 m", "2 m");
+    let _ = ctx.type_of("3 s").unwrap();
+    expect_output_with_context(&mut ctx, "ans", "2 m");
+
+    assert!(matches!(
+        ctx.type_of("m + s"),
+        Err(NumbatError::TypeCheckError(_))
+    ));
+    assert!(matches!(
+        ctx.type_of("let x = 2 m"),
+        Err(NumbatError::ResolverError(_))
+    ));
+
+    // Only a single expression is accepted
+    assert_eq!(readable_type(&mut ctx, "\n2 m\n"), "Length");
+    for code in ["1)\nlet zz = (2", "1)\n(2 m", "2 m\n3 s", "use units::si"] {
+        assert!(matches!(
+            ctx.type_of(code),
+            Err(NumbatError::ResolverError(_))
+        ));
+    }
+    assert!(!ctx.variable_names().any(|name| name == "zz"));
+}
+
+#[test]
+fn test_type_check_file() {
+    let ctx = get_test_context();
+
+    let summary = ctx
+        .type_check_file(
+            "dimension Pace = Time / Length
+             unit min_per_km: Pace = min / km
+             unit quork
+             fn pace(time: Time, distance: Length) = time / distance
+             fn twice<D: Dim>(x: D) -> D = 2 x
+             let runners = 3",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    assert_eq!(
+        summary.functions,
+        vec![
+            FunctionSummary {
+                name: "pace".into(),
+                type_parameters: vec![],
+                parameters: vec![
+                    ("time".into(), "Time".into()),
+                    ("distance".into(), "Length".into())
+                ],
+                return_type: "Pace".into(),
+            },
+            FunctionSummary {
+                name: "twice".into(),
+                type_parameters: vec![("D".into(), Some("Dim".into()))],
+                parameters: vec![("x".into(), "D".into())],
+                return_type: "D".into(),
+            },
+        ]
+    );
+    assert_eq!(
+        summary.units,
+        vec![
+            UnitSummary {
+                name: "min_per_km".into(),
+                dimension: "Pace".into(),
+            },
+            UnitSummary {
+                name: "quork".into(),
+                dimension: "Quork".into(),
+            },
+        ]
+    );
+    assert_eq!(summary.dimensions.len(), 1);
+    assert_eq!(summary.dimensions[0].name, "Pace");
+    assert_eq!(
+        summary.dimensions[0].base_representation.to_string(),
+        ctx.base_representation_of("Velocity")
+            .unwrap()
+            .invert()
+            .to_string()
+    );
+
+    // The definitions are not added to the context
+    assert!(!ctx.function_names().any(|name| name == "pace"));
+    assert!(ctx.base_representation_of("Pace").is_none());
+
+    assert!(matches!(
+        ctx.type_check_file("fn f(x: Length) -> Time = x", CodeSource::Internal),
+        Err(NumbatError::TypeCheckError(_))
+    ));
+}
+
+#[test]
+fn test_plural_unit_names() {
+    let mut ctx = get_test_context();
+    let _ = ctx
+        .interpret(
+            "@metric_prefixes
+             @aliases(quorks)
+             @plural(\"quorks\")
+             unit quork",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    expect_output_with_context(&mut ctx, "1 quork", "1 quork");
+    expect_output_with_context(&mut ctx, "-1 quork", "-1 quork");
+    expect_output_with_context(&mut ctx, "2 quork", "2 quorks");
+    expect_output_with_context(&mut ctx, "0.5 quorks", "0.5 quorks");
+    expect_output_with_context(&mut ctx, "3 kiloquork", "3 kiloquorks");
+
+    // Only simple units are pluralized
+    expect_output_with_context(&mut ctx, "2 quork/s", "2 quork/s");
+    expect_output_with_context(&mut ctx, "2 quork^2", "2 quork²");
+
+    // Units without the decorator are not pluralized
+    expect_output_with_context(&mut ctx, "2 week", "2 week");
+}
+
+#[test]
+fn test_mutable_variables() {
+    expect_output("let mut x = 1 m\nx = 2 m\nx", "2 m");
+    expect_output("let mut x = 1 m\nx = x + 1 km\nx", "1001 m");
+
+    // Functions see the new value
+    expect_output("let mut x = 1\nfn f() = 2 x\nx = 3\nf()", "6");
+
+    // Aliases refer to the same variable
+    expect_output("@aliases(y) let mut x = 1\nx = x + 1\ny", "2");
+    expect_output("@aliases(y) let mut x = 1\ny = 5\nx", "5");
+
+    expect_failure("let x = 1\nx = 2", "Can not assign to 'x'");
+    expect_failure("let mut x = 1 m\nx = 2 s", "actual dimension: Time");
+}
+
+#[test]
+fn test_display_systems() {
+    let mut ctx = get_test_context();
+
+    let computation = "3 m + 5 m";
+    expect_output_with_context(&mut ctx, computation, "8 m");
+
+    ctx.set_display_system(Some(DisplaySystem::imperial()));
+    expect_output_with_context(&mut ctx, computation, "26.2467 ft");
+    expect_output_with_context(&mut ctx, "100 km/h", "62.1371 mph");
+    expect_output_with_context(&mut ctx, "2 m * 3 m", "64.5835 ft²");
+    expect_output_with_context(&mut ctx, "1 L", "0.264172 gal");
+    expect_output_with_context(&mut ctx, "9.81 m/s^2", "32.185 ft/s²");
+
+    // Explicit conversions, scalars and dimensions without a preferred unit are left alone
+    expect_output_with_context(&mut ctx, "3 m -> cm", "300 cm");
+    expect_output_with_context(&mut ctx, "2 m / 4 m", "0.5");
+    expect_output_with_context(&mut ctx, "3 A", "3 A");
+
+    ctx.set_display_system(Some(DisplaySystem::metric()));
+    expect_output_with_context(&mut ctx, computation, "8 m");
+    expect_output_with_context(&mut ctx, "6 ft", "1.8288 m");
+    expect_output_with_context(&mut ctx, "1 gal", "3.78541 l");
+    expect_output_with_context(&mut ctx, "60 mph", "26.8224 m/s");
+
+    // For dimensions with the same base representation, the first entry wins
+    ctx.set_display_system(Some(
+        DisplaySystem::new()
+            .with_unit("Energy", "kWh")
+            .with_unit("Torque", "N m"),
+    ));
+    expect_output_with_context(&mut ctx, "3.6 MJ", "1 kWh");
+
+    ctx.set_display_system(None);
+    expect_output_with_context(&mut ctx, computation, "8 m");
+}
+
+#[test]
+fn test_suggest_unit_for() {
+    let mut ctx = get_test_context();
+
+    let mut suggest = |code: &str| {
+        let (_, result) = ctx.interpret(code, CodeSource::Internal).unwrap();
+        let InterpreterResult::Value(Value::Quantity(q)) = result else {
+            panic!("Expected a quantity");
+        };
+        let suggested = ctx.suggest_unit_for(&q);
+        let fmt = PlainTextFormatter {};
+        fmt.format(&suggested.pretty_print(), false)
+            .trim()
+            .to_string()
+    };
+
+    assert_eq!(suggest("0.00001 m"), "10 µm");
+    assert_eq!(suggest("0.5 m"), "500 mm");
+    assert_eq!(suggest("1 m"), "1 m");
+    assert_eq!(suggest("999 m"), "999 m");
+    assert_eq!(suggest("1000 m"), "1 km");
+    assert_eq!(suggest("-123456 m"), "-123.456 km");
+    assert_eq!(suggest("4.2e10 m"), "42 Gm");
+    assert_eq!(suggest("0.02 km"), "20 m");
+    assert_eq!(suggest("3e-25 m"), "300 rm");
+
+    // Quantities that are zero, units without metric prefixes and compound units are unchanged
+    assert_eq!(suggest("0.001 ft"), "0.001 ft");
+    assert_eq!(suggest("0.001 m/s"), "0.001 m/s");
+    assert_eq!(suggest("0.001 m²"), "0.001 m²");
+
+    let (_, result) = ctx.interpret("0 km", CodeSource::Internal).unwrap();
+    let InterpreterResult::Value(Value::Quantity(zero)) = result else {
+        panic!("Expected a quantity");
+    };
+    assert_eq!(ctx.suggest_unit_for(&zero).unit(), zero.unit());
+}
+
+#[test]
+fn test_info_contains_links() {
+    let mut ctx = get_test_context();
+
+    let _ = ctx
+        .interpret(
+            "@url(\"https://example.com/foo_unit\")\nunit foo_unit",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    let is_link_to = |markup: &numbat::markup::Markup, url: &str| {
+        markup
+            .0
+            .iter()
+            .any(|FormattedString(_, format_type, text)| {
+                *format_type == FormatType::Link && text == url
+            })
+    };
+
+    let info = ctx.print_info_for_keyword("foo_unit");
+    assert!(is_link_to(&info, "https://example.com/foo_unit"));
+    assert!(PlainTextFormatter {}
+        .format(&info, false)
+        .contains("(https://example.com/foo_unit)"));
+
+    let info = ctx.print_info_for_keyword("sqrt");
+    assert!(is_link_to(
+        &info,
+        "https://en.wikipedia.org/wiki/Square_root"
+    ));
+}
+
+#[test]
+fn test_help_for_evaluates_examples() {
+    let mut ctx = get_test_context();
+
+    let _ = ctx.interpret(
+        "@example(\"3 foo_length -> m\")\n@example(\"1 foo_length + 1 foo\")\nunit foo_length = 201.168 m",
+        CodeSource::Internal,
+    )
+    .unwrap();
+
+    let help = PlainTextFormatter {}.format(&ctx.help_for("foo_length"), false);
+    assert!(help.contains("Unit: foo_length"));
+    assert!(help.contains(">>> 3 foo_length -> m"));
+    assert!(help.contains("= 603.504 m"));
+    assert!(help.contains(">>> 1 foo_length + 1 foo"));
+    assert!(help.contains("Unknown identifier 'foo'"));
+
+    // Examples are evaluated in a copy of the context
+    let _ = ctx
+        .interpret(
+            "@example(\"let side = 2 m\")\n@example(\"side^2\")\nfn square(x) = x^2",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    let help = PlainTextFormatter {}.format(&ctx.help_for("square"), false);
+    assert!(help.contains("= 4 m²"));
+    assert!(ctx.interpret("side", CodeSource::Internal).is_err());
+
+    let help = PlainTextFormatter {}.format(&ctx.help_for("meter"), false);
+    assert!(!help.contains("Examples:"));
+}
+
+#[test]
+fn test_user_defined_operators() {
+    let mut ctx = get_test_context();
+
+    let _ = ctx
+        .interpret(
+            "fn combine(a: Length, b: Length) -> Length = sqrt(a^2 + b^2)
+         operator <> = combine",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_output_with_context(&mut ctx, "3 m <> 4 m", "5 m");
+    expect_output_with_context(&mut ctx, "1 m + 2 m <> 4 m", "5 m");
+    expect_output_with_context(&mut ctx, "3 m <> 4 m <> 12 m", "13 m");
+    expect_output_with_context(&mut ctx, "30 cm <> 0.4 m -> cm", "50 cm");
+
+    // Operators can be rebound
+    let _ = ctx
+        .interpret(
+            "fn concat(a: String, b: String) -> String = \"{a}{b}\"
+         operator <> = concat",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_output_with_context(&mut ctx, "\"foo\" <> \"bar\"", "\"foobar\"");
+
+    expect_failure("1 <*> 2", "Unknown operator '<*>'");
+}
+
+#[test]
+fn test_method_call_syntax() {
+    expect_output("(4 m^2).sqrt()", "2 m");
+    expect_output("(2 km).to(m)", "2000 m");
+    expect_output("(2 km).to(m).sqrt()", "44.7214 m^(1/2)");
+    expect_output("(3 m).hypot2(4 m)", "5 m");
+    expect_output("let x = 9 m^2\nx.sqrt()", "3 m");
+    expect_output("[3, 1, 2].sort().head()", "1");
+
+    expect_failure("(2 m).cos()", "argument type: Length");
+    expect_failure(
+        "(2 m).unknown_function()",
+        "Unknown identifier 'unknown_function'",
+    );
+
+    // A field that holds a function is called directly
+    expect_output(
+        "struct S { f: Fn[(Scalar) -> Scalar] }
+         fn twice(x: Scalar) -> Scalar = 2 x
+         let st = S { f: twice }
+         st.f(3)",
+        "6",
+    );
+    expect_output(
+        "struct S { sqrt: Fn[(Scalar) -> Scalar] }
+         fn times_three(x: Scalar) -> Scalar = 3 x
+         let st = S { sqrt: times_three }
+         st.sqrt(3)",
+        "9",
+    );
+    expect_output(
+        "struct S { f: Fn[(Scalar) -> Scalar] }
+         fn apply(obj: S, x: Scalar) -> Scalar = obj.f(x)
+         fn twice(x: Scalar) -> Scalar = 2 x
+         apply(S { f: twice }, 4)",
+        "8",
+    );
+}
+
+#[test]
+fn test_prelude_features_can_be_excluded() {
+    use numbat::module_importer::{BuiltinModuleImporter, PreludeFeature};
+
+    let importer = BuiltinModuleImporter::default()
+        .without(PreludeFeature::Currencies)
+        .without(PreludeFeature::ObscureUnits);
+    let mut ctx = Context::new(importer);
+    let _ = ctx.interpret("use prelude", CodeSource::Internal).unwrap();
+
+    expect_output_with_context(&mut ctx, "1 km -> m", "1000 m");
+    expect_output_with_context(&mut ctx, "2 weeks -> days", "14 day");
+
+    expect_failure_with_context(&mut ctx, "1 smoot", "Unknown identifier 'smoot'");
+    expect_failure_with_context(&mut ctx, "1 furlong", "Unknown identifier 'furlong'");
+    expect_failure_with_context(&mut ctx, "2 EUR", "Unknown identifier 'EUR'");
+
+    // Excluded modules can still be imported explicitly
+    let _ = ctx
+        .interpret("use units::humorous", CodeSource::Internal)
+        .unwrap();
+    expect_output_with_context(&mut ctx, "1 smoot -> cm", "170.18 cm");
+}
+
+#[test]
+fn test_canonical_unit_name() {
+    let mut ctx = get_test_context();
+
+    let _ = ctx
+        .interpret(
+            "@metric_prefixes
+             @aliases(widgets, widget: long, wdg: short)
+             @canonical(widget)
+             unit my_widget: Length = 2 meter
+
+             @aliases(gdg: short, gdg2: short)
+             @canonical(gdg2)
+             unit my_gadget: Time = 3 second
+
+             @aliases(dgt: short)
+             @canonical(my_doodad)
+             unit my_doodad: Mass = 4 gram",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    expect_output_with_context(&mut ctx, "2 widgets", "2 widget");
+    expect_output_with_context(&mut ctx, "3 kilowidget", "3 kilowidget");
+    expect_output_with_context(&mut ctx, "3 kwdg", "3 kilowidget");
+    expect_output_with_context(&mut ctx, "4 gdg", "4 gdg2");
+    expect_output_with_context(&mut ctx, "5 dgt", "5 my_doodad");
+}
+
+#[test]
+fn test_csv() {
+    use numbat::markup::Markup;
+    use numbat::InterpreterSettings;
+    use std::sync::{Arc, Mutex};
+
+    let output = Arc::new(Mutex::new(String::new()));
+    let output_c = output.clone();
+    let mut settings = InterpreterSettings {
+        print_fn: Box::new(move |s: &Markup| {
+            *output_c.lock().unwrap() += &format!("{s}\n");
+        }),
+        ..InterpreterSettings::default()
+    };
+
+    let mut ctx = get_test_context();
+    let _ = ctx
+        .interpret_with_settings(
+            &mut settings,
+            "csv([1 m, 2 m, 3 m])
+             csv([1 m, 2.5 km], \";\", true)
+             csv([1 m/s, 36 km/h], \",\", false)
+             csv([2 km/h, 1 m/s])",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    assert_eq!(
+        *output.lock().unwrap(),
+        "1,metre\n2,metre\n3,metre\n1;metre\n2500;metre\n1\n10\n2,kilometre/hour\n3.6,kilometre/hour\n"
+    );
+
+    // Units are written with their canonical name, if that is a long name
+    output.lock().unwrap().clear();
+    let _ = ctx
+        .interpret_with_settings(
+            &mut settings,
+            "@aliases(widgets, widget: long, wdg: short)
+             @canonical(widget)
+             unit my_widget: Length = 2 meter
+
+             @aliases(gdg: short)
+             unit my_gadget: Time = 3 second
+
+             csv([1 widgets, 2 wdg])
+             csv([3 gdg])
+             csv([])",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    assert_eq!(*output.lock().unwrap(), "1,widget\n2,widget\n3,my_gadget\n");
+
+    expect_failure("csv([1 m, 2 s])", "Incompatible types in list");
+    expect_failure("csv([1 m], 2)", "Incompatible types in function call");
+}
+
+#[test]
+fn test_debug_procedure() {
+    use numbat::markup::Markup;
+    use numbat::InterpreterSettings;
+    use std::sync::{Arc, Mutex};
+
+    let output = Arc::new(Mutex::new(String::new()));
+    let output_c = output.clone();
+    let mut settings = InterpreterSettings {
+        print_fn: Box::new(move |s: &Markup| {
+            *output_c.lock().unwrap() += &format!("{s}\n");
+        }),
+        ..InterpreterSettings::default()
+    };
+
+    let mut ctx = get_test_context();
+    let _ = ctx
+        .interpret_with_settings(
+            &mut settings,
+            "debug(9.81 m/s^2)
+             debug(3 km/h)
+             debug(2 < 3)",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    assert_eq!(
+        *output.lock().unwrap(),
+        "9.81 m/s²    [type: Length / Time² (Acceleration), base units: 9.81 m/s²]\n\
+         3 km/h    [type: Length / Time (Velocity), base units: 0.833333 m/s]\n\
+         true    [type: Bool]\n"
+    );
+
+    expect_failure("debug(\"hello\")", "Expected dimension type");
+    expect_failure(
+        "debug(1, 2)",
+        "called with 2 arguments, but needs 1 argument",
+    );
+}
+
+#[test]
+fn test_type_of_dimensionless_quantities() {
+    use numbat::markup::Markup;
+    use numbat::InterpreterSettings;
+    use std::sync::{Arc, Mutex};
+
+    let output = Arc::new(Mutex::new(String::new()));
+    let output_c = output.clone();
+    let mut settings = InterpreterSettings {
+        print_fn: Box::new(move |s: &Markup| {
+            *output_c.lock().unwrap() += &format!("{s}\n");
+        }),
+        ..InterpreterSettings::default()
+    };
+
+    let mut ctx = get_test_context();
+    let _ = ctx
+        .interpret_with_settings(
+            &mut settings,
+            "type(2)
+             type(2%)
+             type(1 rad)
+             type(1 m / cm)
+             type([1 sr])",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    assert_eq!(
+        *output.lock().unwrap(),
+        "= Scalar\n= Scalar\n= Scalar\n= Scalar\n= List<Scalar>\n"
+    );
+}
+
+#[test]
+fn test_binary_prefixes() {
+    for (from, to, expected) in [
+        ("1 KiB", "B", "1024 B"),
+        ("1 MiB", "KiB", "1024 KiB"),
+        ("1 GiB", "MiB", "1024 MiB"),
+        ("1 TiB", "GiB", "1024 GiB"),
+        ("1 PiB", "TiB", "1024 TiB"),
+        ("1 EiB", "PiB", "1024 PiB"),
+        ("1 ZiB", "EiB", "1024 EiB"),
+        ("1 YiB", "ZiB", "1024 ZiB"),
+        ("1 GiB", "B", "1_073_741_824 B"),
+        ("1 kibibyte", "byte", "1024 B"),
+        ("1 mebibytes", "kibibytes", "1024 KiB"),
+        ("1 KiB", "kB", "1.024 kB"),
+        ("1 MB", "MiB", "0.953674 MiB"),
+        ("1 KiB", "bit", "8192 bit"),
+        ("1 MiB/s", "KiB/s", "1024 KiB/s"),
+        ("(1 KiB)^2", "B^2", "1_048_576 B²"),
+        ("2048 B", "KiB", "2 KiB"),
+    ] {
+        expect_output(&format!("{from} -> {to}"), expected);
+    }
+
+    expect_output("1 KiB == 1024 B", "true");
+    expect_output("1 KiB + 1 kB -> B", "2024 B");
+
+    // Units only accept binary prefixes if they are explicitly enabled, and
+    // binary prefixes do not imply metric prefixes (or vice versa)
+    let mut ctx = get_test_context();
+    let _ = ctx
+        .interpret(
+            "@binary_prefixes
+             @aliases(wd: short)
+             unit word: DigitalInformation = 16 bit",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_output_with_context(&mut ctx, "1 kibiword -> word", "1024 wd");
+    expect_output_with_context(&mut ctx, "1 Kiwd -> B", "2048 B");
+    expect_failure_with_context(&mut ctx, "1 kiloword", "Unknown identifier 'kiloword'");
+    expect_failure_with_context(&mut ctx, "1 kwd", "Unknown identifier 'kwd'");
+    expect_failure("1 kibimeter", "Unknown identifier 'kibimeter'");
+    expect_failure("1 Kim", "Unknown identifier 'Kim'");
+}
+
+#[test]
+fn test_warnings_are_reported_alongside_results() {
+    use numbat::Warning;
+
+    let mut ctx = get_test_context();
+
+    let _ = ctx
+        .interpret("let distance = 3 m", CodeSource::Internal)
+        .unwrap();
+    assert!(ctx.warnings().is_empty());
+
+    let (_, result) = ctx
+        .interpret("let distance = 2 s\ndistance", CodeSource::Internal)
+        .unwrap();
+    assert_eq!(result.value_as_string().unwrap(), "2 s");
+    assert_eq!(ctx.warnings().len(), 1);
+    assert!(matches!(
+        &ctx.warnings()[0],
+        Warning::RedefinitionChangesType { identifier, original_type, new_type, .. }
+            if identifier == "distance" && original_type == "Length" && new_type == "Time"
+    ));
+    assert_eq!(
+        ctx.warnings()[0].to_string(),
+        "Redefinition of 'distance' changes its type from Length to Time"
+    );
+
+    // Warnings only refer to the last call to `interpret`
+    let _ = ctx.interpret("distance", CodeSource::Internal).unwrap();
+    assert!(ctx.warnings().is_empty());
+}
+
+#[test]
+fn test_assert_eq_with_relative_tolerance() {
+    let passes = |code: &str| {
+        get_test_context()
+            .interpret(code, CodeSource::Internal)
+            .is_ok()
+    };
+
+    // Absolute tolerance
+    assert!(passes("assert_eq(1.0 m, 1.001 m, 0.01 m)"));
+    expect_failure(
+        "assert_eq(1.0 m, 1.1 m, 1 cm)",
+        "Assertion failed because the following two quantities differ by more than 1 cm:\n  1 m\n  1.1 m",
+    );
+    assert!(passes("assert_eq(0.5, 0.51, 0.1)"));
+    expect_failure(
+        "assert_eq(1, 3, 1%)",
+        "Assertion failed because the following two quantities differ by more than 0.01:\n  1\n  3",
+    );
+
+    // Relative tolerance
+    assert!(passes("assert_eq(1 mile, 1.6 km, 1%)"));
+    assert!(passes("assert_eq(101 m, 100 m, 1%)"));
+    assert!(passes("assert_eq(1 kN, 980 N, 0.05)"));
+    expect_failure(
+        "assert_eq(102 m, 100 m, 1%)",
+        "Assertion failed because the following two quantities differ by more than 1% (relative to the second one):\n  102 m\n  100 m",
+    );
+    expect_failure(
+        "assert_eq(1 mile, 1.5 km, 5%)",
+        "Assertion failed because the following two quantities differ by more than 5% (relative to the second one)",
+    );
+
+    expect_failure(
+        "assert_eq(1 m, 1 m, 1 s)",
+        "Argument types in assert_eq calls must match",
+    );
+}
+
+#[test]
+fn test_value_in() {
+    expect_output("value_in(3 m, cm)", "300");
+    expect_output("value_in(20 km/h, m/s)", "5.55556");
+    expect_output("value_in(1 km, 100 m)", "10");
+    expect_output("value_in(5, 1)", "5");
+    expect_output("let v = value_in(2 min, s)\nv + 1", "121");
+
+    expect_failure(
+        "value_in(3 m, s)",
+        "previous argument: Length\n         argument: Time",
+    );
+    expect_failure(
+        "value_in(3 m, 2)",
+        "previous argument: Length\n         argument: Scalar",
+    );
+}
+
+#[test]
+fn test_function_body_blocks() {
+    expect_output(
+        "fn foo(x: Length) -> Length = { let y = 2 x; y + x }\nfoo(2 m)",
+        "6 m",
+    );
+    expect_output(
+        "fn foo(x: Length) -> Length = {\n  let y: Length = 2 x\n  let z = y + 1 cm\n  z + x\n}\nfoo(3 cm)",
+        "10 cm",
+    );
+    expect_output("fn foo(x) = { let x = 2 x; x * x }\nfoo(3 m)", "36 m²");
+    expect_output(
+        "fn foo<T: Dim>(x: T) -> T = { let two = 2; two * x }\nfoo(3 s)",
+        "6 s",
+    );
+    expect_output(
+        "fn fact(n: Scalar) -> Scalar = { let k = n - 1; if n <= 1 then 1 else n * fact(k) }\nfact(5)",
+        "120",
+    );
+
+    expect_failure(
+        "fn foo(x: Length) -> Length = { let y: Time = x; x }",
+        "specified dimension: Time",
+    );
+    expect_failure(
+        "fn foo(x: Length) -> Length = { let y = 2 x; y }\ny",
+        "Unknown identifier 'y'",
+    );
+}
+
+#[test]
+fn test_intervals() {
+    expect_output("[9.79 .. 9.83] m/s²", "[9.79 .. 9.83] m/s²");
+    expect_output("interval(1, 2) m", "[1 .. 2] m");
+    expect_output("[1..2] m", "[1 .. 2] m");
+
+    // [a, b] + [c, d] = [a + c, b + d]
+    expect_output("[1 .. 2] m + [10 .. 20] cm", "[1.1 .. 2.2] m");
+    expect_output("[1 .. 2] m + 3 m", "[4 .. 5] m");
+    // [a, b] - [c, d] = [a - d, b - c]
+    expect_output("[1 .. 2] m - [-3 .. 4] m", "[-3 .. 5] m");
+    expect_output("5 s - [1 .. 2] s", "[3 .. 4] s");
+    // [a, b] × [c, d] = [min(ac, ad, bc, bd), max(ac, ad, bc, bd)]
+    expect_output("[1 .. 2] m * [-3 .. 4] m", "[-6 .. 8] m²");
+    expect_output("[-2 .. -1] * [-3 .. 4]", "[-8 .. 6]");
+    expect_output("[9.79 .. 9.83] m/s² * 2 s", "[19.58 .. 19.66] m/s");
+    // [a, b] / [c, d] = [a, b] × [1/d, 1/c] if 0 ∉ [c, d]
+    expect_output("[1 .. 2] / [-4 .. -2]", "[-1 .. -0.25]");
+    expect_output("[10 .. 20] m / [2 .. 5] s", "[2 .. 10] m/s");
+    expect_output("1 / [2 .. 4] s -> Hz", "[0.25 .. 0.5] Hz");
+
+    expect_output("-[1 .. 2] km -> m", "[-2000 .. -1000] m");
+    expect_output("[1 .. 2] km/m", "[1000 .. 2000]");
+    expect_output("let x = [1 .. 2] m\n\"x = {x}\"", "\"x = [1 .. 2] m\"");
+    expect_output(
+        "fn area(width: Length, height: Length) -> Area = width * height\narea([2 .. 3] m, [10 .. 20] cm) -> cm²",
+        "[2000 .. 6000] cm²",
+    );
+
+    expect_failure(
+        "[2 .. 1]",
+        "Lower bound of interval is larger than its upper bound",
+    );
+    expect_failure("1 / [-1 .. 1]", "Division by zero");
+    expect_failure(
+        "[1 .. 2] m + 3 s",
+        "Could not solve the following constraints",
+    );
+    expect_failure(
+        "sqrt([1 .. 4] m²)",
+        "Intervals can only be used in additions, subtractions, multiplications, divisions and unit conversions",
+    );
+    expect_failure(
+        "[1 .. 2]^2",
+        "Intervals can only be used in additions, subtractions, multiplications, divisions and unit conversions",
+    );
+    expect_failure(
+        "[1 .. 2] m > 1 m",
+        "Intervals can only be used in additions, subtractions, multiplications, divisions and unit conversions",
+    );
+}
+
+#[test]
+fn test_forward_declared_units() {
+    expect_output(
+        "unit foo: Length = ...\nunit foo = 30 cm\n3 foo -> m",
+        "0.9 m",
+    );
+    expect_output(
+        "@metric_prefixes\n@aliases(fo: short)\nunit foo: Length = ...\nunit foo = 2 m\n3 kfo -> km",
+        "6 km",
+    );
+
+    let mut ctx = get_test_context();
+    let _ = ctx
+        .interpret(
+            "unit foo: Length = ...\nfn per_foo(x: Length) -> Scalar = x / foo",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_failure_with_context(
+        &mut ctx,
+        "per_foo(90 cm)",
+        "Unit 'foo' was declared, but its value has not been defined yet",
+    );
+    expect_failure_with_context(&mut ctx, "unit foo = 3 s", "specified dimension: Length");
+    let _ = ctx
+        .interpret("unit foo = 30 cm", CodeSource::Internal)
+        .unwrap();
+    expect_output_with_context(&mut ctx, "per_foo(90 cm)", "3");
+    expect_failure_with_context(&mut ctx, "unit foo = 3 m", "Identifier is already in use");
+
+    expect_failure(
+        "unit foo: Length = ...\n@aliases(fo)\nunit foo = 2 m",
+        "Decorators of the forward-declared unit 'foo' need to be attached to its declaration",
+    );
+    expect_failure(
+        "unit foo = ...",
+        "Expected dimension annotation in unit declaration",
+    );
+}
+
+#[test]
+fn test_version_directive() {
+    assert_eq!(numbat::version(), env!("CARGO_PKG_VERSION"));
+
+    expect_output("#!version 1.0\n2 + 3", "5");
+    expect_output(&format!("#!version {}\n2 + 3", numbat::version()), "5");
+
+    expect_failure(
+        "#!version 999.0\n2 + 3",
+        &format!(
+            "This code requires Numbat 999.0 or newer, but this is Numbat {}",
+            numbat::version()
+        ),
+    );
+    expect_failure(
+        "#!version 1.x",
+        "Invalid version '1.x' in version directive",
+    );
+}
+
+#[test]
+fn test_importing_compiled_functions() {
+    let mut ctx_a = get_test_context();
+    let _ = ctx_a
+        .interpret(
+            "let factor = 3
+             fn scaled(x: Length) -> Length = factor × x + 1 cm
+             fn fac(n: Scalar) -> Scalar = if n <= 1 then 1 else n × fac(n - 1)
+             fn call_twice<A>(f: Fn[(A) -> A], x: A) -> A = f(f(x))
+             fn scaled_twice(x: Length) -> Length = call_twice(scaled, x)
+             struct Pair { first: Scalar, second: Scalar }
+             fn make_pair(x: Scalar) -> Pair = Pair { first: x, second: x }",
+            CodeSource::Internal,
+        )
+        .unwrap();
+
+    let fac = ctx_a.export_function("fac").unwrap();
+    let scaled = ctx_a.export_function("scaled").unwrap();
+    let call_twice = ctx_a.export_function("call_twice").unwrap();
+    let scaled_twice = ctx_a.export_function("scaled_twice").unwrap();
+    assert_eq!(scaled.name(), "scaled");
+
+    let mut ctx_b = get_test_context();
+    ctx_b.import_function(&fac).unwrap();
+    expect_output_with_context(&mut ctx_b, "fac(5)", "120");
+    expect_output_with_context(&mut ctx_b, "fac(3) + 1", "7");
+    expect_failure_with_context(&mut ctx_b, "fac(1 m)", "argument type: Length");
+    assert!(ctx_b.functions().any(|(name, ..)| name == "fac"));
+
+    // Functions and global variables are looked up by name
+    assert_eq!(
+        ctx_b.import_function(&scaled),
+        Err(CompiledFunctionError::MissingDependency {
+            function: "scaled".into(),
+            dependency: "factor".into()
+        })
+    );
+    let _ = ctx_b
+        .interpret("let factor = 2", CodeSource::Internal)
+        .unwrap();
+    ctx_b.import_function(&scaled).unwrap();
+    expect_output_with_context(&mut ctx_b, "scaled(2 m)", "4.01 m");
+
+    assert_eq!(
+        ctx_b.import_function(&scaled_twice),
+        Err(CompiledFunctionError::MissingDependency {
+            function: "scaled_twice".into(),
+            dependency: "call_twice".into()
+        })
+    );
+    ctx_b.import_function(&call_twice).unwrap();
+    ctx_b.import_function(&scaled_twice).unwrap();
+    expect_output_with_context(&mut ctx_b, "scaled_twice(1 m)", "4.03 m");
+    expect_output_with_context(&mut ctx_b, "call_twice(fac, 3)", "720");
+
+    let mut ctx_c = get_test_context();
+    let _ = ctx_c
+        .interpret("let factor = 2 s\nlet fac = 1", CodeSource::Internal)
+        .unwrap();
+    assert_eq!(
+        ctx_c.import_function(&scaled),
+        Err(CompiledFunctionError::IncompatibleDependency {
+            function: "scaled".into(),
+            dependency: "factor".into()
+        })
+    );
+    assert!(matches!(
+        ctx_c.import_function(&fac),
+        Err(CompiledFunctionError::NameResolutionError(_))
+    ));
+    expect_output_with_context(&mut ctx_c, "fac", "1");
+
+    assert_eq!(
+        ctx_a.export_function("make_pair").unwrap_err(),
+        CompiledFunctionError::UsesStructs("make_pair".into())
+    );
+    assert_eq!(
+        ctx_a.export_function("abs").unwrap_err(),
+        CompiledFunctionError::UnknownFunction("abs".into())
+    );
+    assert_eq!(
+        ctx_a.export_function("factor").unwrap_err(),
+        CompiledFunctionError::UnknownFunction("factor".into())
+    );
+}
+
+#[test]
+fn test_foreign_function_filter() {
+    let deny = |names: &[&str]| {
+        ForeignFunctionFilter::Deny(names.iter().map(|name| name.to_string()).collect())
+    };
+
+    let mut ctx = Context::new_without_importer();
+    let _ = ctx
+        .interpret(
+            "fn abs<T: Dim>(x: T) -> T
+             fn magnitude<T: Dim>(x: T) -> T = abs(x)",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    ctx.set_foreign_function_filter(deny(&["abs", "round", "print"]));
+
+    // Declaring a denied foreign function is fine, calling or referencing it is not
+    let _ = ctx
+        .interpret("fn round<T: Dim>(x: T) -> T", CodeSource::Internal)
+        .unwrap();
+    assert!(matches!(
+        ctx.interpret("abs(-2)", CodeSource::Internal),
+        Err(NumbatError::TypeCheckError(_))
+    ));
+    expect_failure_with_context(
+        &mut ctx,
+        "abs(-2)",
+        "Foreign function or procedure 'abs' is not available in this context",
+    );
+    expect_failure_with_context(&mut ctx, "let f = abs", "'abs'");
+    expect_failure_with_context(&mut ctx, "fn g<T: Dim>(x: T) -> T = abs(x)", "'abs'");
+
+    // Functions that have been compiled before the filter was set fail at runtime
+    assert!(matches!(
+        ctx.interpret("magnitude(-2)", CodeSource::Internal),
+        Err(NumbatError::RuntimeError(
+            RuntimeError::ForeignFunctionNotAvailable(name)
+        )) if name == "abs"
+    ));
+
+    // A function with a body that shadows a denied foreign function can be called
+    expect_output_with_context(&mut ctx, "fn abs(x) = 3\nabs(-2)", "3");
+    expect_failure_with_context(
+        &mut ctx,
+        "print(1)",
+        "Foreign function or procedure 'print' is not available in this context",
+    );
+
+    let _ = ctx
+        .interpret("fn floor<T: Dim>(x: T) -> T", CodeSource::Internal)
+        .unwrap();
+    expect_output_with_context(&mut ctx, "floor(2.5)", "2");
+    assert!(ctx
+        .interpret("assert_eq(floor(2.5), 2)", CodeSource::Internal)
+        .is_ok());
+
+    let mut ctx = Context::new_without_importer();
+    ctx.set_foreign_function_filter(ForeignFunctionFilter::AllowOnly(
+        ["floor".to_string()].into(),
+    ));
+    let _ = ctx
+        .interpret(
+            "fn floor<T: Dim>(x: T) -> T
+             fn ceil<T: Dim>(x: T) -> T",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_output_with_context(&mut ctx, "floor(2.5)", "2");
+    expect_failure_with_context(&mut ctx, "ceil(2.5)", "'ceil'");
+
+    // The filter can be set after loading the prelude
+    let mut ctx = get_test_context();
+    ctx.set_foreign_function_filter(deny(&["abs", "round"]));
+    expect_output_with_context(&mut ctx, "floor(2.5)", "2");
+    expect_failure_with_context(&mut ctx, "abs(-2) + round(4.2)", "'abs'");
+    expect_failure_with_context(&mut ctx, "round(4.2)", "'round'");
+
+    // Compiled functions that use a denied foreign function can not be imported
+    let mut ctx_a = Context::new_without_importer();
+    let _ = ctx_a
+        .interpret(
+            "fn abs<T: Dim>(x: T) -> T
+             fn magnitude<T: Dim>(x: T) -> T = abs(x)",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    let magnitude = ctx_a.export_function("magnitude").unwrap();
+
+    let mut ctx_b = Context::new_without_importer();
+    let _ = ctx_b
+        .interpret("fn abs<T: Dim>(x: T) -> T", CodeSource::Internal)
+        .unwrap();
+    ctx_b.set_foreign_function_filter(deny(&["abs"]));
+    assert_eq!(
+        ctx_b.import_function(&magnitude),
+        Err(CompiledFunctionError::MissingDependency {
+            function: "magnitude".into(),
+            dependency: "abs".into()
+        })
+    );
+}
+
+#[test]
+fn test_partial_application() {
+    let mut ctx = get_test_context();
+    let definitions = "fn add(x: Length, y: Length) -> Length = x + y
+                       fn volume(a: Length, b: Length, c: Length) -> Volume = a b c";
+    let _ = ctx.interpret(definitions, CodeSource::Internal).unwrap();
+
+    // Opt-in only
+    expect_failure_with_context(
+        &mut ctx,
+        "add(3 m)",
+        "called with 1 argument, but needs 2 arguments",
+    );
+
+    ctx.set_partial_application(true);
+
+    // add(3 m) is a function awaiting one more Length
+    let _ = ctx
+        .interpret(
+            "let add3: Fn[(Length) -> Length] = add(3 m)",
+            CodeSource::Internal,
+        )
+        .unwrap();
+    expect_output_with_context(&mut ctx, "add3(2 m)", "5 m");
+    expect_output_with_context(&mut ctx, "add(3 m)(20 cm)", "3.2 m");
+    expect_failure_with_context(
+        &mut ctx,
+        "add3(2 s)",
+        "Could not solve the following constraints",
+    );
+    expect_failure_with_context(
+        &mut ctx,
+        "let f: Fn[(Time) -> Length] = add(3 m)",
+        "Incompatible types in definition",
+    );
+
+    // Partial applications can be called with the remaining arguments (but not be
+    // applied partially again), and passed to other functions
+    expect_output_with_context(&mut ctx, "volume(2 m)(3 m, 4 m)", "24 m³");
+    expect_failure_with_context(
+        &mut ctx,
+        "volume(2 m)(3 m)(4 m)",
+        "called with 1 argument, but needs 2 arguments",
+    );
+    expect_output_with_context(
+        &mut ctx,
+        "map(volume(1 m, 2 m), [1 m, 2 m])",
+        "[2 m³, 4 m³]",
+    );
+    expect_output_with_context(
+        &mut ctx,
+        "map(atan2(1 m), [1 m, 1 km])",
+        "[0.785398, 0.001]",
+    );
+
+    // Calls with all arguments or without any argument are not affected
+    expect_output_with_context(&mut ctx, "add(1 m, 2 m)", "3 m");
+    expect_failure_with_context(&mut ctx, "add()", "called with 0 arguments");
+}
+
+#[test]
+fn test_lazy_globals() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let counter = |evaluation| {
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_c = count.clone();
+        let mut ctx = get_test_context();
+        let meter = get_quantity(&mut ctx, "1 m");
+        ctx.define_lazy_global("counter", "Length", evaluation, move || {
+            let n = count_c.fetch_add(1, Ordering::SeqCst) + 1;
+            Value::Quantity(Quantity::from_scalar(n as f64) * meter.clone())
+        })
+        .unwrap();
+        (ctx, count)
+    };
+
+    let (mut ctx, count) = counter(LazyEvaluation::EachAccess);
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+    expect_output_with_context(&mut ctx, "counter", "1 m");
+    expect_output_with_context(&mut ctx, "counter -> cm", "200 cm");
+    expect_output_with_context(&mut ctx, "[counter, counter]", "[3 m, 4 m]");
+    expect_output_with_context(&mut ctx, "fn next() = counter\nnext() + next()", "11 m");
+    assert_eq!(count.load(Ordering::SeqCst), 6);
+
+    let (mut ctx, count) = counter(LazyEvaluation::Once);
+    expect_output_with_context(&mut ctx, "counter + counter", "2 m");
+    expect_output_with_context(&mut ctx, "counter", "1 m");
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // Lazy globals are type checked like other variables, and can be shadowed
+    expect_failure_with_context(&mut ctx, "counter + 1 s", "right hand side: Time");
+    let _ = ctx
+        .interpret("let counter = 2 s", CodeSource::Internal)
+        .unwrap();
+    expect_output_with_context(&mut ctx, "counter", "2 s");
+
+    // Functions that use lazy globals can only be imported into contexts that define them
+    let (mut ctx, _) = counter(LazyEvaluation::EachAccess);
+    let _ = ctx
+        .interpret("fn twice() = 2 counter", CodeSource::Internal)
+        .unwrap();
+    let twice = ctx.export_function("twice").unwrap();
+
+    let (mut ctx, _) = counter(LazyEvaluation::Once);
+    ctx.import_function(&twice).unwrap();
+    expect_output_with_context(&mut ctx, "twice() + twice()", "4 m");
+
+    let mut ctx = get_test_context();
+    assert_eq!(
+        ctx.import_function(&twice),
+        Err(CompiledFunctionError::MissingDependency {
+            function: "twice".into(),
+            dependency: "counter".into()
+        })
+    );
+
+    let zero = || Value::Quantity(Quantity::from_scalar(0.0));
+    assert!(ctx
+        .define_lazy_global("pi", "Scalar", LazyEvaluation::Once, zero)
+        .is_err());
+    assert!(ctx
+        .define_lazy_global("x", "UnknownDimension", LazyEvaluation::Once, zero)
+        .is_err());
+    assert!(ctx
+        .define_lazy_global("not valid", "Scalar", LazyEvaluation::Once, zero)
+        .is_err());
+    expect_failure_with_context(&mut ctx, "x", "Unknown identifier 'x'");
+}